@@ -34,7 +34,8 @@ use std::{env, fmt, io, num::ParseIntError, process, sync};
 
 use cfxkey::{
     brain_recover, sign, verify_address, verify_public, Brain, BrainPrefix,
-    Error as EthkeyError, Generator, KeyPair, Prefix, Random,
+    DerivationPath, Error as EthkeyError, Generator, KeyPair, Language,
+    Mnemonic, MnemonicError, Prefix, Random,
 };
 use docopt::Docopt;
 use rustc_hex::{FromHex, FromHexError};
@@ -46,6 +47,7 @@ Usage:
     cfxkey info <secret-or-phrase> [options]
     cfxkey generate random [options]
     cfxkey generate prefix <prefix> [options]
+    cfxkey generate mnemonic [--words=<words>] [options]
     cfxkey sign <secret> <message>
     cfxkey verify public <public> <signature> <message>
     cfxkey verify address <address> <signature> <message>
@@ -58,11 +60,19 @@ Options:
     -p, --public       Display only the public key.
     -a, --address      Display only the address.
     -b, --brain        Use parity brain wallet algorithm. Not recommended.
+    -m, --mnemonic     Use BIP39 mnemonic phrase.
+    --passphrase=<passphrase>
+                       Passphrase of the BIP39 mnemonic, empty by default.
+    --path=<path>      BIP32 derivation path of the key from the mnemonic
+                       [default: m/44'/503'/0'/0/0].
+    --words=<words>    Number of words of the mnemonic, 12, 15, 18, 21 or 24
+                       [default: 12].
 
 Commands:
     info               Display public key and address of the secret.
     generate random    Generates new random Ethereum key.
     generate prefix    Random generation, but address must start with a prefix ("vanity address").
+    generate mnemonic  Generates new random BIP39 mnemonic, and the key at the derivation path.
     sign               Sign message using a secret key.
     verify             Verify signer of the signature by public key or address.
     recover            Try to find brain phrase matching given address from partial phrase.
@@ -74,6 +84,7 @@ struct Args {
     cmd_generate: bool,
     cmd_random: bool,
     cmd_prefix: bool,
+    cmd_mnemonic: bool,
     cmd_sign: bool,
     cmd_verify: bool,
     cmd_public: bool,
@@ -91,11 +102,16 @@ struct Args {
     flag_public: bool,
     flag_address: bool,
     flag_brain: bool,
+    flag_mnemonic: bool,
+    flag_passphrase: String,
+    flag_path: String,
+    flag_words: usize,
 }
 
 #[derive(Debug)]
 enum Error {
     Ethkey(EthkeyError),
+    Mnemonic(MnemonicError),
     FromHex(FromHexError),
     ParseInt(ParseIntError),
     Docopt(docopt::Error),
@@ -106,6 +122,10 @@ impl From<EthkeyError> for Error {
     fn from(err: EthkeyError) -> Self { Error::Ethkey(err) }
 }
 
+impl From<MnemonicError> for Error {
+    fn from(err: MnemonicError) -> Self { Error::Mnemonic(err) }
+}
+
 impl From<FromHexError> for Error {
     fn from(err: FromHexError) -> Self { Error::FromHex(err) }
 }
@@ -126,6 +146,7 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match *self {
             Error::Ethkey(ref e) => write!(f, "{}", e),
+            Error::Mnemonic(ref e) => write!(f, "{}", e),
             Error::FromHex(ref e) => write!(f, "{}", e),
             Error::ParseInt(ref e) => write!(f, "{}", e),
             Error::Docopt(ref e) => write!(f, "{}", e),
@@ -200,6 +221,13 @@ where
                 .generate()
                 .expect("Brain wallet generator is infallible; qed");
             (keypair, Some(phrase_info))
+        } else if args.flag_mnemonic {
+            let path: DerivationPath = args.flag_path.parse()?;
+            let mnemonic = Mnemonic::from_phrase(
+                &args.arg_secret_or_phrase,
+                Language::English,
+            )?;
+            (mnemonic.derive(&args.flag_passphrase, &path)?, None)
         } else {
             let secret = args
                 .arg_secret_or_phrase
@@ -220,6 +248,17 @@ where
             } else {
                 (Random.generate()?, None)
             }
+        } else if args.cmd_mnemonic {
+            let path: DerivationPath = args.flag_path.parse()?;
+            let mnemonic =
+                Mnemonic::generate(args.flag_words, Language::English)?;
+            let keypair = mnemonic.derive(&args.flag_passphrase, &path)?;
+            let phrase = format!(
+                "recovery phrase: {}\nderivation path: {}",
+                mnemonic.phrase(),
+                path
+            );
+            (keypair, Some(phrase))
         } else if args.cmd_prefix {
             let prefix: Vec<u8> = args.arg_prefix.from_hex()?;
             let brain = args.flag_brain;
@@ -447,6 +486,55 @@ address: 10a33d9f95b22fe53024331c036db6e824a25bab".to_owned();
         assert_eq!(execute(command).unwrap(), expected);
     }
 
+    #[test]
+    fn mnemonic() {
+        let command = vec![
+            "cfxkey",
+            "info",
+            "--mnemonic",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        ]
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<String>>();
+
+        let expected =
+"secret:  15aec8aed073713e8abe0191ef56328872dad9a77261e447c582905ced2ab899
+public:  d61c3bfde8788660ae0b8c656d5b9564181b4120638ce1d5e3f93bc2482d497d1116664920d59bb945d7912fc03b88d3bd44413eff5b2bfe3230cac075f4cc0c
+address: 18416599fddf76126effa8db4880c3a24fe2152b".to_owned();
+        assert_eq!(execute(command).unwrap(), expected);
+
+        let with_path = |path: &str| {
+            let command = vec![
+                "cfxkey",
+                "info",
+                "--mnemonic",
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                "--path",
+                path,
+            ]
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<String>>();
+            execute(command)
+        };
+        assert_eq!(with_path("m/44'/503'/0'/0/0").unwrap(), expected);
+        assert_ne!(with_path("m/44'/503'/0'/0/1").unwrap(), expected);
+        assert_ne!(with_path("m/1h/2").unwrap(), expected);
+        assert!(with_path("44'/503'").is_err());
+
+        let command = vec![
+            "cfxkey",
+            "info",
+            "-m",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon",
+        ]
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<String>>();
+        assert!(execute(command).is_err());
+    }
+
     #[test]
     fn sign() {
         let command = vec![
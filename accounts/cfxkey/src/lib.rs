@@ -45,6 +45,7 @@ mod error;
 mod extended;
 mod keccak;
 mod keypair;
+mod mnemonic;
 mod password;
 mod prefix;
 mod random;
@@ -65,6 +66,10 @@ pub use self::{
     },
     keypair::{public_to_address, KeyPair},
     math::public_is_valid,
+    mnemonic::{
+        DerivationPath, Error as MnemonicError, Language, Mnemonic,
+        CFX_COIN_TYPE,
+    },
     parity_wordlist::Error as WordlistError,
     password::Password,
     prefix::Prefix,
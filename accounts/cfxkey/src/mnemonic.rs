@@ -0,0 +1,430 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! BIP39 mnemonic phrases and BIP44 derivation paths
+//! https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0044.mediawiki
+
+use extended::{Derivation, ExtendedKeyPair};
+use keypair::KeyPair;
+use parity_crypto::{digest, pbkdf2};
+use rand::{rngs::OsRng, RngCore};
+use std::{fmt, str::FromStr};
+
+/// Registered BIP44 coin type of Conflux.
+pub const CFX_COIN_TYPE: u32 = 503;
+
+/// Index bit of hardened derivation.
+const HARDENED_BIT: u32 = 1 << 31;
+
+/// Rounds of PBKDF2 to derive the seed of a mnemonic.
+const SEED_ITERATIONS: u32 = 2048;
+
+lazy_static! {
+    static ref ENGLISH: Vec<&'static str> =
+        include_str!("../res/bip39/english.txt").lines().collect();
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        InvalidWordCount(count: usize) {
+            display("invalid number of mnemonic words: {}, expected 12, 15, 18, 21 or 24", count)
+        }
+        InvalidEntropyLength(len: usize) {
+            display("invalid length of mnemonic entropy: {} bytes", len)
+        }
+        UnknownWord(word: String) {
+            display("the word '{}' is not in the wordlist", word)
+        }
+        InvalidChecksum {
+            display("invalid mnemonic checksum")
+        }
+        InvalidPath(path: String) {
+            display("invalid derivation path: {}", path)
+        }
+        InvalidDerivation {
+            display("key derivation failed")
+        }
+    }
+}
+
+/// Wordlist of mnemonic phrases.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    English,
+}
+
+impl Language {
+    /// The 2048 words of the wordlist, in order.
+    pub fn wordlist(&self) -> &'static [&'static str] {
+        match self {
+            Language::English => &ENGLISH[..],
+        }
+    }
+
+    fn index_of(&self, word: &str) -> Option<usize> {
+        // wordlists are sorted
+        self.wordlist().binary_search(&word).ok()
+    }
+}
+
+impl Default for Language {
+    fn default() -> Self { Language::English }
+}
+
+/// Mnemonic phrase encoding 128 to 256 bits of entropy with a checksum.
+#[derive(Clone, PartialEq)]
+pub struct Mnemonic {
+    phrase: String,
+    entropy: Vec<u8>,
+    language: Language,
+}
+
+impl Mnemonic {
+    /// Generate a mnemonic of `word_count` words from random entropy.
+    pub fn generate(
+        word_count: usize, language: Language,
+    ) -> Result<Mnemonic, Error> {
+        let mut entropy = vec![0u8; entropy_len(word_count)?];
+        OsRng.fill_bytes(&mut entropy);
+        Mnemonic::from_entropy(&entropy, language)
+    }
+
+    /// Encode the entropy, of 16, 20, 24, 28 or 32 bytes.
+    pub fn from_entropy(
+        entropy: &[u8], language: Language,
+    ) -> Result<Mnemonic, Error> {
+        if entropy.len() < 16 || entropy.len() > 32 || entropy.len() % 4 != 0
+        {
+            return Err(Error::InvalidEntropyLength(entropy.len()));
+        }
+
+        // entropy followed by the first `entropy bits / 32` bits of its
+        // sha256, split into 11 bits per word
+        let checksum = digest::sha256(entropy)[0];
+        let bits = entropy.len() * 8 + entropy.len() / 4;
+        let bit = |i: usize| {
+            let byte = if i < entropy.len() * 8 {
+                entropy[i / 8]
+            } else {
+                checksum
+            };
+            (byte >> (7 - i % 8)) & 1
+        };
+
+        let wordlist = language.wordlist();
+        let words: Vec<&str> = (0..bits / 11)
+            .map(|w| {
+                let index = (0..11).fold(0usize, |acc, i| {
+                    (acc << 1) | bit(w * 11 + i) as usize
+                });
+                wordlist[index]
+            })
+            .collect();
+
+        Ok(Mnemonic {
+            phrase: words.join(" "),
+            entropy: entropy.to_vec(),
+            language,
+        })
+    }
+
+    /// Parse a mnemonic phrase, checking the words and the checksum.
+    pub fn from_phrase(
+        phrase: &str, language: Language,
+    ) -> Result<Mnemonic, Error> {
+        let words: Vec<String> =
+            phrase.split_whitespace().map(str::to_lowercase).collect();
+        let entropy_bytes = entropy_len(words.len())?;
+
+        let mut bits = Vec::with_capacity(words.len() * 11);
+        for word in &words {
+            let index = language
+                .index_of(word)
+                .ok_or_else(|| Error::UnknownWord(word.clone()))?;
+            bits.extend((0..11).rev().map(|i| (index >> i) & 1 == 1));
+        }
+
+        let entropy: Vec<u8> = bits[..entropy_bytes * 8]
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8))
+            .collect();
+        let mnemonic = Mnemonic::from_entropy(&entropy, language)?;
+        if mnemonic.phrase != words.join(" ") {
+            return Err(Error::InvalidChecksum);
+        }
+        Ok(mnemonic)
+    }
+
+    /// Check if the phrase is a valid mnemonic.
+    pub fn validate(phrase: &str, language: Language) -> Result<(), Error> {
+        Mnemonic::from_phrase(phrase, language).map(|_| ())
+    }
+
+    pub fn phrase(&self) -> &str { &self.phrase }
+
+    pub fn entropy(&self) -> &[u8] { &self.entropy }
+
+    pub fn language(&self) -> Language { self.language }
+
+    /// The 64 byte seed of the mnemonic, protected by an optional
+    /// passphrase. The passphrase is used as is, without unicode
+    /// normalization.
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let salt = format!("mnemonic{}", passphrase);
+        let mut seed = [0u8; 64];
+        pbkdf2::sha512(
+            SEED_ITERATIONS,
+            pbkdf2::Salt(salt.as_bytes()),
+            pbkdf2::Secret(self.phrase.as_bytes()),
+            &mut seed,
+        );
+        seed
+    }
+
+    /// Derive the key pair at `path` from the seed of the mnemonic.
+    pub fn derive(
+        &self, passphrase: &str, path: &DerivationPath,
+    ) -> Result<KeyPair, Error> {
+        path.derive_from_seed(&self.to_seed(passphrase))
+    }
+}
+
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // do not leak the phrase into logs
+        f.debug_struct("Mnemonic")
+            .field("words", &self.phrase.split(' ').count())
+            .field("language", &self.language)
+            .finish()
+    }
+}
+
+fn entropy_len(word_count: usize) -> Result<usize, Error> {
+    match word_count {
+        12 | 15 | 18 | 21 | 24 => Ok(word_count * 11 * 32 / 33 / 8),
+        _ => Err(Error::InvalidWordCount(word_count)),
+    }
+}
+
+/// BIP32 derivation path, e.g. `m/44'/503'/0'/0/0`. The indices of hardened
+/// derivation have the highest bit set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    pub fn new(indices: Vec<u32>) -> Self { DerivationPath(indices) }
+
+    /// BIP44 path of the address `index` of the Conflux account `account`,
+    /// i.e. `m/44'/503'/<account>'/0/<index>`.
+    pub fn bip44(account: u32, index: u32) -> Self {
+        DerivationPath(vec![
+            44 | HARDENED_BIT,
+            CFX_COIN_TYPE | HARDENED_BIT,
+            account | HARDENED_BIT,
+            0,
+            index,
+        ])
+    }
+
+    pub fn indices(&self) -> &[u32] { &self.0 }
+
+    /// Derive the key pair at the path from the master key of `seed`.
+    pub fn derive_from_seed(&self, seed: &[u8]) -> Result<KeyPair, Error> {
+        let mut extended = ExtendedKeyPair::with_seed(seed)
+            .map_err(|_| Error::InvalidDerivation)?;
+        for &index in &self.0 {
+            extended = extended
+                .derive(Derivation::from(index))
+                .map_err(|_| Error::InvalidDerivation)?;
+        }
+        KeyPair::from_secret(extended.secret().as_raw().clone())
+            .map_err(|_| Error::InvalidDerivation)
+    }
+}
+
+impl Default for DerivationPath {
+    fn default() -> Self { DerivationPath::bip44(0, 0) }
+}
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidPath(s.to_string());
+        let mut parts = s.trim().split('/');
+        if parts.next() != Some("m") {
+            return Err(invalid());
+        }
+
+        let mut indices = Vec::new();
+        for part in parts {
+            let (index, hardened) =
+                if part.ends_with('\'') || part.ends_with('h') {
+                    (&part[..part.len() - 1], true)
+                } else {
+                    (part, false)
+                };
+            let index: u32 = index.parse().map_err(|_| invalid())?;
+            if index & HARDENED_BIT != 0 {
+                return Err(invalid());
+            }
+            if hardened {
+                indices.push(index | HARDENED_BIT);
+            } else {
+                indices.push(index);
+            }
+        }
+        Ok(DerivationPath(indices))
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            if index & HARDENED_BIT != 0 {
+                write!(f, "/{}'", index & !HARDENED_BIT)?;
+            } else {
+                write!(f, "/{}", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DerivationPath, Error, Language, Mnemonic};
+    use rustc_hex::{FromHex, ToHex};
+    use std::str::FromStr;
+
+    // test vectors of the reference implementation
+    // https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+    const VECTORS: &[(&str, &str, &str)] = &[
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+            "ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+        ),
+        (
+            "9e885d952ad362caeb4efe34a8e91bd2",
+            "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+            "274ddc525802f7c828d8ef7ddbcdc5304e87ac3535913611fbbfa986d0c9e5476c91689f9c8a54fd55bd38606aa6a8595ad213d4c9c9f9aca3fb217069a41028",
+        ),
+        (
+            "b63a9c59a6e641f288ebc103017f1da9f8290b3da6bdef7b",
+            "renew stay biology evidence goat welcome casual join adapt armor shuffle fault little machine walk stumble urge swap",
+            "9248d83e06f4cd98debf5b6f010542760df925ce46cf38a1bdb4e4de7d21f5c39366941c69e1bdbf2966e0f6e6dbece898a0e2f0a4c2b3e640953dfe8b7bbdc5",
+        ),
+        (
+            "f585c11aec520db57dd353c69554b21a89b20fb0650966fa0a9d6f74fd989d8f",
+            "void come effort suffer camp survey warrior heavy shoot primary clutch crush open amazing screen patrol group space point ten exist slush involve unfold",
+            "01f5bced59dec48e362f2c45b5de68b9fd6c92c6634f44d6d40aab69056506f0e35524a518034ddc1192e1dacd32c1ed3eaa3c3b131c88ed8e7e54c49a5d0998",
+        ),
+    ];
+
+    #[test]
+    fn test_vectors() {
+        for &(entropy, phrase, seed) in VECTORS {
+            let entropy: Vec<u8> = entropy.from_hex().unwrap();
+            let mnemonic =
+                Mnemonic::from_entropy(&entropy, Language::English).unwrap();
+            assert_eq!(mnemonic.phrase(), phrase);
+
+            let parsed =
+                Mnemonic::from_phrase(phrase, Language::English).unwrap();
+            assert_eq!(parsed.entropy(), &entropy[..]);
+
+            assert_eq!(mnemonic.to_seed("TREZOR")[..].to_hex(), seed);
+        }
+    }
+
+    #[test]
+    fn test_invalid_phrase() {
+        // wrong checksum
+        match Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon",
+            Language::English,
+        ) {
+            Err(Error::InvalidChecksum) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon sparta",
+            Language::English,
+        ) {
+            Err(Error::UnknownWord(ref word)) if word == "sparta" => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match Mnemonic::from_phrase("abandon about", Language::English) {
+            Err(Error::InvalidWordCount(2)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        for &count in &[12, 15, 18, 21, 24] {
+            let mnemonic =
+                Mnemonic::generate(count, Language::English).unwrap();
+            assert_eq!(mnemonic.phrase().split(' ').count(), count);
+            assert!(Mnemonic::validate(mnemonic.phrase(), Language::English)
+                .is_ok());
+        }
+        assert!(Mnemonic::generate(13, Language::English).is_err());
+    }
+
+    #[test]
+    fn test_derivation_path() {
+        let path = DerivationPath::from_str("m/44'/503'/0'/0/1").unwrap();
+        assert_eq!(
+            path.indices(),
+            &[0x8000_002c, 0x8000_01f7, 0x8000_0000, 0, 1]
+        );
+        assert_eq!(path, DerivationPath::bip44(0, 1));
+        assert_eq!(path.to_string(), "m/44'/503'/0'/0/1");
+        assert_eq!(
+            DerivationPath::from_str("m/1h/2").unwrap().to_string(),
+            "m/1'/2"
+        );
+        assert!(DerivationPath::from_str("m").unwrap().indices().is_empty());
+
+        assert!(DerivationPath::from_str("44'/503'").is_err());
+        assert!(DerivationPath::from_str("m/x").is_err());
+        assert!(DerivationPath::from_str("m/2147483648").is_err());
+    }
+
+    #[test]
+    fn test_derive() {
+        let mnemonic = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap();
+        let keypair = mnemonic.derive("", &DerivationPath::default()).unwrap();
+        assert_eq!(
+            format!("{:x}", keypair.secret()),
+            "15aec8aed073713e8abe0191ef56328872dad9a77261e447c582905ced2ab899"
+        );
+        assert_ne!(
+            mnemonic
+                .derive("", &DerivationPath::bip44(0, 1))
+                .unwrap()
+                .secret(),
+            keypair.secret()
+        );
+    }
+}
@@ -14,7 +14,9 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Ethereum.  If not, see <http://www.gnu.org/licenses/>.
 
-use cfxkey::{Address, Message, Password, Public, Secret, Signature};
+use cfxkey::{
+    Address, DerivationPath, Message, Password, Public, Secret, Signature,
+};
 use ethereum_types::H256;
 use json::{OpaqueKeyFile, Uuid};
 use std::{
@@ -253,3 +255,18 @@ pub enum Derivation {
     /// Hash derivation, hard.
     HardHash(H256),
 }
+
+impl<'a> From<&'a DerivationPath> for Derivation {
+    fn from(path: &'a DerivationPath) -> Self {
+        Derivation::Hierarchical(
+            path.indices()
+                .iter()
+                .map(|&index| IndexDerivation {
+                    // hardened indices have the highest bit set
+                    soft: index < (1 << 31),
+                    index,
+                })
+                .collect(),
+        )
+    }
+}
@@ -27,6 +27,8 @@ pub enum SignError {
     NotFound,
     /// Low-level error from store
     SStore(SSError),
+    /// Error of the external signer.
+    External(String),
 }
 
 impl fmt::Display for SignError {
@@ -35,6 +37,7 @@ impl fmt::Display for SignError {
             SignError::NotUnlocked => write!(f, "Account is locked"),
             SignError::NotFound => write!(f, "Account does not exist"),
             SignError::SStore(ref e) => write!(f, "{}", e),
+            SignError::External(ref e) => write!(f, "External signer: {}", e),
         }
     }
 }
@@ -20,7 +20,9 @@
 
 mod account_data;
 mod error;
+mod signer;
 mod stores;
+pub mod typed_data;
 
 use self::{
     account_data::{AccountData, Unlock},
@@ -32,7 +34,10 @@ use std::{
     time::{Duration, Instant},
 };
 
-use cfxkey::{Address, Generator, Message, Password, Public, Random, Secret};
+use cfxkey::{
+    Address, DerivationPath, Generator, Language, Message, Mnemonic, Password,
+    Public, Random, Secret,
+};
 use cfxstore::{
     accounts_dir::MemoryDirectory, random_string, CfxMultiStore, CfxStore,
     OpaqueSecret, SecretStore, SecretVaultRef, SimpleSecretStore,
@@ -44,7 +49,12 @@ use parking_lot::RwLock;
 pub use cfxkey::Signature;
 pub use cfxstore::{Derivation, Error, IndexDerivation, KeyFile};
 
-pub use self::{account_data::AccountMeta, error::SignError};
+pub use self::{
+    account_data::AccountMeta,
+    error::SignError,
+    signer::{ExternalSigner, Signer},
+    typed_data::{Error as TypedDataError, TypedData},
+};
 
 type AccountToken = Password;
 
@@ -75,6 +85,8 @@ pub struct AccountProvider {
     unlock_keep_secret: bool,
     /// Disallowed accounts.
     blacklisted_accounts: Vec<Address>,
+    /// Backend to sign with instead of the local accounts.
+    signer: Option<Box<dyn Signer>>,
 }
 
 fn transient_sstore() -> CfxMultiStore {
@@ -111,9 +123,18 @@ impl AccountProvider {
             transient_sstore: transient_sstore(),
             unlock_keep_secret: settings.unlock_keep_secret,
             blacklisted_accounts: settings.blacklisted_accounts,
+            signer: None,
         }
     }
 
+    /// Sets the backend to list the accounts and sign messages with, e.g. an
+    /// `ExternalSigner`. The other operations still apply to the local
+    /// accounts.
+    pub fn with_signer(mut self, signer: Box<dyn Signer>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
     /// Creates not disk backed provider.
     pub fn transient_provider() -> Self {
         AccountProvider {
@@ -127,6 +148,7 @@ impl AccountProvider {
             transient_sstore: transient_sstore(),
             unlock_keep_secret: false,
             blacklisted_accounts: vec![],
+            signer: None,
         }
     }
 
@@ -169,9 +191,24 @@ impl AccountProvider {
         Ok(account.address)
     }
 
+    /// Imports the account at `path` of a BIP39 mnemonic, e.g. the first
+    /// Conflux address at `m/44'/503'/0'/0/0`. The `passphrase` of the
+    /// mnemonic is empty if not set, and is not the account password.
+    pub fn import_mnemonic(
+        &self, phrase: &str, passphrase: &str, path: &DerivationPath,
+        password: &Password,
+    ) -> Result<Address, Error>
+    {
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English)?;
+        let keypair = mnemonic.derive(passphrase, path)?;
+        self.insert_account(keypair.secret().clone(), password)
+    }
+
     /// Generates new derived account based on the existing one
     /// If password is not provided, account must be unlocked
     /// New account will be created with the same password (if save: true)
+    /// A derivation path, e.g. `m/0'/1`, converts into a hierarchical
+    /// derivation.
     pub fn derive_account(
         &self, address: &Address, password: Option<Password>,
         derivation: Derivation, save: bool,
@@ -221,6 +258,9 @@ impl AccountProvider {
 
     /// Returns addresses of all accounts.
     pub fn accounts(&self) -> Result<Vec<Address>, Error> {
+        if let Some(ref signer) = self.signer {
+            return signer.accounts().map_err(|e| Error::Custom(e.to_string()));
+        }
         let accounts = self.sstore.accounts()?;
         Ok(accounts
             .into_iter()
@@ -468,10 +508,13 @@ impl AccountProvider {
     }
 
     /// Signs the message. If password is not provided the account must be
-    /// unlocked.
+    /// unlocked, unless the signer is set.
     pub fn sign(
         &self, address: Address, password: Option<Password>, message: Message,
     ) -> Result<Signature, SignError> {
+        if let Some(ref signer) = self.signer {
+            return signer.sign(address, password, message);
+        }
         let account = self.sstore.account_ref(&address)?;
         match self.unlocked_secrets.read().get(&account) {
             Some(secret) => {
@@ -682,8 +725,11 @@ impl AccountProvider {
 
 #[cfg(test)]
 mod tests {
-    use super::{AccountProvider, Unlock};
-    use cfxkey::{Address, Generator, Random};
+    use super::{AccountProvider, SignError, Signer, Unlock};
+    use cfxkey::{
+        sign, Address, DerivationPath, Generator, KeyPair, Message, Password,
+        Random, Signature,
+    };
     use cfxstore::{Derivation, StoreAccountRef};
     use ethereum_types::H256;
     use std::time::{Duration, Instant};
@@ -762,6 +808,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn import_mnemonic() {
+        let ap = AccountProvider::transient_provider();
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let import = |phrase: &str, passphrase: &str, path: DerivationPath| {
+            ap.import_mnemonic(phrase, passphrase, &path, &"test".into())
+        };
+
+        let first = import(phrase, "", DerivationPath::bip44(0, 0))
+            .expect("Importing a valid mnemonic should not fail");
+        let second = import(phrase, "", DerivationPath::bip44(0, 1))
+            .expect("Importing a valid mnemonic should not fail");
+        assert_ne!(first, second);
+        assert_eq!(ap.accounts().unwrap().len(), 2);
+
+        // a different passphrase derives a different account
+        let protected =
+            import(phrase, "secret", DerivationPath::bip44(0, 0)).unwrap();
+        assert_ne!(first, protected);
+
+        assert!(import("abandon about", "", DerivationPath::default()).is_err());
+    }
+
+    #[test]
+    fn derived_account_path() {
+        let kp = Random.generate().unwrap();
+        let ap = AccountProvider::transient_provider();
+        assert!(ap
+            .insert_account(kp.secret().clone(), &"base".into())
+            .is_ok());
+
+        let path: DerivationPath = "m/0'/1".parse().unwrap();
+        let derived_addr = ap
+            .derive_account(
+                &kp.address(),
+                Some("base".into()),
+                Derivation::from(&path),
+                true,
+            )
+            .expect("Derivation should not fail");
+        assert_ne!(derived_addr, kp.address());
+        assert!(ap
+            .unlock_account_permanently(derived_addr, "base".into())
+            .is_ok());
+    }
+
     #[test]
     fn derived_account_sign() {
         let kp = Random.generate().unwrap();
@@ -894,4 +986,44 @@ mod tests {
         );
         assert_eq!(ap.accounts().unwrap(), vec![]);
     }
+
+    struct KeySigner(KeyPair);
+
+    impl Signer for KeySigner {
+        fn accounts(&self) -> Result<Vec<Address>, SignError> {
+            Ok(vec![self.0.address()])
+        }
+
+        fn sign(
+            &self, address: Address, _password: Option<Password>,
+            message: Message,
+        ) -> Result<Signature, SignError>
+        {
+            if address != self.0.address() {
+                return Err(SignError::NotFound);
+            }
+            Ok(sign(self.0.secret(), &message).unwrap())
+        }
+    }
+
+    #[test]
+    fn sign_with_signer() {
+        let local = Random.generate().unwrap();
+        let external = Random.generate().unwrap();
+        let ap = AccountProvider::transient_provider();
+        assert!(ap
+            .insert_account(local.secret().clone(), &"test".into())
+            .is_ok());
+        let ap = ap.with_signer(Box::new(KeySigner(external.clone())));
+
+        assert_eq!(ap.accounts().unwrap(), vec![external.address()]);
+        let message = Message::random();
+        assert_eq!(
+            ap.sign(external.address(), None, message).unwrap(),
+            sign(external.secret(), &message).unwrap()
+        );
+        assert!(ap
+            .sign(local.address(), Some("test".into()), message)
+            .is_err());
+    }
 }
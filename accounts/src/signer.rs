@@ -0,0 +1,464 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Signing backends of the account provider.
+//!
+//! By default the accounts are signed for with the keys in the local key
+//! store. An [`ExternalSigner`] forwards the requests instead to a signer
+//! running in a separate process, in the style of Clef, over JSON-RPC via
+//! HTTP or a Unix socket. The signer serves two methods:
+//! - `account_list`, returning the hex encoded addresses it signs for;
+//! - `account_signHash` with params `[address, hash]`, returning the hex
+//!   encoded 65 bytes signature `r ‖ s ‖ v` of the 32 bytes hash, where `v` is
+//!   0 or 1, or 27 or 28 in the Ethereum style.
+//!
+//! The signatures returned are checked to be signed by the requested account.
+//!
+//! The external signer authorizes the requests on its own, e.g. by asking
+//! its operator, so the passwords of the accounts are not forwarded.
+
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use cfxkey::{verify_address, Address, Message, Password, Signature};
+use rustc_hex::FromHex;
+use serde_json::{json, Value};
+
+use crate::SignError;
+
+/// Time to wait for a response of the external signer, which may ask its
+/// operator to confirm the request.
+const SIGNER_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Backend signing messages with the keys of accounts.
+pub trait Signer: Send + Sync {
+    /// Returns the addresses of the accounts the backend signs for.
+    fn accounts(&self) -> Result<Vec<Address>, SignError>;
+
+    /// Signs the message with the key of the account. The password is only
+    /// used by the backends which keep the keys encrypted.
+    fn sign(
+        &self, address: Address, password: Option<Password>, message: Message,
+    ) -> Result<Signature, SignError>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Endpoint {
+    /// `host:port` and path of an HTTP URL.
+    Http { host: String, path: String },
+    /// Path of a Unix socket.
+    Ipc(String),
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self, SignError> {
+        if url.starts_with("http://") {
+            let rest = &url["http://".len()..];
+            let (host, path) = match rest.find('/') {
+                Some(pos) => (&rest[..pos], &rest[pos..]),
+                None => (rest, "/"),
+            };
+            if host.is_empty() {
+                return Err(external_error(format!(
+                    "missing host in signer URL {}",
+                    url
+                )));
+            }
+            Ok(Endpoint::Http {
+                host: host.to_string(),
+                path: path.to_string(),
+            })
+        } else if url.contains("://") {
+            Err(external_error(format!("unsupported signer URL {}", url)))
+        } else if url.is_empty() {
+            Err(external_error("empty signer socket path"))
+        } else {
+            Ok(Endpoint::Ipc(url.to_string()))
+        }
+    }
+}
+
+fn external_error<E: ToString>(e: E) -> SignError {
+    SignError::External(e.to_string())
+}
+
+/// Signer in a separate process, reached with JSON-RPC over HTTP or a Unix
+/// socket.
+pub struct ExternalSigner {
+    endpoint: Endpoint,
+    next_id: AtomicUsize,
+}
+
+impl ExternalSigner {
+    /// Creates the signer at `url`, either an `http://` URL or the path of a
+    /// Unix socket.
+    pub fn new(url: &str) -> Result<Self, SignError> {
+        Ok(ExternalSigner {
+            endpoint: Endpoint::parse(url)?,
+            next_id: AtomicUsize::new(1),
+        })
+    }
+
+    /// Sends the JSON-RPC request, and returns the result of the response.
+    fn call(&self, method: &str, params: Value) -> Result<Value, SignError> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        })
+        .to_string();
+
+        let mut response: Value = match self.endpoint {
+            Endpoint::Http { ref host, ref path } => {
+                let body = http_post(host, path, &request)?;
+                serde_json::from_slice(&body).map_err(external_error)?
+            }
+            Endpoint::Ipc(ref path) => ipc_call(path, &request)?,
+        };
+
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            return Err(external_error(format!(
+                "{} failed: {}",
+                method, message
+            )));
+        }
+        match response.get_mut("result") {
+            Some(result) => Ok(result.take()),
+            None => Err(external_error(format!(
+                "no result in response to {}",
+                method
+            ))),
+        }
+    }
+}
+
+impl Signer for ExternalSigner {
+    fn accounts(&self) -> Result<Vec<Address>, SignError> {
+        let result = self.call("account_list", json!([]))?;
+        let addresses = match result.as_array() {
+            Some(addresses) => addresses,
+            None => {
+                return Err(external_error(format!(
+                    "invalid account list {}",
+                    result
+                )))
+            }
+        };
+        addresses
+            .iter()
+            .map(|address| match address.as_str().map(parse_hex) {
+                Some(Ok(ref bytes)) if bytes.len() == 20 => {
+                    Ok(Address::from_slice(bytes))
+                }
+                _ => {
+                    Err(external_error(format!("invalid address {}", address)))
+                }
+            })
+            .collect()
+    }
+
+    fn sign(
+        &self, address: Address, _password: Option<Password>, message: Message,
+    ) -> Result<Signature, SignError> {
+        let result = self.call(
+            "account_signHash",
+            json!([format!("{:?}", address), format!("{:?}", message)]),
+        )?;
+        let mut signature = match result.as_str().map(parse_hex) {
+            Some(Ok(ref bytes)) if bytes.len() == 65 => {
+                let mut signature = [0u8; 65];
+                signature.copy_from_slice(bytes);
+                signature
+            }
+            _ => {
+                return Err(external_error(format!(
+                    "invalid signature {}",
+                    result
+                )))
+            }
+        };
+        if signature[64] >= 27 {
+            signature[64] -= 27;
+        }
+        if signature[64] > 1 {
+            return Err(external_error(format!(
+                "invalid signature {}",
+                result
+            )));
+        }
+
+        let signature = Signature::from(signature);
+        match verify_address(&address, &signature, &message) {
+            Ok(true) => Ok(signature),
+            _ => Err(external_error(format!(
+                "signature {} is not signed by {:?}",
+                result, address
+            ))),
+        }
+    }
+}
+
+fn parse_hex(s: &str) -> Result<Vec<u8>, SignError> {
+    let s = if s.starts_with("0x") { &s[2..] } else { s };
+    s.from_hex().map_err(external_error)
+}
+
+/// Posts the request to `http://<host><path>`, and returns the body of the
+/// response.
+fn http_post(host: &str, path: &str, body: &str) -> Result<Vec<u8>, SignError> {
+    let mut stream = TcpStream::connect(host).map_err(external_error)?;
+    stream
+        .set_read_timeout(Some(SIGNER_TIMEOUT))
+        .map_err(external_error)?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        host,
+        body.len(),
+        body
+    )
+    .map_err(external_error)?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).map_err(external_error)?;
+
+    let header_end = match response.windows(4).position(|w| w == b"\r\n\r\n")
+    {
+        Some(pos) => pos,
+        None => return Err(external_error("invalid HTTP response")),
+    };
+    let header = String::from_utf8_lossy(&response[..header_end]);
+    let status = header.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(external_error(format!("HTTP status {}", status)));
+    }
+    if header
+        .to_ascii_lowercase()
+        .contains("transfer-encoding: chunked")
+    {
+        return Err(external_error("chunked HTTP response is not supported"));
+    }
+    Ok(response.split_off(header_end + 4))
+}
+
+/// Sends the request over the Unix socket at `path`, and reads a JSON
+/// response.
+#[cfg(unix)]
+fn ipc_call(path: &str, request: &str) -> Result<Value, SignError> {
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(path).map_err(external_error)?;
+    stream
+        .set_read_timeout(Some(SIGNER_TIMEOUT))
+        .map_err(external_error)?;
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|_| stream.write_all(b"\n"))
+        .map_err(external_error)?;
+
+    match serde_json::Deserializer::from_reader(stream)
+        .into_iter::<Value>()
+        .next()
+    {
+        Some(response) => response.map_err(external_error),
+        None => Err(external_error("connection closed by signer")),
+    }
+}
+
+#[cfg(not(unix))]
+fn ipc_call(_path: &str, _request: &str) -> Result<Value, SignError> {
+    Err(external_error("Unix socket signer is not supported"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cfxkey::{sign, Generator, KeyPair, Random};
+    use ethereum_types::H256;
+    use rustc_hex::ToHex;
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        thread,
+    };
+
+    /// Response of a signer with the single key to the request. `v` of the
+    /// signatures is increased by `v_offset`.
+    fn respond(key: &KeyPair, v_offset: u8, request: &Value) -> Value {
+        let result = match request["method"].as_str().unwrap() {
+            "account_list" => json!([format!("{:?}", key.address())]),
+            "account_signHash" => {
+                let hash: H256 = request["params"][1]
+                    .as_str()
+                    .unwrap()
+                    .trim_start_matches("0x")
+                    .parse()
+                    .unwrap();
+                let mut sig: [u8; 65] =
+                    sign(key.secret(), &hash).unwrap().into();
+                sig[64] += v_offset;
+                json!(format!("0x{}", sig[..].to_hex()))
+            }
+            _ => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": {"code": -32601, "message": "Method not found"},
+                })
+            }
+        };
+        json!({"jsonrpc": "2.0", "id": request["id"], "result": result})
+    }
+
+    /// Serves `count` HTTP requests, and returns the URL of the signer.
+    fn serve_http(key: KeyPair, v_offset: u8, count: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().take(count) {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    let line = line.to_ascii_lowercase();
+                    if line.starts_with("content-length:") {
+                        length = line[15..].trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0u8; length];
+                reader.read_exact(&mut body).unwrap();
+
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                let response = respond(&key, v_offset, &request).to_string();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                    response.len(),
+                    response
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn parse_endpoint() {
+        assert_eq!(
+            Endpoint::parse("http://127.0.0.1:8550").unwrap(),
+            Endpoint::Http {
+                host: "127.0.0.1:8550".into(),
+                path: "/".into()
+            }
+        );
+        assert_eq!(
+            Endpoint::parse("http://localhost:8550/rpc").unwrap(),
+            Endpoint::Http {
+                host: "localhost:8550".into(),
+                path: "/rpc".into()
+            }
+        );
+        assert_eq!(
+            Endpoint::parse("/tmp/clef.ipc").unwrap(),
+            Endpoint::Ipc("/tmp/clef.ipc".into())
+        );
+        assert!(Endpoint::parse("https://127.0.0.1:8550").is_err());
+        assert!(Endpoint::parse("http:///rpc").is_err());
+        assert!(Endpoint::parse("").is_err());
+    }
+
+    #[test]
+    fn http_signer() {
+        let key = Random.generate().unwrap();
+        let signer =
+            ExternalSigner::new(&serve_http(key.clone(), 0, 2)).unwrap();
+
+        assert_eq!(signer.accounts().unwrap(), vec![key.address()]);
+        let message = Message::random();
+        let signature = signer.sign(key.address(), None, message).unwrap();
+        assert!(verify_address(&key.address(), &signature, &message).unwrap());
+    }
+
+    #[test]
+    fn http_signer_ethereum_v() {
+        let key = Random.generate().unwrap();
+        let signer =
+            ExternalSigner::new(&serve_http(key.clone(), 27, 1)).unwrap();
+
+        let message = Message::random();
+        let signature = signer.sign(key.address(), None, message).unwrap();
+        assert!(signature.v() <= 1);
+        assert!(verify_address(&key.address(), &signature, &message).unwrap());
+    }
+
+    #[test]
+    fn http_signer_wrong_account() {
+        let key = Random.generate().unwrap();
+        let other = Random.generate().unwrap();
+        let signer = ExternalSigner::new(&serve_http(other, 0, 1)).unwrap();
+        match signer.sign(key.address(), None, Message::random()) {
+            Err(SignError::External(e)) => {
+                assert!(e.contains("is not signed by"))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn http_signer_error() {
+        let key = Random.generate().unwrap();
+        let signer = ExternalSigner::new(&serve_http(key, 0, 1)).unwrap();
+        match signer.call("account_unknown", json!([])) {
+            Err(SignError::External(e)) => {
+                assert!(e.contains("Method not found"))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ipc_signer() {
+        use std::os::unix::net::UnixListener;
+        use tempdir::TempDir;
+
+        let key = Random.generate().unwrap();
+        let dir = TempDir::new("signer").unwrap();
+        let path = dir.path().join("signer.ipc");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server_key = key.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let request: Value = serde_json::from_str(&line).unwrap();
+                let response = respond(&server_key, 0, &request).to_string();
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let signer = ExternalSigner::new(path.to_str().unwrap()).unwrap();
+        assert_eq!(signer.accounts().unwrap(), vec![key.address()]);
+        let message = Message::random();
+        let signature = signer.sign(key.address(), None, message).unwrap();
+        assert!(verify_address(&key.address(), &signature, &message).unwrap());
+    }
+}
@@ -0,0 +1,550 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Typed structured data hashing for signing, as specified by EIP-712.
+//! https://eips.ethereum.org/EIPS/eip-712
+//!
+//! The message to sign is `keccak256("\x19\x01" ‖ domainSeparator ‖
+//! hashStruct(message))`, where the domain separator is the struct hash of
+//! the `EIP712Domain` of the data. The `chainId` of the domain is the id of
+//! the Conflux chain to sign for.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    str::FromStr,
+};
+
+use cfxkey::Address;
+use ethereum_types::{H256, U256};
+use keccak_hash::keccak;
+use rustc_hex::FromHex;
+use serde_derive::Deserialize;
+use serde_json::Value;
+
+/// Name of the type of the signing domain.
+pub const DOMAIN_TYPE: &str = "EIP712Domain";
+
+/// Typed data error.
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The data is not valid JSON of typed data.
+    InvalidJson(String),
+    /// Type referenced but not defined.
+    UnknownType(String),
+    /// Malformed type.
+    InvalidType(String),
+    /// Struct value without the field.
+    MissingField(String, String),
+    /// Value not of the type.
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::InvalidJson(ref e) => write!(f, "Invalid typed data: {}", e),
+            Error::UnknownType(ref t) => write!(f, "Unknown type {}", t),
+            Error::InvalidType(ref t) => write!(f, "Invalid type {}", t),
+            Error::MissingField(ref t, ref field) => {
+                write!(f, "Missing field {} of {}", field, t)
+            }
+            Error::InvalidValue(ref t, ref value) => {
+                write!(f, "Invalid value {} of type {}", value, t)
+            }
+        }
+    }
+}
+
+/// Member of a struct type.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Field {
+    /// Name of the member.
+    pub name: String,
+    /// Type of the member, e.g. `uint256`, `Person` or `Person[]`.
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+/// Typed structured data to sign.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+    /// Struct types by name, including the `EIP712Domain`.
+    pub types: BTreeMap<String, Vec<Field>>,
+    /// Type of the message.
+    pub primary_type: String,
+    /// Signing domain.
+    pub domain: Value,
+    /// Message to sign.
+    pub message: Value,
+}
+
+impl FromStr for TypedData {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s).map_err(|e| Error::InvalidJson(e.to_string()))
+    }
+}
+
+impl TypedData {
+    /// Parse typed data from a JSON value, or a string of JSON.
+    pub fn from_json(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::String(s) => s.parse(),
+            value => serde_json::from_value(value)
+                .map_err(|e| Error::InvalidJson(e.to_string())),
+        }
+    }
+
+    /// Chain id of the signing domain, if set. A `chainId` which is not a
+    /// field of the domain type is ignored, since it is not signed.
+    pub fn chain_id(&self) -> Result<Option<U256>, Error> {
+        let declared = self.types.get(DOMAIN_TYPE).map_or(false, |fields| {
+            fields.iter().any(|field| field.name == "chainId")
+        });
+        if !declared {
+            return Ok(None);
+        }
+        match self.domain.get("chainId") {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => encode_uint("uint256", 256, value).map(Some),
+        }
+    }
+
+    /// Struct hash of the signing domain.
+    pub fn domain_separator(&self) -> Result<H256, Error> {
+        self.hash_struct(DOMAIN_TYPE, &self.domain)
+    }
+
+    /// Hash of the data to sign.
+    pub fn sign_hash(&self) -> Result<H256, Error> {
+        let mut data = vec![0x19, 0x01];
+        data.extend_from_slice(self.domain_separator()?.as_bytes());
+        data.extend_from_slice(
+            self.hash_struct(&self.primary_type, &self.message)?
+                .as_bytes(),
+        );
+        Ok(keccak(data))
+    }
+
+    /// Encoding of a struct type, followed by the types it references in
+    /// alphabetical order, e.g. `Mail(Person from,Person to,string
+    /// contents)Person(string name,address wallet)`.
+    pub fn encode_type(&self, name: &str) -> Result<String, Error> {
+        let mut deps = BTreeSet::new();
+        self.find_dependencies(name, &mut deps)?;
+        deps.remove(name);
+
+        let mut encoded = String::new();
+        for dep in Some(name).into_iter().chain(deps.iter().map(|d| &d[..]))
+        {
+            let fields: Vec<String> = self.types[dep]
+                .iter()
+                .map(|field| format!("{} {}", field.type_, field.name))
+                .collect();
+            encoded.push_str(&format!("{}({})", dep, fields.join(",")));
+        }
+        Ok(encoded)
+    }
+
+    /// Hash of the encoding of a struct type.
+    pub fn type_hash(&self, name: &str) -> Result<H256, Error> {
+        Ok(keccak(self.encode_type(name)?))
+    }
+
+    /// Hash of a struct value, i.e. `keccak256(typeHash ‖ encodeData)`.
+    pub fn hash_struct(
+        &self, name: &str, value: &Value,
+    ) -> Result<H256, Error> {
+        let fields = self
+            .types
+            .get(name)
+            .ok_or_else(|| Error::UnknownType(name.into()))?;
+        let object = value.as_object().ok_or_else(|| {
+            Error::InvalidValue(name.into(), value.to_string())
+        })?;
+
+        let mut data = self.type_hash(name)?.as_bytes().to_vec();
+        for field in fields {
+            let value = object.get(&field.name).ok_or_else(|| {
+                Error::MissingField(name.into(), field.name.clone())
+            })?;
+            data.extend_from_slice(
+                self.encode_value(&field.type_, value)?.as_bytes(),
+            );
+        }
+        Ok(keccak(data))
+    }
+
+    fn find_dependencies(
+        &self, name: &str, deps: &mut BTreeSet<String>,
+    ) -> Result<(), Error> {
+        if deps.contains(name) {
+            return Ok(());
+        }
+        let fields = self
+            .types
+            .get(name)
+            .ok_or_else(|| Error::UnknownType(name.into()))?;
+        deps.insert(name.into());
+
+        for field in fields {
+            let base = base_type(&field.type_);
+            if self.types.contains_key(base) {
+                self.find_dependencies(base, deps)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode a value as a 32 byte word. Dynamic values, arrays and structs
+    /// are encoded as their hashes.
+    fn encode_value(&self, type_: &str, value: &Value) -> Result<H256, Error> {
+        let invalid = || Error::InvalidValue(type_.into(), value.to_string());
+
+        if type_.ends_with(']') {
+            let open = type_.rfind('[').ok_or_else(|| invalid_type(type_))?;
+            let (item_type, size) =
+                (&type_[..open], &type_[open + 1..type_.len() - 1]);
+            let items = value.as_array().ok_or_else(invalid)?;
+            if !size.is_empty() {
+                let size: usize =
+                    size.parse().map_err(|_| invalid_type(type_))?;
+                if items.len() != size {
+                    return Err(invalid());
+                }
+            }
+
+            let mut data = Vec::with_capacity(items.len() * 32);
+            for item in items {
+                data.extend_from_slice(
+                    self.encode_value(item_type, item)?.as_bytes(),
+                );
+            }
+            return Ok(keccak(data));
+        }
+
+        if self.types.contains_key(type_) {
+            return self.hash_struct(type_, value);
+        }
+
+        match type_ {
+            "string" => Ok(keccak(value.as_str().ok_or_else(invalid)?)),
+            "bytes" => Ok(keccak(decode_hex(type_, value)?)),
+            "bool" => Ok(H256::from_low_u64_be(
+                value.as_bool().ok_or_else(invalid)? as u64,
+            )),
+            "address" => {
+                let address: Address = value
+                    .as_str()
+                    .and_then(|s| strip_hex_prefix(s).parse().ok())
+                    .ok_or_else(invalid)?;
+                Ok(address.into())
+            }
+            _ if type_.starts_with("bytes") => {
+                let size = bits_of(type_, "bytes", 1, 32)?;
+                let bytes = decode_hex(type_, value)?;
+                if bytes.len() > size {
+                    return Err(invalid());
+                }
+                let mut word = H256::zero();
+                word[..bytes.len()].copy_from_slice(&bytes);
+                Ok(word)
+            }
+            _ if type_.starts_with("uint") => {
+                let bits = bits_of(type_, "uint", 8, 256)?;
+                Ok(to_word(encode_uint(type_, bits, value)?))
+            }
+            _ if type_.starts_with("int") => {
+                let bits = bits_of(type_, "int", 8, 256)?;
+                Ok(to_word(encode_int(type_, bits, value)?))
+            }
+            _ => Err(Error::UnknownType(type_.into())),
+        }
+    }
+}
+
+fn invalid_type(type_: &str) -> Error { Error::InvalidType(type_.into()) }
+
+/// Type without the array dimensions, e.g. `Person` of `Person[][2]`.
+fn base_type(type_: &str) -> &str {
+    match type_.find('[') {
+        Some(pos) => &type_[..pos],
+        None => type_,
+    }
+}
+
+fn strip_hex_prefix(s: &str) -> &str {
+    if s.starts_with("0x") || s.starts_with("0X") {
+        &s[2..]
+    } else {
+        s
+    }
+}
+
+/// Size of `bytesN`, or bits of `uintN` and `intN`, which must be a
+/// multiple of `min` up to `max`.
+fn bits_of(
+    type_: &str, prefix: &str, min: usize, max: usize,
+) -> Result<usize, Error> {
+    match type_[prefix.len()..].parse::<usize>() {
+        Ok(size) if size >= min && size <= max && size % min == 0 => Ok(size),
+        _ => Err(invalid_type(type_)),
+    }
+}
+
+fn decode_hex(type_: &str, value: &Value) -> Result<Vec<u8>, Error> {
+    value
+        .as_str()
+        .and_then(|s| strip_hex_prefix(s).from_hex().ok())
+        .ok_or_else(|| Error::InvalidValue(type_.into(), value.to_string()))
+}
+
+fn to_word(value: U256) -> H256 {
+    let mut word = H256::zero();
+    value.to_big_endian(word.as_bytes_mut());
+    word
+}
+
+/// Parse an unsigned integer of a number, a decimal string or a hex string.
+fn parse_uint(type_: &str, value: &Value) -> Result<U256, Error> {
+    let invalid = || Error::InvalidValue(type_.into(), value.to_string());
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from).ok_or_else(invalid),
+        Value::String(s) if s.starts_with("0x") || s.starts_with("0X") => {
+            U256::from_str(&s[2..]).map_err(|_| invalid())
+        }
+        Value::String(s) => U256::from_dec_str(s).map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+fn encode_uint(type_: &str, bits: usize, value: &Value) -> Result<U256, Error> {
+    let n = parse_uint(type_, value)?;
+    if bits < 256 && n >> bits != U256::zero() {
+        return Err(Error::InvalidValue(type_.into(), value.to_string()));
+    }
+    Ok(n)
+}
+
+/// Encode a signed integer in two's complement.
+fn encode_int(type_: &str, bits: usize, value: &Value) -> Result<U256, Error> {
+    let invalid = || Error::InvalidValue(type_.into(), value.to_string());
+    let (negative, magnitude) = match value {
+        Value::Number(n) => match n.as_i64() {
+            Some(i) if i < 0 => (true, U256::from(i.wrapping_neg() as u64)),
+            Some(i) => (false, U256::from(i as u64)),
+            None => return Err(invalid()),
+        },
+        Value::String(s) if s.starts_with('-') => {
+            (true, parse_uint(type_, &Value::String(s[1..].into()))?)
+        }
+        _ => (false, parse_uint(type_, value)?),
+    };
+
+    // -2^(bits-1) <= value < 2^(bits-1)
+    let limit = U256::one() << (bits - 1);
+    if magnitude > limit || (!negative && magnitude == limit) {
+        return Err(invalid());
+    }
+    if negative {
+        Ok((!magnitude).overflowing_add(U256::one()).0)
+    } else {
+        Ok(magnitude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, TypedData, DOMAIN_TYPE};
+    use ethereum_types::{H256, U256};
+    use serde_json::json;
+    use std::str::FromStr;
+
+    // the example of EIP-712
+    const MAIL: &str = r#"{
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" }
+            ],
+            "Person": [
+                { "name": "name", "type": "string" },
+                { "name": "wallet", "type": "address" }
+            ],
+            "Mail": [
+                { "name": "from", "type": "Person" },
+                { "name": "to", "type": "Person" },
+                { "name": "contents", "type": "string" }
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "name": "Ether Mail",
+            "version": "1",
+            "chainId": 1,
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+        },
+        "message": {
+            "from": {
+                "name": "Cow",
+                "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"
+            },
+            "to": {
+                "name": "Bob",
+                "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"
+            },
+            "contents": "Hello, Bob!"
+        }
+    }"#;
+
+    fn h256(s: &str) -> H256 { H256::from_str(s).unwrap() }
+
+    #[test]
+    fn test_eip712_example() {
+        let data = TypedData::from_str(MAIL).unwrap();
+        assert_eq!(
+            data.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            data.type_hash("Mail").unwrap(),
+            h256("a0cedeb2dc280ba39b857546d74f5549c3a1d7bdc2dd96bf881f76108e23dac2")
+        );
+        assert_eq!(
+            data.hash_struct("Mail", &data.message).unwrap(),
+            h256("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e")
+        );
+        assert_eq!(
+            data.domain_separator().unwrap(),
+            h256("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+        assert_eq!(
+            data.sign_hash().unwrap(),
+            h256("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+        assert_eq!(data.chain_id().unwrap(), Some(U256::from(1)));
+
+        // also accepted as a string of JSON
+        let value = serde_json::Value::String(MAIL.into());
+        assert_eq!(TypedData::from_json(value).unwrap(), data);
+
+        // the chain id is not signed without the field in the domain type
+        let mut data = data;
+        data.types
+            .get_mut(DOMAIN_TYPE)
+            .unwrap()
+            .retain(|field| field.name != "chainId");
+        assert_eq!(data.chain_id().unwrap(), None);
+    }
+
+    #[test]
+    fn test_encode_values() {
+        let mut data = TypedData::from_str(MAIL).unwrap();
+        let encode = |data: &TypedData, type_: &str, value| {
+            data.encode_value(type_, &value)
+        };
+
+        assert_eq!(
+            encode(&data, "uint8", json!(255)).unwrap(),
+            H256::from_low_u64_be(255)
+        );
+        assert_eq!(
+            encode(&data, "uint256", json!("0x100")).unwrap(),
+            encode(&data, "uint256", json!("256")).unwrap()
+        );
+        assert!(encode(&data, "uint8", json!(256)).is_err());
+        assert!(encode(&data, "uint7", json!(1)).is_err());
+        assert_eq!(
+            encode(&data, "int8", json!(-1)).unwrap(),
+            H256::repeat_byte(0xff)
+        );
+        assert_eq!(
+            encode(&data, "int16", json!("-128")).unwrap(),
+            encode(&data, "int256", json!(-128)).unwrap()
+        );
+        assert!(encode(&data, "int8", json!(128)).is_err());
+        assert!(encode(&data, "int8", json!(-128)).is_ok());
+        assert!(encode(&data, "int8", json!(-129)).is_err());
+
+        let mut word = H256::zero();
+        word[..2].copy_from_slice(&[0x12, 0x34]);
+        assert_eq!(encode(&data, "bytes2", json!("0x1234")).unwrap(), word);
+        assert!(encode(&data, "bytes1", json!("0x1234")).is_err());
+        assert_eq!(
+            encode(&data, "bool", json!(true)).unwrap(),
+            H256::from_low_u64_be(1)
+        );
+        assert!(encode(&data, "address", json!("0x12")).is_err());
+        assert_eq!(
+            encode(&data, "uint", json!(1)),
+            Err(Error::InvalidType("uint".into()))
+        );
+        assert_eq!(
+            encode(&data, "Unknown", json!({})),
+            Err(Error::UnknownType("Unknown".into()))
+        );
+
+        // fixed size arrays
+        assert!(encode(&data, "uint8[2]", json!([1, 2])).is_ok());
+        assert!(encode(&data, "uint8[2]", json!([1])).is_err());
+
+        // missing field of a struct
+        data.message.as_object_mut().unwrap().remove("contents");
+        assert_eq!(
+            data.sign_hash(),
+            Err(Error::MissingField("Mail".into(), "contents".into()))
+        );
+    }
+
+    #[test]
+    fn test_arrays() {
+        let data: TypedData = serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "chainId", "type": "uint256" }
+                ],
+                "Group": [
+                    { "name": "members", "type": "Person[]" },
+                    { "name": "tags", "type": "string[]" }
+                ],
+                "Person": [
+                    { "name": "name", "type": "string" },
+                    { "name": "wallets", "type": "address[]" }
+                ]
+            },
+            "primaryType": "Group",
+            "domain": { "name": "Group", "chainId": "1029" },
+            "message": {
+                "members": [
+                    {
+                        "name": "Cow",
+                        "wallets": [
+                            "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826",
+                            "0xDeaDbeefdEAdbeefdEadbEEFdeadbeEFdEaDbeeF"
+                        ]
+                    },
+                    { "name": "Bob", "wallets": [] }
+                ],
+                "tags": ["a", "b"]
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            data.encode_type("Group").unwrap(),
+            "Group(Person[] members,string[] tags)Person(string name,address[] wallets)"
+        );
+        assert_eq!(data.chain_id().unwrap(), Some(U256::from(1029)));
+        assert_eq!(
+            data.sign_hash().unwrap(),
+            h256("ea7f8b9a4ab50c3311f6340e1c208e0e12e7b467bb8bf548b73c3270046e8485")
+        );
+    }
+}
@@ -3,7 +3,9 @@
 // See http://www.gnu.org/licenses/
 
 use app_dirs::{get_app_root, AppDataType, AppInfo};
-use cfxcore_accounts::{AccountProvider, AccountProviderSettings};
+use cfxcore_accounts::{
+    AccountProvider, AccountProviderSettings, ExternalSigner,
+};
 use cfxstore::{accounts_dir::RootDiskDirectory, CfxStore};
 use dir::helpers::replace_home;
 use std::path::PathBuf;
@@ -23,6 +25,22 @@ pub fn account_provider(
     ))
 }
 
+/// The account provider of the node, which signs with the external signer
+/// at `external_signer` if set.
+pub fn node_account_provider(
+    external_signer: Option<&str>,
+) -> Result<AccountProvider, String> {
+    let accounts = account_provider(Some(keys_path()), None)?;
+    match external_signer {
+        Some(url) => {
+            let signer = ExternalSigner::new(url)
+                .map_err(|e| format!("Invalid external signer: {}", e))?;
+            Ok(accounts.with_signer(Box::new(signer)))
+        }
+        None => Ok(accounts),
+    }
+}
+
 pub fn keys_dir(path: String) -> Result<RootDiskDirectory, String> {
     let path = PathBuf::from(&path);
     RootDiskDirectory::create(path)
@@ -117,71 +117,31 @@ pub mod client_methods {
     };
 }
 
-pub fn initialize_common_modules(
-    conf: &Configuration, exit: Arc<(Mutex<bool>, Condvar)>, is_full_node: bool,
-) -> Result<
-    (
-        Arc<Machine>,
-        Arc<SecretStore>,
-        Arc<BlockDataManager>,
-        Arc<TransactionPool>,
-        Arc<ConsensusGraph>,
-        Arc<SynchronizationGraph>,
-        Arc<NetworkService>,
-        Arc<CommonRpcImpl>,
-        PubSubClient,
-        Runtime,
-    ),
-    String,
-> {
-    info!("Working directory: {:?}", std::env::current_dir());
-
-    metrics::initialize(conf.metrics_config());
-
+/// Open the block database and the state storage in the data directory of
+/// `conf`. This is also used by the subcommands which work on the data of a
+/// stopped node.
+pub fn initialize_data_manager(
+    conf: &Configuration, secret_store: &SecretStore, is_full_node: bool,
+) -> Result<Arc<BlockDataManager>, String> {
     let worker_thread_pool = Arc::new(Mutex::new(ThreadPool::with_name(
         "Tx Recover".into(),
         WORKER_COMPUTATION_PARALLELISM,
     )));
 
-    let network_config = conf.net_config()?;
-    let cache_config = conf.cache_config();
-
-    let db_config = conf.db_config();
-    let ledger_db =
-        db::open_database(conf.raw_conf.block_db_dir.as_str(), &db_config)
-            .map_err(|e| format!("Failed to open database {:?}", e))?;
+    let ledger_db = db::open_database(
+        conf.raw_conf.block_db_dir.as_str(),
+        &conf.db_config(),
+    )
+    .map_err(|e| format!("Failed to open database {:?}", e))?;
 
-    let secret_store = Arc::new(SecretStore::new());
     let storage_manager = Arc::new(
-        StorageManager::new(conf.storage_config())
-            .expect("Failed to initialize storage."),
+        StorageManager::new(conf.storage_config(is_full_node))
+            .map_err(|e| format!("Failed to initialize storage {:?}", e))?,
     );
-    {
-        let storage_manager_log_weak_ptr = Arc::downgrade(&storage_manager);
-        let exit_clone = exit.clone();
-        thread::spawn(move || loop {
-            let mut exit_lock = exit_clone.0.lock();
-            if exit_clone
-                .1
-                .wait_for(&mut exit_lock, Duration::from_millis(5000))
-                .timed_out()
-            {
-                let manager = storage_manager_log_weak_ptr.upgrade();
-                match manager {
-                    None => return,
-                    Some(manager) => manager.log_usage(),
-                };
-            } else {
-                return;
-            }
-        });
-    }
 
     let genesis_accounts = if conf.is_test_or_dev_mode() {
         match conf.raw_conf.genesis_secrets {
-            Some(ref file) => {
-                genesis::load_secrets_file(file, secret_store.as_ref())?
-            }
+            Some(ref file) => genesis::load_secrets_file(file, secret_store)?,
             None => genesis::default(conf.is_test_or_dev_mode()),
         }
     } else {
@@ -199,35 +159,55 @@ pub fn initialize_common_modules(
     );
     debug!("Initialize genesis_block={:?}", genesis_block);
 
-    let data_man = Arc::new(BlockDataManager::new(
-        cache_config,
+    Ok(Arc::new(BlockDataManager::new(
+        conf.cache_config(),
         Arc::new(genesis_block),
-        ledger_db.clone(),
+        ledger_db,
         storage_manager,
         worker_thread_pool,
         conf.data_mananger_config(),
-    ));
+    )))
+}
 
+/// Build the transaction pool, the consensus graph and the synchronization
+/// graph on top of `data_man`. The transaction pool journal is only used if
+/// `use_tx_pool_journal`, which is false for the offline tools, so that they
+/// do not replay or rewrite the journal of the node.
+pub fn initialize_synchronization_graph(
+    conf: &Configuration, data_man: Arc<BlockDataManager>,
+    notifications: Arc<Notifications>, is_full_node: bool,
+    use_tx_pool_journal: bool,
+) -> (
+    Arc<Machine>,
+    Arc<TransactionPool>,
+    Arc<ConsensusGraph>,
+    Arc<SynchronizationGraph>,
+)
+{
     let machine = Arc::new(new_machine_with_builtin());
 
+    let mut txpool_config = conf.txpool_config();
+    if !use_tx_pool_journal {
+        txpool_config.journal_path = None;
+    }
     let txpool = Arc::new(TransactionPool::new(
-        conf.txpool_config(),
+        txpool_config,
         conf.verification_config(),
         data_man.clone(),
         machine.clone(),
+        notifications.clone(),
     ));
 
     let statistics = Arc::new(Statistics::new());
     let vm = VmFactory::new(1024 * 32);
     let pow_config = conf.pow_config();
-    let notifications = Notifications::init();
 
     let consensus = Arc::new(ConsensusGraph::new(
         conf.consensus_config(),
         vm,
         txpool.clone(),
         statistics,
-        data_man.clone(),
+        data_man,
         pow_config.clone(),
         notifications.clone(),
         conf.execution_config(),
@@ -243,10 +223,113 @@ pub fn initialize_common_modules(
         verification_config,
         pow_config,
         sync_config,
-        notifications.clone(),
+        notifications,
         is_full_node,
         machine.clone(),
     ));
+    (machine, txpool, consensus, sync_graph)
+}
+
+/// Interval to check if the transaction pool journal needs rotation.
+const TX_POOL_JOURNAL_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+pub fn initialize_common_modules(
+    conf: &Configuration, exit: Arc<(Mutex<bool>, Condvar)>, is_full_node: bool,
+) -> Result<
+    (
+        Arc<Machine>,
+        Arc<SecretStore>,
+        Arc<BlockDataManager>,
+        Arc<TransactionPool>,
+        Arc<ConsensusGraph>,
+        Arc<SynchronizationGraph>,
+        Arc<NetworkService>,
+        Arc<CommonRpcImpl>,
+        PubSubClient,
+        Runtime,
+    ),
+    String,
+> {
+    info!("Working directory: {:?}", std::env::current_dir());
+
+    metrics::initialize(conf.metrics_config());
+
+    let network_config = conf.net_config()?;
+
+    let secret_store = Arc::new(SecretStore::new());
+    let data_man = initialize_data_manager(conf, &secret_store, is_full_node)?;
+    {
+        let storage_manager_log_weak_ptr =
+            Arc::downgrade(&data_man.storage_manager);
+        let exit_clone = exit.clone();
+        thread::spawn(move || loop {
+            let mut exit_lock = exit_clone.0.lock();
+            if exit_clone
+                .1
+                .wait_for(&mut exit_lock, Duration::from_millis(5000))
+                .timed_out()
+            {
+                let manager = storage_manager_log_weak_ptr.upgrade();
+                match manager {
+                    None => return,
+                    Some(manager) => manager.log_usage(),
+                };
+            } else {
+                return;
+            }
+        });
+    }
+
+    if conf.raw_conf.rebuild_log_bloom_index
+        && !data_man.log_bloom_index_rebuilt()
+    {
+        // Full nodes only keep the execution results since the checkpoint.
+        let from_epoch = if is_full_node {
+            data_man
+                .block_height_by_hash(
+                    &data_man.get_cur_consensus_era_genesis_hash(),
+                )
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        info!("Rebuilding the log bloom index from epoch {}", from_epoch);
+        let epoch_count = data_man.rebuild_log_bloom_index(from_epoch);
+        info!("Indexed the log blooms of {} epochs", epoch_count);
+    }
+
+    let notifications = Notifications::init();
+    let (machine, txpool, consensus, sync_graph) =
+        initialize_synchronization_graph(
+            conf,
+            data_man.clone(),
+            notifications.clone(),
+            is_full_node,
+            true, /* use_tx_pool_journal */
+        );
+    {
+        let txpool_weak_ptr = Arc::downgrade(&txpool);
+        let exit_clone = exit.clone();
+        thread::Builder::new()
+            .name("Tx Pool Journal".into())
+            .spawn(move || loop {
+                let mut exit_lock = exit_clone.0.lock();
+                if exit_clone
+                    .1
+                    .wait_for(&mut exit_lock, TX_POOL_JOURNAL_CHECK_INTERVAL)
+                    .timed_out()
+                {
+                    drop(exit_lock);
+                    match txpool_weak_ptr.upgrade() {
+                        None => return,
+                        Some(txpool) => txpool.maybe_rotate_journal(),
+                    }
+                } else {
+                    return;
+                }
+            })
+            .expect("Tx pool journal thread spawn error");
+    }
 
     let network = {
         let mut network = NetworkService::new(network_config);
@@ -254,11 +337,15 @@ pub fn initialize_common_modules(
         Arc::new(network)
     };
 
+    let accounts = Arc::new(node_account_provider(
+        conf.raw_conf.external_signer.as_ref().map(String::as_str),
+    )?);
     let common_impl = Arc::new(CommonRpcImpl::new(
         exit,
         consensus.clone(),
         network.clone(),
         txpool.clone(),
+        accounts,
     ));
 
     let runtime = Runtime::with_default_thread_count();
@@ -426,6 +513,8 @@ pub fn initialize_not_light_node_modules(
         maybe_direct_txgen,
         conf.rpc_impl_config(),
         machine,
+        common_impl.accounts.clone(),
+        runtime.executor(),
     ));
 
     let debug_rpc_http_server = super::rpc::start_http(
@@ -655,6 +744,7 @@ pub mod delegate_convert {
 
 pub use crate::configuration::Configuration;
 use crate::{
+    accounts::node_account_provider,
     rpc::{
         extractor::RpcExtractor,
         impls::{
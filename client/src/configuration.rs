@@ -23,13 +23,16 @@ use cfxcore::{
     consensus_parameters::*,
     storage::{
         self, defaults::DEFAULT_DEBUG_SNAPSHOT_CHECKER_THREADS, storage_dir,
-        ConsensusParam, StorageConfiguration,
+        ConsensusParam, StateRetentionPolicy, StorageConfiguration,
     },
     sync::{ProtocolConfiguration, StateSyncConfiguration, SyncGraphConfig},
     sync_parameters::*,
-    transaction_pool::{TxPoolConfig, DEFAULT_MAX_TRANSACTION_GAS_LIMIT},
+    transaction_pool::{
+        TxPoolConfig, DEFAULT_MAX_TRANSACTION_GAS_LIMIT, TX_POOL_JOURNAL_FILE,
+    },
 };
 use metrics::MetricsConfiguration;
+use network::{dns_discovery::LinkEntry, ReputationConfig};
 use primitives::ChainIdParams;
 use rand::Rng;
 use std::convert::TryInto;
@@ -137,6 +140,7 @@ build_config! {
         (jsonrpc_http_port, (Option<u16>), None)
         (jsonrpc_cors, (Option<String>), None)
         (jsonrpc_http_keep_alive, (bool), false)
+        (jsonrpc_poll_lifetime_s, (u32), 60)
         // The network_id, if unset, defaults to the chain_id.
         // Only override the network_id for local experiments,
         // when user would like to keep the existing blockchain data
@@ -187,11 +191,21 @@ build_config! {
         (discovery_fast_refresh_timeout_ms, (u64), 10_000)
         (discovery_housekeeping_timeout_ms, (u64), 1_000)
         (discovery_round_timeout_ms, (u64), 500)
+        (dns_discovery_urls, (Option<String>), None)
         (enable_discovery, (bool), true)
+        (encrypt_p2p_sessions, (bool), false)
+        (compress_p2p_packets, (bool), true)
+        (require_p2p_session_encryption, (bool), false)
         (netconf_dir, (Option<String>), Some("./net_config".to_string()))
         (net_key, (Option<String>), None)
         (node_table_timeout_s, (u64), 300)
         (node_table_promotion_timeout_s, (u64), 3 * 24 * 3600)
+        (reputation_ban_duration_s, (u64), 24 * 3600)
+        (reputation_ban_threshold, (f64), -300.0)
+        (reputation_half_life_s, (u64), 600)
+        (reputation_throttle_threshold, (f64), -100.0)
+        (reserved_peers_file, (Option<String>), None)
+        (reserved_peers_only, (bool), false)
         (session_ip_limits, (String), "1,8,4,2".into())
         (subnet_quota, (usize), 32)
 
@@ -199,6 +213,9 @@ build_config! {
         (tx_cache_index_maintain_timeout_ms, (u64), 300_000)
         (tx_pool_size, (usize), 500_000)
         (tx_pool_min_tx_gas_price, (u64), 1)
+        (tx_pool_journal, (bool), true)
+        (tx_pool_journal_max_size_mb, (u64), 16)
+        (tx_pool_journal_rotate_interval_s, (u64), 3600)
         (tx_weight_scaling, (u64), 1)
         (tx_weight_exp, (u8), 1)
 
@@ -220,15 +237,20 @@ build_config! {
         (storage_delta_mpts_node_map_vec_size, (u32), storage::defaults::MAX_CACHED_TRIE_NODES_R_LFU_COUNTER)
         (storage_delta_mpts_slab_idle_size, (u32), storage::defaults::DEFAULT_DELTA_MPTS_SLAB_IDLE_SIZE)
         (storage_max_open_snapshots, (u16), storage::defaults::DEFAULT_MAX_OPEN_SNAPSHOTS)
+        (storage_archive_state_retention_epochs, (Option<u64>), None)
 
         // General/Unclassified section.
         (enable_optimistic_execution, (bool), true)
+        (external_signer, (Option<String>), None)
         (future_block_buffer_capacity, (usize), 32768)
         (get_logs_filter_max_limit, (Option<usize>), None)
         (get_logs_epoch_batch_size, (usize), 128)
         (max_trans_count_received_in_catch_up, (u64), 60_000)
+        (persist_block_traces, (bool), false)
+        (persist_log_bloom_index, (bool), false)
         (persist_tx_index, (bool), false)
         (print_memory_usage_period_s, (Option<u64>), None)
+        (rebuild_log_bloom_index, (bool), false)
         (target_block_gas_limit, (u64), DEFAULT_TARGET_BLOCK_GAS_LIMIT)
 
         // TreeGraph Section.
@@ -302,8 +324,21 @@ impl Configuration {
 
         network_config.is_consortium = self.raw_conf.is_consortium;
         network_config.discovery_enabled = self.raw_conf.enable_discovery;
+        network_config.encrypt_sessions = self.raw_conf.encrypt_p2p_sessions
+            || self.raw_conf.require_p2p_session_encryption;
+        network_config.require_session_encryption =
+            self.raw_conf.require_p2p_session_encryption;
+        network_config.compression_enabled = self.raw_conf.compress_p2p_packets;
         network_config.boot_nodes = to_bootnodes(&self.raw_conf.bootnodes)
             .map_err(|e| format!("failed to parse bootnodes: {}", e))?;
+        if let Some(ref urls) = self.raw_conf.dns_discovery_urls {
+            for url in urls.split(',').filter(|url| !url.is_empty()) {
+                url.parse::<LinkEntry>().map_err(|e| {
+                    format!("invalid DNS discovery URL {}: {}", url, e)
+                })?;
+                network_config.dns_discovery_urls.push(url.to_string());
+            }
+        }
         if self.raw_conf.netconf_dir.is_some() {
             network_config.config_path = self.raw_conf.netconf_dir.clone();
         }
@@ -339,6 +374,19 @@ impl Configuration {
             Duration::from_secs(self.raw_conf.node_table_promotion_timeout_s);
         network_config.test_mode = self.is_test_mode();
         network_config.subnet_quota = self.raw_conf.subnet_quota;
+        network_config.reserved_peers_file =
+            self.raw_conf.reserved_peers_file.clone();
+        network_config.reserved_only = self.raw_conf.reserved_peers_only;
+        network_config.reputation_config = ReputationConfig {
+            half_life: Duration::from_secs(
+                self.raw_conf.reputation_half_life_s,
+            ),
+            throttle_threshold: self.raw_conf.reputation_throttle_threshold,
+            ban_threshold: self.raw_conf.reputation_ban_threshold,
+            ban_duration: Duration::from_secs(
+                self.raw_conf.reputation_ban_duration_s,
+            ),
+        };
         network_config.session_ip_limit_config =
             self.raw_conf.session_ip_limits.clone().try_into().map_err(
                 |e| format!("failed to parse session ip limit config: {}", e),
@@ -492,8 +540,15 @@ impl Configuration {
         }
     }
 
-    pub fn storage_config(&self) -> StorageConfiguration {
+    pub fn storage_config(&self, is_full_node: bool) -> StorageConfiguration {
         let conflux_data_path = Path::new(&self.raw_conf.conflux_data_dir);
+        let state_retention =
+            match self.raw_conf.storage_archive_state_retention_epochs {
+                Some(epochs) if !is_full_node => {
+                    StateRetentionPolicy::Epochs(epochs)
+                }
+                _ => StateRetentionPolicy::Recent,
+            };
         StorageConfiguration {
             consensus_param: ConsensusParam {
                 snapshot_epoch_count: if self.is_test_mode() {
@@ -526,6 +581,7 @@ impl Configuration {
                 .join(&*storage_dir::SNAPSHOT_INFO_DB_PATH),
             path_storage_dir: conflux_data_path
                 .join(&*storage_dir::STORAGE_DIR),
+            state_retention,
         }
     }
 
@@ -629,6 +685,8 @@ impl Configuration {
     pub fn data_mananger_config(&self) -> DataManagerConfiguration {
         DataManagerConfiguration::new(
             self.raw_conf.persist_tx_index,
+            self.raw_conf.persist_block_traces,
+            self.raw_conf.persist_log_bloom_index,
             Duration::from_millis(
                 self.raw_conf.tx_cache_index_maintain_timeout_ms,
             ),
@@ -676,12 +734,27 @@ impl Configuration {
             tx_weight_scaling: self.raw_conf.tx_weight_scaling,
             tx_weight_exp: self.raw_conf.tx_weight_exp,
             target_block_gas_limit: self.raw_conf.target_block_gas_limit,
+            journal_path: if self.raw_conf.tx_pool_journal {
+                Some(
+                    Path::new(&self.raw_conf.conflux_data_dir)
+                        .join(TX_POOL_JOURNAL_FILE),
+                )
+            } else {
+                None
+            },
+            journal_max_size: self.raw_conf.tx_pool_journal_max_size_mb
+                * 1024
+                * 1024,
+            journal_rotate_interval: Duration::from_secs(
+                self.raw_conf.tx_pool_journal_rotate_interval_s,
+            ),
         }
     }
 
     pub fn rpc_impl_config(&self) -> RpcImplConfiguration {
         RpcImplConfiguration {
             get_logs_filter_max_limit: self.raw_conf.get_logs_filter_max_limit,
+            poll_lifetime_s: self.raw_conf.jsonrpc_poll_lifetime_s,
         }
     }
 
@@ -75,8 +75,11 @@ impl LightClient {
         ));
         light.register().unwrap();
 
-        let rpc_impl =
-            Arc::new(RpcImpl::new(conf.rpc_impl_config(), light.clone()));
+        let rpc_impl = Arc::new(RpcImpl::new(
+            conf.rpc_impl_config(),
+            light.clone(),
+            common_impl.accounts.clone(),
+        ));
         let debug_rpc_http_server = super::rpc::start_http(
             conf.local_http_config(),
             setup_debug_rpc_apis_light(
@@ -42,7 +42,7 @@ pub mod codes {
     /// by 1.
     ///
     /// Do not recycle deprecated error codes.
-    const NEXT_SERVER_ERROR_CODE: i64 = -32073;
+    const NEXT_SERVER_ERROR_CODE: i64 = -32077;
     /// When the above number is equal to -32100, take the number below on the
     /// right for new error code, then increase it by 1.
     const CFX_EXTRA_SERVER_ERROR_CODE: i64 = -31999;
@@ -61,6 +61,9 @@ pub mod codes {
     /// The node is not able to serve the request due to configuration. e.g. Not
     /// mining, light node, not archive node.
     pub const INCAPABLE: i64 = -32703;
+    /// The state of the requested epoch has been removed according to the
+    /// state retention policy of the node.
+    pub const STATE_PRUNED: i64 = -32073;
 
     /* Rpc usage related error codes */
     /// When there are too many rpc requests. We limit the number of allowed rpc
@@ -147,6 +150,25 @@ pub fn invalid_params<T: fmt::Debug>(param: &str, details: T) -> Error {
     }
 }
 
+pub fn filter_not_found() -> Error {
+    Error {
+        code: ErrorCode::InvalidParams,
+        message: "Filter not found".into(),
+        data: None,
+    }
+}
+
+pub fn state_pruned(epoch: u64, earliest_epoch: u64) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::STATE_PRUNED),
+        message: "State pruned".into(),
+        data: Some(Value::String(format!(
+            "The state of epoch {} is pruned, the earliest epoch with state is {}",
+            epoch, earliest_epoch
+        ))),
+    }
+}
+
 pub fn call_execution_error(message: String, output: Vec<u8>) -> Error {
     let output_bytes = Bytes::new(output);
     Error {
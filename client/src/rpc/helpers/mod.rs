@@ -1,5 +1,12 @@
+mod poll_filter;
 mod poll_manager;
 mod subscribers;
 //mod subscription_mananger;
 
-pub use self::subscribers::{Id as SubscriberId, Subscribers};
+pub use self::{
+    poll_filter::{
+        new_epochs, PendingTransactionHashes, PollFilter, ReportedEpochs,
+    },
+    poll_manager::PollManager,
+    subscribers::{Id as SubscriberId, Subscribers},
+};
@@ -0,0 +1,194 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Filters polled with `cfx_getFilterChanges`.
+
+use cfx_types::H256;
+use primitives::{epoch::EpochNumber, filter::Filter};
+use std::{
+    cmp::{max, min},
+    collections::VecDeque,
+};
+
+/// The number of the most recently reported epochs checked for pivot chain
+/// reorgs. A reorg reverting more epochs reverts all of them.
+const MAX_REPORTED_EPOCHS: usize = 256;
+
+/// The number of the most recent transaction hashes kept for the pending
+/// transaction filters. A filter which is not polled in time misses the
+/// oldest ones.
+const MAX_PENDING_TRANSACTION_HASHES: usize = 10_000;
+
+/// The state of a filter between two polls.
+#[derive(Clone)]
+pub enum PollFilter {
+    /// Reports the hashes of the blocks of new epochs.
+    Block(ReportedEpochs),
+    /// Reports the hashes of new transactions in the pool, from the position
+    /// in `PendingTransactionHashes` of the next one.
+    PendingTransaction(u64),
+    /// Reports the logs matching `filter` in new executed epochs.
+    Logs {
+        reported: ReportedEpochs,
+        filter: Filter,
+    },
+}
+
+/// The pivot blocks of the epochs reported by a filter.
+#[derive(Clone, Debug)]
+pub struct ReportedEpochs {
+    last_epoch: u64,
+    recent: VecDeque<(u64, H256)>,
+}
+
+impl ReportedEpochs {
+    pub fn new(epoch: u64, pivot_hash: H256) -> Self {
+        let mut recent = VecDeque::new();
+        recent.push_back((epoch, pivot_hash));
+        ReportedEpochs {
+            last_epoch: epoch,
+            recent,
+        }
+    }
+
+    pub fn last_epoch(&self) -> u64 { self.last_epoch }
+
+    pub fn push(&mut self, epoch: u64, pivot_hash: H256) {
+        if self.recent.len() == MAX_REPORTED_EPOCHS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((epoch, pivot_hash));
+        self.last_epoch = epoch;
+    }
+
+    /// Forget the reported epochs whose pivot block is not
+    /// `pivot_hash_of(epoch)` anymore. Returns the last epoch still valid if
+    /// any epoch is reverted.
+    pub fn revert<F>(&mut self, pivot_hash_of: F) -> Option<u64>
+    where F: Fn(u64) -> Option<H256> {
+        let mut first_reverted = None;
+        while let Some(&(epoch, pivot_hash)) = self.recent.back() {
+            if pivot_hash_of(epoch) == Some(pivot_hash) {
+                break;
+            }
+            self.recent.pop_back();
+            first_reverted = Some(epoch);
+        }
+
+        let revert_to = first_reverted?.saturating_sub(1);
+        self.last_epoch = revert_to;
+        Some(revert_to)
+    }
+}
+
+/// The hashes of the most recent transactions accepted by the transaction
+/// pool, shared by the pending transaction filters.
+#[derive(Default)]
+pub struct PendingTransactionHashes {
+    /// The number of hashes removed from the front of `hashes`.
+    evicted: u64,
+    hashes: VecDeque<H256>,
+}
+
+impl PendingTransactionHashes {
+    /// The position of the next transaction hash.
+    pub fn next_position(&self) -> u64 {
+        self.evicted + self.hashes.len() as u64
+    }
+
+    pub fn extend(&mut self, hashes: impl IntoIterator<Item = H256>) {
+        for hash in hashes {
+            if self.hashes.len() == MAX_PENDING_TRANSACTION_HASHES {
+                self.hashes.pop_front();
+                self.evicted += 1;
+            }
+            self.hashes.push_back(hash);
+        }
+    }
+
+    /// Returns the hashes kept from `position` on.
+    pub fn hashes_from(&self, position: u64) -> Vec<H256> {
+        let skipped = position.saturating_sub(self.evicted) as usize;
+        self.hashes.iter().skip(skipped).cloned().collect()
+    }
+}
+
+/// Returns the range of epochs after `last_epoch` and up to `latest_epoch`
+/// which `filter` applies to. Only numbered epochs restrict the range, as
+/// the other epochs move with the pivot chain.
+pub fn new_epochs(
+    filter: &Filter, last_epoch: u64, latest_epoch: u64,
+) -> Option<(u64, u64)> {
+    let mut from_epoch = last_epoch + 1;
+    let mut to_epoch = latest_epoch;
+    if let EpochNumber::Number(epoch) = filter.from_epoch {
+        from_epoch = max(from_epoch, epoch);
+    }
+    if let EpochNumber::Number(epoch) = filter.to_epoch {
+        to_epoch = min(to_epoch, epoch);
+    }
+
+    if from_epoch <= to_epoch {
+        Some((from_epoch, to_epoch))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revert_reported_epochs() {
+        let pivot_chain: Vec<_> = (0..10).map(H256::from_low_u64_be).collect();
+        let mut reported = ReportedEpochs::new(3, pivot_chain[3]);
+        for epoch in 4..8 {
+            reported.push(epoch, pivot_chain[epoch as usize]);
+        }
+        let pivot_hash_of = |epoch| pivot_chain.get(epoch as usize).cloned();
+        assert_eq!(reported.revert(pivot_hash_of), None);
+        assert_eq!(reported.last_epoch(), 7);
+
+        // Epochs 6 and 7 are reverted, and the pivot chain is shorter.
+        let mut forked_chain = pivot_chain[..7].to_vec();
+        forked_chain[6] = H256::from_low_u64_be(60);
+        let pivot_hash_of = |epoch| forked_chain.get(epoch as usize).cloned();
+        assert_eq!(reported.revert(pivot_hash_of), Some(5));
+        assert_eq!(reported.last_epoch(), 5);
+        assert_eq!(reported.revert(pivot_hash_of), None);
+    }
+
+    #[test]
+    fn test_pending_transaction_hashes() {
+        let hashes: Vec<_> = (0..MAX_PENDING_TRANSACTION_HASHES as u64 + 2)
+            .map(H256::from_low_u64_be)
+            .collect();
+        let mut pending = PendingTransactionHashes::default();
+        pending.extend(hashes[..3].iter().cloned());
+        let position = pending.next_position();
+        assert_eq!(position, 3);
+        assert_eq!(pending.hashes_from(1), hashes[1..3].to_vec());
+        assert!(pending.hashes_from(position).is_empty());
+
+        // The oldest hashes are dropped.
+        pending.extend(hashes[3..].iter().cloned());
+        assert_eq!(pending.next_position(), hashes.len() as u64);
+        assert_eq!(pending.hashes_from(0), hashes[2..].to_vec());
+        assert_eq!(pending.hashes_from(position), hashes[3..].to_vec());
+    }
+
+    #[test]
+    fn test_new_epochs() {
+        let mut filter = Filter::default();
+        assert_eq!(new_epochs(&filter, 5, 5), None);
+        assert_eq!(new_epochs(&filter, 5, 9), Some((6, 9)));
+
+        filter.from_epoch = EpochNumber::Number(8);
+        filter.to_epoch = EpochNumber::Number(12);
+        assert_eq!(new_epochs(&filter, 5, 9), Some((8, 9)));
+        assert_eq!(new_epochs(&filter, 9, 20), Some((10, 12)));
+        assert_eq!(new_epochs(&filter, 12, 20), None);
+    }
+}
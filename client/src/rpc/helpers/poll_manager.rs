@@ -75,6 +75,9 @@ where T: Timer
         self.polls.get_mut(id)
     }
 
+    /// Removes the polls which are not used within their lifetime.
+    pub fn prune(&mut self) { self.polls.prune(); }
+
     /// Removes poll info.
     pub fn remove_poll(&mut self, id: &PollId) -> bool {
         self.polls.remove(id).is_some()
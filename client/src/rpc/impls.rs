@@ -2,6 +2,16 @@
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
+use crate::rpc::{
+    error_codes::call_execution_error,
+    types::{Bytes, EstimateGasAndCollateralResponse},
+};
+use cfxcore::{
+    executive::{ExecutionError, ExecutionOutcome},
+    vm,
+};
+use jsonrpc_core::Result as JsonRpcResult;
+
 // macro for reducing boilerplate for unsupported methods
 #[macro_use]
 macro_rules! not_supported {
@@ -30,6 +40,82 @@ macro_rules! not_supported {
 #[derive(Default)]
 pub struct RpcImplConfiguration {
     pub get_logs_filter_max_limit: Option<usize>,
+    /// The number of seconds after which a filter not polled is removed.
+    pub poll_lifetime_s: u32,
+}
+
+/// Converts the outcome of a virtual call into the result of `cfx_call`.
+fn call_result(outcome: ExecutionOutcome) -> JsonRpcResult<Bytes> {
+    match outcome {
+        ExecutionOutcome::NotExecutedOldNonce(expected, got) => {
+            Err(call_execution_error(
+                "Transaction can not be executed".into(),
+                format! {"nonce is too old expected {:?} got {:?}", expected, got}.into_bytes()
+            ))
+        }
+        ExecutionOutcome::NotExecutedToReconsiderPacking(e) => {
+            Err(call_execution_error(
+                "Transaction can not be executed".into(),
+                format! {"{:?}", e}.into_bytes(),
+            ))
+        }
+        ExecutionOutcome::ExecutionErrorBumpNonce(
+            ExecutionError::VmError(vm::Error::Reverted),
+            executed,
+        ) => Err(call_execution_error(
+            "Transaction reverted".into(),
+            executed.output,
+        )),
+        ExecutionOutcome::ExecutionErrorBumpNonce(e, _) => {
+            Err(call_execution_error(
+                "Transaction execution failed".into(),
+                format! {"{:?}", e}.into_bytes(),
+            ))
+        }
+        ExecutionOutcome::Finished(executed) => Ok(executed.output.into()),
+    }
+}
+
+/// Converts the outcome of a virtual call into the result of
+/// `cfx_estimateGasAndCollateral`.
+fn estimate_gas_and_collateral_result(
+    outcome: ExecutionOutcome,
+) -> JsonRpcResult<EstimateGasAndCollateralResponse> {
+    let executed = match outcome {
+        ExecutionOutcome::NotExecutedOldNonce(expected, got) => {
+            return Err(call_execution_error(
+                "Can not estimate: transaction can not be executed".into(),
+                format! {"nonce is too old expected {:?} got {:?}", expected, got}.into_bytes()
+            ));
+        }
+        ExecutionOutcome::NotExecutedToReconsiderPacking(e) => {
+            return Err(call_execution_error(
+                "Can not estimate: transaction can not be executed".into(),
+                format! {"{:?}", e}.into_bytes(),
+            ));
+        }
+        ExecutionOutcome::ExecutionErrorBumpNonce(
+            ExecutionError::VmError(vm::Error::Reverted),
+            executed,
+        ) => executed,
+        ExecutionOutcome::ExecutionErrorBumpNonce(e, _) => {
+            return Err(call_execution_error(
+                format! {"Can not estimate: transaction execution failed, \
+                all gas will be charged (execution error: {:?})", e}
+                .into(),
+                format! {"{:?}", e}.into_bytes(),
+            ));
+        }
+        ExecutionOutcome::Finished(executed) => executed,
+    };
+    let mut storage_collateralized = 0;
+    for storage_change in &executed.storage_collateralized {
+        storage_collateralized += storage_change.amount;
+    }
+    Ok(EstimateGasAndCollateralResponse {
+        gas_used: executed.gas_used.into(),
+        storage_collateralized: storage_collateralized.into(),
+    })
 }
 
 pub mod cfx;
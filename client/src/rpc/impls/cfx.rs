@@ -3,45 +3,73 @@
 // See http://www.gnu.org/licenses/
 
 use crate::rpc::{
-    error_codes::{call_execution_error, invalid_params},
-    impls::{common::RpcImpl as CommonImpl, RpcImplConfiguration},
+    error_codes::{
+        call_execution_error, filter_not_found, invalid_params, state_pruned,
+    },
+    helpers::{
+        new_epochs, PendingTransactionHashes, PollFilter, PollManager,
+        ReportedEpochs,
+    },
+    impls::{
+        call_result, common::RpcImpl as CommonImpl,
+        estimate_gas_and_collateral_result, RpcImplConfiguration,
+    },
     traits::{cfx::Cfx, debug::LocalRpc, test::TestRpc},
     types::{
-        sign_call, Account as RpcAccount, BlameInfo, Block as RpcBlock,
-        BlockHashOrEpochNumber, Bytes, CallRequest,
+        sign_call, transaction_in_block, Account as RpcAccount, AccountProof,
+        BlameInfo, Block as RpcBlock, BlockHashOrEpochNumber,
+        BlockTrace as RpcBlockTrace, Bytes, CallRequest,
         CheckBalanceAgainstTransactionResponse, ConsensusGraphStates,
-        EpochNumber, EstimateGasAndCollateralResponse, Filter as RpcFilter,
-        Log as RpcLog, Receipt as RpcReceipt, RewardInfo as RpcRewardInfo,
-        SendTxRequest, SponsorInfo as RpcSponsorInfo, Status as RpcStatus,
-        StorageRoot as RpcStorageRoot, SyncGraphStates,
-        Transaction as RpcTransaction, H160 as RpcH160, H256 as RpcH256,
-        H520 as RpcH520, U128 as RpcU128, U256 as RpcU256, U64 as RpcU64,
+        EpochNumber, EstimateGasAndCollateralResponse, ExecutionTrace,
+        FeeHistory as RpcFeeHistory, Filter as RpcFilter, FilterChanges,
+        FilterLog, GasPriceSuggestion, Index, Log as RpcLog,
+        Receipt as RpcReceipt, RewardInfo as RpcRewardInfo, SendTxRequest,
+        SponsorInfo as RpcSponsorInfo, Status as RpcStatus, StorageProof,
+        StorageRoot as RpcStorageRoot, SyncGraphStates, Trace as RpcTrace,
+        TraceOptions, Transaction as RpcTransaction, H160 as RpcH160,
+        H256 as RpcH256, H520 as RpcH520, U128 as RpcU128, U256 as RpcU256,
+        U64 as RpcU64,
     },
     RpcResult,
 };
 use blockgen::BlockGenerator;
 use cfx_types::{H160, H256, U256};
 use cfxcore::{
-    block_data_manager::BlockExecutionResultWithEpoch, machine::Machine,
-    state_exposer::STATE_EXPOSER, test_context::*, vm, ConsensusGraph,
-    ConsensusGraphTrait, PeerInfo, SharedConsensusGraph,
+    block_data_manager::BlockExecutionResultWithEpoch,
+    consensus_internal_parameters::MAX_FEE_HISTORY_EPOCH_COUNT,
+    consensus_parameters::ONE_GDRIP_IN_DRIP,
+    machine::Machine,
+    state_exposer::STATE_EXPOSER,
+    test_context::*,
+    trace::{NoopTracer, StructLogger, Tracer},
+    ConsensusGraph, ConsensusGraphTrait, PeerInfo, SharedConsensusGraph,
     SharedSynchronizationService, SharedTransactionPool,
 };
+use cfxcore_accounts::AccountProvider;
 use delegate::delegate;
-use jsonrpc_core::{BoxFuture, Error as JsonRpcError, Result as JsonRpcResult};
+use futures::{FutureExt, TryFutureExt};
+use jsonrpc_core::{
+    BoxFuture, Error as JsonRpcError, Result as JsonRpcResult, Value,
+};
 use network::{
     node_table::{Node, NodeId},
-    throttling, SessionDetails, UpdateNodeOperation,
+    throttling, Ban, PeerReputation, SessionDetails, UpdateNodeOperation,
 };
 use parking_lot::Mutex;
 use primitives::{
-    filter::Filter, transaction::Action::Call, Account, SignedTransaction,
+    filter::Filter, transaction::Action::Call, Account,
+    EpochNumber as PrimitiveEpochNumber, SignedTransaction, TransactionIndex,
     TransactionWithSignature,
 };
 use rlp::Rlp;
-use std::{collections::BTreeMap, net::SocketAddr, sync::Arc};
+use runtime::Executor;
+use std::{cmp::min, collections::BTreeMap, net::SocketAddr, sync::Arc};
 use txgen::{DirectTransactionGenerator, TransactionGenerator};
 
+/// The maximum number of storage keys in a single `cfx_getProof` request,
+/// since every key requires a separate proof of the storage trie.
+const MAX_PROOF_STORAGE_KEYS: usize = 1024;
+
 pub struct RpcImpl {
     config: RpcImplConfiguration,
     pub consensus: SharedConsensusGraph,
@@ -51,6 +79,9 @@ pub struct RpcImpl {
     maybe_txgen: Option<Arc<TransactionGenerator>>,
     maybe_direct_txgen: Option<Arc<Mutex<DirectTransactionGenerator>>>,
     machine: Arc<Machine>,
+    filters: Arc<Mutex<PollManager<PollFilter>>>,
+    pending_transactions: Arc<Mutex<PendingTransactionHashes>>,
+    accounts: Arc<AccountProvider>,
 }
 
 impl RpcImpl {
@@ -60,8 +91,27 @@ impl RpcImpl {
         maybe_txgen: Option<Arc<TransactionGenerator>>,
         maybe_direct_txgen: Option<Arc<Mutex<DirectTransactionGenerator>>>,
         config: RpcImplConfiguration, machine: Arc<Machine>,
+        accounts: Arc<AccountProvider>, executor: Executor,
     ) -> Self
     {
+        let filters =
+            Arc::new(Mutex::new(PollManager::new(config.poll_lifetime_s)));
+        let pending_transactions =
+            Arc::new(Mutex::new(PendingTransactionHashes::default()));
+
+        // The expired filters are also removed when new transactions come,
+        // as they may not be polled again.
+        let receiver = tx_pool.new_pending_transactions().subscribe();
+        let filters_clone = filters.clone();
+        let pending_transactions_clone = pending_transactions.clone();
+        let fut = receiver.for_each(move |txs| {
+            pending_transactions_clone
+                .lock()
+                .extend(txs.iter().map(|tx| tx.hash()));
+            filters_clone.lock().prune();
+        });
+        executor.spawn(fut.unit_error().boxed().compat());
+
         RpcImpl {
             consensus,
             sync,
@@ -71,9 +121,32 @@ impl RpcImpl {
             maybe_direct_txgen,
             config,
             machine,
+            filters,
+            pending_transactions,
+            accounts,
         }
     }
 
+    /// Fails with a "state pruned" error if the state of `epoch_number` has
+    /// been removed by this node.
+    fn check_state_retained(
+        &self, epoch_number: &EpochNumber,
+    ) -> RpcResult<()> {
+        let consensus_graph = self
+            .consensus
+            .as_any()
+            .downcast_ref::<ConsensusGraph>()
+            .expect("downcast should succeed");
+
+        let epoch = consensus_graph
+            .get_height_from_epoch_number(epoch_number.clone().into())?;
+        let earliest_epoch = consensus_graph.earliest_state_epoch_number();
+        if epoch < earliest_epoch {
+            bail!(state_pruned(epoch, earliest_epoch));
+        }
+        Ok(())
+    }
+
     fn code(
         &self, addr: RpcH160, epoch_number: Option<EpochNumber>,
     ) -> RpcResult<Bytes> {
@@ -83,6 +156,8 @@ impl RpcImpl {
             "RPC Request: cfx_getCode address={:?} epoch_num={:?}",
             address, epoch_number
         );
+        self.check_state_retained(&epoch_number)?;
+
         let consensus_graph = self
             .consensus
             .as_any()
@@ -104,6 +179,8 @@ impl RpcImpl {
             address, num
         );
 
+        self.check_state_retained(&num)?;
+
         let consensus_graph = self
             .consensus
             .as_any()
@@ -123,6 +200,8 @@ impl RpcImpl {
             address, num
         );
 
+        self.check_state_retained(&num)?;
+
         let cg = self
             .consensus
             .as_any()
@@ -144,6 +223,8 @@ impl RpcImpl {
             address, num
         );
 
+        self.check_state_retained(&num)?;
+
         let cg = self
             .consensus
             .as_any()
@@ -165,6 +246,8 @@ impl RpcImpl {
             address, num
         );
 
+        self.check_state_retained(&num)?;
+
         let consensus_graph = self
             .consensus
             .as_any()
@@ -186,6 +269,8 @@ impl RpcImpl {
             address, num
         );
 
+        self.check_state_retained(&num)?;
+
         let consensus_graph = self
             .consensus
             .as_any()
@@ -208,6 +293,8 @@ impl RpcImpl {
             "RPC Request: cfx_getAccount address={:?} epoch_num={:?}",
             address, epoch_num
         );
+        self.check_state_retained(&epoch_num)?;
+
         let consensus_graph = self
             .consensus
             .as_any()
@@ -232,6 +319,8 @@ impl RpcImpl {
         &self, epoch_num: Option<EpochNumber>,
     ) -> RpcResult<RpcU256> {
         let epoch_num = epoch_num.unwrap_or(EpochNumber::LatestState);
+        self.check_state_retained(&epoch_num)?;
+
         let consensus_graph = self
             .consensus
             .as_any()
@@ -248,6 +337,8 @@ impl RpcImpl {
         &self, epoch_num: Option<EpochNumber>,
     ) -> RpcResult<RpcU256> {
         let epoch_num = epoch_num.unwrap_or(EpochNumber::LatestState);
+        self.check_state_retained(&epoch_num)?;
+
         let consensus_graph = self
             .consensus
             .as_any()
@@ -284,6 +375,8 @@ impl RpcImpl {
             address, position, epoch_num
         );
 
+        self.check_state_retained(&epoch_num)?;
+
         let consensus_graph = self
             .consensus
             .as_any()
@@ -304,7 +397,7 @@ impl RpcImpl {
             }
         }
         let (signed_trans, failed_trans) =
-            self.tx_pool.insert_new_transactions(vec![tx]);
+            self.tx_pool.insert_local_transactions(vec![tx]);
         // FIXME: how is it possible?
         if signed_trans.len() + failed_trans.len() > 1 {
             // This should never happen
@@ -357,14 +450,14 @@ impl RpcImpl {
 
         let epoch_height = consensus_graph.best_epoch_number();
         let chain_id = consensus_graph.best_chain_id();
-        let tx =
-            tx.sign_with(epoch_height, chain_id, password)
-                .map_err(|e| {
-                    invalid_params(
-                        "tx",
-                        format!("failed to send transaction: {:?}", e),
-                    )
-                })?;
+        let tx = tx
+            .sign_with(epoch_height, chain_id, password, &self.accounts)
+            .map_err(|e| {
+                invalid_params(
+                    "tx",
+                    format!("failed to send transaction: {:?}", e),
+                )
+            })?;
 
         Ok(tx)
     }
@@ -389,6 +482,8 @@ impl RpcImpl {
             address, epoch_num
         );
 
+        self.check_state_retained(&epoch_num)?;
+
         let consensus_graph = self
             .consensus
             .as_any()
@@ -400,6 +495,68 @@ impl RpcImpl {
             .map(RpcStorageRoot::from_primitive))
     }
 
+    fn account_proof(
+        &self, address: RpcH160, storage_keys: Vec<RpcH256>,
+        epoch_number: Option<EpochNumber>,
+    ) -> RpcResult<AccountProof>
+    {
+        let address: H160 = address.into();
+        let epoch_number = epoch_number.unwrap_or(EpochNumber::LatestState);
+        if storage_keys.len() > MAX_PROOF_STORAGE_KEYS {
+            bail!(invalid_params(
+                "storage_keys",
+                format!(
+                    "At most {} storage keys are allowed, got {}",
+                    MAX_PROOF_STORAGE_KEYS,
+                    storage_keys.len()
+                )
+            ));
+        }
+
+        info!(
+            "RPC Request: cfx_getProof address={:?} storage_keys={:?} epoch_number={:?}",
+            address, storage_keys, epoch_number
+        );
+
+        self.check_state_retained(&epoch_number)?;
+
+        let consensus_graph = self
+            .consensus
+            .as_any()
+            .downcast_ref::<ConsensusGraph>()
+            .expect("downcast should succeed");
+
+        let positions: Vec<H256> =
+            storage_keys.iter().cloned().map(Into::into).collect();
+        let (state_root, entries) = consensus_graph.get_account_proof(
+            address,
+            &positions,
+            epoch_number.into(),
+        )?;
+
+        let mut entries = entries.into_iter().map(|(value, proof)| {
+            (
+                value.map(|value| Bytes::new(value.into_vec())),
+                Bytes::new(rlp::encode(&proof)),
+            )
+        });
+        let (account, account_proof) =
+            entries.next().expect("account entry is always returned");
+        let storage_proof = storage_keys
+            .into_iter()
+            .zip(entries)
+            .map(|(key, (value, proof))| StorageProof { key, value, proof })
+            .collect();
+
+        Ok(AccountProof {
+            address: address.into(),
+            account,
+            account_proof,
+            storage_proof,
+            state_root,
+        })
+    }
+
     fn send_usable_genesis_accounts(
         &self, account_start_index: usize,
     ) -> RpcResult<Bytes> {
@@ -451,6 +608,97 @@ impl RpcImpl {
         Ok(None)
     }
 
+    fn block_transaction_count_by_hash(
+        &self, block_hash: RpcH256,
+    ) -> RpcResult<Option<RpcU256>> {
+        let block_hash: H256 = block_hash.into();
+        info!(
+            "RPC Request: cfx_getBlockTransactionCountByHash({:?})",
+            block_hash
+        );
+
+        Ok(self.block_transaction_count(&block_hash))
+    }
+
+    fn block_transaction_count_by_epoch_number(
+        &self, epoch_number: EpochNumber,
+    ) -> RpcResult<Option<RpcU256>> {
+        info!(
+            "RPC Request: cfx_getBlockTransactionCountByEpochNumber({:?})",
+            epoch_number
+        );
+
+        let pivot_hash = self
+            .consensus
+            .get_hash_from_epoch_number(epoch_number.into())?;
+        Ok(self.block_transaction_count(&pivot_hash))
+    }
+
+    fn block_transaction_count(&self, block_hash: &H256) -> Option<RpcU256> {
+        self.consensus
+            .get_data_manager()
+            .block_by_hash(block_hash, false /* update_cache */)
+            .map(|block| block.transactions.len().into())
+    }
+
+    fn transaction_by_block_hash_and_index(
+        &self, block_hash: RpcH256, index: Index,
+    ) -> RpcResult<Option<RpcTransaction>> {
+        let block_hash: H256 = block_hash.into();
+        info!(
+            "RPC Request: cfx_getTransactionByBlockHashAndIndex({:?}, {:?})",
+            block_hash, index
+        );
+
+        Ok(self.block_transaction(&block_hash, index.value()))
+    }
+
+    fn transaction_by_epoch_number_and_index(
+        &self, epoch_number: EpochNumber, index: Index,
+    ) -> RpcResult<Option<RpcTransaction>> {
+        info!(
+            "RPC Request: cfx_getTransactionByEpochNumberAndIndex({:?}, {:?})",
+            epoch_number, index
+        );
+
+        let pivot_hash = self
+            .consensus
+            .get_hash_from_epoch_number(epoch_number.into())?;
+        Ok(self.block_transaction(&pivot_hash, index.value()))
+    }
+
+    /// Returns the transaction at `index` in block `block_hash`, with the
+    /// same execution information as in `cfx_getBlockByHash`.
+    fn block_transaction(
+        &self, block_hash: &H256, index: usize,
+    ) -> Option<RpcTransaction> {
+        let consensus_graph = self
+            .consensus
+            .as_any()
+            .downcast_ref::<ConsensusGraph>()
+            .expect("downcast should succeed");
+        let data_man = consensus_graph.get_data_manager();
+
+        let block = data_man
+            .block_by_hash(block_hash, false /* update_cache */)?;
+        if index >= block.transactions.len() {
+            return None;
+        }
+
+        // Only the lookup of the execution result needs the consensus lock.
+        let execution_result = consensus_graph
+            .inner
+            .read()
+            .block_execution_results_by_hash(
+                block_hash, false, /* update_cache */
+            )
+            .map(|BlockExecutionResultWithEpoch(_, result)| result);
+        let block_receipts = execution_result
+            .as_ref()
+            .map(|result| &*result.block_receipts);
+        Some(transaction_in_block(&*block, index, block_receipts))
+    }
+
     fn prepare_receipt(&self, hash: H256) -> RpcResult<Option<RpcReceipt>> {
         // Get a consistent view from ConsensusInner
         let consensus_graph = self
@@ -534,6 +782,187 @@ impl RpcImpl {
         self.prepare_receipt(hash)
     }
 
+    /// Returns the receipts of a block along with the pivot block of the
+    /// epoch executing it, or `None` if the block is not executed on the
+    /// current pivot chain yet.
+    fn prepare_block_receipts(
+        &self, block_hash: H256,
+    ) -> RpcResult<Option<(H256, Vec<RpcReceipt>)>> {
+        let consensus_graph = self
+            .consensus
+            .as_any()
+            .downcast_ref::<ConsensusGraph>()
+            .expect("downcast should succeed");
+
+        let (
+            BlockExecutionResultWithEpoch(epoch_hash, execution_result),
+            state_root,
+        ) = match consensus_graph.get_block_execution_result(&block_hash) {
+            None => return Ok(None),
+            Some(result_tuple) => result_tuple,
+        };
+
+        let data_man = self.consensus.get_data_manager();
+        let epoch_number = data_man
+            .block_header_by_hash(&epoch_hash)
+            // FIXME: server error, client should request another server.
+            .ok_or("Inconsistent state")?
+            .height();
+        if epoch_number > consensus_graph.best_executed_state_epoch_number() {
+            // The receipts are only visible to optimistic execution.
+            return Ok(None);
+        }
+
+        let block = data_man
+            .block_by_hash(&block_hash, true)
+            // FIXME: server error, client should request another server.
+            .ok_or("Inconsistent state")?;
+        let receipts = &execution_result.block_receipts.receipts;
+        if receipts.len() != block.transactions.len() {
+            // FIXME: server error, client should request another server.
+            bail!("Inconsistent state");
+        }
+
+        let mut prior_gas_used = U256::zero();
+        let mut rpc_receipts = Vec::with_capacity(receipts.len());
+        for (index, (tx, receipt)) in
+            block.transactions.iter().zip(receipts).enumerate()
+        {
+            rpc_receipts.push(RpcReceipt::new(
+                (**tx).clone(),
+                receipt.clone(),
+                TransactionIndex { block_hash, index },
+                prior_gas_used,
+                Some(epoch_number),
+                Some(state_root),
+            ));
+            prior_gas_used = receipt.accumulated_gas_used;
+        }
+
+        Ok(Some((epoch_hash, rpc_receipts)))
+    }
+
+    fn epoch_receipts(
+        &self, epoch_number: EpochNumber,
+    ) -> RpcResult<Option<Vec<Vec<RpcReceipt>>>> {
+        info!("RPC Request: cfx_getEpochReceipts({:?})", epoch_number);
+
+        let block_hashes = self
+            .consensus
+            .get_block_hashes_by_epoch(epoch_number.into())?;
+        let pivot_hash =
+            *block_hashes.last().expect("pivot block always exist");
+
+        let mut epoch_receipts = Vec::with_capacity(block_hashes.len());
+        for block_hash in block_hashes {
+            match self.prepare_block_receipts(block_hash)? {
+                Some((epoch_hash, receipts)) if epoch_hash == pivot_hash => {
+                    epoch_receipts.push(receipts)
+                }
+                // The epoch is not executed yet, or the pivot chain has
+                // changed since the blocks of the epoch were retrieved.
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(Some(epoch_receipts))
+    }
+
+    fn block_receipts(
+        &self, block_hash: RpcH256,
+    ) -> RpcResult<Option<Vec<RpcReceipt>>> {
+        let hash: H256 = block_hash.into();
+        info!("RPC Request: cfx_getBlockReceipts({:?})", hash);
+        Ok(self
+            .prepare_block_receipts(hash)?
+            .map(|(_, receipts)| receipts))
+    }
+
+    fn gas_price_suggestion(&self) -> RpcResult<GasPriceSuggestion> {
+        info!("RPC Request: cfx_gasPriceSuggestion()");
+        let consensus_graph = self
+            .consensus
+            .as_any()
+            .downcast_ref::<ConsensusGraph>()
+            .expect("downcast should succeed");
+        let default_price: U256 = ONE_GDRIP_IN_DRIP.into();
+        Ok(consensus_graph
+            .gas_price_suggestion()
+            .map(Into::into)
+            .unwrap_or(GasPriceSuggestion {
+                slow: default_price.into(),
+                standard: default_price.into(),
+                fast: default_price.into(),
+            }))
+    }
+
+    fn fee_history(
+        &self, epoch_count: RpcU64, newest_epoch: EpochNumber,
+        reward_percentiles: Vec<f64>,
+    ) -> RpcResult<RpcFeeHistory>
+    {
+        info!(
+            "RPC Request: cfx_feeHistory({:?}, {:?}, {:?})",
+            epoch_count, newest_epoch, reward_percentiles
+        );
+        let epoch_count = epoch_count.as_usize() as u64;
+        if epoch_count == 0 || epoch_count > MAX_FEE_HISTORY_EPOCH_COUNT {
+            bail!(invalid_params(
+                "epoch_count",
+                format!(
+                    "expected between 1 and {}",
+                    MAX_FEE_HISTORY_EPOCH_COUNT
+                )
+            ));
+        }
+        if reward_percentiles
+            .iter()
+            .any(|p| !(*p >= 0.0 && *p <= 100.0))
+            || reward_percentiles.windows(2).any(|w| w[0] > w[1])
+        {
+            bail!(invalid_params(
+                "reward_percentiles",
+                "expected ascending percentiles between 0 and 100"
+            ));
+        }
+
+        let consensus_graph = self
+            .consensus
+            .as_any()
+            .downcast_ref::<ConsensusGraph>()
+            .expect("downcast should succeed");
+        // Only executed epochs have fee statistics.
+        let newest_epoch = min(
+            consensus_graph
+                .get_height_from_epoch_number(newest_epoch.into())?,
+            consensus_graph.best_executed_state_epoch_number(),
+        );
+        let oldest_epoch = (newest_epoch + 1).saturating_sub(epoch_count);
+
+        let mut gas_used_ratio = Vec::new();
+        let mut reward = Vec::new();
+        for epoch_number in oldest_epoch..=newest_epoch {
+            let stats = consensus_graph
+                .epoch_fee_stats(epoch_number)
+                .ok_or_else(|| {
+                    format!("Fee history of epoch {} unavailable", epoch_number)
+                })?;
+            gas_used_ratio.push(stats.gas_used_ratio());
+            reward.push(
+                reward_percentiles
+                    .iter()
+                    .map(|p| stats.percentile(*p).into())
+                    .collect(),
+            );
+        }
+
+        Ok(RpcFeeHistory {
+            oldest_epoch: oldest_epoch.into(),
+            gas_used_ratio,
+            reward,
+        })
+    }
+
     fn generate_empty_blocks(&self, num_blocks: usize) -> RpcResult<Vec<H256>> {
         info!("RPC Request: generate({:?})", num_blocks);
         let mut hashes = Vec::new();
@@ -702,15 +1131,17 @@ impl RpcImpl {
     }
 
     fn get_logs(&self, filter: RpcFilter) -> RpcResult<Vec<RpcLog>> {
+        info!("RPC Request: cfx_getLogs({:?})", filter);
+        self.logs(filter.into_primitive()?)
+    }
+
+    fn logs(&self, mut filter: Filter) -> RpcResult<Vec<RpcLog>> {
         let consensus_graph = self
             .consensus
             .as_any()
             .downcast_ref::<ConsensusGraph>()
             .expect("downcast should succeed");
 
-        info!("RPC Request: cfx_getLogs({:?})", filter);
-        let mut filter: Filter = filter.into_primitive()?;
-
         // If max_limit is set, the value in `filter` will be modified to
         // satisfy this limitation to avoid loading too many blocks
         // TODO Should the response indicate that the filter is modified?
@@ -728,6 +1159,168 @@ impl RpcImpl {
             .collect())
     }
 
+    fn new_filter(&self, filter: RpcFilter) -> RpcResult<RpcU256> {
+        info!("RPC Request: cfx_newFilter({:?})", filter);
+        let filter: Filter = filter.into_primitive()?;
+        if filter.block_hashes.is_some() {
+            bail!(invalid_params(
+                "filter",
+                "Filters on blockHashes are not supported, use cfx_getLogs."
+            ));
+        }
+
+        let consensus_graph = self
+            .consensus
+            .as_any()
+            .downcast_ref::<ConsensusGraph>()
+            .expect("downcast should succeed");
+        let reported = self.reported_epochs(
+            consensus_graph.best_executed_state_epoch_number(),
+        )?;
+
+        let id = self
+            .filters
+            .lock()
+            .create_poll(PollFilter::Logs { reported, filter });
+        Ok(id.into())
+    }
+
+    fn new_block_filter(&self) -> RpcResult<RpcU256> {
+        info!("RPC Request: cfx_newBlockFilter()");
+        let reported =
+            self.reported_epochs(self.consensus.best_epoch_number())?;
+        let id = self.filters.lock().create_poll(PollFilter::Block(reported));
+        Ok(id.into())
+    }
+
+    fn new_pending_transaction_filter(&self) -> RpcResult<RpcU256> {
+        info!("RPC Request: cfx_newPendingTransactionFilter()");
+        let position = self.pending_transactions.lock().next_position();
+        let id = self
+            .filters
+            .lock()
+            .create_poll(PollFilter::PendingTransaction(position));
+        Ok(id.into())
+    }
+
+    fn filter_changes(&self, filter_id: Index) -> RpcResult<FilterChanges> {
+        info!("RPC Request: cfx_getFilterChanges({:?})", filter_id);
+        // The filters are not locked while the changes are retrieved. The
+        // filter is only updated once they are, so that a failed poll can be
+        // retried.
+        let mut filter = match self.filters.lock().poll(&filter_id.value()) {
+            Some(filter) => filter.clone(),
+            None => bail!(filter_not_found()),
+        };
+
+        let changes = match &mut filter {
+            PollFilter::Block(reported) => {
+                // blocks of reverted epochs are reported again
+                self.revert_reported_epochs(reported);
+
+                let mut hashes = vec![];
+                let latest_epoch = self.consensus.best_epoch_number();
+                for epoch in reported.last_epoch() + 1..=latest_epoch {
+                    let epoch_hashes =
+                        self.consensus.get_block_hashes_by_epoch(
+                            PrimitiveEpochNumber::Number(epoch),
+                        )?;
+                    let pivot_hash = *epoch_hashes
+                        .last()
+                        .expect("epoch should not be empty");
+                    reported.push(epoch, pivot_hash);
+                    hashes.extend(epoch_hashes.into_iter().map(RpcH256::from));
+                }
+
+                FilterChanges::Hashes(hashes)
+            }
+            PollFilter::PendingTransaction(position) => {
+                let pending_transactions = self.pending_transactions.lock();
+                let hashes = pending_transactions
+                    .hashes_from(*position)
+                    .into_iter()
+                    .map(RpcH256::from)
+                    .collect();
+                *position = pending_transactions.next_position();
+                FilterChanges::Hashes(hashes)
+            }
+            PollFilter::Logs { reported, filter } => {
+                let mut logs = vec![];
+                if let Some(epoch) = self.revert_reported_epochs(reported) {
+                    logs.push(FilterLog::ChainReorg {
+                        revert_to: epoch.into(),
+                    });
+                }
+
+                let consensus_graph = self
+                    .consensus
+                    .as_any()
+                    .downcast_ref::<ConsensusGraph>()
+                    .expect("downcast should succeed");
+                let last_epoch = reported.last_epoch();
+                let latest_epoch =
+                    consensus_graph.best_executed_state_epoch_number();
+                for epoch in last_epoch + 1..=latest_epoch {
+                    reported.push(epoch, self.pivot_hash(epoch)?);
+                }
+
+                if let Some((from_epoch, to_epoch)) =
+                    new_epochs(filter, last_epoch, latest_epoch)
+                {
+                    let mut filter = filter.clone();
+                    filter.from_epoch =
+                        PrimitiveEpochNumber::Number(from_epoch);
+                    filter.to_epoch = PrimitiveEpochNumber::Number(to_epoch);
+                    logs.extend(
+                        self.logs(filter)?.into_iter().map(FilterLog::Log),
+                    );
+                }
+
+                FilterChanges::Logs(logs)
+            }
+        };
+
+        // The filter may have been uninstalled meanwhile.
+        if let Some(stored) = self.filters.lock().poll_mut(&filter_id.value()) {
+            *stored = filter;
+        }
+        Ok(changes)
+    }
+
+    fn filter_logs(&self, filter_id: Index) -> RpcResult<Vec<RpcLog>> {
+        info!("RPC Request: cfx_getFilterLogs({:?})", filter_id);
+        let filter = match self.filters.lock().poll(&filter_id.value()) {
+            Some(PollFilter::Logs { filter, .. }) => filter.clone(),
+            // block and pending transaction filters have no logs
+            Some(_) => return Ok(vec![]),
+            None => bail!(filter_not_found()),
+        };
+        self.logs(filter)
+    }
+
+    fn uninstall_filter(&self, filter_id: Index) -> RpcResult<bool> {
+        info!("RPC Request: cfx_uninstallFilter({:?})", filter_id);
+        Ok(self.filters.lock().remove_poll(&filter_id.value()))
+    }
+
+    fn pivot_hash(&self, epoch: u64) -> RpcResult<H256> {
+        Ok(self
+            .consensus
+            .get_hash_from_epoch_number(PrimitiveEpochNumber::Number(epoch))?)
+    }
+
+    fn reported_epochs(&self, epoch: u64) -> RpcResult<ReportedEpochs> {
+        Ok(ReportedEpochs::new(epoch, self.pivot_hash(epoch)?))
+    }
+
+    /// Forget the epochs reported to a filter which are not on the pivot
+    /// chain anymore, and return the last epoch still valid if any.
+    fn revert_reported_epochs(
+        &self, reported: &mut ReportedEpochs,
+    ) -> Option<u64> {
+        reported.revert(|epoch| self.pivot_hash(epoch).ok())
+    }
+
     fn get_block_reward_info(
         &self, epoch: EpochNumber,
     ) -> RpcResult<Vec<RpcRewardInfo>> {
@@ -751,77 +1344,94 @@ impl RpcImpl {
         Ok(ret)
     }
 
+    fn trace_block(
+        &self, block_hash: RpcH256,
+    ) -> RpcResult<Option<RpcBlockTrace>> {
+        let block_hash: H256 = block_hash.into();
+        info!("RPC Request: cfx_traceBlock({:?})", block_hash);
+
+        let consensus_graph = self
+            .consensus
+            .as_any()
+            .downcast_ref::<ConsensusGraph>()
+            .expect("downcast should succeed");
+        let (epoch_hash, traces) =
+            match consensus_graph.block_traces(&block_hash)? {
+                None => return Ok(None),
+                Some(epoch_traces) => epoch_traces,
+            };
+        let block = self
+            .consensus
+            .get_data_manager()
+            .block_by_hash(&block_hash, true)
+            // FIXME: server error, client should request another server.
+            .ok_or("Inconsistent state")?;
+        if block.transactions.len() != traces.0.len() {
+            bail!("Inconsistent state");
+        }
+
+        let transaction_traces = block
+            .transactions
+            .iter()
+            .zip(traces.0.into_iter())
+            .map(|(tx, tx_traces)| (tx.hash(), tx_traces.0))
+            .collect();
+        Ok(Some(RpcBlockTrace::new(
+            block_hash,
+            epoch_hash,
+            transaction_traces,
+        )))
+    }
+
+    fn trace_transaction(
+        &self, tx_hash: RpcH256,
+    ) -> RpcResult<Option<Vec<RpcTrace>>> {
+        let hash: H256 = tx_hash.into();
+        info!("RPC Request: cfx_traceTransaction({:?})", hash);
+
+        let consensus_graph = self
+            .consensus
+            .as_any()
+            .downcast_ref::<ConsensusGraph>()
+            .expect("downcast should succeed");
+        let address = match consensus_graph
+            .get_transaction_receipt_and_block_info(&hash)
+        {
+            None => return Ok(None),
+            Some((_, address, _)) => address,
+        };
+        let (_, mut traces) =
+            match consensus_graph.block_traces(&address.block_hash)? {
+                None => return Ok(None),
+                Some(epoch_traces) => epoch_traces,
+            };
+        if address.index >= traces.0.len() {
+            // FIXME: server error, client should request another server.
+            bail!("Inconsistent state");
+        }
+        Ok(Some(
+            traces
+                .0
+                .swap_remove(address.index)
+                .0
+                .into_iter()
+                .map(RpcTrace::from)
+                .collect(),
+        ))
+    }
+
     fn call(
         &self, request: CallRequest, epoch: Option<EpochNumber>,
     ) -> RpcResult<Bytes> {
-        match self.exec_transaction(request, epoch)? {
-            ExecutionOutcome::NotExecutedOldNonce(expected, got) => {
-                bail!(call_execution_error(
-                    "Transaction can not be executed".into(),
-                    format! {"nonce is too old expected {:?} got {:?}", expected, got}.into_bytes()
-                ))
-            }
-            ExecutionOutcome::NotExecutedToReconsiderPacking(e) => {
-                bail!(call_execution_error(
-                    "Transaction can not be executed".into(),
-                    format! {"{:?}", e}.into_bytes()
-                ))
-            }
-            ExecutionOutcome::ExecutionErrorBumpNonce(
-                ExecutionError::VmError(vm::Error::Reverted),
-                executed,
-            ) => bail!(call_execution_error(
-                "Transaction reverted".into(),
-                executed.output
-            )),
-            ExecutionOutcome::ExecutionErrorBumpNonce(e, _) => {
-                bail!(call_execution_error(
-                    "Transaction execution failed".into(),
-                    format! {"{:?}", e}.into_bytes()
-                ))
-            }
-            ExecutionOutcome::Finished(executed) => Ok(executed.output.into()),
-        }
+        let outcome = self.exec_transaction(request, epoch, &mut NoopTracer)?;
+        Ok(call_result(outcome)?)
     }
 
     fn estimate_gas_and_collateral(
         &self, request: CallRequest, epoch: Option<EpochNumber>,
     ) -> RpcResult<EstimateGasAndCollateralResponse> {
-        let executed = match self.exec_transaction(request, epoch)? {
-            ExecutionOutcome::NotExecutedOldNonce(expected, got) => {
-                bail!(call_execution_error(
-                    "Can not estimate: transaction can not be executed".into(),
-                    format! {"nonce is too old expected {:?} got {:?}", expected, got}.into_bytes()
-                ))
-            }
-            ExecutionOutcome::NotExecutedToReconsiderPacking(e) => {
-                bail!(call_execution_error(
-                    "Can not estimate: transaction can not be executed".into(),
-                    format! {"{:?}", e}.into_bytes()
-                ))
-            }
-            ExecutionOutcome::ExecutionErrorBumpNonce(
-                ExecutionError::VmError(vm::Error::Reverted),
-                executed,
-            ) => executed,
-            ExecutionOutcome::ExecutionErrorBumpNonce(e, _) => {
-                bail!(call_execution_error(
-                    format! {"Can not estimate: transaction execution failed, \
-                     all gas will be charged (execution error: {:?})", e}.into(),
-                    format! {"{:?}", e}.into_bytes()
-                ))
-            }
-            ExecutionOutcome::Finished(executed) => executed,
-        };
-        let mut storage_collateralized = 0;
-        for storage_change in &executed.storage_collateralized {
-            storage_collateralized += storage_change.amount;
-        }
-        let response = EstimateGasAndCollateralResponse {
-            gas_used: executed.gas_used.into(),
-            storage_collateralized: storage_collateralized.into(),
-        };
-        Ok(response)
+        let outcome = self.exec_transaction(request, epoch, &mut NoopTracer)?;
+        Ok(estimate_gas_and_collateral_result(outcome)?)
     }
 
     fn check_balance_against_transaction(
@@ -863,7 +1473,9 @@ impl RpcImpl {
 
     fn exec_transaction(
         &self, request: CallRequest, epoch: Option<EpochNumber>,
-    ) -> RpcResult<ExecutionOutcome> {
+        tracer: &mut dyn Tracer,
+    ) -> RpcResult<ExecutionOutcome>
+    {
         let consensus_graph = self
             .consensus
             .as_any()
@@ -875,7 +1487,86 @@ impl RpcImpl {
         let chain_id = consensus_graph.best_chain_id();
         let signed_tx = sign_call(best_epoch_height, chain_id, request);
         trace!("call tx {:?}", signed_tx);
-        consensus_graph.call_virtual(&signed_tx, epoch.into())
+        consensus_graph.call_virtual(&signed_tx, epoch.into(), tracer)
+    }
+
+    fn debug_trace_call(
+        &self, request: CallRequest, epoch: Option<EpochNumber>,
+        options: Option<TraceOptions>,
+    ) -> RpcResult<ExecutionTrace>
+    {
+        info!(
+            "RPC Request: debug_traceCall request={:?} epoch={:?} options={:?}",
+            request, epoch, options
+        );
+        let mut tracer = StructLogger::new(options.unwrap_or_default().into());
+        let gas_used = match self.exec_transaction(
+            request,
+            epoch,
+            &mut tracer,
+        )? {
+            ExecutionOutcome::NotExecutedOldNonce(expected, got) => {
+                bail!(call_execution_error(
+                    "Transaction can not be executed".into(),
+                    format! {"nonce is too old expected {:?} got {:?}", expected, got}.into_bytes()
+                ))
+            }
+            ExecutionOutcome::NotExecutedToReconsiderPacking(e) => {
+                bail!(call_execution_error(
+                    "Transaction can not be executed".into(),
+                    format! {"{:?}", e}.into_bytes()
+                ))
+            }
+            ExecutionOutcome::ExecutionErrorBumpNonce(_, executed)
+            | ExecutionOutcome::Finished(executed) => executed.gas_used,
+        };
+        Ok(ExecutionTrace::new(gas_used, tracer.drain()))
+    }
+
+    fn debug_trace_transaction(
+        &self, tx_hash: RpcH256, options: Option<TraceOptions>,
+    ) -> RpcResult<Option<ExecutionTrace>> {
+        let hash: H256 = tx_hash.into();
+        info!(
+            "RPC Request: debug_traceTransaction({:?}) options={:?}",
+            hash, options
+        );
+
+        let consensus_graph = self
+            .consensus
+            .as_any()
+            .downcast_ref::<ConsensusGraph>()
+            .expect("downcast should succeed");
+        let (BlockExecutionResultWithEpoch(_, execution_result), address, _) =
+            match consensus_graph.get_transaction_receipt_and_block_info(&hash)
+            {
+                None => return Ok(None),
+                Some(result_tuple) => result_tuple,
+            };
+        let receipts = &execution_result.block_receipts.receipts;
+        let accumulated_gas_used = receipts
+            .get(address.index)
+            // FIXME: server error, client should request another server.
+            .ok_or("Inconsistent state")?
+            .accumulated_gas_used;
+        let prior_gas_used = if address.index == 0 {
+            U256::zero()
+        } else {
+            receipts[address.index - 1].accumulated_gas_used
+        };
+
+        let mut tracer = StructLogger::new(options.unwrap_or_default().into());
+        if !consensus_graph.trace_transaction_execution(
+            &address.block_hash,
+            &hash,
+            &mut tracer,
+        )? {
+            return Ok(None);
+        }
+        Ok(Some(ExecutionTrace::new(
+            accumulated_gas_used - prior_gas_used,
+            tracer.drain(),
+        )))
     }
 
     fn current_sync_phase(&self) -> RpcResult<String> {
@@ -951,7 +1642,7 @@ impl CfxHandler {
 // To convert from RpcResult to BoxFuture by delegate! macro automatically.
 use crate::common::delegate_convert;
 use cfx_types::address_util::AddressUtil;
-use cfxcore::executive::{ExecutionError, ExecutionOutcome};
+use cfxcore::executive::ExecutionOutcome;
 
 impl Cfx for CfxHandler {
     delegate! {
@@ -989,21 +1680,38 @@ impl Cfx for CfxHandler {
             fn collateral_for_storage(&self, address: RpcH160, num: Option<EpochNumber>)
                 -> BoxFuture<RpcU256>;
             fn call(&self, request: CallRequest, epoch: Option<EpochNumber>)
-                -> JsonRpcResult<Bytes>;
+                -> BoxFuture<Bytes>;
             fn estimate_gas_and_collateral(
                 &self, request: CallRequest, epoch_number: Option<EpochNumber>)
-                -> JsonRpcResult<EstimateGasAndCollateralResponse>;
+                -> BoxFuture<EstimateGasAndCollateralResponse>;
             fn check_balance_against_transaction(
                 &self, account_addr: RpcH160, contract_addr: RpcH160, gas_limit: RpcU256, gas_price: RpcU256, storage_limit: RpcU256, epoch: Option<EpochNumber>,
             ) -> JsonRpcResult<CheckBalanceAgainstTransactionResponse>;
             fn get_logs(&self, filter: RpcFilter) -> BoxFuture<Vec<RpcLog>>;
+            fn new_filter(&self, filter: RpcFilter) -> JsonRpcResult<RpcU256>;
+            fn new_block_filter(&self) -> JsonRpcResult<RpcU256>;
+            fn new_pending_transaction_filter(&self) -> JsonRpcResult<RpcU256>;
+            fn filter_changes(&self, filter_id: Index) -> JsonRpcResult<FilterChanges>;
+            fn filter_logs(&self, filter_id: Index) -> JsonRpcResult<Vec<RpcLog>>;
+            fn uninstall_filter(&self, filter_id: Index) -> JsonRpcResult<bool>;
             fn get_block_reward_info(&self, num: EpochNumber) -> JsonRpcResult<Vec<RpcRewardInfo>>;
+            fn trace_block(&self, block_hash: RpcH256) -> JsonRpcResult<Option<RpcBlockTrace>>;
+            fn trace_transaction(&self, tx_hash: RpcH256) -> JsonRpcResult<Option<Vec<RpcTrace>>>;
             fn send_raw_transaction(&self, raw: Bytes) -> JsonRpcResult<RpcH256>;
             fn storage_at(&self, addr: RpcH160, pos: RpcH256, epoch_number: Option<EpochNumber>)
                 -> BoxFuture<Option<RpcH256>>;
             fn transaction_by_hash(&self, hash: RpcH256) -> BoxFuture<Option<RpcTransaction>>;
             fn transaction_receipt(&self, tx_hash: RpcH256) -> BoxFuture<Option<RpcReceipt>>;
+            fn epoch_receipts(&self, epoch_number: EpochNumber) -> JsonRpcResult<Option<Vec<Vec<RpcReceipt>>>>;
+            fn gas_price_suggestion(&self) -> JsonRpcResult<GasPriceSuggestion>;
+            fn fee_history(&self, epoch_count: RpcU64, newest_epoch: EpochNumber, reward_percentiles: Vec<f64>) -> JsonRpcResult<RpcFeeHistory>;
+            fn block_receipts(&self, block_hash: RpcH256) -> JsonRpcResult<Option<Vec<RpcReceipt>>>;
+            fn block_transaction_count_by_hash(&self, block_hash: RpcH256) -> BoxFuture<Option<RpcU256>>;
+            fn block_transaction_count_by_epoch_number(&self, epoch_number: EpochNumber) -> BoxFuture<Option<RpcU256>>;
+            fn transaction_by_block_hash_and_index(&self, block_hash: RpcH256, index: Index) -> BoxFuture<Option<RpcTransaction>>;
+            fn transaction_by_epoch_number_and_index(&self, epoch_number: EpochNumber, index: Index) -> BoxFuture<Option<RpcTransaction>>;
             fn storage_root(&self, address: RpcH160, epoch_num: Option<EpochNumber>) -> JsonRpcResult<Option<RpcStorageRoot>>;
+            fn account_proof(&self, address: RpcH160, storage_keys: Vec<RpcH256>, epoch_number: Option<EpochNumber>) -> JsonRpcResult<AccountProof>;
         }
     }
 }
@@ -1083,6 +1791,11 @@ impl LocalRpc for LocalRpcImpl {
             fn net_disconnect_node(&self, id: NodeId, op: Option<UpdateNodeOperation>)
                 -> JsonRpcResult<bool>;
             fn net_sessions(&self, node_id: Option<NodeId>) -> JsonRpcResult<Vec<SessionDetails>>;
+            fn net_reputations(&self) -> JsonRpcResult<Vec<PeerReputation>>;
+            fn net_bans(&self) -> JsonRpcResult<Vec<Ban>>;
+            fn net_ban(&self, target: String, duration_secs: Option<u64>) -> JsonRpcResult<bool>;
+            fn net_unban(&self, target: String) -> JsonRpcResult<bool>;
+            fn net_reload_reserved_peers(&self) -> JsonRpcResult<usize>;
             fn net_throttling(&self) -> JsonRpcResult<throttling::Service>;
             fn tx_inspect(&self, hash: RpcH256) -> JsonRpcResult<BTreeMap<String, String>>;
             fn txpool_content(&self) -> JsonRpcResult<
@@ -1099,6 +1812,8 @@ impl LocalRpc for LocalRpcImpl {
             fn lock_account(&self, address: RpcH160) -> JsonRpcResult<bool>;
             fn sign(&self, data: Bytes, address: RpcH160, password: Option<String>)
                 -> JsonRpcResult<RpcH520>;
+            fn sign_typed_data(&self, address: RpcH160, typed_data: Value, password: Option<String>)
+                -> JsonRpcResult<RpcH520>;
         }
 
         to self.rpc_impl {
@@ -1107,6 +1822,11 @@ impl LocalRpc for LocalRpcImpl {
             fn sync_graph_state(&self) -> JsonRpcResult<SyncGraphStates>;
             fn send_transaction(
                 &self, tx: SendTxRequest, password: Option<String>) -> BoxFuture<RpcH256>;
+            fn debug_trace_call(
+                &self, request: CallRequest, epoch: Option<EpochNumber>, options: Option<TraceOptions>)
+                -> JsonRpcResult<ExecutionTrace>;
+            fn debug_trace_transaction(&self, tx_hash: RpcH256, options: Option<TraceOptions>)
+                -> JsonRpcResult<Option<ExecutionTrace>>;
         }
     }
 }
@@ -18,7 +18,7 @@ use cfxcore::{
     BlockDataManager, ConsensusGraph, ConsensusGraphTrait, PeerInfo,
     SharedConsensusGraph, SharedTransactionPool,
 };
-use cfxcore_accounts::AccountProvider;
+use cfxcore_accounts::{AccountProvider, TypedData};
 use cfxkey::Password;
 use keccak_hash::keccak;
 use primitives::{Action, SignedTransaction};
@@ -26,11 +26,10 @@ use primitives::{Action, SignedTransaction};
 use network::{
     node_table::{Node, NodeEndpoint, NodeEntry, NodeId},
     throttling::{self, THROTTLING_SERVICE},
-    NetworkService, SessionDetails, UpdateNodeOperation,
+    Ban, BanTarget, NetworkService, PeerReputation, SessionDetails,
+    UpdateNodeOperation,
 };
 
-use crate::accounts::{account_provider, keys_path};
-
 use crate::rpc::types::{
     Block as RpcBlock, BlockHashOrEpochNumber, Bytes, EpochNumber,
     Status as RpcStatus, Transaction as RpcTransaction, H160 as RpcH160,
@@ -66,21 +65,16 @@ pub struct RpcImpl {
     data_man: Arc<BlockDataManager>,
     network: Arc<NetworkService>,
     tx_pool: SharedTransactionPool,
-    accounts: Arc<AccountProvider>,
+    pub accounts: Arc<AccountProvider>,
 }
 
 impl RpcImpl {
     pub fn new(
         exit: Arc<(Mutex<bool>, Condvar)>, consensus: SharedConsensusGraph,
         network: Arc<NetworkService>, tx_pool: SharedTransactionPool,
+        accounts: Arc<AccountProvider>,
     ) -> Self
     {
-        let accounts = Arc::new(
-            account_provider(Some(keys_path()), None)
-                .ok()
-                .expect("failed to initialize account provider"),
-        );
-
         let data_man = consensus.get_data_manager().clone();
 
         RpcImpl {
@@ -510,6 +504,38 @@ impl RpcImpl {
         }
     }
 
+    pub fn net_reputations(&self) -> RpcResult<Vec<PeerReputation>> {
+        Ok(self.network.get_reputations().unwrap_or_default())
+    }
+
+    pub fn net_bans(&self) -> RpcResult<Vec<Ban>> {
+        Ok(self.network.get_bans().unwrap_or_default())
+    }
+
+    /// Ban a node id or an IP subnet, for `duration_secs` or permanently.
+    pub fn net_ban(
+        &self, target: String, duration_secs: Option<u64>,
+    ) -> RpcResult<bool> {
+        let target: BanTarget =
+            target.parse().map_err(RpcError::invalid_params)?;
+        Ok(self
+            .network
+            .ban(target, duration_secs.map(Duration::from_secs)))
+    }
+
+    pub fn net_unban(&self, target: String) -> RpcResult<bool> {
+        let target: BanTarget =
+            target.parse().map_err(RpcError::invalid_params)?;
+        Ok(self.network.unban(&target))
+    }
+
+    pub fn net_reload_reserved_peers(&self) -> RpcResult<usize> {
+        self.network.reload_reserved_peers().map_err(|e| {
+            warn!("Could not reload reserved peers. With error {:?}", e);
+            RpcError::internal_error()
+        })
+    }
+
     pub fn net_throttling(&self) -> RpcResult<throttling::Service> {
         Ok(THROTTLING_SERVICE.read().clone())
     }
@@ -728,6 +754,50 @@ impl RpcImpl {
         Ok(RpcH520(signature.into()))
     }
 
+    pub fn sign_typed_data(
+        &self, address: RpcH160, typed_data: RpcValue, password: Option<String>,
+    ) -> RpcResult<RpcH520> {
+        let typed_data = TypedData::from_json(typed_data)
+            .map_err(|e| RpcError::invalid_params(e.to_string()))?;
+        let chain_id = typed_data
+            .chain_id()
+            .map_err(|e| RpcError::invalid_params(e.to_string()))?;
+        let current_chain_id = self.consensus.best_chain_id();
+        match chain_id {
+            // Without a chain id, the signature could be replayed on any
+            // chain.
+            None => {
+                return Err(RpcError::invalid_params(
+                    "chainId of the domain is required",
+                ))
+            }
+            Some(chain_id) if chain_id != current_chain_id.into() => {
+                return Err(RpcError::invalid_params(format!(
+                    "chainId {} of the domain does not match the current chain id {}",
+                    chain_id, current_chain_id
+                )));
+            }
+            Some(_) => {}
+        }
+        let message = typed_data
+            .sign_hash()
+            .map_err(|e| RpcError::invalid_params(e.to_string()))?;
+
+        let password = password.map(Password::from);
+        let signature =
+            match self.accounts.sign(address.into(), password, message) {
+                Ok(signature) => signature,
+                Err(err) => {
+                    warn!(
+                        "Unable to sign the typed data. With error {:?}",
+                        err
+                    );
+                    return Err(RpcError::internal_error());
+                }
+            };
+        Ok(RpcH520(signature.into()))
+    }
+
     pub fn save_node_db(&self) -> RpcResult<()> {
         self.network.save_node_db();
         Ok(())
@@ -4,33 +4,54 @@
 
 use crate::rpc::{
     error_codes,
-    impls::{common::RpcImpl as CommonImpl, RpcImplConfiguration},
+    impls::{
+        call_result, common::RpcImpl as CommonImpl,
+        estimate_gas_and_collateral_result, RpcImplConfiguration,
+    },
     traits::{cfx::Cfx, debug::LocalRpc, test::TestRpc},
     types::{
-        Account as RpcAccount, BlameInfo, Block as RpcBlock,
-        BlockHashOrEpochNumber, Bytes, CallRequest,
-        CheckBalanceAgainstTransactionResponse, ConsensusGraphStates,
-        EpochNumber, EstimateGasAndCollateralResponse, Filter as RpcFilter,
-        Log as RpcLog, Receipt as RpcReceipt, RewardInfo as RpcRewardInfo,
-        SendTxRequest, SponsorInfo as RpcSponsorInfo, Status as RpcStatus,
-        StorageRoot as RpcStorageRoot, SyncGraphStates,
-        Transaction as RpcTransaction, H160 as RpcH160, H256 as RpcH256,
-        H520 as RpcH520, U128 as RpcU128, U256 as RpcU256, U64 as RpcU64,
+        sign_call, Account as RpcAccount, AccountProof, BlameInfo,
+        Block as RpcBlock, BlockHashOrEpochNumber, BlockTrace as RpcBlockTrace,
+        Bytes, CallRequest, CheckBalanceAgainstTransactionResponse,
+        ConsensusGraphStates, EpochNumber, EstimateGasAndCollateralResponse,
+        ExecutionTrace, FeeHistory as RpcFeeHistory, Filter as RpcFilter,
+        FilterChanges, GasPriceSuggestion, Index, Log as RpcLog,
+        Receipt as RpcReceipt, RewardInfo as RpcRewardInfo, SendTxRequest,
+        SponsorInfo as RpcSponsorInfo, Status as RpcStatus,
+        StorageRoot as RpcStorageRoot, SyncGraphStates, Trace as RpcTrace,
+        TraceOptions, Transaction as RpcTransaction, H160 as RpcH160,
+        H256 as RpcH256, H520 as RpcH520, U128 as RpcU128, U256 as RpcU256,
+        U64 as RpcU64,
     },
 };
 use cfx_types::{H160, H256, U256};
-use cfxcore::{LightQueryService, PeerInfo};
+use cfxcore::{executive::ExecutionOutcome, LightQueryService, PeerInfo};
+use cfxcore_accounts::AccountProvider;
 use delegate::delegate;
-use futures::future::{FutureExt, TryFutureExt};
+use futures::{
+    channel::oneshot,
+    future::{FutureExt, TryFutureExt},
+};
 use futures01;
-use jsonrpc_core::{BoxFuture, Error as RpcError, Result as RpcResult};
+use jsonrpc_core::{BoxFuture, Error as RpcError, Result as RpcResult, Value};
 use network::{
     node_table::{Node, NodeId},
-    throttling, SessionDetails, UpdateNodeOperation,
+    throttling, Ban, PeerReputation, SessionDetails, UpdateNodeOperation,
 };
+use parking_lot::Mutex;
 use primitives::{Account, TransactionWithSignature};
 use rlp::Encodable;
-use std::{collections::BTreeMap, net::SocketAddr, sync::Arc};
+use std::{collections::BTreeMap, future::Future, net::SocketAddr, sync::Arc};
+use threadpool::ThreadPool;
+
+/// The number of threads signing the transactions sent with
+/// `send_transaction`. An external signer may block one of them until its
+/// operator confirms the request.
+const SIGNER_THREADS: usize = 2;
+
+/// The maximum number of transactions being signed or waiting to be signed.
+/// Further `send_transaction` requests are rejected.
+const MAX_PENDING_SIGNATURES: usize = 16;
 
 pub struct RpcImpl {
     // configuration parameters
@@ -38,13 +59,31 @@ pub struct RpcImpl {
 
     // helper API for retrieving verified information from peers
     light: Arc<LightQueryService>,
+
+    // accounts to sign the transactions sent with
+    accounts: Arc<AccountProvider>,
+
+    // threads to sign the transactions sent with, off the futures executor
+    signer_pool: Arc<Mutex<ThreadPool>>,
 }
 
 impl RpcImpl {
     pub fn new(
         config: RpcImplConfiguration, light: Arc<LightQueryService>,
-    ) -> Self {
-        RpcImpl { config, light }
+        accounts: Arc<AccountProvider>,
+    ) -> Self
+    {
+        let signer_pool = Arc::new(Mutex::new(ThreadPool::with_name(
+            "RPC Signer".into(),
+            SIGNER_THREADS,
+        )));
+
+        RpcImpl {
+            config,
+            light,
+            accounts,
+            signer_pool,
+        }
     }
 
     fn account(
@@ -216,12 +255,17 @@ impl RpcImpl {
         Box::new(fut.boxed().compat())
     }
 
-    #[allow(unused_variables)]
     fn call(
         &self, request: CallRequest, epoch: Option<EpochNumber>,
-    ) -> RpcResult<Bytes> {
-        // TODO(thegaram)
-        Err(error_codes::unimplemented(None))
+    ) -> BoxFuture<Bytes> {
+        info!(
+            "RPC Request: cfx_call request={:?} epoch={:?}",
+            request, epoch
+        );
+
+        let outcome = self.exec_transaction(request, epoch);
+        let fut = async move { call_result(outcome.await?) };
+        Box::new(fut.boxed().compat())
     }
 
     fn code(
@@ -250,12 +294,43 @@ impl RpcImpl {
         Box::new(fut.boxed().compat())
     }
 
-    #[allow(unused_variables)]
     fn estimate_gas_and_collateral(
         &self, request: CallRequest, epoch_number: Option<EpochNumber>,
-    ) -> RpcResult<EstimateGasAndCollateralResponse> {
-        // TODO(thegaram)
-        Err(error_codes::unimplemented(None))
+    ) -> BoxFuture<EstimateGasAndCollateralResponse> {
+        info!(
+            "RPC Request: cfx_estimateGasAndCollateral request={:?} epoch={:?}",
+            request, epoch_number
+        );
+
+        let outcome = self.exec_transaction(request, epoch_number);
+        let fut =
+            async move { estimate_gas_and_collateral_result(outcome.await?) };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn exec_transaction(
+        &self, request: CallRequest, epoch: Option<EpochNumber>,
+    ) -> impl Future<Output = RpcResult<ExecutionOutcome>> + Send {
+        let epoch = epoch.unwrap_or(EpochNumber::LatestState).into();
+
+        // clone `self.light` to avoid lifetime issues due to capturing `self`
+        let light = self.light.clone();
+
+        async move {
+            let epoch_height = light
+                .get_latest_verifiable_epoch_number()
+                .map_err(|e| RpcError::invalid_params(format!("{}", e)))?;
+            let chain_id = light
+                .get_latest_verifiable_chain_id()
+                .map_err(|e| RpcError::invalid_params(format!("{}", e)))?;
+            let signed_tx = sign_call(epoch_height, chain_id, request);
+            trace!("call tx {:?}", signed_tx);
+
+            light
+                .call(epoch, signed_tx)
+                .await
+                .map_err(RpcError::invalid_params)
+        }
     }
 
     fn get_logs(&self, filter: RpcFilter) -> BoxFuture<Vec<RpcLog>> {
@@ -325,6 +400,8 @@ impl RpcImpl {
 
         // clone `self.light` to avoid lifetime issues due to capturing `self`
         let light = self.light.clone();
+        let accounts = self.accounts.clone();
+        let signer_pool = self.signer_pool.clone();
 
         let fut = async move {
             if tx.nonce.is_none() {
@@ -352,14 +429,41 @@ impl RpcImpl {
             let chain_id = light.get_latest_verifiable_chain_id().map_err(|_| {
                 RpcError::invalid_params(format!("the light client cannot retrieve/verify the latest chain_id."))
             })?;
-            let tx = tx.sign_with(epoch_height, chain_id, password).map_err(
-                |e| {
+            // The account may be signed for by an external signer, which
+            // blocks until its operator confirms the request, so do not sign
+            // in the executor of the futures.
+            let (sender, receiver) = oneshot::channel();
+            {
+                let signer_pool = signer_pool.lock();
+                if signer_pool.active_count() + signer_pool.queued_count()
+                    >= MAX_PENDING_SIGNATURES
+                {
+                    return Err(
+                        error_codes::request_rejected_too_many_request_error(
+                            Some("too many transactions being signed".into()),
+                        ),
+                    );
+                }
+                signer_pool.execute(move || {
+                    let signed = tx.sign_with(
+                        epoch_height,
+                        chain_id,
+                        password,
+                        &accounts,
+                    );
+                    sender.send(signed).ok();
+                });
+            }
+            let tx = receiver
+                .await
+                .map_err(|_| "signing thread exited".to_string())
+                .and_then(|signed| signed)
+                .map_err(|e| {
                     RpcError::invalid_params(format!(
                         "failed to send transaction: {:?}",
                         e
                     ))
-                },
-            )?;
+                })?;
 
             Self::send_tx_helper(light, Bytes::new(tx.rlp_bytes()))
         };
@@ -463,6 +567,133 @@ impl RpcImpl {
 
         Box::new(fut.boxed().compat())
     }
+
+    fn block_transaction_count_by_hash(
+        &self, block_hash: RpcH256,
+    ) -> BoxFuture<Option<RpcU256>> {
+        let hash: H256 = block_hash.into();
+        info!(
+            "RPC Request: cfx_getBlockTransactionCountByHash hash={:?}",
+            hash
+        );
+
+        // clone `self.light` to avoid lifetime issues due to capturing `self`
+        let light = self.light.clone();
+
+        let fut = async move {
+            let txs = light
+                .get_block_txs(hash)
+                .await
+                .map_err(RpcError::invalid_params)?;
+
+            Ok(txs.map(|txs| txs.len().into()))
+        };
+
+        Box::new(fut.boxed().compat())
+    }
+
+    fn block_transaction_count_by_epoch_number(
+        &self, epoch_number: EpochNumber,
+    ) -> BoxFuture<Option<RpcU256>> {
+        info!(
+            "RPC Request: cfx_getBlockTransactionCountByEpochNumber epoch={:?}",
+            epoch_number
+        );
+
+        // clone `self.light` to avoid lifetime issues due to capturing `self`
+        let light = self.light.clone();
+
+        let fut = async move {
+            let hash = light
+                .get_pivot_hash(epoch_number.into())
+                .map_err(RpcError::invalid_params)?;
+
+            let txs = light
+                .get_block_txs(hash)
+                .await
+                .map_err(RpcError::invalid_params)?;
+
+            Ok(txs.map(|txs| txs.len().into()))
+        };
+
+        Box::new(fut.boxed().compat())
+    }
+
+    fn transaction_by_block_hash_and_index(
+        &self, block_hash: RpcH256, index: Index,
+    ) -> BoxFuture<Option<RpcTransaction>> {
+        let hash: H256 = block_hash.into();
+        info!(
+            "RPC Request: cfx_getTransactionByBlockHashAndIndex hash={:?} index={:?}",
+            hash, index
+        );
+
+        // clone `self.light` to avoid lifetime issues due to capturing `self`
+        let light = self.light.clone();
+
+        let fut = async move {
+            Self::retrieve_block_tx(light, hash, index.value()).await
+        };
+
+        Box::new(fut.boxed().compat())
+    }
+
+    fn transaction_by_epoch_number_and_index(
+        &self, epoch_number: EpochNumber, index: Index,
+    ) -> BoxFuture<Option<RpcTransaction>> {
+        info!(
+            "RPC Request: cfx_getTransactionByEpochNumberAndIndex epoch={:?} index={:?}",
+            epoch_number, index
+        );
+
+        // clone `self.light` to avoid lifetime issues due to capturing `self`
+        let light = self.light.clone();
+
+        let fut = async move {
+            let hash = light
+                .get_pivot_hash(epoch_number.into())
+                .map_err(RpcError::invalid_params)?;
+
+            Self::retrieve_block_tx(light, hash, index.value()).await
+        };
+
+        Box::new(fut.boxed().compat())
+    }
+
+    async fn retrieve_block_tx(
+        light: Arc<LightQueryService>, hash: H256, index: usize,
+    ) -> RpcResult<Option<RpcTransaction>> {
+        let (tx, maybe_info) = match light
+            .get_block_tx(hash, index)
+            .await
+            .map_err(RpcError::invalid_params)?
+        {
+            Some(tx) => tx,
+            None => return Ok(None),
+        };
+
+        let receipt = maybe_info.map(
+            |(
+                tx,
+                receipt,
+                address,
+                maybe_epoch,
+                maybe_state_root,
+                prior_gas_used,
+            )| {
+                RpcReceipt::new(
+                    tx,
+                    receipt,
+                    address,
+                    prior_gas_used,
+                    maybe_epoch,
+                    maybe_state_root,
+                )
+            },
+        );
+
+        Ok(Some(RpcTransaction::from_signed(&tx, receipt)))
+    }
 }
 
 pub struct CfxHandler {
@@ -498,10 +729,10 @@ impl Cfx for CfxHandler {
             fn account(&self, address: RpcH160, num: Option<EpochNumber>) -> BoxFuture<RpcAccount>;
             fn admin(&self, address: RpcH160, num: Option<EpochNumber>) -> BoxFuture<Option<RpcH160>>;
             fn balance(&self, address: RpcH160, num: Option<EpochNumber>) -> BoxFuture<RpcU256>;
-            fn call(&self, request: CallRequest, epoch: Option<EpochNumber>) -> RpcResult<Bytes>;
+            fn call(&self, request: CallRequest, epoch: Option<EpochNumber>) -> BoxFuture<Bytes>;
             fn code(&self, address: RpcH160, epoch_num: Option<EpochNumber>) -> BoxFuture<Bytes>;
             fn collateral_for_storage(&self, address: RpcH160, num: Option<EpochNumber>) -> BoxFuture<RpcU256>;
-            fn estimate_gas_and_collateral(&self, request: CallRequest, epoch_num: Option<EpochNumber>) -> RpcResult<EstimateGasAndCollateralResponse>;
+            fn estimate_gas_and_collateral(&self, request: CallRequest, epoch_num: Option<EpochNumber>) -> BoxFuture<EstimateGasAndCollateralResponse>;
             fn get_logs(&self, filter: RpcFilter) -> BoxFuture<Vec<RpcLog>>;
             fn send_raw_transaction(&self, raw: Bytes) -> RpcResult<RpcH256>;
             fn sponsor_info(&self, address: RpcH160, num: Option<EpochNumber>) -> BoxFuture<RpcSponsorInfo>;
@@ -510,14 +741,31 @@ impl Cfx for CfxHandler {
             fn storage_root(&self, address: RpcH160, epoch_num: Option<EpochNumber>) -> RpcResult<Option<RpcStorageRoot>>;
             fn transaction_by_hash(&self, hash: RpcH256) -> BoxFuture<Option<RpcTransaction>>;
             fn transaction_receipt(&self, tx_hash: RpcH256) -> BoxFuture<Option<RpcReceipt>>;
+            fn block_transaction_count_by_hash(&self, block_hash: RpcH256) -> BoxFuture<Option<RpcU256>>;
+            fn block_transaction_count_by_epoch_number(&self, epoch_number: EpochNumber) -> BoxFuture<Option<RpcU256>>;
+            fn transaction_by_block_hash_and_index(&self, block_hash: RpcH256, index: Index) -> BoxFuture<Option<RpcTransaction>>;
+            fn transaction_by_epoch_number_and_index(&self, epoch_number: EpochNumber, index: Index) -> BoxFuture<Option<RpcTransaction>>;
         }
     }
 
     not_supported! {
+        fn account_proof(&self, address: RpcH160, storage_keys: Vec<RpcH256>, epoch_number: Option<EpochNumber>) -> RpcResult<AccountProof>;
         fn accumulate_interest_rate(&self, num: Option<EpochNumber>) -> RpcResult<RpcU256>;
         fn interest_rate(&self, num: Option<EpochNumber>) -> RpcResult<RpcU256>;
+        fn new_block_filter(&self) -> RpcResult<RpcU256>;
+        fn new_filter(&self, filter: RpcFilter) -> RpcResult<RpcU256>;
+        fn new_pending_transaction_filter(&self) -> RpcResult<RpcU256>;
+        fn block_receipts(&self, block_hash: RpcH256) -> RpcResult<Option<Vec<RpcReceipt>>>;
         fn check_balance_against_transaction(&self, account_addr: RpcH160, contract_addr: RpcH160, gas_limit: RpcU256, gas_price: RpcU256, storage_limit: RpcU256, epoch: Option<EpochNumber>) -> RpcResult<CheckBalanceAgainstTransactionResponse>;
+        fn epoch_receipts(&self, epoch_number: EpochNumber) -> RpcResult<Option<Vec<Vec<RpcReceipt>>>>;
+        fn fee_history(&self, epoch_count: RpcU64, newest_epoch: EpochNumber, reward_percentiles: Vec<f64>) -> RpcResult<RpcFeeHistory>;
+        fn filter_changes(&self, filter_id: Index) -> RpcResult<FilterChanges>;
+        fn filter_logs(&self, filter_id: Index) -> RpcResult<Vec<RpcLog>>;
+        fn gas_price_suggestion(&self) -> RpcResult<GasPriceSuggestion>;
         fn get_block_reward_info(&self, num: EpochNumber) -> RpcResult<Vec<RpcRewardInfo>>;
+        fn trace_block(&self, block_hash: RpcH256) -> RpcResult<Option<RpcBlockTrace>>;
+        fn trace_transaction(&self, tx_hash: RpcH256) -> RpcResult<Option<Vec<RpcTrace>>>;
+        fn uninstall_filter(&self, filter_id: Index) -> RpcResult<bool>;
     }
 }
 
@@ -585,6 +833,11 @@ impl LocalRpc for DebugRpcImpl {
             fn net_node(&self, id: NodeId) -> RpcResult<Option<(String, Node)>>;
             fn net_disconnect_node(&self, id: NodeId, op: Option<UpdateNodeOperation>) -> RpcResult<bool>;
             fn net_sessions(&self, node_id: Option<NodeId>) -> RpcResult<Vec<SessionDetails>>;
+            fn net_reputations(&self) -> RpcResult<Vec<PeerReputation>>;
+            fn net_bans(&self) -> RpcResult<Vec<Ban>>;
+            fn net_ban(&self, target: String, duration_secs: Option<u64>) -> RpcResult<bool>;
+            fn net_unban(&self, target: String) -> RpcResult<bool>;
+            fn net_reload_reserved_peers(&self) -> RpcResult<usize>;
             fn net_throttling(&self) -> RpcResult<throttling::Service>;
             fn tx_inspect(&self, hash: RpcH256) -> RpcResult<BTreeMap<String, String>>;
             fn txpool_content(&self) -> RpcResult<BTreeMap<String, BTreeMap<String, BTreeMap<usize, Vec<RpcTransaction>>>>>;
@@ -596,6 +849,7 @@ impl LocalRpc for DebugRpcImpl {
             fn unlock_account(&self, address: RpcH160, password: String, duration: Option<RpcU128>) -> RpcResult<bool>;
             fn lock_account(&self, address: RpcH160) -> RpcResult<bool>;
             fn sign(&self, data: Bytes, address: RpcH160, password: Option<String>) -> RpcResult<RpcH520>;
+            fn sign_typed_data(&self, address: RpcH160, typed_data: Value, password: Option<String>) -> RpcResult<RpcH520>;
         }
 
         to self.rpc_impl {
@@ -607,5 +861,7 @@ impl LocalRpc for DebugRpcImpl {
         fn current_sync_phase(&self) -> RpcResult<String>;
         fn consensus_graph_state(&self) -> RpcResult<ConsensusGraphStates>;
         fn sync_graph_state(&self) -> RpcResult<SyncGraphStates>;
+        fn debug_trace_call(&self, request: CallRequest, epoch: Option<EpochNumber>, options: Option<TraceOptions>) -> RpcResult<ExecutionTrace>;
+        fn debug_trace_transaction(&self, tx_hash: RpcH256, options: Option<TraceOptions>) -> RpcResult<Option<ExecutionTrace>>;
     }
 }
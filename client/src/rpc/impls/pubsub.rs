@@ -26,7 +26,7 @@ use std::{
 
 use cfxcore::{
     block_data_manager::BlockExecutionResult, channel::Channel,
-    BlockDataManager, Notifications, SharedConsensusGraph,
+    sync::SyncStatus, BlockDataManager, Notifications, SharedConsensusGraph,
     SynchronizationGraph,
 };
 
@@ -40,7 +40,7 @@ use parking_lot::RwLock;
 use primitives::{
     filter::Filter,
     log_entry::{LocalizedLogEntry, LogEntry},
-    BlockHeader, BlockReceipts,
+    BlockHeader, BlockReceipts, SignedTransaction,
 };
 use runtime::Executor;
 use tokio_timer::sleep;
@@ -54,6 +54,9 @@ pub struct PubSubClient {
     heads_subscribers: Arc<RwLock<Subscribers<Client>>>,
     epochs_subscribers: Arc<RwLock<Subscribers<Client>>>,
     logs_subscribers: Arc<RwLock<Subscribers<(Client, Filter)>>>,
+    pending_transactions_subscribers:
+        Arc<RwLock<Subscribers<(Client, pubsub::PendingTransactionsFilter)>>>,
+    syncing_subscribers: Arc<RwLock<Subscribers<Client>>>,
     epochs_ordered: Arc<Channel<(u64, Vec<H256>)>>,
 }
 
@@ -67,6 +70,9 @@ impl PubSubClient {
         let heads_subscribers = Arc::new(RwLock::new(Subscribers::default()));
         let epochs_subscribers = Arc::new(RwLock::new(Subscribers::default()));
         let logs_subscribers = Arc::new(RwLock::new(Subscribers::default()));
+        let pending_transactions_subscribers =
+            Arc::new(RwLock::new(Subscribers::default()));
+        let syncing_subscribers = Arc::new(RwLock::new(Subscribers::default()));
 
         let handler = Arc::new(ChainNotificationHandler {
             executor,
@@ -75,6 +81,9 @@ impl PubSubClient {
             heads_subscribers: heads_subscribers.clone(),
             epochs_subscribers: epochs_subscribers.clone(),
             logs_subscribers: logs_subscribers.clone(),
+            pending_transactions_subscribers: pending_transactions_subscribers
+                .clone(),
+            syncing_subscribers: syncing_subscribers.clone(),
         });
 
         // --------- newHeads ---------
@@ -91,11 +100,35 @@ impl PubSubClient {
         // run futures@0.3 future on tokio@0.1 executor
         handler.executor.spawn(fut.unit_error().boxed().compat());
 
+        // --------- newPendingTransactions ---------
+        // subscribe to the `new_pending_transactions` channel
+        let receiver = notifications.new_pending_transactions.subscribe();
+        let handler_clone = handler.clone();
+
+        let fut = receiver.for_each(move |txs| {
+            handler_clone.notify_pending_transactions(&txs);
+        });
+
+        handler.executor.spawn(fut.unit_error().boxed().compat());
+
+        // --------- syncing ---------
+        // subscribe to the `sync_status` channel
+        let receiver = notifications.sync_status.subscribe();
+        let handler_clone = handler.clone();
+
+        let fut = receiver.for_each(move |status| {
+            handler_clone.notify_sync_status(status);
+        });
+
+        handler.executor.spawn(fut.unit_error().boxed().compat());
+
         PubSubClient {
             handler,
             heads_subscribers,
             epochs_subscribers,
             logs_subscribers,
+            pending_transactions_subscribers,
+            syncing_subscribers,
             epochs_ordered: notifications.epochs_ordered.clone(),
         }
     }
@@ -203,6 +236,9 @@ pub struct ChainNotificationHandler {
     heads_subscribers: Arc<RwLock<Subscribers<Client>>>,
     epochs_subscribers: Arc<RwLock<Subscribers<Client>>>,
     logs_subscribers: Arc<RwLock<Subscribers<(Client, Filter)>>>,
+    pending_transactions_subscribers:
+        Arc<RwLock<Subscribers<(Client, pubsub::PendingTransactionsFilter)>>>,
+    syncing_subscribers: Arc<RwLock<Subscribers<Client>>>,
 }
 
 impl ChainNotificationHandler {
@@ -252,6 +288,42 @@ impl ChainNotificationHandler {
         }
     }
 
+    // notify each subscriber about the hashes of the transactions in `txs`
+    // matching its filter, in the order of insertion
+    fn notify_pending_transactions(&self, txs: &[Arc<SignedTransaction>]) {
+        trace!("notify_pending_transactions({:?})", txs.len());
+
+        for (subscriber, filter) in
+            self.pending_transactions_subscribers.read().values()
+        {
+            let hashes = txs
+                .iter()
+                .filter(|tx| filter.matches(tx))
+                .map(|tx| pubsub::Result::TransactionHash(tx.hash().into()));
+
+            // FIXME(thegaram): Sink::notify flushes after each item.
+            // consider sending them in a batch.
+            for result in hashes {
+                Self::notify(&self.executor, subscriber, result);
+            }
+        }
+    }
+
+    fn notify_sync_status(&self, status: SyncStatus) {
+        trace!("notify_sync_status({:?})", status);
+
+        let subscribers = self.syncing_subscribers.read();
+        let status = pubsub::SyncStatus::from(status);
+
+        for subscriber in subscribers.values() {
+            Self::notify(
+                &self.executor,
+                subscriber,
+                pubsub::Result::SyncState(status.clone()),
+            );
+        }
+    }
+
     async fn notify_epoch(&self, subscriber: Client, epoch: (u64, Vec<H256>)) {
         trace!("notify_epoch({:?})", epoch);
 
@@ -451,7 +523,37 @@ impl PubSub for PubSubClient {
                 "logs",
                 "Expected filter parameter.",
             ),
-            _ => error_codes::unimplemented(None),
+            // --------- newPendingTransactions ---------
+            (pubsub::Kind::NewPendingTransactions, None) => {
+                self.pending_transactions_subscribers
+                    .write()
+                    .push(subscriber, Default::default());
+                return;
+            }
+            (
+                pubsub::Kind::NewPendingTransactions,
+                Some(pubsub::Params::PendingTransactions(filter)),
+            ) => {
+                self.pending_transactions_subscribers
+                    .write()
+                    .push(subscriber, filter);
+                return;
+            }
+            (pubsub::Kind::NewPendingTransactions, _) => {
+                error_codes::invalid_params(
+                    "newPendingTransactions",
+                    "Expected no parameters or a from/to filter.",
+                )
+            }
+            // --------- syncing ---------
+            (pubsub::Kind::Syncing, None) => {
+                self.syncing_subscribers.write().push(subscriber);
+                return;
+            }
+            (pubsub::Kind::Syncing, _) => error_codes::invalid_params(
+                "syncing",
+                "Expected no parameters.",
+            ),
         };
 
         let _ = subscriber.reject(error);
@@ -463,7 +565,13 @@ impl PubSub for PubSubClient {
         let res0 = self.heads_subscribers.write().remove(&id).is_some();
         let res1 = self.epochs_subscribers.write().remove(&id).is_some();
         let res2 = self.logs_subscribers.write().remove(&id).is_some();
-
-        Ok(res0 || res1 || res2)
+        let res3 = self
+            .pending_transactions_subscribers
+            .write()
+            .remove(&id)
+            .is_some();
+        let res4 = self.syncing_subscribers.write().remove(&id).is_some();
+
+        Ok(res0 || res1 || res2 || res3 || res4)
     }
 }
@@ -3,13 +3,14 @@
 // See http://www.gnu.org/licenses/
 
 use super::super::types::{
-    Account as RpcAccount, Block, Bytes, CallRequest,
-    CheckBalanceAgainstTransactionResponse, EpochNumber,
-    EstimateGasAndCollateralResponse, Filter as RpcFilter, Log as RpcLog,
-    Receipt as RpcReceipt, RewardInfo as RpcRewardInfo,
+    Account as RpcAccount, AccountProof, Block, BlockTrace as RpcBlockTrace,
+    Bytes, CallRequest, CheckBalanceAgainstTransactionResponse, EpochNumber,
+    EstimateGasAndCollateralResponse, FeeHistory as RpcFeeHistory,
+    Filter as RpcFilter, FilterChanges, GasPriceSuggestion, Index,
+    Log as RpcLog, Receipt as RpcReceipt, RewardInfo as RpcRewardInfo,
     SponsorInfo as RpcSponsorInfo, Status as RpcStatus,
-    StorageRoot as RpcStorageRoot, Transaction, H160 as RpcH160,
-    H256 as RpcH256, U256 as RpcU256, U64 as RpcU64,
+    StorageRoot as RpcStorageRoot, Trace as RpcTrace, Transaction,
+    H160 as RpcH160, H256 as RpcH256, U256 as RpcU256, U64 as RpcU64,
 };
 use crate::rpc::types::BlockHashOrEpochNumber;
 use jsonrpc_core::{BoxFuture, Result as JsonRpcResult};
@@ -38,6 +39,19 @@ pub trait Cfx {
     #[rpc(name = "cfx_gasPrice")]
     fn gas_price(&self) -> JsonRpcResult<RpcU256>;
 
+    /// Returns the suggested gas prices for slow, standard and fast packing.
+    #[rpc(name = "cfx_gasPriceSuggestion")]
+    fn gas_price_suggestion(&self) -> JsonRpcResult<GasPriceSuggestion>;
+
+    /// Returns the gas used ratios of the `epoch_count` executed epochs up to
+    /// `newest_epoch`, and the gas prices at the given percentiles of the gas
+    /// used in each of them.
+    #[rpc(name = "cfx_feeHistory")]
+    fn fee_history(
+        &self, epoch_count: RpcU64, newest_epoch: EpochNumber,
+        reward_percentiles: Vec<f64>,
+    ) -> JsonRpcResult<RpcFeeHistory>;
+
     /// Returns highest epoch number.
     #[rpc(name = "cfx_epochNumber")]
     fn epoch_number(
@@ -91,6 +105,14 @@ pub trait Cfx {
         &self, address: RpcH160, epoch_num: Option<EpochNumber>,
     ) -> JsonRpcResult<Option<RpcStorageRoot>>;
 
+    /// Returns the account and the given storage entries of an address, with
+    /// their Merkle proofs against the state root of the epoch.
+    #[rpc(name = "cfx_getProof")]
+    fn account_proof(
+        &self, address: RpcH160, storage_keys: Vec<RpcH256>,
+        epoch_number: Option<EpochNumber>,
+    ) -> JsonRpcResult<AccountProof>;
+
     /// Returns block with given hash.
     #[rpc(name = "cfx_getBlockByHash")]
     fn block_by_hash(
@@ -120,15 +142,18 @@ pub trait Cfx {
         &self, addr: RpcH160, epoch_number: Option<BlockHashOrEpochNumber>,
     ) -> JsonRpcResult<RpcU256>;
 
-    //        /// Returns the number of transactions in a block with given hash.
-    //        #[rpc(name = "cfx_getBlockTransactionCountByHash")]
-    //        fn block_transaction_count_by_hash(&self, RpcH256) ->
-    // BoxFuture<Option<RpcU256>>;
+    /// Returns the number of transactions in a block with given hash.
+    #[rpc(name = "cfx_getBlockTransactionCountByHash")]
+    fn block_transaction_count_by_hash(
+        &self, block_hash: RpcH256,
+    ) -> BoxFuture<Option<RpcU256>>;
 
-    //        /// Returns the number of transactions in a block with given block
-    // number.        #[rpc(name = "cfx_getBlockTransactionCountByNumber")]
-    //        fn block_trasaction_count_by_number(&self, BlockNumber) ->
-    // BoxFuture<Option<RpcU256>>;
+    /// Returns the number of transactions in the pivot block of the given
+    /// epoch.
+    #[rpc(name = "cfx_getBlockTransactionCountByEpochNumber")]
+    fn block_transaction_count_by_epoch_number(
+        &self, epoch_number: EpochNumber,
+    ) -> BoxFuture<Option<RpcU256>>;
 
     /// Sends signed transaction, returning its hash.
     #[rpc(name = "cfx_sendRawTransaction")]
@@ -142,12 +167,37 @@ pub trait Cfx {
     #[rpc(name = "cfx_call")]
     fn call(
         &self, tx: CallRequest, epoch_number: Option<EpochNumber>,
-    ) -> JsonRpcResult<Bytes>;
+    ) -> BoxFuture<Bytes>;
 
     /// Returns logs matching the filter provided.
     #[rpc(name = "cfx_getLogs")]
     fn get_logs(&self, filter: RpcFilter) -> BoxFuture<Vec<RpcLog>>;
 
+    /// Creates a filter of the logs in new epochs, and returns its id.
+    #[rpc(name = "cfx_newFilter")]
+    fn new_filter(&self, filter: RpcFilter) -> JsonRpcResult<RpcU256>;
+
+    /// Creates a filter of the blocks in new epochs, and returns its id.
+    #[rpc(name = "cfx_newBlockFilter")]
+    fn new_block_filter(&self) -> JsonRpcResult<RpcU256>;
+
+    /// Creates a filter of the new transactions in the pool, and returns its
+    /// id.
+    #[rpc(name = "cfx_newPendingTransactionFilter")]
+    fn new_pending_transaction_filter(&self) -> JsonRpcResult<RpcU256>;
+
+    /// Returns the changes of a filter since the last poll.
+    #[rpc(name = "cfx_getFilterChanges")]
+    fn filter_changes(&self, filter_id: Index) -> JsonRpcResult<FilterChanges>;
+
+    /// Returns all logs matching a log filter.
+    #[rpc(name = "cfx_getFilterLogs")]
+    fn filter_logs(&self, filter_id: Index) -> JsonRpcResult<Vec<RpcLog>>;
+
+    /// Uninstalls a filter, returning whether it existed.
+    #[rpc(name = "cfx_uninstallFilter")]
+    fn uninstall_filter(&self, filter_id: Index) -> JsonRpcResult<bool>;
+
     /// Get transaction by its hash.
     #[rpc(name = "cfx_getTransactionByHash")]
     fn transaction_by_hash(
@@ -158,7 +208,7 @@ pub trait Cfx {
     #[rpc(name = "cfx_estimateGasAndCollateral")]
     fn estimate_gas_and_collateral(
         &self, request: CallRequest, epoch_number: Option<EpochNumber>,
-    ) -> JsonRpcResult<EstimateGasAndCollateralResponse>;
+    ) -> BoxFuture<EstimateGasAndCollateralResponse>;
 
     /// Check if user balance is enough for the transaction.
     #[rpc(name = "cfx_checkBalanceAgainstTransaction")]
@@ -183,6 +233,19 @@ pub trait Cfx {
         &self, tx_hash: RpcH256,
     ) -> BoxFuture<Option<RpcReceipt>>;
 
+    /// Returns the receipts of all the blocks of an epoch, grouped per block
+    /// in execution order.
+    #[rpc(name = "cfx_getEpochReceipts")]
+    fn epoch_receipts(
+        &self, epoch_number: EpochNumber,
+    ) -> JsonRpcResult<Option<Vec<Vec<RpcReceipt>>>>;
+
+    /// Returns the receipts of a block as executed in its epoch.
+    #[rpc(name = "cfx_getBlockReceipts")]
+    fn block_receipts(
+        &self, block_hash: RpcH256,
+    ) -> JsonRpcResult<Option<Vec<RpcReceipt>>>;
+
     /// Return account related states of the given account
     #[rpc(name = "cfx_getAccount")]
     fn account(
@@ -215,15 +278,30 @@ pub trait Cfx {
         &self, num: EpochNumber,
     ) -> JsonRpcResult<Vec<RpcRewardInfo>>;
 
-    //        /// Returns transaction at given block hash and index.
-    //        #[rpc(name = "cfx_getTransactionByBlockHashAndIndex")]
-    //        fn transaction_by_block_hash_and_index(&self, RpcH256, Index) ->
-    // BoxFuture<Option<Transaction>>;
+    /// Returns the call traces of all transactions in a block.
+    #[rpc(name = "cfx_traceBlock")]
+    fn trace_block(
+        &self, block_hash: RpcH256,
+    ) -> JsonRpcResult<Option<RpcBlockTrace>>;
 
-    //        /// Returns transaction by given block number and index.
-    //        #[rpc(name = "cfx_getTransactionByBlockNumberAndIndex")]
-    //        fn transaction_by_block_number_and_index(&self, BlockNumber,
-    // Index) -> BoxFuture<Option<Transaction>>;
+    /// Returns the call traces of a transaction.
+    #[rpc(name = "cfx_traceTransaction")]
+    fn trace_transaction(
+        &self, tx_hash: RpcH256,
+    ) -> JsonRpcResult<Option<Vec<RpcTrace>>>;
+
+    /// Returns transaction at given block hash and index.
+    #[rpc(name = "cfx_getTransactionByBlockHashAndIndex")]
+    fn transaction_by_block_hash_and_index(
+        &self, block_hash: RpcH256, index: Index,
+    ) -> BoxFuture<Option<Transaction>>;
+
+    /// Returns transaction at given index in the pivot block of the given
+    /// epoch.
+    #[rpc(name = "cfx_getTransactionByEpochNumberAndIndex")]
+    fn transaction_by_epoch_number_and_index(
+        &self, epoch_number: EpochNumber, index: Index,
+    ) -> BoxFuture<Option<Transaction>>;
 
     //        /// Returns uncles at given block and index.
     //        #[rpc(name = "cfx_getUnclesByBlockHashAndIndex")]
@@ -3,16 +3,17 @@
 // See http://www.gnu.org/licenses/
 
 use super::super::types::{
-    Bytes as RpcBytes, ConsensusGraphStates, SyncGraphStates,
+    Bytes as RpcBytes, CallRequest, ConsensusGraphStates, EpochNumber,
+    ExecutionTrace, SyncGraphStates, TraceOptions,
     Transaction as RpcTransaction, H160 as RpcH160, H256 as RpcH256,
     H520 as RpcH520, U128 as RpcU128,
 };
 use crate::rpc::types::SendTxRequest;
-use jsonrpc_core::{BoxFuture, Result as JsonRpcResult};
+use jsonrpc_core::{BoxFuture, Result as JsonRpcResult, Value};
 use jsonrpc_derive::rpc;
 use network::{
     node_table::{Node, NodeId},
-    throttling, SessionDetails, UpdateNodeOperation,
+    throttling, Ban, PeerReputation, SessionDetails, UpdateNodeOperation,
 };
 use std::collections::BTreeMap;
 
@@ -67,6 +68,27 @@ pub trait LocalRpc {
         &self, node_id: Option<NodeId>,
     ) -> JsonRpcResult<Vec<SessionDetails>>;
 
+    #[rpc(name = "net_reputations")]
+    fn net_reputations(&self) -> JsonRpcResult<Vec<PeerReputation>>;
+
+    #[rpc(name = "net_bans")]
+    fn net_bans(&self) -> JsonRpcResult<Vec<Ban>>;
+
+    /// Ban a node id or an IP subnet in CIDR notation, permanently if
+    /// `duration_secs` is not specified.
+    #[rpc(name = "net_ban")]
+    fn net_ban(
+        &self, target: String, duration_secs: Option<u64>,
+    ) -> JsonRpcResult<bool>;
+
+    #[rpc(name = "net_unban")]
+    fn net_unban(&self, target: String) -> JsonRpcResult<bool>;
+
+    /// Reload the reserved peers file, and return the number of reserved
+    /// peers.
+    #[rpc(name = "net_reload_reserved_peers")]
+    fn net_reload_reserved_peers(&self) -> JsonRpcResult<usize>;
+
     #[rpc(name = "current_sync_phase")]
     fn current_sync_phase(&self) -> JsonRpcResult<String>;
 
@@ -103,4 +125,27 @@ pub trait LocalRpc {
     fn sign(
         &self, data: RpcBytes, address: RpcH160, password: Option<String>,
     ) -> JsonRpcResult<RpcH520>;
+
+    /// Signs typed structured data as specified by EIP-712. The typed data
+    /// is a JSON object, or a string of it, and the `chainId` of its domain
+    /// must be the current chain id if set.
+    #[rpc(name = "cfx_signTypedData")]
+    fn sign_typed_data(
+        &self, address: RpcH160, typed_data: Value, password: Option<String>,
+    ) -> JsonRpcResult<RpcH520>;
+
+    /// Executes a call like `cfx_call` and returns the vm state at each
+    /// executed instruction.
+    #[rpc(name = "debug_traceCall")]
+    fn debug_trace_call(
+        &self, request: CallRequest, epoch: Option<EpochNumber>,
+        options: Option<TraceOptions>,
+    ) -> JsonRpcResult<ExecutionTrace>;
+
+    /// Re-executes a transaction and returns the vm state at each executed
+    /// instruction.
+    #[rpc(name = "debug_traceTransaction")]
+    fn debug_trace_transaction(
+        &self, tx_hash: RpcH256, options: Option<TraceOptions>,
+    ) -> JsonRpcResult<Option<ExecutionTrace>>;
 }
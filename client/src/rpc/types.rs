@@ -9,16 +9,20 @@ mod bytes;
 mod call_request;
 mod consensus_graph_states;
 mod epoch_number;
+mod fee_history;
 mod filter;
 mod hash;
 mod index;
 mod log;
+mod proof;
 mod provenance;
 mod receipt;
 mod reward_info;
 mod status;
 mod storage_root;
+mod struct_log;
 mod sync_graph_states;
+mod trace;
 mod transaction;
 mod uint;
 
@@ -27,7 +31,7 @@ pub mod pubsub;
 pub use self::{
     account::{Account, SponsorInfo},
     blame_info::BlameInfo,
-    block::{Block, BlockTransactions, Header},
+    block::{transaction_in_block, Block, BlockTransactions, Header},
     bytes::Bytes,
     call_request::{
         sign_call, CallRequest, CheckBalanceAgainstTransactionResponse,
@@ -35,16 +39,20 @@ pub use self::{
     },
     consensus_graph_states::ConsensusGraphStates,
     epoch_number::{BlockHashOrEpochNumber, EpochNumber},
-    filter::Filter,
+    fee_history::{FeeHistory, GasPriceSuggestion},
+    filter::{Filter, FilterChanges, FilterLog},
     hash::{H160, H2048, H256, H512, H520, H64},
     index::Index,
     log::Log,
+    proof::{AccountProof, StorageProof},
     provenance::Origin,
-    receipt::Receipt,
+    receipt::{Receipt, StorageChange},
     reward_info::RewardInfo,
     status::Status,
     storage_root::StorageRoot,
+    struct_log::{ExecutionTrace, TraceOptions},
     sync_graph_states::SyncGraphStates,
+    trace::{BlockTrace, Trace},
     transaction::{SendTxRequest, Transaction},
     uint::{U128, U256, U64},
 };
@@ -17,7 +17,7 @@ use primitives::{
         TRANSACTION_OUTCOME_SUCCESS,
     },
     Block as PrimitiveBlock, BlockHeader as PrimitiveBlockHeader,
-    BlockHeaderBuilder, TransactionIndex,
+    BlockHeaderBuilder, BlockReceipts, TransactionIndex,
 };
 use serde::{
     de::{Deserialize, Deserializer, Error, Unexpected},
@@ -141,55 +141,18 @@ impl Block {
                     .collect(),
             ),
             true => {
-                let tx_vec = match consensus_inner
-                    .block_execution_results_by_hash(&b.hash(), false /* update_cache */)
-                {
-                    Some(BlockExecutionResultWithEpoch(_, execution_result)) => b
-                        .transactions
-                        .iter()
-                        .enumerate()
-                        .map(|(idx, tx)| {
-                            let receipt = execution_result.block_receipts.receipts.get(idx).unwrap();
-                            let prior_gas_used = if idx == 0 {
-                                 CfxU256::zero()
-                            } else {
-                                execution_result.block_receipts.receipts.get(idx - 1).unwrap().accumulated_gas_used
-                            };
-                            match receipt.outcome_status {
-                                TRANSACTION_OUTCOME_SUCCESS
-                                | TRANSACTION_OUTCOME_EXCEPTION_WITH_NONCE_BUMPING => {
-                                    Transaction::from_signed(
-                                        tx,
-                                        Some(Receipt::new(
-                                            (**tx).clone(),
-                                            receipt.clone(),
-                                            TransactionIndex {
-                                                block_hash: b.hash(),
-                                                index: idx,
-                                            },
-                                            prior_gas_used,
-                                            // TODO: set these fields below.
-                                            /* maybe_epoch_number = */
-                                            None,
-                                            /* maybe_state_root = */ None,
-                                        )),
-                                    )
-                                }
-                                TRANSACTION_OUTCOME_EXCEPTION_WITHOUT_NONCE_BUMPING => {
-                                    Transaction::from_signed(tx, None)
-                                }
-                                _ => {
-                                    unreachable!();
-                                }
-                            }
-                        })
-                        .collect(),
-                    None => b
-                        .transactions
-                        .iter()
-                        .map(|x| Transaction::from_signed(x, None))
-                        .collect(),
-                };
+                let execution_result = consensus_inner
+                    .block_execution_results_by_hash(
+                        &b.hash(),
+                        false, /* update_cache */
+                    )
+                    .map(|BlockExecutionResultWithEpoch(_, result)| result);
+                let block_receipts = execution_result
+                    .as_ref()
+                    .map(|result| &*result.block_receipts);
+                let tx_vec = (0..b.transactions.len())
+                    .map(|idx| transaction_in_block(b, idx, block_receipts))
+                    .collect();
                 BlockTransactions::Full(tx_vec)
             }
         };
@@ -366,6 +329,52 @@ impl Header {
     }
 }
 
+/// Returns the transaction at `idx` in block `b` as in a block with full
+/// transactions. The receipt is included if the block is executed and the
+/// transaction is not skipped.
+pub fn transaction_in_block(
+    b: &PrimitiveBlock, idx: usize, block_receipts: Option<&BlockReceipts>,
+) -> Transaction {
+    let tx = &b.transactions[idx];
+    let receipts = match block_receipts {
+        Some(block_receipts) => &block_receipts.receipts,
+        None => return Transaction::from_signed(tx, None),
+    };
+    let receipt = receipts.get(idx).unwrap();
+    let prior_gas_used = if idx == 0 {
+        CfxU256::zero()
+    } else {
+        receipts.get(idx - 1).unwrap().accumulated_gas_used
+    };
+    match receipt.outcome_status {
+        TRANSACTION_OUTCOME_SUCCESS
+        | TRANSACTION_OUTCOME_EXCEPTION_WITH_NONCE_BUMPING => {
+            Transaction::from_signed(
+                tx,
+                Some(Receipt::new(
+                    (**tx).clone(),
+                    receipt.clone(),
+                    TransactionIndex {
+                        block_hash: b.hash(),
+                        index: idx,
+                    },
+                    prior_gas_used,
+                    // TODO: set these fields below.
+                    /* maybe_epoch_number = */
+                    None,
+                    /* maybe_state_root = */ None,
+                )),
+            )
+        }
+        TRANSACTION_OUTCOME_EXCEPTION_WITHOUT_NONCE_BUMPING => {
+            Transaction::from_signed(tx, None)
+        }
+        _ => {
+            unreachable!();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{Block, BlockTransactions, Header};
@@ -0,0 +1,35 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::rpc::types::{U256, U64};
+use cfxcore::consensus::fee_history::GasPriceSuggestion as CoreGasPriceSuggestion;
+
+#[derive(Debug, Serialize, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeHistory {
+    /// The first epoch of the returned range.
+    pub oldest_epoch: U64,
+    /// The ratio of gas used to the sum of the block gas limits per epoch.
+    pub gas_used_ratio: Vec<f64>,
+    /// The gas prices at the requested percentiles of the gas used per epoch.
+    pub reward: Vec<Vec<U256>>,
+}
+
+#[derive(Debug, Serialize, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPriceSuggestion {
+    pub slow: U256,
+    pub standard: U256,
+    pub fast: U256,
+}
+
+impl From<CoreGasPriceSuggestion> for GasPriceSuggestion {
+    fn from(suggestion: CoreGasPriceSuggestion) -> Self {
+        GasPriceSuggestion {
+            slow: suggestion.slow.into(),
+            standard: suggestion.standard.into(),
+            fast: suggestion.fast.into(),
+        }
+    }
+}
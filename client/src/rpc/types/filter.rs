@@ -2,7 +2,7 @@
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-use super::EpochNumber;
+use super::{EpochNumber, Log, H256 as RpcH256, U256 as RpcU256};
 use cfx_types::{H160, H256, U64};
 use jsonrpc_core::Error as RpcError;
 use primitives::filter::Filter as PrimitiveFilter;
@@ -174,13 +174,37 @@ impl Filter {
     }
 }
 
+/// Results of `cfx_getFilterChanges`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum FilterChanges {
+    /// New logs of a log filter.
+    Logs(Vec<FilterLog>),
+    /// New hashes of a block or pending transaction filter.
+    Hashes(Vec<RpcH256>),
+}
+
+/// An item of the changes of a log filter. Like the `ChainReorg`
+/// notification of log subscriptions, a `ChainReorg` item tells that the
+/// logs reported after epoch `revert_to` are not valid anymore.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum FilterLog {
+    Log(Log),
+
+    #[serde(rename_all = "camelCase")]
+    ChainReorg {
+        revert_to: RpcU256,
+    },
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{EpochNumber, Filter, VariadicValue};
+    use super::{EpochNumber, Filter, FilterChanges, FilterLog, VariadicValue};
     use cfx_types::{Address, H160, H256, U64};
     use primitives::{
         epoch::EpochNumber as PrimitiveEpochNumber,
-        filter::Filter as PrimitiveFilter,
+        filter::Filter as PrimitiveFilter, log_entry::LogEntry,
     };
     use serde_json;
     use std::str::FromStr;
@@ -397,4 +421,32 @@ mod tests {
 
         assert_eq!(filter.into_primitive(), Ok(primitive_filter));
     }
+
+    #[test]
+    fn test_serialize_filter_changes() {
+        let changes = FilterChanges::Hashes(vec![H256::zero().into()]);
+        assert_eq!(
+            serde_json::to_string(&changes).unwrap(),
+            "[\"0x0000000000000000000000000000000000000000000000000000000000000000\"]"
+        );
+
+        let log = LogEntry {
+            address: Address::zero(),
+            topics: vec![],
+            data: vec![],
+        };
+        let changes = FilterChanges::Logs(vec![
+            FilterLog::ChainReorg {
+                revert_to: 10u64.into(),
+            },
+            FilterLog::Log(log.into()),
+        ]);
+        assert_eq!(
+            serde_json::to_string(&changes).unwrap(),
+            "[\
+             {\"revertTo\":\"0xa\"},\
+             {\"address\":\"0x0000000000000000000000000000000000000000\",\"topics\":[],\"data\":\"0x\"}\
+             ]"
+        );
+    }
 }
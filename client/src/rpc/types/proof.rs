@@ -0,0 +1,30 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::rpc::types::{Bytes, H160, H256};
+use cfxcore::storage::StateRootWithAuxInfo;
+
+/// The state entries of an account and of some of its storage slots, with
+/// their Merkle proofs. Values and proofs are RLP encoded as in the state,
+/// and a missing value is proven not to exist.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    pub address: H160,
+    pub account: Option<Bytes>,
+    pub account_proof: Bytes,
+    pub storage_proof: Vec<StorageProof>,
+    /// The state root of the epoch, whose hash is the deferred state root in
+    /// the header of the pivot block `DEFERRED_STATE_EPOCH_COUNT` epochs
+    /// later.
+    pub state_root: StateRootWithAuxInfo,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageProof {
+    pub key: H256,
+    pub value: Option<Bytes>,
+    pub proof: Bytes,
+}
@@ -20,7 +20,10 @@
 
 //! Pub-Sub types.
 
-use super::{Filter, Header, Log, H256, U256};
+use super::{filter::VariadicValue, Filter, Header, Log, H256, U256};
+use cfx_types::H160;
+use cfxcore::sync::SyncStatus as PrimitiveSyncStatus;
+use primitives::{Action, SignedTransaction};
 use serde::{de::Error, Deserialize, Deserializer, Serialize};
 use serde_json::{from_value, Value};
 
@@ -50,6 +53,33 @@ pub enum Result {
     /// Chain reorg
     #[serde(rename_all = "camelCase")]
     ChainReorg { revert_to: U256 },
+
+    /// Sync status
+    SyncState(SyncStatus),
+}
+
+/// Sync status of the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    /// Name of the current sync phase.
+    pub phase: String,
+    pub catch_up_mode: bool,
+    /// Latest epoch of the local node.
+    pub current_epoch: U256,
+    /// Median latest epoch of the peers, if known.
+    pub highest_epoch: Option<U256>,
+}
+
+impl From<PrimitiveSyncStatus> for SyncStatus {
+    fn from(status: PrimitiveSyncStatus) -> Self {
+        SyncStatus {
+            phase: status.phase.into(),
+            catch_up_mode: status.catch_up_mode,
+            current_epoch: status.current_epoch.into(),
+            highest_epoch: status.highest_epoch.map(Into::into),
+        }
+    }
 }
 
 /// Subscription kind.
@@ -76,6 +106,44 @@ pub enum Params {
     None,
     /// Log parameters.
     Logs(Filter),
+    /// Pending transactions parameters.
+    PendingTransactions(PendingTransactionsFilter),
+}
+
+/// Filter of the `newPendingTransactions` subscription.
+///
+/// A transaction matches if its sender is in `from` and its receiver is in
+/// `to`. An omitted field matches any transaction, while contract creations
+/// never match `to`.
+#[derive(Debug, Default, Deserialize, PartialEq, Eq, Hash, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PendingTransactionsFilter {
+    pub from: Option<VariadicValue<H160>>,
+    pub to: Option<VariadicValue<H160>>,
+}
+
+impl PendingTransactionsFilter {
+    pub fn matches(&self, tx: &SignedTransaction) -> bool {
+        let to = match tx.action {
+            Action::Call(address) => Some(address),
+            Action::Create => None,
+        };
+        Self::contains(&self.from, Some(tx.sender()))
+            && Self::contains(&self.to, to)
+    }
+
+    fn contains(
+        addresses: &Option<VariadicValue<H160>>, address: Option<H160>,
+    ) -> bool {
+        match (addresses, address) {
+            (None, _) | (Some(VariadicValue::Null), _) => true,
+            (_, None) => false,
+            (Some(VariadicValue::Single(a)), Some(address)) => *a == address,
+            (Some(VariadicValue::Multiple(a)), Some(address)) => {
+                a.contains(&address)
+            }
+        }
+    }
 }
 
 impl Default for Params {
@@ -93,12 +161,98 @@ impl<'a> Deserialize<'a> for Params {
             return Ok(Params::None);
         }
 
-        from_value(v).map(Params::Logs).map_err(|e| {
+        // Only the pending transactions filter has `from` or `to` fields.
+        let result = if v.get("from").is_some() || v.get("to").is_some() {
+            from_value(v).map(Params::PendingTransactions)
+        } else {
+            from_value(v).map(Params::Logs)
+        };
+
+        result.map_err(|e| {
             D::Error::custom(format!("Invalid Pub-Sub parameters: {}", e))
         })
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::Transaction;
+    use serde_json;
+
+    #[test]
+    fn test_deserialize_params() {
+        let none = serde_json::from_str::<Params>(r#"null"#).unwrap();
+        assert_eq!(none, Params::None);
+
+        let logs = serde_json::from_str::<Params>(r#"{}"#).unwrap();
+        match logs {
+            Params::Logs(filter) => assert_eq!(filter.limit, None),
+            _ => panic!("expected logs filter"),
+        }
+
+        let pending = serde_json::from_str::<Params>(
+            r#"{"to":["0x0000000000000000000000000000000000000001"]}"#,
+        )
+        .unwrap();
+        let receiver = H160::from_low_u64_be(1);
+        assert_eq!(
+            pending,
+            Params::PendingTransactions(PendingTransactionsFilter {
+                from: None,
+                to: Some(VariadicValue::Multiple(vec![receiver])),
+            })
+        );
+
+        assert!(serde_json::from_str::<Params>(
+            r#"{"from":null,"topics":null}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_pending_transactions_filter() {
+        let sender = H160::from_low_u64_be(1);
+        let receiver = H160::from_low_u64_be(2);
+        let call = Transaction {
+            action: Action::Call(receiver),
+            ..Default::default()
+        }
+        .fake_sign(sender);
+        let create = Transaction::default().fake_sign(sender);
+
+        assert!(PendingTransactionsFilter::default().matches(&call));
+        assert!(PendingTransactionsFilter::default().matches(&create));
+
+        let filter = PendingTransactionsFilter {
+            from: Some(VariadicValue::Single(sender)),
+            to: Some(VariadicValue::Multiple(vec![receiver])),
+        };
+        assert!(filter.matches(&call));
+        assert!(!filter.matches(&create));
+
+        let filter = PendingTransactionsFilter {
+            from: Some(VariadicValue::Single(receiver)),
+            to: None,
+        };
+        assert!(!filter.matches(&call));
+    }
+
+    #[test]
+    fn test_serialize_sync_status() {
+        let status = Result::SyncState(SyncStatus {
+            phase: "CatchUpSyncBlockPhase".into(),
+            catch_up_mode: true,
+            current_epoch: 10u64.into(),
+            highest_epoch: None,
+        });
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            r#"{"phase":"CatchUpSyncBlockPhase","catchUpMode":true,"currentEpoch":"0xa","highestEpoch":null}"#
+        );
+    }
+}
+
 //#[cfg(test)]
 //mod tests {
 //    use serde_json;
@@ -2,15 +2,35 @@
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-use crate::rpc::types::{Log, H256, U256};
+use crate::rpc::types::{Log, H256, U256, U64};
 use cfx_types::{Address, Bloom, H256 as CfxH256, U256 as CfxU256};
 use cfxcore::{executive::contract_address, vm::CreateContractAddress};
 use primitives::{
-    receipt::Receipt as PrimitiveReceipt, transaction::Action,
+    receipt::{
+        Receipt as PrimitiveReceipt, StorageChange as PrimitiveStorageChange,
+    },
+    transaction::Action,
     SignedTransaction as PrimitiveTransaction, TransactionIndex,
 };
 use serde_derive::Serialize;
 
+#[derive(Debug, Serialize, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageChange {
+    pub address: Address,
+    /// Number of bytes.
+    pub amount: U64,
+}
+
+impl From<PrimitiveStorageChange> for StorageChange {
+    fn from(change: PrimitiveStorageChange) -> Self {
+        StorageChange {
+            address: change.address,
+            amount: change.amount.into(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Receipt {
@@ -41,6 +61,14 @@ pub struct Receipt {
     pub state_root: H256,
     /// Transaction outcome.
     pub outcome_status: u8,
+    /// Whether the gas fee is paid by the sponsor of the contract.
+    pub gas_covered_by_sponsor: bool,
+    /// Whether the storage collateral is paid by the sponsor of the contract.
+    pub storage_covered_by_sponsor: bool,
+    /// Storage collateralized in the execution of the transaction.
+    pub storage_collateralized: Vec<StorageChange>,
+    /// Storage released in the execution of the transaction.
+    pub storage_released: Vec<StorageChange>,
 }
 
 impl Receipt {
@@ -72,6 +100,18 @@ impl Receipt {
                 Action::Call(ref address) => Some(address.clone()),
             },
             outcome_status: receipt.outcome_status,
+            gas_covered_by_sponsor: receipt.gas_sponsor_paid,
+            storage_covered_by_sponsor: receipt.storage_sponsor_paid,
+            storage_collateralized: receipt
+                .storage_collateralized
+                .into_iter()
+                .map(StorageChange::from)
+                .collect(),
+            storage_released: receipt
+                .storage_released
+                .into_iter()
+                .map(StorageChange::from)
+                .collect(),
             contract_created: address,
             logs: receipt.logs.into_iter().map(Log::from).collect(),
             logs_bloom: receipt.log_bloom,
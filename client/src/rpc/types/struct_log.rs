@@ -0,0 +1,157 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::rpc::types::{Bytes, U256};
+use cfx_types::U256 as CfxU256;
+use cfxcore::trace::{
+    StructLog as PrimitiveStructLog, StructLoggerConfig, StructLoggerResult,
+};
+use rustc_hex::ToHex;
+use std::collections::BTreeMap;
+
+/// Options of `debug_traceCall` and `debug_traceTransaction`.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceOptions {
+    #[serde(default)]
+    pub disable_stack: bool,
+    #[serde(default)]
+    pub disable_memory: bool,
+    #[serde(default)]
+    pub disable_storage: bool,
+    /// The maximum number of instructions to log.
+    pub limit: Option<usize>,
+}
+
+impl From<TraceOptions> for StructLoggerConfig {
+    fn from(options: TraceOptions) -> Self {
+        StructLoggerConfig {
+            disable_stack: options.disable_stack,
+            disable_memory: options.disable_memory,
+            disable_storage: options.disable_storage,
+            limit: options.limit.unwrap_or(0),
+        }
+    }
+}
+
+/// The vm state before the execution of an instruction. Like geth, memory is
+/// split in 32 bytes words and memory words and storage slots are hex encoded
+/// without prefix.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: usize,
+    pub op: String,
+    pub gas: U256,
+    pub gas_cost: U256,
+    pub depth: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<Vec<U256>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<BTreeMap<String, String>>,
+}
+
+impl From<PrimitiveStructLog> for StructLog {
+    fn from(log: PrimitiveStructLog) -> Self {
+        StructLog {
+            pc: log.pc,
+            op: log.op,
+            gas: log.gas.into(),
+            gas_cost: log.gas_cost.into(),
+            depth: log.depth,
+            error: log.error,
+            stack: log
+                .stack
+                .map(|stack| stack.into_iter().map(Into::into).collect()),
+            memory: log.memory.map(|memory| {
+                memory.chunks(32).map(|word| word.to_hex()).collect()
+            }),
+            storage: log.storage.map(|storage| {
+                storage
+                    .iter()
+                    .map(|(key, value)| (to_word(key), to_word(value)))
+                    .collect()
+            }),
+        }
+    }
+}
+
+fn to_word(value: &CfxU256) -> String {
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    word.to_hex()
+}
+
+/// The result of `debug_traceCall` and `debug_traceTransaction`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionTrace {
+    /// The gas used by the transaction.
+    pub gas: U256,
+    pub failed: bool,
+    pub return_value: Bytes,
+    pub struct_logs: Vec<StructLog>,
+}
+
+impl ExecutionTrace {
+    pub fn new(gas_used: CfxU256, result: StructLoggerResult) -> Self {
+        ExecutionTrace {
+            gas: gas_used.into(),
+            failed: result.failed,
+            return_value: Bytes::new(result.return_value),
+            struct_logs: result
+                .struct_logs
+                .into_iter()
+                .map(StructLog::from)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn test_serialize_struct_log() {
+        let mut storage = BTreeMap::new();
+        storage.insert(CfxU256::zero(), CfxU256::from(0x2a));
+        let log = StructLog::from(PrimitiveStructLog {
+            pc: 4,
+            op: "SSTORE".into(),
+            gas: 1000.into(),
+            gas_cost: 200.into(),
+            depth: 1,
+            error: None,
+            stack: Some(vec![0x2a.into(), 0.into()]),
+            memory: Some(vec![0; 64]),
+            storage: Some(storage),
+        });
+        let serialized = serde_json::to_string(&log).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"pc":4,"op":"SSTORE","gas":"0x3e8","gasCost":"0xc8","depth":1,"stack":["0x2a","0x0"],"memory":["0000000000000000000000000000000000000000000000000000000000000000","0000000000000000000000000000000000000000000000000000000000000000"],"storage":{"0000000000000000000000000000000000000000000000000000000000000000":"000000000000000000000000000000000000000000000000000000000000002a"}}"#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_trace_options() {
+        let options: TraceOptions =
+            serde_json::from_str(r#"{"disableMemory":true,"limit":10}"#)
+                .unwrap();
+        assert_eq!(
+            options,
+            TraceOptions {
+                disable_memory: true,
+                limit: Some(10),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::rpc::types::{Bytes, H160, H256, U256};
+use cfx_types::H256 as CfxH256;
+use cfxcore::{
+    trace::{
+        trace::{
+            Action as PrimitiveAction, Call as PrimitiveCall,
+            Create as PrimitiveCreate, Res, Suicide as PrimitiveSuicide,
+            TraceError,
+        },
+        FlatTrace,
+    },
+    vm::CallType,
+};
+use serde_derive::Serialize;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Call {
+    pub from: H160,
+    pub to: H160,
+    pub value: U256,
+    pub gas: U256,
+    pub input: Bytes,
+    /// One of `call`, `callcode`, `delegatecall` and `staticcall`.
+    pub call_type: String,
+}
+
+impl From<PrimitiveCall> for Call {
+    fn from(call: PrimitiveCall) -> Self {
+        let call_type = match call.call_type {
+            CallType::None => "none",
+            CallType::Call => "call",
+            CallType::CallCode => "callcode",
+            CallType::DelegateCall => "delegatecall",
+            CallType::StaticCall => "staticcall",
+        };
+        Call {
+            from: call.from.into(),
+            to: call.to.into(),
+            value: call.value.into(),
+            gas: call.gas.into(),
+            input: Bytes::new(call.input),
+            call_type: call_type.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Create {
+    pub from: H160,
+    pub value: U256,
+    pub gas: U256,
+    pub init: Bytes,
+}
+
+impl From<PrimitiveCreate> for Create {
+    fn from(create: PrimitiveCreate) -> Self {
+        Create {
+            from: create.from.into(),
+            value: create.value.into(),
+            gas: create.gas.into(),
+            init: Bytes::new(create.init),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Suicide {
+    pub address: H160,
+    pub balance: U256,
+    pub refund_address: H160,
+}
+
+impl From<PrimitiveSuicide> for Suicide {
+    fn from(suicide: PrimitiveSuicide) -> Self {
+        Suicide {
+            address: suicide.address.into(),
+            balance: suicide.balance.into(),
+            refund_address: suicide.refund_address.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase", tag = "type", content = "action")]
+pub enum Action {
+    Call(Call),
+    Create(Create),
+    InternalCall(Call),
+    Suicide(Suicide),
+}
+
+impl From<PrimitiveAction> for Action {
+    fn from(action: PrimitiveAction) -> Self {
+        match action {
+            PrimitiveAction::Call(call) => Action::Call(call.into()),
+            PrimitiveAction::Create(create) => Action::Create(create.into()),
+            PrimitiveAction::InternalCall(call) => {
+                Action::InternalCall(call.into())
+            }
+            PrimitiveAction::Suicide(suicide) => {
+                Action::Suicide(suicide.into())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CallResult {
+    pub gas_used: U256,
+    pub output: Bytes,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateResult {
+    pub gas_used: U256,
+    pub code: Bytes,
+    pub address: H160,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(untagged)]
+pub enum TraceResult {
+    Call(CallResult),
+    Create(CreateResult),
+}
+
+/// A single frame of the call tree of a transaction.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Trace {
+    #[serde(flatten)]
+    pub action: Action,
+    /// The result of the frame. It's null if the frame failed with an error,
+    /// and for suicides.
+    pub result: Option<TraceResult>,
+    /// `Reverted` if the frame executed `REVERT`, otherwise the vm error
+    /// which failed the frame.
+    pub error: Option<String>,
+    /// The decoded message of a solidity `revert("...")`.
+    pub revert_reason: Option<String>,
+    pub subtraces: usize,
+    pub trace_address: Vec<usize>,
+}
+
+impl From<FlatTrace> for Trace {
+    fn from(trace: FlatTrace) -> Self {
+        let mut revert_reason = None;
+        let (result, error) = match trace.result {
+            Res::Call(res) => (
+                Some(TraceResult::Call(CallResult {
+                    gas_used: res.gas_used.into(),
+                    output: Bytes::new(res.output),
+                })),
+                None,
+            ),
+            Res::Create(res) => (
+                Some(TraceResult::Create(CreateResult {
+                    gas_used: res.gas_used.into(),
+                    code: Bytes::new(res.code),
+                    address: res.address.into(),
+                })),
+                None,
+            ),
+            Res::FailedCall(err) | Res::FailedCreate(err) => {
+                revert_reason = err.revert_reason();
+                match err {
+                    TraceError::Reverted { gas_used, output } => (
+                        Some(TraceResult::Call(CallResult {
+                            gas_used: gas_used.into(),
+                            output: Bytes::new(output),
+                        })),
+                        Some("Reverted".into()),
+                    ),
+                    TraceError::Error(message) => (None, Some(message)),
+                }
+            }
+            Res::None => (None, None),
+        };
+        Trace {
+            action: trace.action.into(),
+            result,
+            error,
+            revert_reason,
+            subtraces: trace.subtraces,
+            trace_address: trace.trace_address,
+        }
+    }
+}
+
+/// The traces of a transaction.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionTrace {
+    pub transaction_hash: H256,
+    pub traces: Vec<Trace>,
+}
+
+/// The traces of all transactions in a block.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTrace {
+    pub block_hash: H256,
+    /// The pivot block of the epoch which executed the block.
+    pub epoch_hash: H256,
+    pub transaction_traces: Vec<TransactionTrace>,
+}
+
+impl BlockTrace {
+    pub fn new(
+        block_hash: CfxH256, epoch_hash: CfxH256,
+        transaction_traces: Vec<(CfxH256, Vec<FlatTrace>)>,
+    ) -> Self
+    {
+        BlockTrace {
+            block_hash: block_hash.into(),
+            epoch_hash: epoch_hash.into(),
+            transaction_traces: transaction_traces
+                .into_iter()
+                .map(|(transaction_hash, traces)| TransactionTrace {
+                    transaction_hash: transaction_hash.into(),
+                    traces: traces.into_iter().map(Into::into).collect(),
+                })
+                .collect(),
+        }
+    }
+}
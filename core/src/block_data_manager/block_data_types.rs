@@ -1,4 +1,4 @@
-use crate::storage::StateRootWithAuxInfo;
+use crate::{storage::StateRootWithAuxInfo, trace::BlockExecTraces};
 use cfx_types::{Bloom, H256, U256};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
@@ -160,6 +160,23 @@ impl Decodable for BlockExecutionResultWithEpoch {
     }
 }
 
+/// The call traces of a block executed under the view of the pivot block
+/// `EpochIndex`.
+#[derive(Debug)]
+pub struct BlockTracesWithEpoch(pub EpochIndex, pub BlockExecTraces);
+
+impl Encodable for BlockTracesWithEpoch {
+    fn rlp_append(&self, stream: &mut RlpStream) {
+        stream.begin_list(2).append(&self.0).append(&self.1);
+    }
+}
+
+impl Decodable for BlockTracesWithEpoch {
+    fn decode(rlp: &Rlp) -> Result<BlockTracesWithEpoch, DecoderError> {
+        Ok(BlockTracesWithEpoch(rlp.val_at(0)?, rlp.val_at(1)?))
+    }
+}
+
 /// The local information about a block. It is NOT consistent across different
 /// nodes.
 #[derive(Copy, Clone, DeriveMallocSizeOf)]
@@ -0,0 +1,359 @@
+// Copyright 2020 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Consistency checks of the persisted execution commitments, to inspect and
+//! repair the database of a stopped node, e.g. after a crash in the middle of
+//! a write.
+
+use crate::{
+    block_data_manager::BlockDataManager,
+    parameters::consensus::DEFERRED_STATE_EPOCH_COUNT,
+    storage::{
+        state_manager::StateIndex, StorageManagerTrait, StorageStateTrait,
+    },
+};
+use cfx_types::H256;
+use primitives::BlockHeader;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum CommitmentCheck {
+    Consistent,
+    /// The commitment is not persisted, e.g. the epoch is not executed yet.
+    NotExecuted,
+    Inconsistent(String),
+}
+
+/// The pivot block of `epoch` in the persisted epoch sets.
+pub fn pivot_hash_by_epoch(
+    data_man: &BlockDataManager, epoch: u64,
+) -> Option<H256> {
+    data_man
+        .executed_epoch_set_hashes_from_db(epoch)?
+        .last()
+        .cloned()
+}
+
+/// Check the persisted execution commitment of `pivot_hash` against the
+/// state root recomputed from the storage. The deferred roots in
+/// `deferred_hash`, the pivot block which commits to the epoch, are also
+/// checked if it is persisted and does not blame its ancestors.
+pub fn check_execution_commitment(
+    data_man: &BlockDataManager, pivot_hash: &H256,
+    deferred_hash: Option<&H256>,
+) -> CommitmentCheck
+{
+    let commitment = match data_man
+        .db_manager
+        .epoch_execution_commitment_from_db(pivot_hash)
+    {
+        Some(commitment) => commitment,
+        None => return CommitmentCheck::NotExecuted,
+    };
+    let committed_root = &commitment.state_root_with_aux_info.state_root;
+
+    let maybe_state = data_man.storage_manager.get_state_no_commit(
+        StateIndex::new_for_readonly(
+            pivot_hash,
+            &commitment.state_root_with_aux_info,
+        ),
+        /* try_open = */ true,
+    );
+    let mut state = match maybe_state {
+        Ok(Some(state)) => state,
+        Ok(None) => {
+            return CommitmentCheck::Inconsistent("the state is missing".into())
+        }
+        Err(e) => {
+            return CommitmentCheck::Inconsistent(format!(
+                "failed to open the state: {:?}",
+                e
+            ))
+        }
+    };
+    match state.compute_state_root() {
+        Ok(root) if root.state_root == *committed_root => {}
+        Ok(root) => {
+            return CommitmentCheck::Inconsistent(format!(
+                "the state root is {:?}, but {:?} is committed",
+                root.state_root, committed_root
+            ))
+        }
+        Err(e) => {
+            return CommitmentCheck::Inconsistent(format!(
+                "failed to compute the state root: {:?}",
+                e
+            ))
+        }
+    }
+
+    let deferred_header =
+        deferred_hash.and_then(|hash| data_man.block_header_by_hash(hash));
+    if let Some(header) = deferred_header {
+        if header.blame() == 0 {
+            if *header.deferred_state_root()
+                != committed_root.compute_state_root_hash()
+            {
+                return CommitmentCheck::Inconsistent(format!(
+                    "the state root differs from the one in block {:?}",
+                    header.hash()
+                ));
+            }
+            if *header.deferred_receipts_root() != commitment.receipts_root
+                || *header.deferred_logs_bloom_hash()
+                    != commitment.logs_bloom_hash
+            {
+                return CommitmentCheck::Inconsistent(format!(
+                    "the receipts differ from the ones in block {:?}",
+                    header.hash()
+                ));
+            }
+        }
+    }
+    CommitmentCheck::Consistent
+}
+
+/// Check the execution commitments of the epochs from `from_epoch` to
+/// `to_epoch`, or to the best executed epoch, by height.
+///
+/// The epoch sets are only persisted some epochs behind the best epoch, and
+/// only the consensus graph knows which of the later executed blocks are on
+/// the pivot chain, so every block with an execution commitment above the
+/// persisted epoch sets is checked.
+pub fn check_execution_commitments(
+    data_man: &BlockDataManager, from_epoch: u64, to_epoch: u64,
+) -> Vec<(u64, H256, CommitmentCheck)> {
+    // The blocks which commit to the checked epochs are later.
+    let blocks = executed_blocks(
+        data_man,
+        from_epoch,
+        to_epoch.saturating_add(DEFERRED_STATE_EPOCH_COUNT),
+    );
+    let mut blocks_by_height: HashMap<u64, Vec<H256>> = HashMap::new();
+    for (height, hash) in &blocks {
+        blocks_by_height.entry(*height).or_default().push(*hash);
+    }
+    blocks
+        .iter()
+        .filter(|(epoch, _)| *epoch <= to_epoch)
+        .map(|(epoch, pivot_hash)| {
+            let deferred_hash = blocks_by_height
+                .get(&(epoch + DEFERRED_STATE_EPOCH_COUNT))
+                .and_then(|hashes| {
+                    hashes.iter().find(|hash| {
+                        ancestor(data_man, hash, DEFERRED_STATE_EPOCH_COUNT)
+                            == Some(*pivot_hash)
+                    })
+                });
+            let result =
+                check_execution_commitment(data_man, pivot_hash, deferred_hash);
+            (*epoch, *pivot_hash, result)
+        })
+        .collect()
+}
+
+/// The result of `truncate_inconsistent_commitments`.
+#[derive(Debug, PartialEq)]
+pub struct Truncation {
+    /// The first epoch whose commitments are removed.
+    pub from_epoch: u64,
+    /// The number of removed commitments.
+    pub removed: usize,
+    /// The epoch of the checkpoint the node is moved back to, if the current
+    /// one is inconsistent.
+    pub checkpoint: Option<u64>,
+}
+
+/// Remove the execution commitments from the first inconsistent epoch after
+/// the current checkpoint on, so that the node executes these epochs again
+/// when it restarts.
+///
+/// If the checkpoint itself is inconsistent, the node is moved back to the
+/// newest consistent checkpoint before it, which is looked for one era of
+/// `era_epoch_count` epochs at a time, and the commitments after the new
+/// checkpoint are removed. Return `None` if the commitments are consistent.
+pub fn truncate_inconsistent_commitments(
+    data_man: &BlockDataManager, era_epoch_count: u64,
+) -> Result<Option<Truncation>, String> {
+    let stable_hash = data_man.get_cur_consensus_era_stable_hash();
+    let stable_height = match data_man.block_header_by_hash(&stable_hash) {
+        Some(header) => header.height(),
+        None => {
+            return Err(format!(
+                "The header of checkpoint {:?} is missing",
+                stable_hash
+            ))
+        }
+    };
+    let first_inconsistent =
+        check_execution_commitments(data_man, stable_height, std::u64::MAX)
+            .into_iter()
+            .find_map(|(epoch, _, result)| match result {
+                CommitmentCheck::Inconsistent(reason) => Some((epoch, reason)),
+                _ => None,
+            });
+    let (from_epoch, checkpoint) = match first_inconsistent {
+        None => return Ok(None),
+        Some((epoch, reason)) if epoch == stable_height => {
+            warn!(
+                "The checkpoint at epoch {} is inconsistent: {}",
+                epoch, reason
+            );
+            let checkpoint =
+                move_checkpoint_back(data_man, stable_height, era_epoch_count)?;
+            (checkpoint + 1, Some(checkpoint))
+        }
+        Some((epoch, reason)) => {
+            warn!("Epoch {} is inconsistent: {}", epoch, reason);
+            (epoch, None)
+        }
+    };
+
+    // The pivot blocks of the last epochs may not be in the persisted epoch
+    // sets, so remove the commitments of all the blocks at or above the
+    // truncated height.
+    let mut removed = 0;
+    for header in blocks_from_height(data_man, from_epoch) {
+        if data_man
+            .db_manager
+            .epoch_execution_commitment_from_db(&header.hash())
+            .is_some()
+        {
+            data_man.remove_epoch_execution_commitment_from_db(&header.hash());
+            removed += 1;
+        }
+    }
+    Ok(Some(Truncation {
+        from_epoch,
+        removed,
+        checkpoint,
+    }))
+}
+
+/// Find the newest consistent checkpoint before the one at `stable_height`,
+/// and make it the checkpoint the node recovers from. Return its epoch.
+fn move_checkpoint_back(
+    data_man: &BlockDataManager, stable_height: u64, era_epoch_count: u64,
+) -> Result<u64, String> {
+    let mut height = stable_height;
+    while era_epoch_count != 0 && height >= era_epoch_count {
+        height -= era_epoch_count;
+        let (stable_hash, genesis_hash) = match (
+            pivot_hash_by_epoch(data_man, height),
+            pivot_hash_by_epoch(
+                data_man,
+                height.saturating_sub(era_epoch_count),
+            ),
+        ) {
+            (Some(stable_hash), Some(genesis_hash)) => {
+                (stable_hash, genesis_hash)
+            }
+            _ => continue,
+        };
+        let deferred_hash =
+            pivot_hash_by_epoch(data_man, height + DEFERRED_STATE_EPOCH_COUNT);
+        match check_execution_commitment(
+            data_man,
+            &stable_hash,
+            deferred_hash.as_ref(),
+        ) {
+            CommitmentCheck::Consistent => {
+                info!("Move the checkpoint back to epoch {}", height);
+                data_man.set_cur_consensus_era_genesis_hash(
+                    &genesis_hash,
+                    &stable_hash,
+                );
+                return Ok(height);
+            }
+            CommitmentCheck::NotExecuted => {}
+            CommitmentCheck::Inconsistent(reason) => {
+                warn!(
+                    "The checkpoint at epoch {} is inconsistent: {}",
+                    height, reason
+                );
+            }
+        }
+    }
+    Err(format!(
+        "No consistent checkpoint is found before epoch {}",
+        stable_height
+    ))
+}
+
+/// The blocks of the persisted epoch sets from `from_epoch` to `to_epoch`,
+/// and the executed blocks above them up to `to_epoch`, with their heights.
+fn executed_blocks(
+    data_man: &BlockDataManager, from_epoch: u64, to_epoch: u64,
+) -> Vec<(u64, H256)> {
+    let mut blocks = Vec::new();
+    let mut first_unpersisted = None;
+    for epoch in from_epoch..=to_epoch {
+        match pivot_hash_by_epoch(data_man, epoch) {
+            Some(hash) => blocks.push((epoch, hash)),
+            None => {
+                first_unpersisted = Some(epoch);
+                break;
+            }
+        }
+    }
+    if let Some(height) = first_unpersisted {
+        let mut unpersisted: Vec<(u64, H256)> =
+            blocks_from_height(data_man, height)
+                .into_iter()
+                .filter(|header| {
+                    header.height() <= to_epoch
+                        && data_man
+                            .db_manager
+                            .epoch_execution_commitment_from_db(&header.hash())
+                            .is_some()
+                })
+                .map(|header| (header.height(), header.hash()))
+                .collect();
+        unpersisted.sort();
+        blocks.extend(unpersisted);
+    }
+    blocks
+}
+
+/// The headers of the blocks at or above `height`. They are all in the past
+/// of the persisted terminals.
+fn blocks_from_height(
+    data_man: &BlockDataManager, height: u64,
+) -> Vec<Arc<BlockHeader>> {
+    let mut queue = data_man.block_terminals_from_db().unwrap_or_default();
+    let mut visited: HashSet<H256> = queue.iter().cloned().collect();
+    let mut headers = Vec::new();
+    while let Some(hash) = queue.pop() {
+        let header = match data_man.block_header_by_hash(&hash) {
+            Some(header) => header,
+            None => continue,
+        };
+        if header.height() < height {
+            continue;
+        }
+        for dependency in std::iter::once(header.parent_hash())
+            .chain(header.referee_hashes().iter())
+        {
+            if visited.insert(*dependency) {
+                queue.push(*dependency);
+            }
+        }
+        headers.push(header);
+    }
+    headers
+}
+
+/// The ancestor `depth` generations above `hash` on its parent chain.
+fn ancestor(
+    data_man: &BlockDataManager, hash: &H256, depth: u64,
+) -> Option<H256> {
+    let mut hash = *hash;
+    for _ in 0..depth {
+        hash = *data_man.block_header_by_hash(&hash)?.parent_hash();
+    }
+    Some(hash)
+}
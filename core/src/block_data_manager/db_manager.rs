@@ -1,7 +1,8 @@
 use crate::{
     block_data_manager::{
-        BlockExecutionResultWithEpoch, BlockRewardResult, CheckpointHashes,
-        EpochExecutionCommitment, EpochExecutionContext, LocalBlockInfo,
+        BlockExecutionResultWithEpoch, BlockRewardResult, BlockTracesWithEpoch,
+        CheckpointHashes, EpochExecutionCommitment, EpochExecutionContext,
+        LocalBlockInfo, LogBloomGroup, LogBloomStore,
     },
     db::{COL_BLOCKS, COL_EPOCH_NUMBER, COL_MISC, COL_TX_INDEX},
     storage::{
@@ -26,8 +27,11 @@ const EPOCH_CONSENSUS_EXECUTION_INFO_SUFFIX_BYTE: u8 = 5;
 const EPOCH_EXECUTED_BLOCK_SET_SUFFIX_BYTE: u8 = 6;
 const EPOCH_SKIPPED_BLOCK_SET_SUFFIX_BYTE: u8 = 7;
 const BLOCK_REWARD_RESULT_SUFFIX_BYTE: u8 = 8;
+const BLOCK_TRACES_SUFFIX_BYTE: u8 = 9;
+const LOG_BLOOM_GROUP_SUFFIX_BYTE: u8 = 10;
 const BLOCK_TERMINAL_KEY: &[u8] = b"block_terminals";
 const HEADER_TERMINAL_KEY: &[u8] = b"header_terminals";
+const LOG_BLOOM_INDEX_REBUILT_KEY: &[u8] = b"log_bloom_index_rebuilt";
 
 #[derive(Clone, Copy, Hash, Ord, PartialOrd, Eq, PartialEq)]
 enum DBTable {
@@ -58,6 +62,8 @@ fn sqlite_db_table(table: DBTable) -> String {
 
 pub struct DBManager {
     table_db: HashMap<DBTable, Box<dyn KeyValueDbTrait<ValueType = Box<[u8]>>>>,
+    /// The RocksDB backing all the tables, if it is used.
+    rocksdb: Option<Arc<SystemDB>>,
 }
 
 impl DBManager {
@@ -78,7 +84,10 @@ impl DBManager {
                     as Box<dyn KeyValueDbTrait<ValueType = Box<[u8]>>>,
             );
         }
-        Self { table_db }
+        Self {
+            table_db,
+            rocksdb: Some(db),
+        }
     }
 }
 
@@ -117,11 +126,39 @@ impl DBManager {
                     as Box<dyn KeyValueDbTrait<ValueType = Box<[u8]>>>,
             );
         }
-        Self { table_db }
+        Self {
+            table_db,
+            rocksdb: None,
+        }
     }
 }
 
 impl DBManager {
+    /// The name of each table, the RocksDB column family it is stored in, and
+    /// the estimated number of keys in it. Return `None` if the tables are
+    /// not stored in RocksDB.
+    pub fn table_stats(&self) -> Option<Vec<(String, String, Option<u64>)>> {
+        let rocksdb = self.rocksdb.as_ref()?;
+        Some(
+            vec![
+                DBTable::Misc,
+                DBTable::Blocks,
+                DBTable::Transactions,
+                DBTable::EpochNumbers,
+            ]
+            .into_iter()
+            .map(|table| {
+                let col = rocks_db_col(table);
+                (
+                    sqlite_db_table(table),
+                    format!("col{}", col),
+                    rocksdb.key_value().estimate_num_keys(col),
+                )
+            })
+            .collect(),
+        )
+    }
+
     /// TODO Use new_with_rlp_size
     pub fn block_from_db(&self, block_hash: &H256) -> Option<Block> {
         Some(Block::new(
@@ -258,6 +295,26 @@ impl DBManager {
         self.remove_from_db(DBTable::Blocks, &block_reward_result_key(hash))
     }
 
+    pub fn insert_block_traces_to_db(
+        &self, hash: &H256, value: &BlockTracesWithEpoch,
+    ) {
+        self.insert_encodable_val(
+            DBTable::Blocks,
+            &block_traces_key(hash),
+            value,
+        )
+    }
+
+    pub fn block_traces_from_db(
+        &self, hash: &H256,
+    ) -> Option<BlockTracesWithEpoch> {
+        self.load_decodable_val(DBTable::Blocks, &block_traces_key(hash))
+    }
+
+    pub fn remove_block_traces_from_db(&self, hash: &H256) {
+        self.remove_from_db(DBTable::Blocks, &block_traces_key(hash))
+    }
+
     pub fn insert_checkpoint_hashes_to_db(
         &self, checkpoint_prev: &H256, checkpoint_cur: &H256,
     ) {
@@ -393,6 +450,41 @@ impl DBManager {
         self.remove_from_db(DBTable::Blocks, &epoch_execution_context_key(hash))
     }
 
+    pub fn insert_log_bloom_group_to_db(
+        &self, level: u8, index: u64, group: &LogBloomGroup,
+    ) {
+        self.insert_encodable_val(
+            DBTable::EpochNumbers,
+            &log_bloom_group_key(level, index),
+            group,
+        );
+    }
+
+    pub fn log_bloom_group_from_db(
+        &self, level: u8, index: u64,
+    ) -> Option<LogBloomGroup> {
+        self.load_decodable_val(
+            DBTable::EpochNumbers,
+            &log_bloom_group_key(level, index),
+        )
+    }
+
+    pub fn insert_log_bloom_index_rebuilt_to_db(&self, from_epoch: u64) {
+        self.insert_encodable_val(
+            DBTable::Misc,
+            LOG_BLOOM_INDEX_REBUILT_KEY,
+            &from_epoch,
+        );
+    }
+
+    pub fn log_bloom_index_rebuilt_from_db(&self) -> Option<u64> {
+        self.load_decodable_val(DBTable::Misc, LOG_BLOOM_INDEX_REBUILT_KEY)
+    }
+
+    pub fn remove_log_bloom_index_rebuilt_from_db(&self) {
+        self.remove_from_db(DBTable::Misc, LOG_BLOOM_INDEX_REBUILT_KEY)
+    }
+
     /// The functions below are private utils used by the DBManager to access
     /// database
     fn insert_to_db(&self, table: DBTable, db_key: &[u8], value: Vec<u8>) {
@@ -477,6 +569,14 @@ fn skipped_epoch_set_key(epoch_number: u64) -> [u8; 9] {
     epoch_key
 }
 
+fn log_bloom_group_key(level: u8, index: u64) -> [u8; 10] {
+    let mut group_key = [0; 10];
+    LittleEndian::write_u64(&mut group_key[0..8], index);
+    group_key[8] = level;
+    group_key[9] = LOG_BLOOM_GROUP_SUFFIX_BYTE;
+    group_key
+}
+
 fn block_execution_result_key(hash: &H256) -> Vec<u8> {
     append_suffix(hash, BLOCK_EXECUTION_RESULT_SUFFIX_BYTE)
 }
@@ -485,6 +585,10 @@ fn block_reward_result_key(hash: &H256) -> Vec<u8> {
     append_suffix(hash, BLOCK_REWARD_RESULT_SUFFIX_BYTE)
 }
 
+fn block_traces_key(hash: &H256) -> Vec<u8> {
+    append_suffix(hash, BLOCK_TRACES_SUFFIX_BYTE)
+}
+
 fn epoch_execution_context_key(hash: &H256) -> Vec<u8> {
     append_suffix(hash, EPOCH_EXECUTION_CONTEXT_SUFFIX_BYTE)
 }
@@ -493,6 +597,18 @@ fn epoch_consensus_epoch_execution_commitment_key(hash: &H256) -> Vec<u8> {
     append_suffix(hash, EPOCH_CONSENSUS_EXECUTION_INFO_SUFFIX_BYTE)
 }
 
+impl LogBloomStore for DBManager {
+    fn log_bloom_group(&self, level: u8, index: u64) -> Option<LogBloomGroup> {
+        self.log_bloom_group_from_db(level, index)
+    }
+
+    fn insert_log_bloom_group(
+        &self, level: u8, index: u64, group: &LogBloomGroup,
+    ) {
+        self.insert_log_bloom_group_to_db(level, index, group)
+    }
+}
+
 impl MallocSizeOf for DBManager {
     fn size_of(&self, ops: &mut MallocSizeOfOps) -> usize {
         // Here we only handle the case that all columns are stored within the
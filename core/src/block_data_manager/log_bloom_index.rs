@@ -0,0 +1,256 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! A hierarchical index of the log blooms of the epochs on the pivot chain,
+//! used to skip the epochs without matching logs when filtering logs.
+//!
+//! The group `i` at level 0 holds the bloom of all the receipts of epoch `i`.
+//! The group `i` at level `l > 0` aggregates the groups
+//! `i * LOG_BLOOM_GROUP_SIZE .. (i + 1) * LOG_BLOOM_GROUP_SIZE` at level
+//! `l - 1`. A group is reset when the first epoch it covers is indexed again,
+//! and its bloom is recomputed from its children when an epoch is indexed
+//! again with a bloom which does not cover the old one, so that the bits of
+//! the epochs reverted by a pivot chain switch are cleared.
+
+use cfx_types::Bloom;
+use rlp_derive::{RlpDecodable, RlpEncodable};
+use std::cmp::{max, min};
+
+/// The number of levels of the index. The top level groups cover
+/// `LOG_BLOOM_GROUP_SIZE ^ (LOG_BLOOM_INDEX_LEVELS - 1)` epochs.
+pub const LOG_BLOOM_INDEX_LEVELS: u8 = 4;
+/// The number of children of a group, which must match the width of
+/// `LogBloomGroup::indexed_children`.
+pub const LOG_BLOOM_GROUP_SIZE: u64 = 16;
+
+const ALL_CHILDREN_INDEXED: u16 = !0;
+
+#[derive(Clone, Debug, Default, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct LogBloomGroup {
+    pub bloom: Bloom,
+    /// Bit `i` is set if the child `i` of the group and all of its
+    /// descendants are indexed. Unused at level 0.
+    pub indexed_children: u16,
+}
+
+impl LogBloomGroup {
+    /// Whether `bloom` covers every epoch of the group. If not, the epochs
+    /// of the group which are not indexed may have any log.
+    fn is_complete(&self, level: u8) -> bool {
+        level == 0 || self.indexed_children == ALL_CHILDREN_INDEXED
+    }
+
+    fn matches_any(&self, blooms: &[Bloom]) -> bool {
+        blooms.iter().any(|bloom| self.bloom.contains_bloom(bloom))
+    }
+}
+
+pub trait LogBloomStore {
+    fn log_bloom_group(&self, level: u8, index: u64) -> Option<LogBloomGroup>;
+
+    fn insert_log_bloom_group(
+        &self, level: u8, index: u64, group: &LogBloomGroup,
+    );
+}
+
+/// The number of epochs covered by a group at `level`.
+fn group_span(level: u8) -> u64 { LOG_BLOOM_GROUP_SIZE.pow(level as u32) }
+
+/// Record `bloom` as the bloom of `epoch` at every level of the index.
+/// Concurrent updates of the same groups must be serialized by the caller.
+pub fn insert_epoch_log_bloom(
+    store: &impl LogBloomStore, epoch: u64, bloom: &Bloom,
+) {
+    let replaced = store
+        .log_bloom_group(0, epoch)
+        .map_or(false, |old| !bloom.contains_bloom(&old.bloom));
+    store.insert_log_bloom_group(
+        0,
+        epoch,
+        &LogBloomGroup {
+            bloom: *bloom,
+            indexed_children: 0,
+        },
+    );
+
+    let mut child_complete = true;
+    for level in 1..LOG_BLOOM_INDEX_LEVELS {
+        let index = epoch / group_span(level);
+        let child = (epoch / group_span(level - 1)) % LOG_BLOOM_GROUP_SIZE;
+        let first_epoch = epoch % group_span(level) == 0;
+        let mut group = if first_epoch {
+            LogBloomGroup::default()
+        } else {
+            store.log_bloom_group(level, index).unwrap_or_default()
+        };
+        if replaced && !first_epoch {
+            group.bloom = children_bloom(store, level, index);
+        } else {
+            group.bloom.accrue_bloom(bloom);
+        }
+        if child_complete {
+            group.indexed_children |= 1 << child;
+        }
+        child_complete = group.is_complete(level);
+        store.insert_log_bloom_group(level, index, &group);
+    }
+}
+
+/// The aggregated bloom of the children of the group `index` at `level`.
+fn children_bloom(store: &impl LogBloomStore, level: u8, index: u64) -> Bloom {
+    let mut bloom = Bloom::zero();
+    let first_child = index * LOG_BLOOM_GROUP_SIZE;
+    for child in first_child..first_child + LOG_BLOOM_GROUP_SIZE {
+        if let Some(group) = store.log_bloom_group(level - 1, child) {
+            bloom.accrue_bloom(&group.bloom);
+        }
+    }
+    bloom
+}
+
+/// Return the epochs between `from_epoch` and `to_epoch` (inclusive), in
+/// ascending order, which may have logs matching one of `blooms`. Epochs
+/// which are not indexed are always returned.
+pub fn filter_epochs_by_log_bloom(
+    store: &impl LogBloomStore, from_epoch: u64, to_epoch: u64,
+    blooms: &[Bloom],
+) -> Vec<u64>
+{
+    let mut epochs = Vec::new();
+    let top_level = LOG_BLOOM_INDEX_LEVELS - 1;
+    let top_span = group_span(top_level);
+    for index in from_epoch / top_span..=to_epoch / top_span {
+        collect_epochs(
+            store,
+            top_level,
+            index,
+            (from_epoch, to_epoch),
+            blooms,
+            &mut epochs,
+        );
+    }
+    epochs
+}
+
+fn collect_epochs(
+    store: &impl LogBloomStore, level: u8, index: u64, range: (u64, u64),
+    blooms: &[Bloom], epochs: &mut Vec<u64>,
+)
+{
+    if let Some(group) = store.log_bloom_group(level, index) {
+        if group.is_complete(level) && !group.matches_any(blooms) {
+            return;
+        }
+    }
+    if level == 0 {
+        epochs.push(index);
+        return;
+    }
+
+    let child_span = group_span(level - 1);
+    let first_child = index * LOG_BLOOM_GROUP_SIZE;
+    let last_child = first_child + LOG_BLOOM_GROUP_SIZE - 1;
+    for child in max(first_child, range.0 / child_span)
+        ..=min(last_child, range.1 / child_span)
+    {
+        collect_epochs(store, level - 1, child, range, blooms, epochs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cfx_types::BloomInput;
+    use std::{cell::RefCell, collections::HashMap};
+
+    #[derive(Default)]
+    struct MemoryStore(RefCell<HashMap<(u8, u64), LogBloomGroup>>);
+
+    impl LogBloomStore for MemoryStore {
+        fn log_bloom_group(
+            &self, level: u8, index: u64,
+        ) -> Option<LogBloomGroup> {
+            self.0.borrow().get(&(level, index)).cloned()
+        }
+
+        fn insert_log_bloom_group(
+            &self, level: u8, index: u64, group: &LogBloomGroup,
+        ) {
+            self.0.borrow_mut().insert((level, index), group.clone());
+        }
+    }
+
+    fn bloom_of(input: &[u8]) -> Bloom { Bloom::from(BloomInput::Raw(input)) }
+
+    #[test]
+    fn test_filter_epochs() {
+        let store = MemoryStore::default();
+        let address = bloom_of(b"address");
+        let span = group_span(LOG_BLOOM_INDEX_LEVELS - 1);
+
+        for epoch in 0..2 * span {
+            let bloom = if epoch == 42 || epoch == span + 7 {
+                address
+            } else {
+                Bloom::zero()
+            };
+            insert_epoch_log_bloom(&store, epoch, &bloom);
+        }
+
+        assert_eq!(
+            filter_epochs_by_log_bloom(&store, 0, 2 * span - 1, &[address]),
+            vec![42, span + 7]
+        );
+        assert_eq!(
+            filter_epochs_by_log_bloom(&store, 43, span + 7, &[address]),
+            vec![span + 7]
+        );
+        let other = bloom_of(b"other");
+        assert!(
+            filter_epochs_by_log_bloom(&store, 0, 2 * span - 1, &[other])
+                .is_empty()
+        );
+        // The epochs after the indexed ones may have any log.
+        let last = 2 * span - 1;
+        assert_eq!(
+            filter_epochs_by_log_bloom(&store, last - 1, last + 2, &[address]),
+            vec![last + 1, last + 2]
+        );
+    }
+
+    #[test]
+    fn test_reindex_epochs() {
+        let store = MemoryStore::default();
+        let address = bloom_of(b"address");
+        // Epochs 1 to 31 are indexed, but not epoch 0.
+        for epoch in 1..32 {
+            insert_epoch_log_bloom(&store, epoch, &Bloom::zero());
+        }
+        assert_eq!(
+            filter_epochs_by_log_bloom(&store, 0, 31, &[address]),
+            vec![0]
+        );
+
+        // Epoch 16 is indexed again after pivot chain switches.
+        insert_epoch_log_bloom(&store, 0, &Bloom::zero());
+        insert_epoch_log_bloom(&store, 16, &address);
+        assert_eq!(
+            filter_epochs_by_log_bloom(&store, 0, 31, &[address]),
+            vec![16]
+        );
+        insert_epoch_log_bloom(&store, 16, &Bloom::zero());
+        assert!(
+            filter_epochs_by_log_bloom(&store, 0, 31, &[address]).is_empty()
+        );
+
+        // The bits of a reverted epoch are cleared from its groups.
+        insert_epoch_log_bloom(&store, 17, &address);
+        assert!(store.0.borrow()[&(1, 1)].bloom.contains_bloom(&address));
+        insert_epoch_log_bloom(&store, 17, &Bloom::zero());
+        for level in 1..LOG_BLOOM_INDEX_LEVELS {
+            let index = 17 / group_span(level);
+            assert_eq!(store.0.borrow()[&(level, index)].bloom, Bloom::zero());
+        }
+    }
+}
@@ -12,6 +12,7 @@ use crate::{
         StateRootWithAuxInfo, StorageManager, StorageManagerTrait,
         StorageStateTrait,
     },
+    trace::BlockExecTraces,
 };
 use cfx_types::{Bloom, H256};
 use malloc_size_of::{new_malloc_size_ops, MallocSizeOf, MallocSizeOfOps};
@@ -33,12 +34,15 @@ use std::{
 };
 use threadpool::ThreadPool;
 pub mod block_data_types;
+pub mod consistency_check;
 pub mod db_manager;
+pub mod log_bloom_index;
 pub mod tx_data_manager;
 use crate::block_data_manager::{
     db_manager::DBManager, tx_data_manager::TransactionDataManager,
 };
 pub use block_data_types::*;
+pub use log_bloom_index::{LogBloomGroup, LogBloomStore};
 use derivative::Derivative;
 use std::{hash::Hash, path::Path, time::Duration};
 
@@ -224,6 +228,9 @@ pub struct BlockDataManager {
 
     tx_data_manager: TransactionDataManager,
     pub db_manager: DBManager,
+    /// Serializes the updates of the log bloom index, which read and write
+    /// the groups shared by consecutive epochs.
+    log_bloom_index_lock: Mutex<()>,
 
     /// This is the original genesis block.
     pub true_genesis: Arc<Block>,
@@ -305,6 +312,7 @@ impl BlockDataManager {
             cur_consensus_era_stable_hash: RwLock::new(true_genesis.hash()),
             tx_data_manager,
             db_manager,
+            log_bloom_index_lock: Mutex::new(()),
             state_availability_boundary: RwLock::new(
                 StateAvailabilityBoundary::new(true_genesis.hash(), 0),
             ),
@@ -361,6 +369,9 @@ impl BlockDataManager {
                     .block_header
                     .deferred_logs_bloom_hash(),
             );
+            // The genesis has no logs, but it completes the first groups of
+            // the log bloom index.
+            data_man.insert_epoch_log_bloom(0, &Bloom::zero());
         } else {
             // Recover ExecutionContext for cur_era_genesis from db
             data_man.insert_epoch_execution_context(
@@ -384,6 +395,11 @@ impl BlockDataManager {
             // The commitments of cur_era_genesis will be recovered in
             // `construct_pivot_state` with other epochs
         }
+        if !data_man.config.persist_log_bloom_index {
+            // The epochs executed from now on are not indexed, so the index
+            // has to be rebuilt again once it is enabled.
+            data_man.db_manager.remove_log_bloom_index_rebuilt_from_db();
+        }
 
         data_man
     }
@@ -722,7 +738,136 @@ impl BlockDataManager {
         if remove_db {
             self.db_manager.remove_block_execution_result_from_db(hash);
             self.db_manager.remove_block_reward_result_from_db(hash);
+            if self.config.persist_block_traces {
+                self.db_manager.remove_block_traces_from_db(hash);
+            }
+        }
+    }
+
+    /// Whether the traces of executed blocks on the pivot chain are persisted.
+    pub fn persist_block_traces(&self) -> bool {
+        self.config.persist_block_traces
+    }
+
+    /// Persist the traces of a block executed in epoch `epoch`. Traces are
+    /// not cached in memory, and nothing is stored unless
+    /// `persist_block_traces` is enabled.
+    pub fn insert_block_traces(
+        &self, hash: H256, epoch: H256, traces: BlockExecTraces,
+    ) {
+        if self.config.persist_block_traces {
+            self.db_manager.insert_block_traces_to_db(
+                &hash,
+                &BlockTracesWithEpoch(epoch, traces),
+            );
+        }
+    }
+
+    /// Return None if the traces of the block are not persisted, or they are
+    /// persisted for an epoch other than `assumed_epoch`.
+    pub fn block_traces_by_hash_with_epoch(
+        &self, hash: &H256, assumed_epoch: &H256,
+    ) -> Option<BlockExecTraces> {
+        let BlockTracesWithEpoch(epoch, traces) =
+            self.db_manager.block_traces_from_db(hash)?;
+        if epoch != *assumed_epoch {
+            debug!(
+                "traces epoch from db {} does not match assumed {}",
+                epoch, assumed_epoch
+            );
+            return None;
+        }
+        Some(traces)
+    }
+
+    /// Whether the log bloom index is maintained and used for log filtering.
+    pub fn persist_log_bloom_index(&self) -> bool {
+        self.config.persist_log_bloom_index
+    }
+
+    /// Record `bloom` as the aggregated bloom of the receipts of the pivot
+    /// chain epoch `epoch_number` in the log bloom index. Nothing is stored
+    /// unless `persist_log_bloom_index` is enabled.
+    pub fn insert_epoch_log_bloom(&self, epoch_number: u64, bloom: &Bloom) {
+        if self.config.persist_log_bloom_index {
+            let _lock = self.log_bloom_index_lock.lock();
+            log_bloom_index::insert_epoch_log_bloom(
+                &self.db_manager,
+                epoch_number,
+                bloom,
+            );
+        }
+    }
+
+    /// Index the blooms of `epoch_block_hashes` executed in the pivot chain
+    /// epoch `epoch_number`, whose pivot block is the last block. Return
+    /// `false` if some execution results are not available.
+    pub fn index_epoch_log_bloom(
+        &self, epoch_number: u64, epoch_block_hashes: &[H256],
+    ) -> bool {
+        let pivot_hash = match epoch_block_hashes.last() {
+            Some(hash) => hash,
+            None => return false,
+        };
+        let mut bloom = Bloom::zero();
+        for hash in epoch_block_hashes {
+            match self.block_execution_result_by_hash_with_epoch(
+                hash, pivot_hash, false, /* update_pivot_assumption */
+                false, /* update_cache */
+            ) {
+                Some(result) => bloom.accrue_bloom(&result.bloom),
+                None => return false,
+            }
+        }
+        self.insert_epoch_log_bloom(epoch_number, &bloom);
+        true
+    }
+
+    /// Whether the log bloom index has been rebuilt since the node last ran
+    /// with `persist_log_bloom_index` disabled.
+    pub fn log_bloom_index_rebuilt(&self) -> bool {
+        self.db_manager.log_bloom_index_rebuilt_from_db().is_some()
+    }
+
+    /// Rebuild the log bloom index from the execution results in the db,
+    /// starting from epoch `from_epoch` and stopping at the first epoch
+    /// whose block set or execution results are not available, and record
+    /// that the index is rebuilt. Return the number of epochs indexed.
+    pub fn rebuild_log_bloom_index(&self, from_epoch: u64) -> u64 {
+        if !self.config.persist_log_bloom_index {
+            return 0;
+        }
+        let mut epoch_number = from_epoch;
+        if epoch_number == 0 {
+            // The true genesis has no logs nor execution results.
+            self.insert_epoch_log_bloom(0, &Bloom::zero());
+            epoch_number = 1;
         }
+        while let Some(hashes) =
+            self.executed_epoch_set_hashes_from_db(epoch_number)
+        {
+            if !self.index_epoch_log_bloom(epoch_number, &hashes) {
+                break;
+            }
+            epoch_number += 1;
+        }
+        self.db_manager
+            .insert_log_bloom_index_rebuilt_to_db(from_epoch);
+        epoch_number - from_epoch
+    }
+
+    /// Return the epochs between `from_epoch` and `to_epoch` (inclusive), in
+    /// ascending order, which may have logs matching one of `blooms`
+    /// according to the log bloom index.
+    pub fn filter_epochs_by_log_bloom(
+        &self, from_epoch: u64, to_epoch: u64, blooms: &[Bloom],
+    ) -> Vec<u64> {
+        log_bloom_index::filter_epochs_by_log_bloom(
+            &self.db_manager,
+            from_epoch,
+            to_epoch,
+            blooms,
+        )
     }
 
     pub fn transaction_index_by_hash(
@@ -1333,6 +1478,8 @@ pub enum DbType {
 
 pub struct DataManagerConfiguration {
     persist_tx_index: bool,
+    persist_block_traces: bool,
+    persist_log_bloom_index: bool,
     tx_cache_index_maintain_timeout: Duration,
     db_type: DbType,
 }
@@ -1343,12 +1490,15 @@ impl MallocSizeOf for DataManagerConfiguration {
 
 impl DataManagerConfiguration {
     pub fn new(
-        persist_tx_index: bool, tx_cache_index_maintain_timeout: Duration,
-        db_type: DbType,
+        persist_tx_index: bool, persist_block_traces: bool,
+        persist_log_bloom_index: bool,
+        tx_cache_index_maintain_timeout: Duration, db_type: DbType,
     ) -> Self
     {
         Self {
             persist_tx_index,
+            persist_block_traces,
+            persist_log_bloom_index,
             tx_cache_index_maintain_timeout,
             db_type,
         }
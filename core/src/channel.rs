@@ -2,9 +2,10 @@
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-use crate::UniqueId;
+use crate::{sync::SyncStatus, UniqueId};
 use cfx_types::H256;
 use parking_lot::RwLock;
+use primitives::SignedTransaction;
 use std::{collections::BTreeMap, sync::Arc};
 use tokio::sync::mpsc::{self, error::TryRecvError};
 
@@ -95,6 +96,9 @@ impl<T: Clone> Channel<T> {
 pub struct Notifications {
     pub new_block_hashes: Arc<Channel<(H256, bool)>>,
     pub epochs_ordered: Arc<Channel<(u64, Vec<H256>)>>,
+    /// Transactions accepted by the transaction pool, in insertion batches.
+    pub new_pending_transactions: Arc<Channel<Vec<Arc<SignedTransaction>>>>,
+    pub sync_status: Arc<Channel<SyncStatus>>,
 }
 
 impl Notifications {
@@ -102,6 +106,10 @@ impl Notifications {
         Arc::new(Notifications {
             new_block_hashes: Arc::new(Channel::new("new-block-hashes")),
             epochs_ordered: Arc::new(Channel::new("epochs-executed")),
+            new_pending_transactions: Arc::new(Channel::new(
+                "new-pending-transactions",
+            )),
+            sync_status: Arc::new(Channel::new("sync-status")),
         })
     }
 }
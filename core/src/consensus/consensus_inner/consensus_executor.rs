@@ -13,6 +13,7 @@ use crate::{
             consensus_new_block_handler::ConsensusNewBlockHandler,
             StateBlameInfo,
         },
+        fee_history::{EpochFeeStats, FeeHistory},
         ConsensusGraphInner,
     },
     executive::{ExecutionOutcome, Executive, InternalContractMap},
@@ -30,6 +31,7 @@ use crate::{
         defaults::DEFAULT_EXECUTION_PREFETCH_THREADS, StateIndex,
         StateRootWithAuxInfo, StorageManagerTrait,
     },
+    trace::{BlockExecTraces, ExecutiveTracer, Tracer},
     verification::{compute_receipts_root, VerificationConfig},
     vm::{Env, Spec},
     vm_factory::VmFactory,
@@ -177,7 +179,7 @@ impl ConsensusExecutor {
     pub fn start(
         tx_pool: SharedTransactionPool, data_man: Arc<BlockDataManager>,
         vm: VmFactory, consensus_inner: Arc<RwLock<ConsensusGraphInner>>,
-        config: ConsensusExecutionConfiguration,
+        fee_history: Arc<FeeHistory>, config: ConsensusExecutionConfiguration,
         verification_config: VerificationConfig, bench_mode: bool,
     ) -> Arc<Self>
     {
@@ -186,6 +188,7 @@ impl ConsensusExecutor {
             tx_pool,
             data_man.clone(),
             vm,
+            fee_history,
             config,
             verification_config,
             machine,
@@ -609,8 +612,31 @@ impl ConsensusExecutor {
 
     pub fn call_virtual(
         &self, tx: &SignedTransaction, epoch_id: &H256, epoch_size: usize,
-    ) -> RpcResult<ExecutionOutcome> {
-        self.handler.call_virtual(tx, epoch_id, epoch_size)
+        tracer: &mut dyn Tracer,
+    ) -> RpcResult<ExecutionOutcome>
+    {
+        self.handler.call_virtual(tx, epoch_id, epoch_size, tracer)
+    }
+
+    pub fn collect_epoch_traces(
+        &self, epoch_hash: &H256, epoch_block_hashes: &Vec<H256>,
+    ) -> RpcResult<Vec<BlockExecTraces>> {
+        self.handler
+            .collect_epoch_traces(epoch_hash, epoch_block_hashes)
+    }
+
+    pub fn recompute_epoch(
+        &self, epoch_hash: &H256, epoch_block_hashes: &Vec<H256>,
+        reward_execution_info: &Option<RewardExecutionInfo>,
+        debug_record: &mut ComputeEpochDebugRecord,
+    ) -> RpcResult<(StateRootWithAuxInfo, Vec<Arc<BlockReceipts>>)>
+    {
+        self.handler.recompute_epoch(
+            epoch_hash,
+            epoch_block_hashes,
+            reward_execution_info,
+            debug_record,
+        )
     }
 
     pub fn stop(&self) {
@@ -789,6 +815,7 @@ pub struct ConsensusExecutionHandler {
     tx_pool: SharedTransactionPool,
     data_man: Arc<BlockDataManager>,
     pub vm: VmFactory,
+    fee_history: Arc<FeeHistory>,
     config: ConsensusExecutionConfiguration,
     verification_config: VerificationConfig,
     machine: Arc<Machine>,
@@ -798,7 +825,8 @@ pub struct ConsensusExecutionHandler {
 impl ConsensusExecutionHandler {
     pub fn new(
         tx_pool: SharedTransactionPool, data_man: Arc<BlockDataManager>,
-        vm: VmFactory, config: ConsensusExecutionConfiguration,
+        vm: VmFactory, fee_history: Arc<FeeHistory>,
+        config: ConsensusExecutionConfiguration,
         verification_config: VerificationConfig, machine: Arc<Machine>,
     ) -> Self
     {
@@ -806,6 +834,7 @@ impl ConsensusExecutionHandler {
             tx_pool,
             data_man,
             vm,
+            fee_history,
             config,
             verification_config,
             machine,
@@ -944,6 +973,16 @@ impl ConsensusExecutionHandler {
                         ));
                 }
             }
+            // The epoch may have been indexed under another pivot chain.
+            if on_local_pivot
+                && self.data_man.persist_log_bloom_index()
+                && !self.data_man.index_epoch_log_bloom(
+                    pivot_block_header.height(),
+                    epoch_block_hashes,
+                )
+            {
+                warn!("Cannot index the log bloom of epoch {:?}", epoch_hash);
+            }
             self.data_man
                 .state_availability_boundary
                 .write()
@@ -1003,6 +1042,8 @@ impl ConsensusExecutionHandler {
                 &epoch_blocks,
                 start_block_number,
                 on_local_pivot,
+                None, /* epoch_traces */
+                None, /* tx_tracer */
             )
             // TODO: maybe propagate the error all the way up so that the
             // program may restart by itself.
@@ -1046,6 +1087,25 @@ impl ConsensusExecutionHandler {
             compute_receipts_root(&epoch_receipts),
             BlockHeaderBuilder::compute_block_logs_bloom_hash(&epoch_receipts),
         );
+        if on_local_pivot && self.data_man.persist_log_bloom_index() {
+            let epoch_bloom = BlockHeaderBuilder::compute_aggregated_bloom(
+                epoch_receipts
+                    .iter()
+                    .flat_map(|block_receipts| &block_receipts.receipts)
+                    .map(|receipt| receipt.log_bloom)
+                    .collect(),
+            );
+            self.data_man.insert_epoch_log_bloom(
+                pivot_block.block_header.height(),
+                &epoch_bloom,
+            );
+        }
+        if on_local_pivot {
+            self.fee_history.insert(
+                pivot_block.block_header.height(),
+                EpochFeeStats::new(*epoch_hash, &epoch_blocks, &epoch_receipts),
+            );
+        }
         let epoch_execution_commitment = self
             .data_man
             .get_epoch_execution_commitment(&epoch_hash)
@@ -1060,10 +1120,17 @@ impl ConsensusExecutionHandler {
             .adjust_upper_bound(&pivot_block.block_header);
     }
 
+    /// If `epoch_traces` is given, the call traces of each block are pushed to
+    /// it. Traces are also collected and persisted for epochs on the local
+    /// pivot chain if `persist_block_traces` is enabled. If `tx_tracer` is
+    /// given, the execution of the transaction with the given hash is
+    /// reported to the tracer instead.
     fn process_epoch_transactions(
         &self, spec: &Spec, epoch_id: EpochId, state: &mut State,
         epoch_blocks: &Vec<Arc<Block>>, start_block_number: u64,
         on_local_pivot: bool,
+        mut epoch_traces: Option<&mut Vec<BlockExecTraces>>,
+        mut tx_tracer: Option<(&H256, &mut dyn Tracer)>,
     ) -> DbResult<Vec<Arc<BlockReceipts>>>
     {
         // Prefetch accounts for transactions.
@@ -1103,6 +1170,8 @@ impl ConsensusExecutionHandler {
         drop(prefetch_join_handles);
 
         let pivot_block = epoch_blocks.last().expect("Epoch not empty");
+        let collect_traces = epoch_traces.is_some()
+            || (on_local_pivot && self.data_man.persist_block_traces());
         let internal_contract_map = InternalContractMap::new();
         let mut epoch_receipts = Vec::with_capacity(epoch_blocks.len());
         let mut to_pending = Vec::new();
@@ -1111,6 +1180,7 @@ impl ConsensusExecutionHandler {
             pivot_block.block_header.parent_hash().clone();
         for block in epoch_blocks.iter() {
             let mut receipts = Vec::new();
+            let mut block_traces = Vec::new();
             debug!(
                 "process txs in block: hash={:?}, tx count={:?}",
                 block.hash(),
@@ -1140,16 +1210,30 @@ impl ConsensusExecutionHandler {
                 let mut storage_released = Vec::new();
                 let mut storage_collateralized = Vec::new();
 
+                let mut tracer = ExecutiveTracer::default();
                 let r = {
-                    Executive::new(
+                    let mut ex = Executive::new(
                         state,
                         &env,
                         self.machine.as_ref(),
                         &spec,
                         &internal_contract_map,
-                    )
-                    .transact(transaction)?
+                    );
+                    match tx_tracer {
+                        Some((tx_hash, ref mut tx_tracer))
+                            if *tx_hash == transaction.hash() =>
+                        {
+                            ex.transact_with_tracer(transaction, *tx_tracer)?
+                        }
+                        _ if collect_traces => {
+                            ex.transact_with_tracer(transaction, &mut tracer)?
+                        }
+                        _ => ex.transact(transaction)?,
+                    }
                 };
+                if collect_traces {
+                    block_traces.push(tracer.drain().into());
+                }
 
                 let gas_fee;
                 let mut gas_sponsor_paid = false;
@@ -1256,6 +1340,20 @@ impl ConsensusExecutionHandler {
                 on_local_pivot,
             );
 
+            if collect_traces {
+                let block_traces = BlockExecTraces::from(block_traces);
+                if on_local_pivot {
+                    self.data_man.insert_block_traces(
+                        block.hash(),
+                        pivot_block.hash(),
+                        block_traces.clone(),
+                    );
+                }
+                if let Some(epoch_traces) = epoch_traces.as_mut() {
+                    epoch_traces.push(block_traces);
+                }
+            }
+
             epoch_receipts.push(block_receipts);
         }
 
@@ -1615,12 +1713,159 @@ impl ConsensusExecutionHandler {
             &epoch_blocks,
             start_block_number,
             false,
+            None, /* epoch_traces */
+            None, /* tx_tracer */
         )
     }
 
+    /// Re-execute the transactions of an epoch from the state of its parent
+    /// epoch and return the call traces of each block in the epoch. Rewards
+    /// are not processed and the resulting state is not committed.
+    pub fn collect_epoch_traces(
+        &self, epoch_hash: &H256, epoch_block_hashes: &Vec<H256>,
+    ) -> RpcResult<Vec<BlockExecTraces>> {
+        let spec = Spec::new_spec();
+        let (epoch_blocks, mut state, start_block_number) =
+            self.state_for_reexecution(epoch_hash, epoch_block_hashes, &spec)?;
+        let mut epoch_traces = Vec::with_capacity(epoch_blocks.len());
+        self.process_epoch_transactions(
+            &spec,
+            *epoch_hash,
+            &mut state,
+            &epoch_blocks,
+            start_block_number,
+            false,
+            Some(&mut epoch_traces),
+            None, /* tx_tracer */
+        )?;
+        Ok(epoch_traces)
+    }
+
+    /// Re-execute the transactions of an epoch like `collect_epoch_traces`,
+    /// and report the execution of the transaction `tx_hash` to `tracer`.
+    pub fn trace_epoch_transaction(
+        &self, epoch_hash: &H256, epoch_block_hashes: &Vec<H256>,
+        tx_hash: &H256, tracer: &mut dyn Tracer,
+    ) -> RpcResult<()>
+    {
+        let spec = Spec::new_spec();
+        let (epoch_blocks, mut state, start_block_number) =
+            self.state_for_reexecution(epoch_hash, epoch_block_hashes, &spec)?;
+        self.process_epoch_transactions(
+            &spec,
+            *epoch_hash,
+            &mut state,
+            &epoch_blocks,
+            start_block_number,
+            false,
+            None, /* epoch_traces */
+            Some((tx_hash, tracer)),
+        )?;
+        Ok(())
+    }
+
+    /// Re-execute an epoch from the state of its parent epoch, and return the
+    /// state root and the receipts without committing the state. The state
+    /// operations and the rewards are recorded in `debug_record`.
+    pub fn recompute_epoch(
+        &self, epoch_hash: &H256, epoch_block_hashes: &Vec<H256>,
+        reward_execution_info: &Option<RewardExecutionInfo>,
+        debug_record: &mut ComputeEpochDebugRecord,
+    ) -> RpcResult<(StateRootWithAuxInfo, Vec<Arc<BlockReceipts>>)>
+    {
+        let spec = Spec::new_spec();
+        let (epoch_blocks, mut state, start_block_number) =
+            self.state_for_reexecution(epoch_hash, epoch_block_hashes, &spec)?;
+        let epoch_receipts = self.process_epoch_transactions(
+            &spec,
+            *epoch_hash,
+            &mut state,
+            &epoch_blocks,
+            start_block_number,
+            false,
+            None, /* epoch_traces */
+            None, /* tx_tracer */
+        )?;
+        if let Some(reward_execution_info) = reward_execution_info {
+            self.process_rewards_and_fees(
+                &mut state,
+                reward_execution_info,
+                false,
+                Some(&mut *debug_record),
+            );
+        }
+        let state_root = state.compute_state_root(Some(debug_record))?;
+        Ok((state_root, epoch_receipts))
+    }
+
+    /// Return the blocks of an epoch, the state of its parent epoch and the
+    /// block number of the first block in the epoch.
+    fn state_for_reexecution(
+        &self, epoch_hash: &H256, epoch_block_hashes: &Vec<H256>, spec: &Spec,
+    ) -> RpcResult<(Vec<Arc<Block>>, State, u64)> {
+        let epoch_blocks = match self.data_man.blocks_by_hash_list(
+            epoch_block_hashes,
+            false, /* update_cache */
+        ) {
+            Some(blocks) => blocks,
+            None => bail!("cannot get the blocks of epoch {:?}", epoch_hash),
+        };
+        let pivot_block = epoch_blocks.last().expect("Epoch not empty");
+        // The genesis epoch is not executed, so it has no parent state to
+        // re-execute from.
+        if pivot_block.block_header.height() == 0 {
+            bail!("the genesis epoch cannot be re-executed");
+        }
+        let parent_hash = pivot_block.block_header.parent_hash();
+        let parent_height = pivot_block.block_header.height() - 1;
+        let start_block_number = match self.data_man.get_epoch_execution_context(epoch_hash) {
+            Some(v) => v.start_block_number,
+            None => bail!("cannot obtain the execution context. Database is potentially corrupted!"),
+        };
+
+        // Keep the lock until we get the desired State, otherwise the State may
+        // expire.
+        let state_availability_boundary =
+            self.data_man.state_availability_boundary.read();
+        if !state_availability_boundary
+            .check_availability(parent_height, parent_hash)
+        {
+            bail!("the state of the parent epoch is not available");
+        }
+        let parent_commitment = match self
+            .data_man
+            .get_epoch_execution_commitment_with_db(parent_hash)
+        {
+            Some(commitment) => commitment,
+            None => bail!("the parent epoch is not executed"),
+        };
+        let state = State::new(
+            StateDb::new(
+                self.data_man
+                    .storage_manager
+                    .get_state_for_next_epoch(StateIndex::new_for_next_epoch(
+                        parent_hash,
+                        &parent_commitment.state_root_with_aux_info,
+                        parent_height,
+                        self.data_man.get_snapshot_epoch_count(),
+                    ))?
+                    // Safe because the state availability is checked.
+                    .expect("State exists"),
+            ),
+            self.vm.clone(),
+            spec,
+            start_block_number - 1, /* block_number */
+        );
+        drop(state_availability_boundary);
+
+        Ok((epoch_blocks, state, start_block_number))
+    }
+
     pub fn call_virtual(
         &self, tx: &SignedTransaction, epoch_id: &H256, epoch_size: usize,
-    ) -> RpcResult<ExecutionOutcome> {
+        tracer: &mut dyn Tracer,
+    ) -> RpcResult<ExecutionOutcome>
+    {
         let spec = Spec::new_spec();
         let internal_contract_map = InternalContractMap::new();
         let best_block_header = self.data_man.block_header_by_hash(epoch_id);
@@ -1694,7 +1939,7 @@ impl ConsensusExecutionHandler {
             &spec,
             &internal_contract_map,
         );
-        let r = ex.transact_virtual(tx);
+        let r = ex.transact_virtual(tx, tracer);
         trace!("Execution result {:?}", r);
         Ok(r?)
     }
@@ -8,16 +8,6 @@ pub fn log_debug_epoch_computation(
     state_root: &StateRootWithAuxInfo,
 ) -> ComputeEpochDebugRecord
 {
-    // Parent state root.
-    let parent_arena_index = inner.arena[epoch_arena_index].parent;
-    let parent_epoch_hash = inner.arena[parent_arena_index].hash;
-    let parent_state_root = inner
-        .data_man
-        .get_epoch_execution_commitment(&parent_epoch_hash)
-        .unwrap()
-        .state_root_with_aux_info
-        .clone();
-
     let reward_index = inner.get_pivot_reward_index(epoch_arena_index);
 
     let reward_execution_info =
@@ -29,58 +19,475 @@ pub fn log_debug_epoch_computation(
         false, /* on_local_pivot */
         false, /* force_recompute */
     );
+    let mut debug_record =
+        new_debug_record(epoch_arena_index, inner, reward_index);
+    debug_record.block_height = block_height;
+    debug_record.block_hash = block_hash;
+    debug_record.state_root_after_applying_rewards = state_root.clone();
+    executor.compute_epoch(task, Some(&mut debug_record));
+
+    debug_record
+}
+
+/// Fill the parent state, the reward epochs and the blocks of an epoch in a
+/// new debug record. The parent epoch must have been executed.
+fn new_debug_record(
+    epoch_arena_index: usize, inner: &ConsensusGraphInner,
+    reward_index: Option<(usize, usize)>,
+) -> ComputeEpochDebugRecord
+{
     let mut debug_record = ComputeEpochDebugRecord::default();
+
+    // Parent state root.
+    let parent_arena_index = inner.arena[epoch_arena_index].parent;
+    let parent_epoch_hash = inner.arena[parent_arena_index].hash;
+    let parent_state_root = inner
+        .data_man
+        .get_epoch_execution_commitment_with_db(&parent_epoch_hash)
+        .unwrap()
+        .state_root_with_aux_info;
+
+    debug_record.parent_epoch_hash = parent_epoch_hash;
+    debug_record.parent_state_root = parent_state_root;
+    debug_record.reward_epoch_hash =
+        if let Some((reward_epoch_block, _)) = reward_index.clone() {
+            Some(inner.arena[reward_epoch_block].hash)
+        } else {
+            None
+        };
+    debug_record.anticone_penalty_cutoff_epoch_hash =
+        if let Some((_, anticone_penalty_cutoff_epoch_block)) =
+            reward_index.clone()
+        {
+            Some(inner.arena[anticone_penalty_cutoff_epoch_block].hash)
+        } else {
+            None
+        };
+
+    let epoch_block_hashes = inner.get_epoch_block_hashes(epoch_arena_index);
+    let blocks = epoch_block_hashes
+        .iter()
+        .map(|hash| {
+            inner
+                .data_man
+                .block_by_hash(hash, false /* update_cache */)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    debug_record.block_hashes = epoch_block_hashes;
+    debug_record.block_txs = blocks
+        .iter()
+        .map(|block| block.transactions.len())
+        .collect::<Vec<_>>();
+    debug_record.transactions = blocks
+        .iter()
+        .flat_map(|block| block.transactions.clone())
+        .collect::<Vec<_>>();
+
+    debug_record.block_authors = blocks
+        .iter()
+        .map(|block| *block.block_header.author())
+        .collect::<Vec<_>>();
+
+    debug_record
+}
+
+/// The result of re-executing an epoch, compared with what is persisted for
+/// the epoch.
+pub struct EpochRecomputation {
+    pub debug_record: ComputeEpochDebugRecord,
+    pub state_root: StateRootWithAuxInfo,
+    pub receipts_root: H256,
+    pub logs_bloom_hash: H256,
+    /// The persisted execution commitment of the epoch, if it is executed.
+    pub committed: Option<EpochExecutionCommitment>,
+    /// The pivot block whose deferred state is the epoch, if it exists and
+    /// does not blame its ancestors.
+    pub deferred_block: Option<Arc<BlockHeader>>,
+    /// The storage keys of the accounts written by the epoch which are
+    /// written, or differ from the committed state, ordered by key.
+    pub state_changes: Vec<StateChange>,
+    pub blocks: Vec<BlockRecomputation>,
+}
+
+/// A storage key compared after an epoch is re-executed.
+pub struct StateChange {
+    /// The last operation on the key, "set", "delete", "delete_all" if the
+    /// key is removed with a prefix, or "unwritten" if the key is not written
+    /// but differs from the committed state.
+    pub op_name: String,
+    pub key: Vec<u8>,
+    pub parent_value: Option<Box<[u8]>>,
+    pub recomputed_value: Option<Box<[u8]>>,
+    /// The value in the committed state of the epoch. It is always `None`
+    /// if the epoch is not executed.
+    pub committed_value: Option<Box<[u8]>>,
+}
+
+pub struct BlockRecomputation {
+    pub block_hash: H256,
+    pub transaction_hashes: Vec<H256>,
+    pub receipts: Arc<BlockReceipts>,
+    /// The persisted receipts of the block executed in the epoch, if any.
+    pub committed_receipts: Option<Arc<BlockReceipts>>,
+}
+
+/// The inputs to re-execute an epoch, which are collected from the consensus
+/// graph before the epoch is executed.
+pub struct EpochRecomputationTask {
+    epoch_hash: H256,
+    parent_hash: H256,
+    parent_commitment: EpochExecutionCommitment,
+    reward_execution_info: Option<RewardExecutionInfo>,
+    debug_record: ComputeEpochDebugRecord,
+    deferred_block: Option<Arc<BlockHeader>>,
+}
+
+/// Collect the inputs to re-execute the epoch of pivot block `epoch_hash`
+/// from the state of its parent epoch.
+pub fn prepare_epoch_recomputation(
+    inner: &mut ConsensusGraphInner, executor: &ConsensusExecutor,
+    epoch_hash: &H256,
+) -> Result<EpochRecomputationTask, String>
+{
+    let epoch_arena_index = match inner.hash_to_arena_indices.get(epoch_hash)
     {
-        debug_record.block_height = block_height;
-        debug_record.block_hash = block_hash;
-        debug_record.state_root_after_applying_rewards = state_root.clone();
-        debug_record.parent_epoch_hash = parent_epoch_hash;
-        debug_record.parent_state_root = parent_state_root;
-        debug_record.reward_epoch_hash =
-            if let Some((reward_epoch_block, _)) = reward_index.clone() {
-                Some(inner.arena[reward_epoch_block].hash)
-            } else {
-                None
-            };
-        debug_record.anticone_penalty_cutoff_epoch_hash =
-            if let Some((_, anticone_penalty_cutoff_epoch_block)) =
-                reward_index.clone()
+        Some(index) => *index,
+        None => {
+            return Err(format!(
+                "Block {:?} is not in the consensus graph",
+                epoch_hash
+            ))
+        }
+    };
+    let parent_arena_index = inner.arena[epoch_arena_index].parent;
+    if parent_arena_index == NULL {
+        return Err(format!(
+            "The parent of block {:?} is not in the consensus graph",
+            epoch_hash
+        ));
+    }
+    let parent_hash = inner.arena[parent_arena_index].hash;
+    let parent_commitment = match inner
+        .data_man
+        .get_epoch_execution_commitment_with_db(&parent_hash)
+    {
+        Some(commitment) => commitment,
+        None => {
+            return Err(format!(
+                "The parent epoch {:?} is not executed",
+                parent_hash
+            ))
+        }
+    };
+
+    let reward_index = inner.get_pivot_reward_index(epoch_arena_index);
+    let reward_execution_info =
+        executor.get_reward_execution_info_from_index(inner, reward_index);
+    let mut debug_record =
+        new_debug_record(epoch_arena_index, inner, reward_index);
+    debug_record.block_height = inner.arena[epoch_arena_index].height;
+    debug_record.block_hash = *epoch_hash;
+
+    Ok(EpochRecomputationTask {
+        epoch_hash: *epoch_hash,
+        parent_hash,
+        parent_commitment,
+        reward_execution_info,
+        debug_record,
+        deferred_block: deferred_block(inner, epoch_hash),
+    })
+}
+
+/// Re-execute an epoch without committing the result, and compare the state
+/// changes and the receipts with the ones committed for the epoch.
+pub fn recompute_epoch(
+    data_man: &BlockDataManager, executor: &ConsensusExecutor,
+    task: EpochRecomputationTask,
+) -> Result<EpochRecomputation, String>
+{
+    let EpochRecomputationTask {
+        epoch_hash,
+        parent_hash,
+        parent_commitment,
+        reward_execution_info,
+        mut debug_record,
+        deferred_block,
+    } = task;
+    let epoch_block_hashes = debug_record.block_hashes.clone();
+    let (state_root, receipts) = executor
+        .recompute_epoch(
+            &epoch_hash,
+            &epoch_block_hashes,
+            &reward_execution_info,
+            &mut debug_record,
+        )
+        .map_err(|e| {
+            format!("Failed to recompute epoch {:?}: {}", epoch_hash, e)
+        })?;
+    debug_record.state_root_after_applying_rewards = state_root.clone();
+
+    let committed =
+        data_man.get_epoch_execution_commitment_with_db(&epoch_hash);
+    let mut parent_state =
+        open_state(data_man, &parent_hash, &parent_commitment)?;
+    let mut committed_state = match &committed {
+        Some(commitment) => {
+            Some(open_state(data_man, &epoch_hash, commitment)?)
+        }
+        None => None,
+    };
+
+    // All the keys of the accounts written by the epoch are compared, so that
+    // the keys removed by "delete_all" and the keys which are only written in
+    // the committed state are found too.
+    let storage_ops = debug_record
+        .state_ops
+        .iter()
+        .filter_map(|op| match op {
+            StateOp::StorageLevelOp {
+                op_name,
+                key,
+                maybe_value,
+            } => Some((op_name, key, maybe_value)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let addresses = storage_ops
+        .iter()
+        .map(|(_, key, _)| {
+            key[..min(key.len(), StorageKey::ACCOUNT_BYTES)].to_vec()
+        })
+        .collect::<BTreeSet<_>>();
+    let parent_values = read_accounts(&mut parent_state, &addresses)?;
+    let committed_values = match &mut committed_state {
+        Some(state) => Some(read_accounts(state, &addresses)?),
+        None => None,
+    };
+
+    // Replay the operations on the parent state. Only the last operation on a
+    // key matters.
+    let mut recomputed_values = parent_values.clone();
+    let mut op_names = BTreeMap::new();
+    for (op_name, key, maybe_value) in storage_ops {
+        if op_name == "delete_all" {
+            let deleted_keys = recomputed_values
+                .range(key.clone()..)
+                .take_while(|(deleted_key, _)| deleted_key.starts_with(key))
+                .map(|(deleted_key, _)| deleted_key.clone())
+                .collect::<Vec<_>>();
+            for deleted_key in deleted_keys {
+                recomputed_values.remove(&deleted_key);
+                op_names.insert(deleted_key, op_name);
+            }
+            continue;
+        }
+        match maybe_value {
+            Some(value) => recomputed_values
+                .insert(key.clone(), value.clone().into_boxed_slice()),
+            None => recomputed_values.remove(key),
+        };
+        op_names.insert(key.clone(), op_name);
+    }
+
+    let mut keys = parent_values.keys().collect::<BTreeSet<_>>();
+    keys.extend(recomputed_values.keys());
+    keys.extend(committed_values.iter().flat_map(|values| values.keys()));
+    let mut state_changes = Vec::new();
+    for key in keys {
+        let recomputed_value = recomputed_values.get(key).cloned();
+        let committed_value = committed_values
+            .as_ref()
+            .and_then(|values| values.get(key).cloned());
+        let differs =
+            committed_values.is_some() && recomputed_value != committed_value;
+        let op_name = match op_names.get(key) {
+            Some(op_name) => (*op_name).clone(),
+            None if differs => "unwritten".into(),
+            None => continue,
+        };
+        state_changes.push(StateChange {
+            op_name,
+            key: key.clone(),
+            parent_value: parent_values.get(key).cloned(),
+            recomputed_value,
+            committed_value,
+        });
+    }
+
+    let mut transactions = debug_record.transactions.iter();
+    let mut blocks = Vec::with_capacity(receipts.len());
+    for ((block_hash, tx_count), block_receipts) in epoch_block_hashes
+        .iter()
+        .zip(&debug_record.block_txs)
+        .zip(&receipts)
+    {
+        let committed_receipts = data_man
+            .block_execution_result_by_hash_from_db(block_hash)
+            .filter(|result| result.0 == epoch_hash)
+            .map(|result| result.1.block_receipts);
+        blocks.push(BlockRecomputation {
+            block_hash: *block_hash,
+            transaction_hashes: transactions
+                .by_ref()
+                .take(*tx_count)
+                .map(|tx| tx.hash())
+                .collect(),
+            receipts: block_receipts.clone(),
+            committed_receipts,
+        });
+    }
+
+    Ok(EpochRecomputation {
+        state_root,
+        receipts_root: compute_receipts_root(&receipts),
+        logs_bloom_hash: BlockHeaderBuilder::compute_block_logs_bloom_hash(
+            &receipts,
+        ),
+        committed,
+        deferred_block,
+        state_changes,
+        blocks,
+        debug_record,
+    })
+}
+
+/// Read all the keys of the accounts from a state.
+fn read_accounts(
+    state: &mut StorageState, addresses: &BTreeSet<Vec<u8>>,
+) -> Result<BTreeMap<Vec<u8>, Box<[u8]>>, String> {
+    let mut values = BTreeMap::new();
+    for address in addresses {
+        let key_values = state
+            .read_all(StorageKey::AccountKey(address))
+            .map_err(|e| format!("Failed to read the state: {:?}", e))?;
+        values.extend(key_values.into_iter().flatten());
+    }
+    Ok(values)
+}
+
+fn open_state(
+    data_man: &BlockDataManager, epoch_hash: &H256,
+    commitment: &EpochExecutionCommitment,
+) -> Result<StorageState, String>
+{
+    let maybe_state = data_man.storage_manager.get_state_no_commit(
+        StateIndex::new_for_readonly(
+            epoch_hash,
+            &commitment.state_root_with_aux_info,
+        ),
+        /* try_open = */ true,
+    );
+    match maybe_state {
+        Ok(Some(state)) => Ok(state),
+        Ok(None) => Err(format!("The state of {:?} is missing", epoch_hash)),
+        Err(e) => Err(format!(
+            "Failed to open the state of {:?}: {:?}",
+            epoch_hash, e
+        )),
+    }
+}
+
+/// The pivot block `DEFERRED_STATE_EPOCH_COUNT` epochs after `epoch_hash` if
+/// `epoch_hash` is on the pivot chain and the block does not blame.
+fn deferred_block(
+    inner: &ConsensusGraphInner, epoch_hash: &H256,
+) -> Option<Arc<BlockHeader>> {
+    let height = inner.data_man.block_header_by_hash(epoch_hash)?.height();
+    if inner.get_pivot_hash_from_epoch_number(height).ok()? != *epoch_hash {
+        return None;
+    }
+    let deferred_hash = inner
+        .get_pivot_hash_from_epoch_number(height + DEFERRED_STATE_EPOCH_COUNT)
+        .ok()?;
+    let header = inner.data_man.block_header_by_hash(&deferred_hash)?;
+    if header.blame() == 0 {
+        Some(header)
+    } else {
+        None
+    }
+}
+
+/// Describe a storage key in the format of `StorageKey::to_key_bytes`.
+pub fn describe_storage_key(key: &[u8]) -> String {
+    match StorageKey::from_key_bytes(key) {
+        StorageKey::AccountKey(address) => {
+            format!("account {:?}", Address::from_slice(address))
+        }
+        StorageKey::StorageRootKey(address) => {
+            format!("storage root {:?}", Address::from_slice(address))
+        }
+        StorageKey::StorageKey {
+            address_bytes,
+            storage_key,
+        } => format!(
+            "storage {:?} key 0x{}",
+            Address::from_slice(address_bytes),
+            storage_key.to_hex()
+        ),
+        StorageKey::CodeRootKey(address) => {
+            format!("code root {:?}", Address::from_slice(address))
+        }
+        StorageKey::CodeKey {
+            address_bytes,
+            code_hash_bytes,
+        } => format!(
+            "code {:?} hash {:?}",
+            Address::from_slice(address_bytes),
+            H256::from_slice(code_hash_bytes)
+        ),
+        StorageKey::DepositListKey(address) => {
+            format!("deposit list {:?}", Address::from_slice(address))
+        }
+        StorageKey::VoteListKey(address) => {
+            format!("vote list {:?}", Address::from_slice(address))
+        }
+    }
+}
+
+/// Decode the value of a storage key, or show the raw bytes if the value is
+/// not in the format of the key.
+pub fn describe_storage_value(key: &[u8], value: &[u8]) -> String {
+    let decoded = match StorageKey::from_key_bytes(key) {
+        StorageKey::AccountKey(address) => {
+            let address = Address::from_slice(address);
+            // Decoding panics on the other types of addresses.
+            if address.is_user_account_address()
+                || address.is_contract_address()
             {
-                Some(inner.arena[anticone_penalty_cutoff_epoch_block].hash)
+                rlp::decode::<Account>(value)
+                    .ok()
+                    .map(|account| format!("{:?}", account))
             } else {
                 None
-            };
-
-        let epoch_block_hashes =
-            inner.get_epoch_block_hashes(epoch_arena_index);
-        let blocks = epoch_block_hashes
-            .iter()
-            .map(|hash| {
-                inner
-                    .data_man
-                    .block_by_hash(hash, false /* update_cache */)
-                    .unwrap()
+            }
+        }
+        // The staking state is stored as plain numbers.
+        StorageKey::StorageKey { .. } => rlp::decode::<StorageValue>(value)
+            .map(|storage_value| format!("{:?}", storage_value))
+            .or_else(|_| {
+                rlp::decode::<U256>(value).map(|number| number.to_string())
             })
-            .collect::<Vec<_>>();
-
-        debug_record.block_hashes = epoch_block_hashes;
-        debug_record.block_txs = blocks
-            .iter()
-            .map(|block| block.transactions.len())
-            .collect::<Vec<_>>();
-        debug_record.transactions = blocks
-            .iter()
-            .flat_map(|block| block.transactions.clone())
-            .collect::<Vec<_>>();
-
-        debug_record.block_authors = blocks
-            .iter()
-            .map(|block| *block.block_header.author())
-            .collect::<Vec<_>>();
-    }
-    executor.compute_epoch(task, Some(&mut debug_record));
-
-    debug_record
+            .ok(),
+        StorageKey::CodeKey { .. } => {
+            rlp::decode::<CodeInfo>(value).ok().map(|code_info| {
+                format!(
+                    "{} bytes of code owned by {:?}",
+                    code_info.code.len(),
+                    code_info.owner
+                )
+            })
+        }
+        StorageKey::DepositListKey(_) => rlp::decode::<DepositList>(value)
+            .ok()
+            .map(|deposit_list| format!("{:?}", deposit_list.0)),
+        StorageKey::VoteListKey(_) => rlp::decode::<VoteStakeList>(value)
+            .ok()
+            .map(|vote_list| format!("{:?}", vote_list.0)),
+        _ => None,
+    };
+    decoded.unwrap_or_else(|| format!("0x{}", value.to_hex()))
 }
 
 pub fn log_invalid_state_root(
@@ -146,15 +553,33 @@ pub fn log_invalid_state_root(
 }
 
 use crate::{
+    block_data_manager::{BlockDataManager, EpochExecutionCommitment},
     consensus::{
         consensus_inner::consensus_executor::{
-            ConsensusExecutor, EpochExecutionTask,
+            ConsensusExecutor, EpochExecutionTask, RewardExecutionInfo,
         },
-        debug::ComputeEpochDebugRecord,
+        debug::{ComputeEpochDebugRecord, StateOp},
         ConsensusGraphInner,
     },
-    storage::StateRootWithAuxInfo,
+    parameters::consensus::{DEFERRED_STATE_EPOCH_COUNT, NULL},
+    storage::{
+        StateIndex, StateRootWithAuxInfo, StorageManagerTrait, StorageState,
+        StorageStateTrait,
+    },
+    verification::compute_receipts_root,
+};
+use cfx_types::{address_util::AddressUtil, Address, H256, U256};
+use parity_bytes::ToPretty;
+use primitives::{
+    Account, BlockHeader, BlockHeaderBuilder, BlockReceipts, CodeInfo,
+    DepositList, StorageKey, StorageValue, VoteStakeList,
 };
-use cfx_types::H256;
 use serde_json;
-use std::{fs::File, io::Write, path::Path};
+use std::{
+    cmp::min,
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::Write,
+    path::Path,
+    sync::Arc,
+};
@@ -0,0 +1,331 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Per-epoch gas price statistics, and the gas price oracle built on them.
+//!
+//! The statistics of an epoch are collected when the epoch is executed on the
+//! local pivot chain, and kept for the most recent epochs in `FeeHistory`.
+//! Older epochs are recomputed from their blocks and receipts on demand.
+
+use crate::parameters::consensus_internal::FEE_HISTORY_CACHE_SIZE;
+use cfx_types::{H256, U256};
+use parking_lot::RwLock;
+use primitives::{receipt::BlockReceipts, Block};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Percentiles of the recent prices used for the slow, standard and fast
+/// suggestions.
+const SLOW_PERCENTILE: f64 = 25.0;
+const STANDARD_PERCENTILE: f64 = 50.0;
+const FAST_PERCENTILE: f64 = 75.0;
+/// Below this average gas used ratio blocks have room for every transaction,
+/// so the slow suggestion drops to the lowest recently packed prices.
+const CONGESTED_GAS_USED_RATIO: f64 = 0.5;
+/// The number of blocks of ready transactions a standard transaction should
+/// be able to outbid. A fast transaction outbids one block of them.
+pub const STANDARD_PENDING_BLOCKS: u64 = 4;
+
+/// Gas statistics of the transactions executed in an epoch.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EpochFeeStats {
+    /// Hash of the pivot block of the epoch.
+    pub pivot_hash: H256,
+    /// Sum of the gas limits of the blocks in the epoch.
+    pub gas_limit: U256,
+    pub gas_used: U256,
+    /// Gas price and gas used of the executed transactions, by ascending
+    /// price.
+    prices: Vec<(U256, U256)>,
+}
+
+impl EpochFeeStats {
+    /// Collect the statistics of an epoch from its blocks and their receipts,
+    /// both in execution order.
+    pub fn new(
+        pivot_hash: H256, blocks: &[Arc<Block>],
+        receipts: &[Arc<BlockReceipts>],
+    ) -> Self
+    {
+        let mut stats = EpochFeeStats {
+            pivot_hash,
+            ..Default::default()
+        };
+        for (block, block_receipts) in blocks.iter().zip(receipts) {
+            stats.gas_limit += *block.block_header.gas_limit();
+            let mut last_accumulated_gas = U256::zero();
+            for (tx, receipt) in
+                block.transactions.iter().zip(&block_receipts.receipts)
+            {
+                // Transactions that are not executed use no gas.
+                let gas_used =
+                    receipt.accumulated_gas_used - last_accumulated_gas;
+                last_accumulated_gas = receipt.accumulated_gas_used;
+                if !gas_used.is_zero() {
+                    stats.gas_used += gas_used;
+                    stats.prices.push((*tx.gas_price(), gas_used));
+                }
+            }
+        }
+        stats.prices.sort();
+        stats
+    }
+
+    /// Whether no transaction is executed in the epoch.
+    pub fn is_empty(&self) -> bool { self.prices.is_empty() }
+
+    pub fn gas_used_ratio(&self) -> f64 {
+        if self.gas_limit.is_zero() {
+            return 0.0;
+        }
+        self.gas_used.low_u64() as f64 / self.gas_limit.low_u64() as f64
+    }
+
+    /// The gas price under which `percentile` percent of the gas of the
+    /// epoch is paid, or zero for an empty epoch.
+    pub fn percentile(&self, percentile: f64) -> U256 {
+        weighted_percentile(&self.prices, self.gas_used, percentile)
+    }
+}
+
+/// The price of `prices`, sorted by ascending price, under which `percentile`
+/// percent of the `total` gas is paid.
+fn weighted_percentile(
+    prices: &[(U256, U256)], total: U256, percentile: f64,
+) -> U256 {
+    let threshold = total.low_u64() as f64 * percentile / 100.0;
+    let mut sum = U256::zero();
+    for (price, gas) in prices {
+        sum += *gas;
+        if sum.low_u64() as f64 >= threshold {
+            return *price;
+        }
+    }
+    prices.last().map_or(U256::zero(), |(price, _)| *price)
+}
+
+/// The statistics of the recently executed epochs, by epoch number.
+#[derive(Default)]
+pub struct FeeHistory {
+    epochs: RwLock<BTreeMap<u64, Arc<EpochFeeStats>>>,
+}
+
+impl FeeHistory {
+    /// Cache the statistics of an epoch and return them. Epochs older than
+    /// the cached ones are not cached once the cache is full, so that
+    /// recomputing an old epoch does not evict a recent one.
+    pub fn insert(
+        &self, epoch_number: u64, stats: EpochFeeStats,
+    ) -> Arc<EpochFeeStats> {
+        let stats = Arc::new(stats);
+        let mut epochs = self.epochs.write();
+        let oldest = epochs.keys().next().cloned();
+        if epochs.len() >= FEE_HISTORY_CACHE_SIZE
+            && oldest.map_or(false, |oldest| epoch_number < oldest)
+        {
+            return stats;
+        }
+        epochs.insert(epoch_number, stats.clone());
+        while epochs.len() > FEE_HISTORY_CACHE_SIZE {
+            let oldest = *epochs.keys().next().expect("not empty");
+            epochs.remove(&oldest);
+        }
+        stats
+    }
+
+    /// Get the statistics of an epoch, if they are collected for the given
+    /// pivot block.
+    pub fn get(
+        &self, epoch_number: u64, pivot_hash: &H256,
+    ) -> Option<Arc<EpochFeeStats>> {
+        self.epochs
+            .read()
+            .get(&epoch_number)
+            .filter(|stats| stats.pivot_hash == *pivot_hash)
+            .cloned()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GasPriceSuggestion {
+    pub slow: U256,
+    pub standard: U256,
+    pub fast: U256,
+}
+
+/// Suggest gas prices from the statistics of recent epochs, and the gas price
+/// and gas limit of the ready transactions in the pool.
+///
+/// The suggestions are the medians of the per-epoch percentiles. When the
+/// epochs are far from full, the slow suggestion drops to the lowest packed
+/// prices. When the ready transactions fill more than a block, the standard
+/// and fast suggestions are raised to compete with them. Return `None` if
+/// there is no transaction to learn from.
+pub fn suggest_gas_price(
+    history: &[Arc<EpochFeeStats>], ready: &[(U256, U256)],
+    block_gas_limit: U256,
+) -> Option<GasPriceSuggestion> {
+    let mut suggestion = if history.iter().any(|stats| !stats.is_empty()) {
+        let gas_used_ratio = history
+            .iter()
+            .map(|stats| stats.gas_used_ratio())
+            .sum::<f64>()
+            / history.len() as f64;
+        let slow_percentile = if gas_used_ratio < CONGESTED_GAS_USED_RATIO {
+            0.0
+        } else {
+            SLOW_PERCENTILE
+        };
+        GasPriceSuggestion {
+            slow: median_percentile(history, slow_percentile),
+            standard: median_percentile(history, STANDARD_PERCENTILE),
+            fast: median_percentile(history, FAST_PERCENTILE),
+        }
+    } else if !ready.is_empty() {
+        let mut prices = ready.to_vec();
+        prices.sort();
+        let total =
+            prices.iter().fold(U256::zero(), |sum, (_, gas)| sum + *gas);
+        GasPriceSuggestion {
+            slow: weighted_percentile(&prices, total, SLOW_PERCENTILE),
+            standard: weighted_percentile(&prices, total, STANDARD_PERCENTILE),
+            fast: weighted_percentile(&prices, total, FAST_PERCENTILE),
+        }
+    } else {
+        return None;
+    };
+
+    // The price at which the most expensive ready transactions fill the
+    // given number of blocks.
+    let mut ready = ready.to_vec();
+    ready.sort_by(|a, b| b.cmp(a));
+    let pending_price = |blocks: u64| {
+        let limit = block_gas_limit * blocks;
+        let mut sum = U256::zero();
+        for (price, gas) in &ready {
+            sum += *gas;
+            if sum >= limit {
+                return Some(*price);
+            }
+        }
+        None
+    };
+    if let Some(price) = pending_price(1) {
+        suggestion.fast = suggestion.fast.max(price);
+    }
+    if let Some(price) = pending_price(STANDARD_PENDING_BLOCKS) {
+        suggestion.standard = suggestion.standard.max(price);
+    }
+
+    suggestion.standard = suggestion.standard.max(suggestion.slow);
+    suggestion.fast = suggestion.fast.max(suggestion.standard);
+    Some(suggestion)
+}
+
+fn median_percentile(history: &[Arc<EpochFeeStats>], percentile: f64) -> U256 {
+    let mut prices: Vec<U256> = history
+        .iter()
+        .filter(|stats| !stats.is_empty())
+        .map(|stats| stats.percentile(percentile))
+        .collect();
+    prices.sort();
+    prices[prices.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(gas_limit: u64, prices: &[(u64, u64)]) -> Arc<EpochFeeStats> {
+        let mut prices: Vec<(U256, U256)> = prices
+            .iter()
+            .map(|(price, gas)| ((*price).into(), (*gas).into()))
+            .collect();
+        prices.sort();
+        Arc::new(EpochFeeStats {
+            pivot_hash: H256::zero(),
+            gas_limit: gas_limit.into(),
+            gas_used: prices
+                .iter()
+                .fold(U256::zero(), |sum, (_, gas)| sum + *gas),
+            prices,
+        })
+    }
+
+    #[test]
+    fn test_percentile() {
+        let epoch = stats(100, &[(30, 10), (10, 20), (20, 10)]);
+        assert_eq!(epoch.gas_used_ratio(), 0.4);
+        assert_eq!(epoch.percentile(0.0), 10.into());
+        assert_eq!(epoch.percentile(50.0), 10.into());
+        assert_eq!(epoch.percentile(60.0), 20.into());
+        assert_eq!(epoch.percentile(100.0), 30.into());
+        assert_eq!(stats(100, &[]).percentile(50.0), U256::zero());
+    }
+
+    #[test]
+    fn test_fee_history_pivot_check() {
+        let history = FeeHistory::default();
+        let mut epoch = (*stats(100, &[(1, 1)])).clone();
+        epoch.pivot_hash = H256::from_low_u64_be(1);
+        history.insert(1, epoch);
+        assert!(history.get(1, &H256::from_low_u64_be(1)).is_some());
+        assert!(history.get(1, &H256::from_low_u64_be(2)).is_none());
+        assert!(history.get(2, &H256::from_low_u64_be(1)).is_none());
+    }
+
+    #[test]
+    fn test_fee_history_eviction() {
+        let history = FeeHistory::default();
+        let size = FEE_HISTORY_CACHE_SIZE as u64;
+        for epoch_number in 1..=size {
+            history.insert(epoch_number, (*stats(100, &[])).clone());
+        }
+        // An older epoch is returned without evicting a recent one.
+        let epoch = (*stats(100, &[(1, 1)])).clone();
+        assert_eq!(*history.insert(0, epoch.clone()), epoch);
+        assert!(history.get(0, &H256::zero()).is_none());
+        assert!(history.get(1, &H256::zero()).is_some());
+        // A newer epoch evicts the oldest one.
+        history.insert(size + 1, epoch);
+        assert!(history.get(1, &H256::zero()).is_none());
+        assert!(history.get(size + 1, &H256::zero()).is_some());
+    }
+
+    #[test]
+    fn test_suggest_gas_price() {
+        assert_eq!(suggest_gas_price(&[], &[], 100.into()), None);
+
+        // Full epochs.
+        let history = vec![
+            stats(100, &[(10, 25), (20, 25), (30, 25), (40, 25)]),
+            stats(100, &[(20, 25), (30, 25), (40, 25), (50, 25)]),
+            stats(100, &[]),
+        ];
+        let suggestion = suggest_gas_price(&history, &[], 100.into()).unwrap();
+        assert_eq!(suggestion.slow, 20.into());
+        assert_eq!(suggestion.standard, 30.into());
+        assert_eq!(suggestion.fast, 40.into());
+
+        // Epochs with room for more transactions.
+        let history = vec![stats(1000, &[(10, 25), (20, 25), (30, 25)])];
+        let suggestion = suggest_gas_price(&history, &[], 100.into()).unwrap();
+        assert_eq!(suggestion.slow, 10.into());
+
+        // A backlog of ready transactions raises the standard and fast prices.
+        let ready: Vec<(U256, U256)> = (1..=10u64)
+            .map(|price| ((price * 10).into(), 50.into()))
+            .collect();
+        let suggestion =
+            suggest_gas_price(&history, &ready, 100.into()).unwrap();
+        assert_eq!(suggestion.slow, 10.into());
+        assert_eq!(suggestion.standard, 30.into());
+        assert_eq!(suggestion.fast, 90.into());
+
+        // Without history the ready transactions are sampled.
+        let suggestion = suggest_gas_price(&[], &ready, 1000.into()).unwrap();
+        assert_eq!(suggestion.slow, 30.into());
+        assert_eq!(suggestion.standard, 50.into());
+        assert_eq!(suggestion.fast, 80.into());
+    }
+}
@@ -6,12 +6,19 @@ mod anticone_cache;
 pub mod consensus_inner;
 mod consensus_trait;
 pub mod debug;
+pub mod fee_history;
 mod pastset_cache;
 
-use super::consensus::consensus_inner::{
-    confirmation_meter::ConfirmationMeter,
-    consensus_executor::ConsensusExecutor,
-    consensus_new_block_handler::ConsensusNewBlockHandler,
+use super::consensus::{
+    consensus_inner::{
+        confirmation_meter::ConfirmationMeter,
+        consensus_executor::ConsensusExecutor,
+        consensus_new_block_handler::ConsensusNewBlockHandler,
+    },
+    fee_history::{
+        suggest_gas_price, EpochFeeStats, FeeHistory, GasPriceSuggestion,
+        STANDARD_PENDING_BLOCKS,
+    },
 };
 pub use crate::consensus::{
     consensus_inner::{ConsensusGraphInner, ConsensusInnerConfig},
@@ -20,8 +27,13 @@ pub use crate::consensus::{
 use crate::{
     block_data_manager::{BlockDataManager, BlockExecutionResultWithEpoch},
     bytes::Bytes,
-    consensus::consensus_inner::{
-        consensus_executor::ConsensusExecutionConfiguration, StateBlameInfo,
+    consensus::{
+        consensus_inner::{
+            consensus_executor::ConsensusExecutionConfiguration, StateBlameInfo,
+        },
+        debug::debug_recompute::{
+            prepare_epoch_recomputation, recompute_epoch, EpochRecomputation,
+        },
     },
     evm::Spec,
     executive::ExecutionOutcome,
@@ -31,9 +43,13 @@ use crate::{
     pow::ProofOfWorkConfig,
     rpc_errors::Result as RpcResult,
     state::State,
-    statedb::StateDb,
+    statedb::{Error as StateDbError, StateDb},
     statistics::SharedStatistics,
-    storage::state_manager::StateManagerTrait,
+    storage::{
+        state_manager::StateManagerTrait, StateIndex, StateProof,
+        StateRootWithAuxInfo,
+    },
+    trace::{BlockExecTraces, Tracer},
     transaction_pool::SharedTransactionPool,
     verification::VerificationConfig,
     vm_factory::VmFactory,
@@ -56,11 +72,7 @@ use primitives::{
 };
 use rayon::prelude::*;
 use std::{
-    any::Any,
-    cmp::min,
-    collections::{HashMap, HashSet},
-    sync::Arc,
-    thread::sleep,
+    any::Any, cmp::min, collections::HashMap, sync::Arc, thread::sleep,
     time::Duration,
 };
 
@@ -150,6 +162,8 @@ pub struct ConsensusGraph {
     pub txpool: SharedTransactionPool,
     pub data_man: Arc<BlockDataManager>,
     executor: Arc<ConsensusExecutor>,
+    /// Gas price statistics of the recently executed epochs.
+    fee_history: Arc<FeeHistory>,
     statistics: SharedStatistics,
     pub new_block_handler: ConsensusNewBlockHandler,
     pub confirmation_meter: ConfirmationMeter,
@@ -205,11 +219,13 @@ impl ConsensusGraph {
                 era_genesis_block_hash,
                 era_stable_block_hash,
             )));
+        let fee_history = Arc::new(FeeHistory::default());
         let executor = ConsensusExecutor::start(
             txpool.clone(),
             data_man.clone(),
             vm,
             inner.clone(),
+            fee_history.clone(),
             execution_conf,
             verification_config,
             conf.bench_mode,
@@ -221,6 +237,7 @@ impl ConsensusGraph {
             txpool: txpool.clone(),
             data_man: data_man.clone(),
             executor: executor.clone(),
+            fee_history,
             statistics: statistics.clone(),
             new_block_handler: ConsensusNewBlockHandler::new(
                 conf.clone(),
@@ -343,54 +360,69 @@ impl ConsensusGraph {
         })
     }
 
-    /// Get the average gas price of the last GAS_PRICE_TRANSACTION_SAMPLE_SIZE
-    /// blocks
-    pub fn gas_price(&self) -> Option<U256> {
-        let inner = self.inner.read();
-        let mut last_epoch_number = inner.best_epoch_number();
-        let mut number_of_blocks_to_sample = GAS_PRICE_BLOCK_SAMPLE_SIZE;
-        let mut tx_hashes = HashSet::new();
-        let mut prices = Vec::new();
-
-        loop {
-            if number_of_blocks_to_sample == 0 || last_epoch_number == 0 {
-                break;
-            }
-            if prices.len() == GAS_PRICE_TRANSACTION_SAMPLE_SIZE {
-                break;
-            }
-            let mut hashes = inner
-                .block_hashes_by_epoch(last_epoch_number.into())
-                .unwrap();
-            hashes.reverse();
-            last_epoch_number -= 1;
-
-            for hash in hashes {
-                let block = self
-                    .data_man
-                    .block_by_hash(&hash, false /* update_cache */)
-                    .unwrap();
-                for tx in block.transactions.iter() {
-                    if tx_hashes.insert(tx.hash()) {
-                        prices.push(tx.gas_price().clone());
-                        if prices.len() == GAS_PRICE_TRANSACTION_SAMPLE_SIZE {
-                            break;
-                        }
-                    }
-                }
-                number_of_blocks_to_sample -= 1;
-                if number_of_blocks_to_sample == 0 {
-                    break;
-                }
-            }
+    /// Get the gas price statistics of an executed epoch on the current pivot
+    /// chain. Return `None` if the epoch is not executed or its receipts are
+    /// not available.
+    pub fn epoch_fee_stats(
+        &self, epoch_number: u64,
+    ) -> Option<Arc<EpochFeeStats>> {
+        let block_hashes = self
+            .inner
+            .read_recursive()
+            .block_hashes_by_epoch(epoch_number)
+            .ok()?;
+        let pivot_hash = *block_hashes.last().expect("Epoch set not empty");
+        if let Some(stats) = self.fee_history.get(epoch_number, &pivot_hash) {
+            return Some(stats);
         }
 
-        prices.sort();
-        if prices.is_empty() {
-            None
-        } else {
-            Some(prices[prices.len() / 2])
+        let blocks = self.data_man.blocks_by_hash_list(
+            &block_hashes,
+            false, /* update_cache */
+        )?;
+        let mut receipts = Vec::with_capacity(block_hashes.len());
+        for hash in &block_hashes {
+            receipts.push(
+                self.data_man
+                    .block_execution_result_by_hash_with_epoch(
+                        hash,
+                        &pivot_hash,
+                        false, /* update_pivot_assumption */
+                        false, /* update_cache */
+                    )?
+                    .block_receipts,
+            );
         }
+        let stats = EpochFeeStats::new(pivot_hash, &blocks, &receipts);
+        Some(self.fee_history.insert(epoch_number, stats))
+    }
+
+    /// Suggest slow, standard and fast gas prices from the statistics of the
+    /// last GAS_PRICE_EPOCH_SAMPLE_SIZE executed epochs and the ready
+    /// transactions in the pool.
+    pub fn gas_price_suggestion(&self) -> Option<GasPriceSuggestion> {
+        let best_epoch = self.best_executed_state_epoch_number();
+        let history: Vec<_> = (0..=best_epoch)
+            .rev()
+            .take(GAS_PRICE_EPOCH_SAMPLE_SIZE as usize)
+            .filter_map(|epoch_number| self.epoch_fee_stats(epoch_number))
+            .collect();
+        let block_gas_limit = self
+            .data_man
+            .block_header_by_hash(&self.best_block_hash())
+            .map_or(U256::zero(), |header| *header.gas_limit());
+        // Only the most expensive ready transactions which fill the blocks
+        // the suggestions compete with are sampled.
+        let ready = self
+            .txpool
+            .ready_gas_prices(block_gas_limit * STANDARD_PENDING_BLOCKS);
+        suggest_gas_price(&history, &ready, block_gas_limit)
+    }
+
+    /// Get the suggested standard gas price.
+    pub fn gas_price(&self) -> Option<U256> {
+        self.gas_price_suggestion()
+            .map(|suggestion| suggestion.standard)
     }
 
     fn validate_stated_epoch(
@@ -426,29 +458,51 @@ impl ConsensusGraph {
         // expire.
         let state_availability_boundary =
             self.data_man.state_availability_boundary.read();
-        if !state_availability_boundary.check_availability(height, &hash) {
-            debug!(
-                "State for epoch (number={:?} hash={:?}) does not exist: out-of-bound {:?}",
-                height, hash, state_availability_boundary
-            );
-            return Err(format!(
-                "State for epoch (number={:?} hash={:?}) does not exist: out-of-bound {:?}",
-                height, hash, state_availability_boundary
-            )
-            .into());
-        }
-        let (_state_index_guard, maybe_state_readonly_index) =
-            self.data_man.get_state_readonly_index(&hash).into();
-        let maybe_state = match maybe_state_readonly_index {
-            Some(state_readonly_index) => self
-                .data_man
-                .storage_manager
-                .get_state_no_commit(
-                    state_readonly_index,
-                    /* try_open = */ true,
+        let lower_bound = state_availability_boundary.lower_bound;
+        let maybe_state = if height < lower_bound
+            && height >= self.earliest_state_height(lower_bound)
+        {
+            // The state of an old era epoch retained by an archive node. The
+            // execution commitments of old eras are only kept in db.
+            match self.data_man.get_epoch_execution_commitment_with_db(&hash) {
+                Some(commitment) => self
+                    .data_man
+                    .storage_manager
+                    .get_state_no_commit(
+                        StateIndex::new_for_readonly(
+                            &hash,
+                            &commitment.state_root_with_aux_info,
+                        ),
+                        /* try_open = */ true,
+                    )
+                    .map_err(|e| format!("Error to get state, err={:?}", e))?,
+                None => None,
+            }
+        } else {
+            if !state_availability_boundary.check_availability(height, &hash) {
+                debug!(
+                    "State for epoch (number={:?} hash={:?}) does not exist: out-of-bound {:?}",
+                    height, hash, state_availability_boundary
+                );
+                return Err(format!(
+                    "State for epoch (number={:?} hash={:?}) does not exist: out-of-bound {:?}",
+                    height, hash, state_availability_boundary
                 )
-                .map_err(|e| format!("Error to get state, err={:?}", e))?,
-            None => None,
+                .into());
+            }
+            let (_state_index_guard, maybe_state_readonly_index) =
+                self.data_man.get_state_readonly_index(&hash).into();
+            match maybe_state_readonly_index {
+                Some(state_readonly_index) => self
+                    .data_man
+                    .storage_manager
+                    .get_state_no_commit(
+                        state_readonly_index,
+                        /* try_open = */ true,
+                    )
+                    .map_err(|e| format!("Error to get state, err={:?}", e))?,
+                None => None,
+            }
         };
 
         let state = match maybe_state {
@@ -465,6 +519,27 @@ impl ConsensusGraph {
         Ok(StateDb::new(state))
     }
 
+    /// Returns the lowest epoch whose state can be queried. States below the
+    /// state availability boundary are only kept by archive nodes, according
+    /// to their state retention policy.
+    pub fn earliest_state_epoch_number(&self) -> u64 {
+        let lower_bound =
+            self.data_man.state_availability_boundary.read().lower_bound;
+        self.earliest_state_height(lower_bound)
+    }
+
+    fn earliest_state_height(&self, state_lower_bound: u64) -> u64 {
+        match self
+            .data_man
+            .storage_manager
+            .get_storage_manager()
+            .get_first_retained_state_height()
+        {
+            Some(retained_height) => min(state_lower_bound, retained_height),
+            None => state_lower_bound,
+        }
+    }
+
     /// Get the code of an address
     pub fn get_code(
         &self, address: H160, epoch_number: EpochNumber,
@@ -567,6 +642,35 @@ impl ConsensusGraph {
         }
     }
 
+    /// Get the raw entries of the account at `address` and of its storage at
+    /// `positions`, along with their proofs and the state root of the epoch
+    /// which the proofs are checked against. The first entry is the account.
+    pub fn get_account_proof(
+        &self, address: H160, positions: &[H256], epoch_number: EpochNumber,
+    ) -> Result<
+        (StateRootWithAuxInfo, Vec<(Option<Box<[u8]>>, StateProof)>),
+        String,
+    > {
+        let state_db = self.get_state_db_by_epoch_number(epoch_number)?;
+        let db_error = |e: StateDbError| {
+            warn!("Unexpected error while retrieving state proof: {}", e);
+            "db error occurred".to_string()
+        };
+
+        let state_root = state_db.get_state_root().map_err(db_error)?;
+        let mut entries = Vec::with_capacity(positions.len() + 1);
+        entries.push(
+            state_db
+                .get_raw_with_proof(StorageKey::new_account_key(&address))
+                .map_err(db_error)?,
+        );
+        for position in positions {
+            let key = StorageKey::new_storage_key(&address, position.as_ref());
+            entries.push(state_db.get_raw_with_proof(key).map_err(db_error)?);
+        }
+        Ok((state_root, entries))
+    }
+
     /// Get the current admin of a contract.
     pub fn get_admin(
         &self, address: H160, epoch_number: EpochNumber,
@@ -680,33 +784,36 @@ impl ConsensusGraph {
         // execution results do not match the current pivot view and return
         // None. If the tx was re-executed in another block on the new pivot
         // chain, `transaction_index_by_hash` will return the updated result.
-        let (results_with_epoch, address) = {
-            let inner = self.inner.read();
-            let address = self.data_man.transaction_index_by_hash(
-                tx_hash, false, /* update_cache */
-            )?;
-            (
-                inner.block_execution_results_by_hash(
-                    &address.block_hash,
-                    true,
-                )?,
-                address,
-            )
-        };
+        let address = self.data_man.transaction_index_by_hash(
+            tx_hash, false, /* update_cache */
+        )?;
+        let (results_with_epoch, state_root) =
+            self.get_block_execution_result(&address.block_hash)?;
+        Some((results_with_epoch, address, state_root))
+    }
+
+    /// Returns the execution result of a block on the current pivot chain,
+    /// along with the state root of the epoch executing it.
+    pub fn get_block_execution_result(
+        &self, block_hash: &H256,
+    ) -> Option<(BlockExecutionResultWithEpoch, H256)> {
+        let results_with_epoch = self
+            .inner
+            .read()
+            .block_execution_results_by_hash(block_hash, true)?;
         let epoch_hash = results_with_epoch.0;
         match self.executor.wait_for_result(epoch_hash) {
             Ok(execution_commitment) => {
-                // We already has transaction address with epoch_hash executed,
-                // so we can always get the state_root with
-                // `wait_for_result`
+                // The block is already executed in epoch_hash, so we can
+                // always get the state_root with `wait_for_result`
                 let state_root = execution_commitment
                     .state_root_with_aux_info
                     .aux_info
                     .state_root_hash;
-                Some((results_with_epoch, address, state_root))
+                Some((results_with_epoch, state_root))
             }
             Err(msg) => {
-                warn!("get_transaction_receipt_and_block_info() gets the following error from ConsensusExecutor: {}", msg);
+                warn!("get_block_execution_result() gets the following error from ConsensusExecutor: {}", msg);
                 None
             }
         }
@@ -943,7 +1050,7 @@ impl ConsensusGraph {
 
     pub fn get_filter_epoch_range(
         &self, filter: &Filter,
-    ) -> Result<impl Iterator<Item = u64>, FilterError> {
+    ) -> Result<(u64, u64), FilterError> {
         // lock so that we have a consistent view
         let _inner = self.inner.read();
 
@@ -966,7 +1073,7 @@ impl ConsensusGraph {
             });
         }
 
-        return Ok((from_epoch..=to_epoch).rev());
+        return Ok((from_epoch, to_epoch));
     }
 
     fn filter_logs_by_epochs(
@@ -980,9 +1087,26 @@ impl ConsensusGraph {
         // that we can check whether it changed between batches
         let mut consistency_check_data: Option<(u64, H256)> = None;
 
-        let mut logs = self
-            // iterate over epochs in reverse order
-            .get_filter_epoch_range(&filter)?
+        let (from_epoch, to_epoch) = self.get_filter_epoch_range(&filter)?;
+
+        // iterate over epochs in reverse order
+        let epochs = if self.data_man.persist_log_bloom_index() {
+            // skip the epochs which have no matching logs for sure
+            Either::Left(
+                self.data_man
+                    .filter_epochs_by_log_bloom(
+                        from_epoch,
+                        to_epoch,
+                        &bloom_possibilities,
+                    )
+                    .into_iter()
+                    .rev(),
+            )
+        } else {
+            Either::Right((from_epoch..=to_epoch).rev())
+        };
+
+        let mut logs = epochs
             // we process epochs in each batch in parallel
             // but batches are processed one-by-one
             .chunks(self.config.get_logs_epoch_batch_size)
@@ -1118,7 +1242,9 @@ impl ConsensusGraph {
 
     pub fn call_virtual(
         &self, tx: &SignedTransaction, epoch: EpochNumber,
-    ) -> RpcResult<ExecutionOutcome> {
+        tracer: &mut dyn Tracer,
+    ) -> RpcResult<ExecutionOutcome>
+    {
         // only allow to call against stated epoch
         self.validate_stated_epoch(&epoch)?;
         let (epoch_id, epoch_size) = if let Ok(v) =
@@ -1128,7 +1254,92 @@ impl ConsensusGraph {
         } else {
             bail!("cannot get block hashes in the specified epoch, maybe it does not exist?");
         };
-        self.executor.call_virtual(tx, &epoch_id, epoch_size)
+        self.executor
+            .call_virtual(tx, &epoch_id, epoch_size, tracer)
+    }
+
+    /// Return the block hashes of the epoch in which block `block_hash` is
+    /// executed, if the epoch is executed.
+    fn executed_epoch_block_hashes(
+        &self, block_hash: &H256,
+    ) -> RpcResult<Option<Vec<H256>>> {
+        let epoch_number = match self.get_block_epoch_number(block_hash) {
+            Some(epoch_number) => epoch_number,
+            None => return Ok(None),
+        };
+        let epoch = EpochNumber::Number(epoch_number);
+        self.validate_stated_epoch(&epoch)?;
+        Ok(Some(self.get_block_hashes_by_epoch(epoch)?))
+    }
+
+    /// Return the pivot hash of the epoch in which block `block_hash` is
+    /// executed, and the call traces of the transactions in the block.
+    /// Persisted traces are used if available, otherwise the epoch is
+    /// re-executed from the state of its parent epoch.
+    pub fn block_traces(
+        &self, block_hash: &H256,
+    ) -> RpcResult<Option<(H256, BlockExecTraces)>> {
+        let epoch_block_hashes =
+            match self.executed_epoch_block_hashes(block_hash)? {
+                Some(hashes) => hashes,
+                None => return Ok(None),
+            };
+        let epoch_hash =
+            *epoch_block_hashes.last().expect("pivot block always exist");
+
+        if let Some(traces) = self
+            .data_man
+            .block_traces_by_hash_with_epoch(block_hash, &epoch_hash)
+        {
+            return Ok(Some((epoch_hash, traces)));
+        }
+
+        let block_index = epoch_block_hashes
+            .iter()
+            .position(|hash| hash == block_hash)
+            .expect("block is in its epoch");
+        let mut epoch_traces = self
+            .executor
+            .collect_epoch_traces(&epoch_hash, &epoch_block_hashes)?;
+        Ok(Some((epoch_hash, epoch_traces.swap_remove(block_index))))
+    }
+
+    /// Re-execute the epoch in which block `block_hash` is executed, and
+    /// report the execution of the transaction `tx_hash` in the block to
+    /// `tracer`. Return `false` if the epoch is not executed yet.
+    pub fn trace_transaction_execution(
+        &self, block_hash: &H256, tx_hash: &H256, tracer: &mut dyn Tracer,
+    ) -> RpcResult<bool> {
+        let epoch_block_hashes =
+            match self.executed_epoch_block_hashes(block_hash)? {
+                Some(hashes) => hashes,
+                None => return Ok(false),
+            };
+        let epoch_hash =
+            *epoch_block_hashes.last().expect("pivot block always exist");
+        self.executor.trace_epoch_transaction(
+            &epoch_hash,
+            &epoch_block_hashes,
+            tx_hash,
+            tracer,
+        )?;
+        Ok(true)
+    }
+
+    /// Re-execute the epoch of pivot block `epoch_hash` for debugging. The
+    /// result is not committed.
+    pub fn recompute_epoch(
+        &self, epoch_hash: &H256,
+    ) -> Result<EpochRecomputation, String> {
+        // Computing the rewards may update the blame and the vote validity of
+        // the blocks, so the lock is taken for writing, but it is released
+        // before the epoch is executed.
+        let task = prepare_epoch_recomputation(
+            &mut *self.inner.write(),
+            &self.executor,
+            epoch_hash,
+        )?;
+        recompute_epoch(&self.data_man, &self.executor, task)
     }
 
     pub fn check_balance_against_transaction(
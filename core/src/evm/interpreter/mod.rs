@@ -435,6 +435,8 @@ impl<Cost: CostType> Interpreter<Cost> {
                         self.reader.position - 1,
                         opcode,
                         requirements.gas_cost.as_u256(),
+                        self.stack.peek_top(self.stack.size()),
+                        &self.mem,
                         Self::mem_written(instruction, &self.stack),
                         Self::store_written(instruction, &self.stack),
                     );
@@ -9,6 +9,7 @@ use crate::{
     machine::Machine,
     parameters::staking::*,
     state::{State, Substate},
+    trace::Tracer,
     vm::{
         self, ActionParams, ActionValue, CallType, Context as ContextTrait,
         ContractCreateResult, CreateContractAddress, Env, MessageCallResult,
@@ -76,6 +77,7 @@ pub struct Context<'a> {
     output: OutputPolicy,
     static_flag: bool,
     internal_contract_map: &'a InternalContractMap,
+    tracer: &'a mut dyn Tracer,
 }
 
 impl<'a> Context<'a> {
@@ -86,6 +88,7 @@ impl<'a> Context<'a> {
         origin: &'a OriginInfo, substate: &'a mut Substate,
         output: OutputPolicy, static_flag: bool,
         internal_contract_map: &'a InternalContractMap,
+        tracer: &'a mut dyn Tracer,
     ) -> Self
     {
         Context {
@@ -100,6 +103,7 @@ impl<'a> Context<'a> {
             output,
             static_flag,
             internal_contract_map,
+            tracer,
         }
     }
 }
@@ -229,6 +233,7 @@ impl<'a> ContextTrait for Context<'a> {
             params,
             self.substate,
             self.stack_depth + 1,
+            self.tracer,
         );
         Ok(into_contract_create_result(out, &address, self.substate))
     }
@@ -400,6 +405,15 @@ impl<'a> ContextTrait for Context<'a> {
             return Err(vm::Error::MutableCallInStaticContext);
         }
 
+        if self.tracer.traces_suicide() {
+            let balance = self.state.balance(&self.origin.address)?;
+            self.tracer.trace_suicide(
+                self.origin.address,
+                balance,
+                *refund_address,
+            );
+        }
+
         suicide_impl(
             &self.origin.address,
             refund_address,
@@ -426,25 +440,32 @@ impl<'a> ContextTrait for Context<'a> {
     }
 
     fn trace_next_instruction(
-        &mut self, _pc: usize, _instruction: u8, _current_gas: U256,
+        &mut self, pc: usize, instruction: u8, current_gas: U256,
     ) -> bool {
-        // TODO
-        false
+        self.tracer
+            .trace_next_instruction(pc, instruction, current_gas)
     }
 
     fn trace_prepare_execute(
-        &mut self, _pc: usize, _instruction: u8, _gas_cost: U256,
-        _mem_written: Option<(usize, usize)>,
-        _store_written: Option<(U256, U256)>,
+        &mut self, pc: usize, instruction: u8, gas_cost: U256,
+        stack: &[U256], mem: &[u8], _mem_written: Option<(usize, usize)>,
+        store_written: Option<(U256, U256)>,
     )
     {
-        // TODO
+        self.tracer.trace_prepare_execute(
+            pc,
+            instruction,
+            gas_cost,
+            stack,
+            mem,
+            store_written,
+        );
     }
 
     fn trace_executed(
-        &mut self, _gas_used: U256, _stack_push: &[U256], _mem: &[u8],
+        &mut self, gas_left: U256, stack_push: &[U256], mem: &[u8],
     ) {
-        // TODO
+        self.tracer.trace_executed(gas_left, stack_push, mem);
     }
 }
 
@@ -460,6 +481,7 @@ mod tests {
             StorageManager,
         },
         test_helpers::get_state_for_genesis_write,
+        trace::NoopTracer,
         vm::{
             CallType, Context as ContextTrait, ContractCreateResult,
             CreateContractAddress, Env, Spec,
@@ -548,6 +570,7 @@ mod tests {
         let mut setup = TestSetup::new();
         let state = &mut setup.state.unwrap();
         let origin = get_test_origin();
+        let mut tracer = NoopTracer;
 
         let ctx = Context::new(
             state,
@@ -561,6 +584,7 @@ mod tests {
             OutputPolicy::InitContract,
             false,
             &setup.internal_contract_map,
+            &mut tracer,
         );
 
         assert_eq!(ctx.env().number, 100);
@@ -571,6 +595,7 @@ mod tests {
         let mut setup = TestSetup::new();
         let state = &mut setup.state.unwrap();
         let origin = get_test_origin();
+        let mut tracer = NoopTracer;
 
         let mut ctx = Context::new(
             state,
@@ -584,6 +609,7 @@ mod tests {
             OutputPolicy::InitContract,
             false,
             &setup.internal_contract_map,
+            &mut tracer,
         );
 
         let hash = ctx.blockhash(
@@ -643,6 +669,7 @@ mod tests {
         let mut setup = TestSetup::new();
         let state = &mut setup.state.unwrap();
         let origin = get_test_origin();
+        let mut tracer = NoopTracer;
 
         let mut ctx = Context::new(
             state,
@@ -656,6 +683,7 @@ mod tests {
             OutputPolicy::InitContract,
             false,
             &setup.internal_contract_map,
+            &mut tracer,
         );
 
         // this should panic because we have no balance on any account
@@ -690,6 +718,7 @@ mod tests {
         let mut setup = TestSetup::new();
         let state = &mut setup.state.unwrap();
         let origin = get_test_origin();
+        let mut tracer = NoopTracer;
 
         {
             let mut ctx = Context::new(
@@ -704,6 +733,7 @@ mod tests {
                 OutputPolicy::InitContract,
                 false,
                 &setup.internal_contract_map,
+                &mut tracer,
             );
             ctx.log(log_topics, &log_data).unwrap();
         }
@@ -719,6 +749,7 @@ mod tests {
         let mut setup = TestSetup::new();
         let state = &mut setup.state.unwrap();
         let mut origin = get_test_origin();
+        let mut tracer = NoopTracer;
 
         let mut contract_address = Address::zero();
         contract_address.set_contract_type_bits();
@@ -749,6 +780,7 @@ mod tests {
                 OutputPolicy::InitContract,
                 false,
                 &setup.internal_contract_map,
+                &mut tracer,
             );
             ctx.suicide(&refund_account).unwrap();
         }
@@ -763,6 +795,7 @@ mod tests {
         let mut setup = TestSetup::new();
         let state = &mut setup.state.unwrap();
         let origin = get_test_origin();
+        let mut tracer = NoopTracer;
 
         let address = {
             let mut ctx = Context::new(
@@ -777,6 +810,7 @@ mod tests {
                 OutputPolicy::InitContract,
                 false,
                 &setup.internal_contract_map,
+                &mut tracer,
             );
             match ctx.create(
                 &U256::max_value(),
@@ -806,6 +840,7 @@ mod tests {
         let mut setup = TestSetup::new();
         let state = &mut setup.state.unwrap();
         let origin = get_test_origin();
+        let mut tracer = NoopTracer;
 
         let address = {
             let mut ctx = Context::new(
@@ -820,6 +855,7 @@ mod tests {
                 OutputPolicy::InitContract,
                 false,
                 &setup.internal_contract_map,
+                &mut tracer,
             );
 
             match ctx.create(
@@ -15,6 +15,7 @@ use crate::{
     parameters::staking::*,
     state::{CleanupMode, CollateralCheckResult, State, Substate},
     statedb::Result as DbResult,
+    trace::{trace::TraceError, NoopTracer, Tracer},
     verification::VerificationConfig,
     vm::{
         self, ActionParams, ActionValue, CallType, CreateContractAddress, Env,
@@ -429,6 +430,7 @@ impl<'a> CallCreateExecutive<'a> {
         spec: &'any Spec, depth: usize, stack_depth: usize, static_flag: bool,
         origin: &'any OriginInfo, substate: &'any mut Substate,
         output: OutputPolicy, internal_contract_map: &'any InternalContractMap,
+        tracer: &'any mut dyn Tracer,
     ) -> Context<'any>
     {
         Context::new(
@@ -443,6 +445,7 @@ impl<'a> CallCreateExecutive<'a> {
             output,
             static_flag,
             internal_contract_map,
+            tracer,
         )
     }
 
@@ -451,7 +454,9 @@ impl<'a> CallCreateExecutive<'a> {
     /// `resume_call` or `resume_create` to continue the execution.
     pub fn exec(
         mut self, state: &mut State, substate: &mut Substate,
-    ) -> ExecutiveTrapResult<'a, FinalizationResult> {
+        tracer: &mut dyn Tracer,
+    ) -> ExecutiveTrapResult<'a, FinalizationResult>
+    {
         match self.kind {
             CallCreateExecutiveKind::Transfer(ref params) => {
                 assert!(!self.is_create);
@@ -686,6 +691,7 @@ impl<'a> CallCreateExecutive<'a> {
                             &mut unconfirmed_substate,
                             OutputPolicy::Return,
                             self.internal_contract_map,
+                            tracer,
                         );
                         match exec.exec(&mut context) {
                             Ok(val) => Ok(val.finalize(context)),
@@ -793,6 +799,7 @@ impl<'a> CallCreateExecutive<'a> {
                             &mut unconfirmed_substate,
                             OutputPolicy::InitContract,
                             self.internal_contract_map,
+                            tracer,
                         );
                         match exec.exec(&mut context) {
                             Ok(val) => Ok(val.finalize(context)),
@@ -857,7 +864,7 @@ impl<'a> CallCreateExecutive<'a> {
     /// Resume execution from a call trap previously trapped by `exec'.
     pub fn resume_call(
         mut self, result: vm::MessageCallResult, state: &mut State,
-        substate: &mut Substate,
+        substate: &mut Substate, tracer: &mut dyn Tracer,
     ) -> ExecutiveTrapResult<'a, FinalizationResult>
     {
         match self.kind {
@@ -885,6 +892,7 @@ impl<'a> CallCreateExecutive<'a> {
                             OutputPolicy::Return
                         },
                         self.internal_contract_map,
+                        tracer,
                     );
                     match exec.exec(&mut context) {
                         Ok(val) => Ok(val.finalize(context)),
@@ -955,7 +963,7 @@ impl<'a> CallCreateExecutive<'a> {
     /// Resume execution from a create trap previously trapped by `exec`.
     pub fn resume_create(
         mut self, result: vm::ContractCreateResult, state: &mut State,
-        substate: &mut Substate,
+        substate: &mut Substate, tracer: &mut dyn Tracer,
     ) -> ExecutiveTrapResult<'a, FinalizationResult>
     {
         match self.kind {
@@ -986,6 +994,7 @@ impl<'a> CallCreateExecutive<'a> {
                             OutputPolicy::Return
                         },
                         self.internal_contract_map,
+                        tracer,
                     );
                     match exec.exec(&mut context) {
                         Ok(val) => Ok(val.finalize(context)),
@@ -1055,9 +1064,11 @@ impl<'a> CallCreateExecutive<'a> {
     /// current-level tracing.
     pub fn consume(
         self, state: &mut State, top_substate: &mut Substate,
-    ) -> vm::Result<FinalizationResult> {
+        tracer: &mut dyn Tracer,
+    ) -> vm::Result<FinalizationResult>
+    {
         let mut last_res =
-            Some((false, self.gas, self.exec(state, top_substate)));
+            Some((false, self.gas, self.exec(state, top_substate, tracer)));
 
         let mut callstack: Vec<(Option<Address>, CallCreateExecutive<'a>)> =
             Vec::new();
@@ -1072,12 +1083,12 @@ impl<'a> CallCreateExecutive<'a> {
                                 None => top_substate,
                             };
 
-                            last_res = Some((exec.is_create, exec.gas, exec.exec(state, parent_substate)));
+                            last_res = Some((exec.is_create, exec.gas, exec.exec(state, parent_substate, tracer)));
                         },
                         None => panic!("When callstack only had one item and it was executed, this function would return; callstack never reaches zero item; qed"),
                     }
                 },
-                Some((is_create, _gas, Ok(val))) => {
+                Some((is_create, gas, Ok(val))) => {
                     let current = callstack.pop();
 
                     match current {
@@ -1085,6 +1096,8 @@ impl<'a> CallCreateExecutive<'a> {
                             if is_create {
                                 let address = address.expect("If the last executed status was from a create executive, then the destination address was pushed to the callstack; address is_some if it is_create; qed");
 
+                                trace_frame_result(tracer, true, gas, &address, &val);
+
                                 let second_last = callstack.last_mut();
                                 let parent_substate = match second_last {
                                     Some((_, ref mut second_last)) => second_last.unconfirmed_substate().expect("Current stack value is created from second last item; second last item must be call or create; qed"),
@@ -1096,8 +1109,11 @@ impl<'a> CallCreateExecutive<'a> {
                                     contract_create_result,
                                     state,
                                     parent_substate,
+                                    tracer,
                                 )));
                             } else {
+                                trace_frame_result(tracer, false, gas, &Address::zero(), &val);
+
                                 let second_last = callstack.last_mut();
                                 let parent_substate = match second_last {
                                     Some((_, ref mut second_last)) => second_last.unconfirmed_substate().expect("Current stack value is created from second last item; second last item must be call or create; qed"),
@@ -1108,6 +1124,7 @@ impl<'a> CallCreateExecutive<'a> {
                                     into_message_call_result(val),
                                     state,
                                     parent_substate,
+                                    tracer,
                                 )));
                             }
                         },
@@ -1115,7 +1132,10 @@ impl<'a> CallCreateExecutive<'a> {
                     }
                 },
                 Some((_, _, Err(TrapError::Call(subparams, mut resume)))) => {
-                    let is_not_internal_contract_and_has_code = subparams.code.is_some() && resume.internal_contract_map.contract(&subparams.code_address).is_none();
+                    let is_internal_contract = resume.internal_contract_map.contract(&subparams.code_address).is_some();
+                    tracer.prepare_trace_call(&subparams, is_internal_contract);
+
+                    let is_not_internal_contract_and_has_code = subparams.code.is_some() && !is_internal_contract;
                     let substate = resume.unconfirmed_substate().unwrap();
                     let mut is_recursive_call = false;
                     let contracts_in_callstack = if is_not_internal_contract_and_has_code {
@@ -1149,6 +1169,8 @@ impl<'a> CallCreateExecutive<'a> {
                     last_res = None;
                 },
                 Some((_, _, Err(TrapError::Create(subparams, address, mut resume)))) => {
+                    tracer.prepare_trace_create(&subparams);
+
                     let substate = resume.unconfirmed_substate().unwrap();
                     let mut contracts_in_callstack = HashSet::<Address>::new();
                     mem::swap(
@@ -1177,6 +1199,39 @@ impl<'a> CallCreateExecutive<'a> {
     }
 }
 
+/// Reports the result of a finished call or create frame to the tracer.
+/// `address` is the created contract and is only used for creations.
+fn trace_frame_result(
+    tracer: &mut dyn Tracer, is_create: bool, gas: U256, address: &Address,
+    result: &vm::Result<FinalizationResult>,
+)
+{
+    match result {
+        Ok(FinalizationResult {
+            gas_left,
+            return_data,
+            apply_state: true,
+        }) => {
+            if is_create {
+                tracer.done_trace_create(gas - *gas_left, return_data, *address)
+            } else {
+                tracer.done_trace_call(gas - *gas_left, return_data)
+            }
+        }
+        Ok(FinalizationResult {
+            gas_left,
+            return_data,
+            apply_state: false,
+        }) => tracer.done_trace_failed(TraceError::Reverted {
+            gas_used: gas - *gas_left,
+            output: return_data.to_vec(),
+        }),
+        Err(err) => {
+            tracer.done_trace_failed(TraceError::Error(err.to_string()))
+        }
+    }
+}
+
 /// Trap result returned by executive.
 pub type ExecutiveTrapResult<'a, T> =
     vm::TrapResult<T, CallCreateExecutive<'a>, CallCreateExecutive<'a>>;
@@ -1249,11 +1304,12 @@ impl<'a> Executive<'a> {
 
     pub fn create_with_stack_depth(
         &mut self, params: ActionParams, substate: &mut Substate,
-        stack_depth: usize,
+        stack_depth: usize, tracer: &mut dyn Tracer,
     ) -> vm::Result<FinalizationResult>
     {
-        let _address = params.address;
-        let _gas = params.gas;
+        let address = params.address;
+        let gas = params.gas;
+        tracer.prepare_trace_create(&params);
 
         let vm_factory = self.state.vm_factory();
         let mut contracts_in_callstack = HashSet::<Address>::new();
@@ -1273,28 +1329,32 @@ impl<'a> Executive<'a> {
             self.internal_contract_map,
             contracts_in_callstack,
         )
-        .consume(self.state, substate);
+        .consume(self.state, substate, tracer);
 
+        trace_frame_result(tracer, true, gas, &address, &result);
         result
     }
 
     pub fn create(
         &mut self, params: ActionParams, substate: &mut Substate,
     ) -> vm::Result<FinalizationResult> {
-        self.create_with_stack_depth(params, substate, 0)
+        self.create_with_stack_depth(params, substate, 0, &mut NoopTracer)
     }
 
     pub fn call_with_stack_depth(
         &mut self, params: ActionParams, substate: &mut Substate,
-        stack_depth: usize,
+        stack_depth: usize, tracer: &mut dyn Tracer,
     ) -> vm::Result<FinalizationResult>
     {
+        let gas = params.gas;
         let vm_factory = self.state.vm_factory();
-        let is_not_internal_contract_and_has_code = params.code.is_some()
-            && self
-                .internal_contract_map
-                .contract(&params.code_address)
-                .is_none();
+        let is_internal_contract = self
+            .internal_contract_map
+            .contract(&params.code_address)
+            .is_some();
+        tracer.prepare_trace_call(&params, is_internal_contract);
+        let is_not_internal_contract_and_has_code =
+            params.code.is_some() && !is_internal_contract;
         let mut is_recursive_call = false;
         let contracts_in_callstack = if is_not_internal_contract_and_has_code {
             is_recursive_call =
@@ -1321,19 +1381,20 @@ impl<'a> Executive<'a> {
             contracts_in_callstack,
             is_recursive_call,
         )
-        .consume(self.state, substate);
+        .consume(self.state, substate, tracer);
 
+        trace_frame_result(tracer, false, gas, &Address::zero(), &result);
         result
     }
 
     pub fn call(
         &mut self, params: ActionParams, substate: &mut Substate,
     ) -> vm::Result<FinalizationResult> {
-        self.call_with_stack_depth(params, substate, 0)
+        self.call_with_stack_depth(params, substate, 0, &mut NoopTracer)
     }
 
     pub fn transact_virtual(
-        &mut self, tx: &SignedTransaction,
+        &mut self, tx: &SignedTransaction, tracer: &mut dyn Tracer,
     ) -> DbResult<ExecutionOutcome> {
         let sender = tx.sender();
         let balance = self.state.balance(&sender)?;
@@ -1347,11 +1408,19 @@ impl<'a> Executive<'a> {
                 CleanupMode::NoEmpty,
             )?;
         }
-        self.transact(tx)
+        self.transact_with_tracer(tx, tracer)
     }
 
     pub fn transact(
         &mut self, tx: &SignedTransaction,
+    ) -> DbResult<ExecutionOutcome> {
+        self.transact_with_tracer(tx, &mut NoopTracer)
+    }
+
+    /// Executes the transaction and reports its call tree to `tracer`. Nothing
+    /// is traced if the transaction is not executed.
+    pub fn transact_with_tracer(
+        &mut self, tx: &SignedTransaction, tracer: &mut dyn Tracer,
     ) -> DbResult<ExecutionOutcome> {
         let spec = &self.spec;
         let sender = tx.sender();
@@ -1597,7 +1666,12 @@ impl<'a> Executive<'a> {
                     params_type: vm::ParamsType::Embedded,
                     storage_limit: total_storage_limit,
                 };
-                let res = self.create(params, &mut substate);
+                let res = self.create_with_stack_depth(
+                    params,
+                    &mut substate,
+                    0,
+                    tracer,
+                );
                 let out = match &res {
                     Ok(res) => res.return_data.to_vec(),
                     _ => Vec::new(),
@@ -1622,7 +1696,12 @@ impl<'a> Executive<'a> {
                     storage_limit: total_storage_limit,
                 };
 
-                let res = self.call(params, &mut substate);
+                let res = self.call_with_stack_depth(
+                    params,
+                    &mut substate,
+                    0,
+                    tracer,
+                );
                 let out = match &res {
                     Ok(res) => res.return_data.to_vec(),
                     _ => Vec::new(),
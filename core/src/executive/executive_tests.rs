@@ -13,6 +13,10 @@ use crate::{
     test_helpers::{
         get_state_for_genesis_write, get_state_for_genesis_write_with_factory,
     },
+    trace::{
+        trace::{Action as TraceAction, Res},
+        ExecutiveTracer,
+    },
     vm::{
         self, ActionParams, ActionValue, CallType, CreateContractAddress, Env,
     },
@@ -297,6 +301,111 @@ fn test_call_to_create() {
     assert_eq!(gas_left, U256::from(59_752));
 }
 
+#[test]
+fn test_call_to_create_traced() {
+    // Same code as `test_call_to_create`.
+    let code = "7c601080600c6000396000f3006000355415600957005b60203560003555600052601d60036017f0600055".from_hex().unwrap();
+
+    let sender =
+        Address::from_str("1d1722f3947def4cf144679da39c4c32bdc35681").unwrap();
+    let address = contract_address(
+        CreateContractAddress::FromSenderNonceAndCodeHash,
+        &sender,
+        &U256::zero(),
+        &[],
+    )
+    .0;
+    let mut params = ActionParams::default();
+    params.address = address;
+    params.code_address = address;
+    params.sender = sender;
+    params.original_sender = sender;
+    params.storage_owner = sender;
+    params.gas = U256::from(100_000);
+    params.code = Some(Arc::new(code));
+    params.value = ActionValue::Transfer(U256::from(100));
+    params.call_type = CallType::Call;
+
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    state
+        .new_contract(&address, U256::zero(), U256::one())
+        .expect(&concat!(file!(), ":", line!(), ":", column!()));
+    state
+        .add_balance(
+            &sender,
+            &(U256::from(100)
+                + *COLLATERAL_PER_STORAGE_KEY
+                + U256::from(15_625_000_000_000_000u64)),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    let env = Env::default();
+    let machine = make_byzantium_machine(5);
+    let internal_contract_map = InternalContractMap::new();
+    let spec = machine.spec(env.number);
+    let mut substate = Substate::new();
+    let mut tracer = ExecutiveTracer::default();
+
+    let FinalizationResult { gas_left, .. } = {
+        let mut ex = Executive::new(
+            &mut state,
+            &env,
+            &machine,
+            &spec,
+            &internal_contract_map,
+        );
+        ex.call_with_stack_depth(params, &mut substate, 0, &mut tracer)
+            .unwrap()
+    };
+    assert_eq!(gas_left, U256::from(59_752));
+
+    let traces = tracer.drain();
+    assert_eq!(traces.len(), 2);
+    match &traces[0].action {
+        TraceAction::Call(call) => {
+            assert_eq!(call.from, sender);
+            assert_eq!(call.to, address);
+            assert_eq!(call.value, U256::from(100));
+        }
+        action => panic!("Expected a call trace, got {:?}", action),
+    }
+    match &traces[0].result {
+        Res::Call(result) => {
+            assert_eq!(result.gas_used, U256::from(100_000 - 59_752))
+        }
+        result => panic!("Expected a successful call, got {:?}", result),
+    }
+    assert_eq!(traces[0].subtraces, 1);
+    assert!(traces[0].trace_address.is_empty());
+    let init_code: Vec<u8> =
+        "601080600c6000396000f3006000355415600957005b60203560003555"
+            .from_hex()
+            .unwrap();
+    match &traces[1].action {
+        TraceAction::Create(create) => {
+            assert_eq!(create.from, address);
+            assert_eq!(create.value, U256::from(23));
+            assert_eq!(create.init, init_code);
+        }
+        action => panic!("Expected a create trace, got {:?}", action),
+    }
+    match &traces[1].result {
+        Res::Create(result) => assert_eq!(
+            result.address,
+            contract_address(
+                CreateContractAddress::FromSenderNonceAndCodeHash,
+                &address,
+                &U256::one(),
+                &init_code,
+            )
+            .0
+        ),
+        result => panic!("Expected a successful create, got {:?}", result),
+    }
+    assert_eq!(traces[1].trace_address, vec![0]);
+}
+
 #[test]
 fn test_revert() {
     let factory = Factory::new(VMType::Interpreter, 1024 * 32);
@@ -67,6 +67,7 @@ pub mod statedb;
 pub mod statistics;
 pub mod storage;
 pub mod sync;
+pub mod trace;
 pub mod transaction_pool;
 pub mod unique_id;
 pub mod verification;
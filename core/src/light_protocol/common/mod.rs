@@ -4,9 +4,11 @@
 
 mod ledger_info;
 mod peers;
+mod verified_storage;
 
 pub use ledger_info::LedgerInfo;
 pub use peers::{FullPeerFilter, FullPeerState, LightPeerState, Peers};
+pub use verified_storage::{VerifiedEntries, VerifiedStorage};
 
 use super::{Error, ErrorKind};
 use primitives::ChainIdParams;
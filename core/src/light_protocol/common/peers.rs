@@ -23,6 +23,9 @@ pub struct FullPeerState {
     pub terminals: HashSet<H256>,
     pub throttled_msgs: ThrottledManager<MsgId>,
     pub unexpected_msgs: TokenBucketManager,
+    /// The number of received items which were not requested from the
+    /// peer, not reported to the network yet.
+    pub useless_items: usize,
 }
 
 #[derive(Default)]
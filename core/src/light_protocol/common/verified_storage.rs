@@ -0,0 +1,164 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::storage::{
+    Result as StorageResult, StateProof, StateRootWithAuxInfo,
+    StorageStateTrait,
+};
+use parking_lot::Mutex;
+use primitives::{EpochId, MerkleHash, StorageKey};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+
+/// The state entries retrieved and verified so far, indexed by their key
+/// bytes. `None` values are verified non-existence proofs.
+pub type VerifiedEntries = HashMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// A storage backed by the state entries that a light node has retrieved
+/// from its peers and verified against the state root.
+///
+/// Reading an entry which has not been retrieved fails, and its key is
+/// recorded so that the caller can retrieve it and retry. Clones share the
+/// recorded keys. Writes are kept in memory and are never committed.
+#[derive(Clone)]
+pub struct VerifiedStorage {
+    entries: Arc<VerifiedEntries>,
+    missing: Arc<Mutex<BTreeSet<Vec<u8>>>>,
+}
+
+impl VerifiedStorage {
+    pub fn new(entries: Arc<VerifiedEntries>) -> Self {
+        VerifiedStorage {
+            entries,
+            missing: Default::default(),
+        }
+    }
+
+    /// Returns the keys of the entries read but not retrieved yet.
+    pub fn take_missing(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut *self.missing.lock())
+            .into_iter()
+            .collect()
+    }
+
+    fn unsupported<T>(operation: &str) -> StorageResult<T> {
+        Err(format!("{} is not supported on light nodes", operation).into())
+    }
+}
+
+impl StorageStateTrait for VerifiedStorage {
+    fn get_with_proof(
+        &self, _access_key: StorageKey,
+    ) -> StorageResult<(Option<Box<[u8]>>, StateProof)> {
+        Self::unsupported("get_with_proof")
+    }
+
+    fn get(&self, access_key: StorageKey) -> StorageResult<Option<Box<[u8]>>> {
+        let key = access_key.to_key_bytes();
+        match self.entries.get(&key) {
+            Some(value) => {
+                Ok(value.as_ref().map(|v| v.clone().into_boxed_slice()))
+            }
+            None => {
+                let message = format!("State entry {:?} not retrieved", key);
+                self.missing.lock().insert(key);
+                Err(message.into())
+            }
+        }
+    }
+
+    fn set(
+        &mut self, access_key: StorageKey, value: Box<[u8]>,
+    ) -> StorageResult<()> {
+        Arc::make_mut(&mut self.entries)
+            .insert(access_key.to_key_bytes(), Some(value.into_vec()));
+        Ok(())
+    }
+
+    fn delete(&mut self, access_key: StorageKey) -> StorageResult<()> {
+        Arc::make_mut(&mut self.entries)
+            .insert(access_key.to_key_bytes(), None);
+        Ok(())
+    }
+
+    fn delete_test_only(
+        &mut self, access_key: StorageKey,
+    ) -> StorageResult<Option<Box<[u8]>>> {
+        let value = self.get(access_key)?;
+        self.delete(access_key)?;
+        Ok(value)
+    }
+
+    fn delete_all(
+        &mut self, _access_key_prefix: StorageKey,
+    ) -> StorageResult<Option<Vec<(Vec<u8>, Box<[u8]>)>>> {
+        Self::unsupported("delete_all")
+    }
+
+    fn read_all(
+        &mut self, _access_key_prefix: StorageKey,
+    ) -> StorageResult<Option<Vec<(Vec<u8>, Box<[u8]>)>>> {
+        Self::unsupported("read_all")
+    }
+
+    fn compute_state_root(&mut self) -> StorageResult<StateRootWithAuxInfo> {
+        Self::unsupported("compute_state_root")
+    }
+
+    fn get_state_root(&self) -> StorageResult<StateRootWithAuxInfo> {
+        Self::unsupported("get_state_root")
+    }
+
+    fn commit(
+        &mut self, _epoch: EpochId,
+    ) -> StorageResult<StateRootWithAuxInfo> {
+        Self::unsupported("commit")
+    }
+
+    fn revert(&mut self) {}
+
+    fn get_node_merkle_all_versions(
+        &self, _access_key: StorageKey,
+    ) -> StorageResult<(
+        Option<MerkleHash>,
+        Option<MerkleHash>,
+        Option<MerkleHash>,
+    )> {
+        Self::unsupported("get_node_merkle_all_versions")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cfx_types::{Address, H256};
+
+    #[test]
+    fn test_missing_entries() {
+        let address = Address::from_low_u64_be(1);
+        let account_key = StorageKey::new_account_key(&address);
+        let storage_key = StorageKey::new_storage_key(&address, &[0; 32]);
+
+        let mut entries = VerifiedEntries::new();
+        entries.insert(account_key.to_key_bytes(), Some(vec![1, 2, 3]));
+        entries.insert(storage_key.to_key_bytes(), None);
+        let storage = VerifiedStorage::new(Arc::new(entries));
+
+        assert_eq!(
+            storage.get(account_key).unwrap(),
+            Some(vec![1, 2, 3].into_boxed_slice())
+        );
+        assert_eq!(storage.get(storage_key).unwrap(), None);
+        assert!(storage.take_missing().is_empty());
+
+        let code_hash = H256::zero();
+        let code_key = StorageKey::new_code_key(&address, &code_hash);
+        assert!(storage.get(code_key).is_err());
+        assert!(storage.clone().get(code_key).is_err());
+        assert_eq!(storage.take_missing(), vec![code_key.to_key_bytes()]);
+        assert!(storage.take_missing().is_empty());
+    }
+}
@@ -24,7 +24,7 @@ use crate::{
         LIGHT_PROTOCOL_VERSION, LIGHT_PROTO_V1,
     },
     message::{decode_msg, decode_rlp_and_check_deprecation, Message, MsgId},
-    network::{NetworkContext, NetworkProtocolHandler},
+    network::{NetworkContext, NetworkProtocolHandler, PeerEvent},
     parameters::light::{
         CATCH_UP_EPOCH_LAG_THRESHOLD, CLEANUP_PERIOD, SYNC_PERIOD,
     },
@@ -37,6 +37,7 @@ use network::{node_table::NodeId, service::ProtocolVersion};
 use parking_lot::RwLock;
 use rlp::Rlp;
 use std::{
+    mem,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -303,6 +304,20 @@ impl Handler {
         }
     }
 
+    /// Report the peer once for each response with items not requested.
+    #[inline]
+    fn report_useless_items(&self, io: &dyn NetworkContext, peer: &NodeId) {
+        let useless_items = match self.peers.get(peer) {
+            Some(state) => mem::replace(&mut state.write().useless_items, 0),
+            None => 0,
+        };
+
+        if useless_items > 0 {
+            debug!("{} useless items received from {:?}", useless_items, peer);
+            io.report_peer(peer, PeerEvent::UselessResponse);
+        }
+    }
+
     #[inline]
     fn catch_up_mode(&self) -> bool {
         match self.median_peer_epoch() {
@@ -701,8 +716,9 @@ impl NetworkProtocolHandler for Handler {
 
         debug!("on_message: peer={:?}, msgid={:?}", peer, msg_id);
 
-        if let Err(e) = self.dispatch_message(io, peer, msg_id.into(), rlp) {
-            handle_error(io, peer, msg_id.into(), e);
+        match self.dispatch_message(io, peer, msg_id.into(), rlp) {
+            Ok(_) => self.report_useless_items(io, peer),
+            Err(e) => handle_error(io, peer, msg_id.into(), e),
         }
     }
 
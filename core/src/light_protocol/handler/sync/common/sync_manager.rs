@@ -110,6 +110,7 @@ where
         };
 
         let peer = self.get_existing_peer_state(peer)?;
+        peer.write().useless_items += 1;
 
         let bucket_name = self.request_msg_id.to_string();
         let bucket = match peer.read().unexpected_msgs.get(&bucket_name) {
@@ -18,6 +18,7 @@ const LIGHT_PROTOCOL_OLD_VERSIONS_TO_SUPPORT: u8 = 2;
 pub const LIGHT_PROTO_V1: ProtocolVersion = ProtocolVersion(1);
 pub const LIGHT_PROTO_V2: ProtocolVersion = ProtocolVersion(2);
 
+pub(crate) use common::VerifiedStorage;
 use error::{handle as handle_error, Error, ErrorKind};
 
 pub use handler::Handler;
@@ -4,18 +4,31 @@
 
 use crate::{
     consensus::SharedConsensusGraph,
+    executive::{ExecutionOutcome, Executive, InternalContractMap},
     light_protocol::{
-        common::{FullPeerFilter, LedgerInfo},
+        common::{
+            FullPeerFilter, LedgerInfo, VerifiedEntries, VerifiedStorage,
+        },
         handler::sync::tx_infos::TxInfoValidated,
         message::msgid,
         Handler as LightHandler, LIGHT_PROTOCOL_ID, LIGHT_PROTOCOL_VERSION,
     },
+    machine::Machine,
     network::{NetworkContext, NetworkService},
     parameters::{
         consensus::DEFERRED_STATE_EPOCH_COUNT,
-        light::{LOG_FILTERING_LOOKAHEAD, MAX_POLL_TIME},
+        light::{
+            LOG_FILTERING_LOOKAHEAD, MAX_CALL_STATE_RETRIEVAL_ROUNDS,
+            MAX_POLL_TIME,
+        },
     },
+    state::State,
+    statedb::{Result as DbResult, StateDb},
     sync::SynchronizationGraph,
+    trace::NoopTracer,
+    verification::VerificationConfig,
+    vm::{Env, Spec},
+    vm_factory::VmFactory,
 };
 use cfx_types::{Bloom, H160, H256, KECCAK_EMPTY_BLOOM, U256};
 use futures::{
@@ -26,6 +39,7 @@ use network::service::ProtocolVersion;
 use primitives::{
     filter::{Filter, FilterError},
     log_entry::{LocalizedLogEntry, LogEntry},
+    receipt::TRANSACTION_OUTCOME_EXCEPTION_WITHOUT_NONCE_BUMPING,
     Account, BlockReceipts, CodeInfo, EpochNumber, Receipt, SignedTransaction,
     StateRoot, StorageKey, StorageValue, TransactionIndex,
 };
@@ -75,6 +89,11 @@ pub struct QueryService {
 
     // shared network service
     network: Arc<NetworkService>,
+
+    // parameters for executing virtual calls
+    machine: Arc<Machine>,
+    verification_config: VerificationConfig,
+    vm: VmFactory,
 }
 
 impl QueryService {
@@ -83,6 +102,8 @@ impl QueryService {
         network: Arc<NetworkService>, throttling_config_file: Option<String>,
     ) -> Self
     {
+        let machine = graph.machine();
+        let verification_config = graph.verification_config.clone();
         let handler = Arc::new(LightHandler::new(
             consensus.clone(),
             graph,
@@ -96,6 +117,9 @@ impl QueryService {
             handler,
             ledger,
             network,
+            machine,
+            verification_config,
+            vm: VmFactory::new(1024 * 32),
         }
     }
 
@@ -313,6 +337,127 @@ impl QueryService {
         }
     }
 
+    /// Executes `tx` on the state of `epoch` without committing anything.
+    ///
+    /// The state entries a call reads are only known once it is executed, so
+    /// we execute it on the entries retrieved so far, then retrieve the ones
+    /// it could not find and execute it again, until nothing is missing.
+    pub async fn call(
+        &self, epoch: EpochNumber, tx: SignedTransaction,
+    ) -> Result<ExecutionOutcome, String> {
+        debug!("call epoch={:?} tx={:?}", epoch, tx);
+
+        let epoch = match self.get_height_from_epoch_number(epoch) {
+            Ok(epoch) => epoch,
+            Err(e) => return Err(format!("{}", e)),
+        };
+
+        let env = self.call_env(epoch, &tx)?;
+
+        // `State::new` reads the staking state, so it is retrieved upfront.
+        let mut missing: Vec<_> = StateDb::staking_state_keys()
+            .iter()
+            .map(StorageKey::to_key_bytes)
+            .collect();
+        let mut entries = Arc::new(VerifiedEntries::new());
+
+        for _ in 0..MAX_CALL_STATE_RETRIEVAL_ROUNDS {
+            let requests = missing.into_iter().map(|key| {
+                self.retrieve_state_entry_raw(epoch, key.clone())
+                    .map_ok(move |value| (key, value))
+            });
+
+            let retrieved =
+                future::try_join_all(requests).await.map_err(|e| {
+                    format!("Unable to retrieve state entry: {}", e)
+                })?;
+            Arc::make_mut(&mut entries).extend(retrieved);
+
+            let storage = VerifiedStorage::new(entries.clone());
+            let outcome = self.execute_virtual(storage.clone(), &env, &tx);
+            missing = storage.take_missing();
+
+            if missing.is_empty() {
+                return outcome.map_err(|e| format!("{}", e));
+            }
+        }
+
+        Err(format!(
+            "Unable to execute call: too many state entries accessed"
+        ))
+    }
+
+    fn call_env(
+        &self, epoch: u64, tx: &SignedTransaction,
+    ) -> Result<Env, String> {
+        let header = self
+            .ledger
+            .pivot_header_of(epoch)
+            .map_err(|e| format!("{}", e))?;
+        let epoch_height = header.height() + 1;
+
+        self.verification_config
+            .verify_transaction_in_block(tx, tx.chain_id, epoch_height)
+            .map_err(|e| format!("Invalid transaction: {}", e))?;
+
+        // The call is executed as if it were in a block right after the
+        // epoch, as on full nodes.
+        let epoch_size = self
+            .ledger
+            .block_hashes_in(epoch)
+            .map_err(|e| format!("{}", e))?
+            .len() as u64;
+        let start_block_number = match self
+            .consensus
+            .get_data_manager()
+            .get_epoch_execution_context(&header.hash())
+        {
+            Some(context) => context.start_block_number,
+            None => {
+                return Err(format!(
+                    "Unable to get the execution context of epoch {}",
+                    epoch
+                ))
+            }
+        };
+
+        Ok(Env {
+            number: start_block_number + epoch_size,
+            author: Default::default(),
+            timestamp: header.timestamp(),
+            difficulty: Default::default(),
+            accumulated_gas_used: U256::zero(),
+            last_hash: header.hash(),
+            gas_limit: tx.gas,
+            epoch_height,
+            transaction_epoch_bound: self
+                .verification_config
+                .transaction_epoch_bound,
+        })
+    }
+
+    fn execute_virtual(
+        &self, storage: VerifiedStorage, env: &Env, tx: &SignedTransaction,
+    ) -> DbResult<ExecutionOutcome> {
+        let spec = Spec::new_spec();
+        let internal_contract_map = InternalContractMap::new();
+        let mut state = State::new(
+            StateDb::new_verified(storage),
+            self.vm.clone(),
+            &spec,
+            env.number,
+        );
+
+        Executive::new(
+            &mut state,
+            env,
+            self.machine.as_ref(),
+            &spec,
+            &internal_contract_map,
+        )
+        .transact_virtual(tx, &mut NoopTracer)
+    }
+
     pub async fn get_tx_info(&self, hash: H256) -> Result<TxInfo, String> {
         debug!("get_tx_info hash={:?}", hash);
 
@@ -384,6 +529,115 @@ impl QueryService {
         .await
     }
 
+    /// Retrieve the transactions of block `hash`, verified against the
+    /// transactions root in its header. Returns `None` for unknown blocks.
+    pub async fn get_block_txs(
+        &self, hash: H256,
+    ) -> Result<Option<Vec<SignedTransaction>>, String> {
+        debug!("get_block_txs hash={:?}", hash);
+
+        if self.ledger.header(hash).is_err() {
+            return Ok(None);
+        }
+
+        with_timeout(
+            *MAX_POLL_TIME,
+            format!("Timeout while retrieving block txs for block {}", hash),
+            self.handler.block_txs.request(hash),
+        )
+        .await
+        .map(Some)
+    }
+
+    /// Retrieve the transaction at `index` in block `hash`, with its
+    /// execution info if it is executed in this block.
+    pub async fn get_block_tx(
+        &self, hash: H256, index: usize,
+    ) -> Result<Option<(SignedTransaction, Option<TxInfo>)>, String> {
+        debug!("get_block_tx hash={:?} index={}", hash, index);
+
+        let tx = match self.get_block_txs(hash).await? {
+            Some(mut txs) if index < txs.len() => txs.swap_remove(index),
+            _ => return Ok(None),
+        };
+
+        let latest_verifiable = self
+            .get_latest_verifiable_epoch_number()
+            .map_err(|e| format!("{}", e))?;
+
+        let epoch = match self.consensus.get_block_epoch_number(&hash) {
+            Some(epoch) if epoch <= latest_verifiable => epoch,
+            _ => return Ok(Some((tx, None))),
+        };
+
+        // the transaction might have been executed in another block, or not
+        // at all, which is told by its receipt in the verified receipts of
+        // the epoch
+        let block_index = self
+            .ledger
+            .block_hashes_in(epoch)
+            .map_err(|e| format!("{}", e))?
+            .iter()
+            .position(|block_hash| *block_hash == hash)
+            .ok_or_else(|| {
+                format!("Block {:?} is not in epoch {}", hash, epoch)
+            })?;
+        let (_, epoch_receipts) = self.retrieve_receipts(epoch).await?;
+        let receipts = match epoch_receipts.get(block_index) {
+            Some(block_receipts) => &block_receipts.receipts,
+            None => {
+                return Err(format!("Receipts of block {:?} missing", hash))
+            }
+        };
+        let receipt = match receipts.get(index) {
+            Some(receipt) => receipt.clone(),
+            None => {
+                return Err(format!("Receipt of tx {:?} missing", tx.hash()))
+            }
+        };
+
+        if receipt.outcome_status
+            == TRANSACTION_OUTCOME_EXCEPTION_WITHOUT_NONCE_BUMPING
+        {
+            return Ok(Some((tx, None)));
+        }
+
+        let prior_gas_used = match index {
+            0 => U256::zero(),
+            _ => receipts[index - 1].accumulated_gas_used,
+        };
+        let address = TransactionIndex {
+            block_hash: hash,
+            index,
+        };
+        let root = self
+            .handler
+            .witnesses
+            .root_hashes_of(epoch)
+            .map(|(state_root, _, _)| state_root);
+        let info = (
+            tx.clone(),
+            receipt,
+            address,
+            Some(epoch),
+            root,
+            prior_gas_used,
+        );
+
+        Ok(Some((tx, Some(info))))
+    }
+
+    /// Return the pivot hash of `epoch`, which must be verifiable.
+    pub fn get_pivot_hash(&self, epoch: EpochNumber) -> Result<H256, String> {
+        let epoch = self
+            .get_height_from_epoch_number(epoch)
+            .map_err(|e| format!("{}", e))?;
+
+        self.ledger
+            .pivot_hash_of(epoch)
+            .map_err(|e| format!("{}", e))
+    }
+
     /// Apply filter to all logs within a receipt.
     /// NOTE: `log.transaction_hash` is not known at this point,
     /// so this field has to be filled later on.
@@ -52,8 +52,13 @@ pub mod consensus_internal {
     pub const MINING_REWARD_DECAY_RATIO_PER_QUARTER: f64 = 0.958;
     // How many quarters that the mining reward keep decaying.
     pub const MINING_REWARD_DECAY_PERIOD_IN_QUARTER: usize = 40;
-    pub const GAS_PRICE_BLOCK_SAMPLE_SIZE: usize = 100;
-    pub const GAS_PRICE_TRANSACTION_SAMPLE_SIZE: usize = 10000;
+    /// The number of recent epochs sampled by the gas price oracle.
+    pub const GAS_PRICE_EPOCH_SAMPLE_SIZE: u64 = 20;
+    /// The number of recent epochs whose gas price statistics are kept in
+    /// memory.
+    pub const FEE_HISTORY_CACHE_SIZE: usize = 1024;
+    /// The maximum number of epochs queried in one `cfx_feeHistory` request.
+    pub const MAX_FEE_HISTORY_EPOCH_COUNT: u64 = 1024;
 
     /// This is the cap of the size of the anticone barrier. If we have more
     /// than this number we will use the brute_force O(n) algorithm instead.
@@ -312,6 +317,11 @@ pub mod light {
     /// there's always plenty of items in flight. This way, we can reduce idle
     /// time when we're waiting to receive an item.
     pub const LOG_FILTERING_LOOKAHEAD: usize = 100;
+
+    /// During virtual calls, the state entries accessed are only discovered
+    /// by executing the call, so we re-execute it each time we retrieve a new
+    /// batch of entries. This bounds the number of re-executions.
+    pub const MAX_CALL_STATE_RETRIEVAL_ROUNDS: usize = 64;
 }
 
 pub const WORKER_COMPUTATION_PARALLELISM: usize = 8;
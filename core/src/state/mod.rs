@@ -1001,12 +1001,10 @@ impl State {
         }
     }
 
-    pub fn commit(
-        &mut self, epoch_id: EpochId,
-        mut debug_record: Option<&mut ComputeEpochDebugRecord>,
-    ) -> DbResult<StateRootWithAuxInfo>
-    {
-        debug!("Commit epoch[{}]", epoch_id);
+    /// Write the dirty accounts and the staking state to the state db.
+    fn precommit(
+        &mut self, mut debug_record: Option<&mut ComputeEpochDebugRecord>,
+    ) -> DbResult<()> {
         assert!(self.checkpoints.get_mut().is_empty());
         assert!(self.staking_state_checkpoints.get_mut().is_empty());
 
@@ -1029,10 +1027,28 @@ impl State {
                 }
             }
         }
-        self.recycle_storage(killed_addresses, debug_record)?;
+        self.recycle_storage(killed_addresses, debug_record)
+    }
+
+    pub fn commit(
+        &mut self, epoch_id: EpochId,
+        debug_record: Option<&mut ComputeEpochDebugRecord>,
+    ) -> DbResult<StateRootWithAuxInfo>
+    {
+        debug!("Commit epoch[{}]", epoch_id);
+        self.precommit(debug_record)?;
         Ok(self.db.commit(epoch_id)?)
     }
 
+    /// Compute the state root after the changes like `commit`, but leave the
+    /// storage uncommitted. The state should be dropped afterwards.
+    pub fn compute_state_root(
+        &mut self, debug_record: Option<&mut ComputeEpochDebugRecord>,
+    ) -> DbResult<StateRootWithAuxInfo> {
+        self.precommit(debug_record)?;
+        Ok(self.db.compute_state_root()?)
+    }
+
     pub fn commit_and_notify(
         &mut self, epoch_id: EpochId, txpool: &SharedTransactionPool,
         debug_record: Option<&mut ComputeEpochDebugRecord>,
@@ -4,16 +4,18 @@
 
 use crate::{
     executive::STORAGE_INTEREST_STAKING_CONTRACT_ADDRESS,
+    light_protocol::VerifiedStorage,
     parameters::staking::*,
     storage::{
-        Error as StorageError, ErrorKind as StorageErrorKind, StateProof,
-        StateRootWithAuxInfo, StorageState, StorageStateTrait,
+        Error as StorageError, ErrorKind as StorageErrorKind,
+        Result as StorageResult, StateProof, StateRootWithAuxInfo,
+        StorageState, StorageStateTrait,
     },
 };
 use cfx_types::{Address, H256, U256};
 use primitives::{
-    Account, CodeInfo, DepositList, EpochId, StorageKey, StorageLayout,
-    StorageRoot, VoteStakeList, MERKLE_NULL_NODE,
+    Account, CodeInfo, DepositList, EpochId, MerkleHash, StorageKey,
+    StorageLayout, StorageRoot, VoteStakeList, MERKLE_NULL_NODE,
 };
 
 mod error;
@@ -21,8 +23,98 @@ mod error;
 pub use self::error::{Error, ErrorKind, Result};
 use crate::consensus::debug::{ComputeEpochDebugRecord, StateOp};
 
+/// The storage under a `StateDb`. It's an enum rather than a trait object so
+/// that the storage accesses during execution are statically dispatched.
+pub enum StateDbStorage {
+    /// The MPT backed storage of full nodes.
+    Mpt(StorageState),
+    /// The verified state entries on light nodes.
+    Verified(VerifiedStorage),
+}
+
+macro_rules! dispatch_storage {
+    ($self:ident, $storage:ident => $e:expr) => {
+        match $self {
+            StateDbStorage::Mpt($storage) => $e,
+            StateDbStorage::Verified($storage) => $e,
+        }
+    };
+}
+
+impl StorageStateTrait for StateDbStorage {
+    fn get_with_proof(
+        &self, access_key: StorageKey,
+    ) -> StorageResult<(Option<Box<[u8]>>, StateProof)> {
+        dispatch_storage!(self, storage => storage.get_with_proof(access_key))
+    }
+
+    fn get(&self, access_key: StorageKey) -> StorageResult<Option<Box<[u8]>>> {
+        dispatch_storage!(self, storage => storage.get(access_key))
+    }
+
+    fn set(
+        &mut self, access_key: StorageKey, value: Box<[u8]>,
+    ) -> StorageResult<()> {
+        dispatch_storage!(self, storage => storage.set(access_key, value))
+    }
+
+    fn delete(&mut self, access_key: StorageKey) -> StorageResult<()> {
+        dispatch_storage!(self, storage => storage.delete(access_key))
+    }
+
+    fn delete_test_only(
+        &mut self, access_key: StorageKey,
+    ) -> StorageResult<Option<Box<[u8]>>> {
+        dispatch_storage!(self, storage => storage.delete_test_only(access_key))
+    }
+
+    fn delete_all(
+        &mut self, access_key_prefix: StorageKey,
+    ) -> StorageResult<Option<Vec<(Vec<u8>, Box<[u8]>)>>> {
+        dispatch_storage!(self, storage => {
+            storage.delete_all(access_key_prefix)
+        })
+    }
+
+    fn read_all(
+        &mut self, access_key_prefix: StorageKey,
+    ) -> StorageResult<Option<Vec<(Vec<u8>, Box<[u8]>)>>> {
+        dispatch_storage!(self, storage => storage.read_all(access_key_prefix))
+    }
+
+    fn compute_state_root(&mut self) -> StorageResult<StateRootWithAuxInfo> {
+        dispatch_storage!(self, storage => storage.compute_state_root())
+    }
+
+    fn get_state_root(&self) -> StorageResult<StateRootWithAuxInfo> {
+        dispatch_storage!(self, storage => storage.get_state_root())
+    }
+
+    fn commit(
+        &mut self, epoch: EpochId,
+    ) -> StorageResult<StateRootWithAuxInfo> {
+        dispatch_storage!(self, storage => storage.commit(epoch))
+    }
+
+    fn revert(&mut self) {
+        dispatch_storage!(self, storage => storage.revert())
+    }
+
+    fn get_node_merkle_all_versions(
+        &self, access_key: StorageKey,
+    ) -> StorageResult<(
+        Option<MerkleHash>,
+        Option<MerkleHash>,
+        Option<MerkleHash>,
+    )> {
+        dispatch_storage!(self, storage => {
+            storage.get_node_merkle_all_versions(access_key)
+        })
+    }
+}
+
 pub struct StateDb {
-    storage: StorageState,
+    storage: StateDbStorage,
 }
 
 impl StateDb {
@@ -33,10 +125,44 @@ impl StateDb {
     const TOTAL_STORAGE_TOKENS_KEY: &'static [u8] = b"total_storage_tokens";
     const TOTAL_TOKENS_KEY: &'static [u8] = b"total_issued_tokens";
 
-    pub fn new(storage: StorageState) -> Self { StateDb { storage } }
+    pub fn new(storage: StorageState) -> Self {
+        StateDb {
+            storage: StateDbStorage::Mpt(storage),
+        }
+    }
+
+    /// Creates a `StateDb` on top of the verified state entries on light
+    /// nodes.
+    pub fn new_verified(storage: VerifiedStorage) -> Self {
+        StateDb {
+            storage: StateDbStorage::Verified(storage),
+        }
+    }
+
+    /// The keys of the global staking state, which is read when a `State` is
+    /// created.
+    pub fn staking_state_keys() -> Vec<StorageKey<'static>> {
+        [
+            Self::ACCUMULATE_INTEREST_RATE_KEY,
+            Self::INTEREST_RATE_KEY,
+            Self::TOTAL_BANK_TOKENS_KEY,
+            Self::TOTAL_STORAGE_TOKENS_KEY,
+            Self::TOTAL_TOKENS_KEY,
+        ]
+        .iter()
+        .map(|&key| {
+            StorageKey::new_storage_key(
+                &STORAGE_INTEREST_STAKING_CONTRACT_ADDRESS,
+                key,
+            )
+        })
+        .collect()
+    }
 
     #[allow(unused)]
-    pub fn get_storage_mut(&mut self) -> &mut StorageState { &mut self.storage }
+    pub fn get_storage_mut(&mut self) -> &mut StateDbStorage {
+        &mut self.storage
+    }
 
     pub fn get<T>(&self, key: StorageKey) -> Result<Option<T>>
     where T: ::rlp::Decodable {
@@ -194,6 +320,12 @@ impl StateDb {
         Ok(self.storage.compute_state_root()?)
     }
 
+    /// The state root of a committed state, which the proofs returned by
+    /// `get_raw_with_proof` are checked against.
+    pub fn get_state_root(&self) -> Result<StateRootWithAuxInfo> {
+        Ok(self.storage.get_state_root()?)
+    }
+
     pub fn commit(
         &mut self, epoch_id: EpochId,
     ) -> Result<StateRootWithAuxInfo> {
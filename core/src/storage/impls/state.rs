@@ -371,23 +371,137 @@ impl StateTrait for State {
         &mut self, access_key_prefix: StorageKey,
     ) -> Result<Option<Vec<(Vec<u8>, Box<[u8]>)>>> {
         self.pre_modification();
+        self.delete_or_read_all(access_key_prefix, true /* delete */)
+    }
+
+    fn read_all(
+        &mut self, access_key_prefix: StorageKey,
+    ) -> Result<Option<Vec<(Vec<u8>, Box<[u8]>)>>> {
+        self.ensure_temp_slab_for_db_load();
+        self.delete_or_read_all(access_key_prefix, false /* delete */)
+    }
+
+    fn compute_state_root(&mut self) -> Result<StateRootWithAuxInfo> {
+        self.ensure_temp_slab_for_db_load();
+
+        let merkle_root = self.compute_merkle_root()?;
+        Ok(self.state_root(merkle_root))
+    }
+
+    fn get_state_root(&self) -> Result<StateRootWithAuxInfo> {
+        self.ensure_temp_slab_for_db_load();
+
+        Ok(self.state_root(self.state_root_check()?))
+    }
+
+    // TODO(yz): replace coarse lock with a queue.
+    fn commit(&mut self, epoch_id: EpochId) -> Result<StateRootWithAuxInfo> {
+        self.ensure_temp_slab_for_db_load();
+
+        let merkle_root = self.state_root_check()?;
+
+        // TODO(yz): Think about leaving these node dirty and only commit when
+        // the dirty node is removed from cache.
+        let commit_result = self.do_db_commit(epoch_id, &merkle_root);
+        debug!(
+            "commit state for epoch {:?}: delta_trie_height={:?} \
+            has_intermediate={}, height={:?}, snapshot_epoch_id={:?}, \
+            intermediate_epoch_id={:?}, intermediate_mpt_id={:?}, delta_mpt_id={}.",
+            epoch_id,
+            self.delta_trie_height,
+            self.maybe_intermediate_trie.is_some(),
+            self.height,
+            self.snapshot_epoch_id,
+            self.intermediate_epoch_id,
+            self.maybe_intermediate_trie.as_ref().map(|mpt| mpt.get_mpt_id()),
+            self.delta_trie.get_mpt_id(),
+        );
+        if commit_result.is_err() {
+            self.revert();
+            debug!("State commitment failed.");
+
+            commit_result?;
+        }
+        if self.delta_trie_height.unwrap()
+            >= self
+                .manager
+                .get_storage_manager()
+                .get_snapshot_epoch_count()
+                / 3
+            && self.maybe_intermediate_trie.is_some()
+        {
+            // TODO: use a better criteria and put it in consensus maybe.
+            let snapshot_height = self.height.clone().unwrap()
+                - self.delta_trie_height.unwrap() as u64;
+            self.manager.check_make_snapshot(
+                self.maybe_intermediate_trie.clone(),
+                self.intermediate_trie_root.clone(),
+                &self.intermediate_epoch_id,
+                snapshot_height,
+            )?;
+        }
+
+        Ok(self.state_root(merkle_root))
+    }
+
+    fn revert(&mut self) {
+        self.dirty = false;
+
+        // Free all modified nodes.
+        let owned_node_set = self.owned_node_set.as_ref().unwrap();
+        for owned_node in owned_node_set {
+            self.delta_trie.get_node_memory_manager().free_owned_node(
+                &mut owned_node.clone(),
+                self.delta_trie.get_mpt_id(),
+            );
+        }
+    }
+}
+
+impl State {
+    fn ensure_temp_slab_for_db_load(&self) {
+        self.delta_trie.get_node_memory_manager().enlarge().ok();
+    }
+
+    fn pre_modification(&mut self) {
+        if !self.dirty {
+            self.dirty = true
+        }
+        self.delta_trie.get_node_memory_manager().enlarge().ok();
+    }
+
+    /// Retrieve all key/value pairs with access_key_prefix as prefix, and
+    /// delete them if `delete` is set. See `delete_all`.
+    fn delete_or_read_all(
+        &mut self, access_key_prefix: StorageKey, delete: bool,
+    ) -> Result<Option<Vec<(Vec<u8>, Box<[u8]>)>>> {
         // TODO: add unit tests
 
-        // Retrieve and delete key/value pairs from delta trie
+        // Retrieve (and delete) key/value pairs from delta trie
         let delta_trie_kvs = match &self.delta_trie_root {
             None => None,
             Some(old_root_node) => {
                 let delta_mpt_key_prefix = access_key_prefix
                     .to_delta_mpt_key_bytes(&self.delta_trie_key_padding);
-                let (deleted, _, root_node) = SubTrieVisitor::new(
+                let visitor = SubTrieVisitor::new(
                     &self.delta_trie,
                     old_root_node.clone(),
                     &mut self.owned_node_set,
-                )?
-                .delete_all(&delta_mpt_key_prefix, &delta_mpt_key_prefix)?;
-                self.delta_trie_root =
-                    root_node.map(|maybe_node| maybe_node.into());
-                deleted
+                )?;
+                if delete {
+                    let (deleted, _, root_node) = visitor.delete_all(
+                        &delta_mpt_key_prefix,
+                        &delta_mpt_key_prefix,
+                    )?;
+                    self.delta_trie_root =
+                        root_node.map(|maybe_node| maybe_node.into());
+                    deleted
+                } else {
+                    visitor.traversal(
+                        &delta_mpt_key_prefix,
+                        &delta_mpt_key_prefix,
+                    )?
+                }
             }
         };
 
@@ -473,7 +587,7 @@ impl StateTrait for State {
             for (k, v) in kvs {
                 let storage_key = StorageKey::from_delta_mpt_key(&k);
                 // Only delete nonempty keys.
-                if v.len() > 0 {
+                if delete && v.len() > 0 {
                     self.delete(storage_key)?;
                 }
                 let k = storage_key.to_key_bytes();
@@ -489,8 +603,9 @@ impl StateTrait for State {
         // No need to check v.len() because there are no tombStone values in
         // snapshot.
         for (k, v) in snapshot_kvs {
-            let storage_key = StorageKey::from_key_bytes(&k);
-            self.delete(storage_key)?;
+            if delete {
+                self.delete(StorageKey::from_key_bytes(&k))?;
+            }
             if !deleted_keys.contains(&k) {
                 result.push((k, v));
             }
@@ -503,95 +618,6 @@ impl StateTrait for State {
         }
     }
 
-    fn compute_state_root(&mut self) -> Result<StateRootWithAuxInfo> {
-        self.ensure_temp_slab_for_db_load();
-
-        let merkle_root = self.compute_merkle_root()?;
-        Ok(self.state_root(merkle_root))
-    }
-
-    fn get_state_root(&self) -> Result<StateRootWithAuxInfo> {
-        self.ensure_temp_slab_for_db_load();
-
-        Ok(self.state_root(self.state_root_check()?))
-    }
-
-    // TODO(yz): replace coarse lock with a queue.
-    fn commit(&mut self, epoch_id: EpochId) -> Result<StateRootWithAuxInfo> {
-        self.ensure_temp_slab_for_db_load();
-
-        let merkle_root = self.state_root_check()?;
-
-        // TODO(yz): Think about leaving these node dirty and only commit when
-        // the dirty node is removed from cache.
-        let commit_result = self.do_db_commit(epoch_id, &merkle_root);
-        debug!(
-            "commit state for epoch {:?}: delta_trie_height={:?} \
-            has_intermediate={}, height={:?}, snapshot_epoch_id={:?}, \
-            intermediate_epoch_id={:?}, intermediate_mpt_id={:?}, delta_mpt_id={}.",
-            epoch_id,
-            self.delta_trie_height,
-            self.maybe_intermediate_trie.is_some(),
-            self.height,
-            self.snapshot_epoch_id,
-            self.intermediate_epoch_id,
-            self.maybe_intermediate_trie.as_ref().map(|mpt| mpt.get_mpt_id()),
-            self.delta_trie.get_mpt_id(),
-        );
-        if commit_result.is_err() {
-            self.revert();
-            debug!("State commitment failed.");
-
-            commit_result?;
-        }
-        if self.delta_trie_height.unwrap()
-            >= self
-                .manager
-                .get_storage_manager()
-                .get_snapshot_epoch_count()
-                / 3
-            && self.maybe_intermediate_trie.is_some()
-        {
-            // TODO: use a better criteria and put it in consensus maybe.
-            let snapshot_height = self.height.clone().unwrap()
-                - self.delta_trie_height.unwrap() as u64;
-            self.manager.check_make_snapshot(
-                self.maybe_intermediate_trie.clone(),
-                self.intermediate_trie_root.clone(),
-                &self.intermediate_epoch_id,
-                snapshot_height,
-            )?;
-        }
-
-        Ok(self.state_root(merkle_root))
-    }
-
-    fn revert(&mut self) {
-        self.dirty = false;
-
-        // Free all modified nodes.
-        let owned_node_set = self.owned_node_set.as_ref().unwrap();
-        for owned_node in owned_node_set {
-            self.delta_trie.get_node_memory_manager().free_owned_node(
-                &mut owned_node.clone(),
-                self.delta_trie.get_mpt_id(),
-            );
-        }
-    }
-}
-
-impl State {
-    fn ensure_temp_slab_for_db_load(&self) {
-        self.delta_trie.get_node_memory_manager().enlarge().ok();
-    }
-
-    fn pre_modification(&mut self) {
-        if !self.dirty {
-            self.dirty = true
-        }
-        self.delta_trie.get_node_memory_manager().enlarge().ok();
-    }
-
     fn get_delta_root_node(&self) -> Option<NodeRefDeltaMpt> {
         self.delta_trie_root.clone()
     }
@@ -36,6 +36,10 @@ pub struct StorageManager {
     snapshot_info_map_by_epoch: RwLock<HashMap<EpochId, SnapshotInfo>>,
 
     last_confirmed_snapshottable_epoch_id: Mutex<Option<EpochId>>,
+    /// The lowest height whose state is kept according to the state
+    /// retention policy. It's recovered from the remaining snapshots at
+    /// start-up, and updated by the snapshot maintenance.
+    first_retained_state_height: Mutex<u64>,
 
     storage_conf: StorageConfiguration,
 }
@@ -160,6 +164,7 @@ impl StorageManager {
             current_snapshots: Default::default(),
             snapshot_info_map_by_epoch: Default::default(),
             last_confirmed_snapshottable_epoch_id: Default::default(),
+            first_retained_state_height: Default::default(),
             storage_conf,
         }));
 
@@ -281,6 +286,17 @@ impl StorageManager {
         self.storage_conf.consensus_param.snapshot_epoch_count
     }
 
+    /// Returns the lowest height whose state is kept by the state retention
+    /// policy, or `None` if only the recent states are kept.
+    pub fn get_first_retained_state_height(&self) -> Option<u64> {
+        match self.storage_conf.state_retention {
+            StateRetentionPolicy::Recent => None,
+            StateRetentionPolicy::Epochs(_) => {
+                Some(*self.first_retained_state_height.lock())
+            }
+        }
+    }
+
     pub fn get_delta_mpt(
         &self, snapshot_epoch_id: &EpochId,
     ) -> Result<Arc<DeltaMpt>> {
@@ -731,8 +747,9 @@ impl StorageManager {
     /// Another maintenance of snapshots shall happen at Conflux start-up and
     /// after pivot chain is recognized.
     ///
-    /// The behavior of old pivot snapshot deletion can be different between
-    /// Archive Node and Full Node.
+    /// Old pivot snapshots are only deleted below the height required by the
+    /// state retention policy, so that an Archive Node can keep the states of
+    /// historical epochs.
     ///
     /// Returns the first available state height after the maintenance.
     pub fn maintain_snapshots_pivot_chain_confirmed(
@@ -777,12 +794,24 @@ impl StorageManager {
         } else {
             0
         };
+        let retained_snapshot_height = retained_snapshot_height(
+            self.storage_conf.state_retention,
+            self.get_snapshot_epoch_count(),
+            confirmed_height,
+            confirmed_snapshot_height,
+        );
+        let first_retained_state_height = if retained_snapshot_height > 0 {
+            retained_snapshot_height + 1
+        } else {
+            0
+        };
 
         debug!(
             "maintain_snapshots_pivot_chain_confirmed: confirmed_height {}, \
              confirmed_epoch_id {:?}, confirmed_intermediate_id {:?}, \
              confirmed_snapshot_id {:?}, confirmed_intermediate_height {}, \
-             confirmed_snapshot_height {}, first_available_state_height {}",
+             confirmed_snapshot_height {}, first_available_state_height {}, \
+             retained_snapshot_height {}",
             confirmed_height,
             confirmed_epoch_id,
             confirmed_state_root.aux_info.intermediate_epoch_id,
@@ -790,6 +819,7 @@ impl StorageManager {
             confirmed_intermediate_height,
             confirmed_snapshot_height,
             first_available_state_height,
+            retained_snapshot_height,
         );
         let mut non_pivot_snapshots_to_remove = HashSet::new();
         let mut old_pivot_snapshots_to_remove = vec![];
@@ -816,10 +846,13 @@ impl StorageManager {
                             .insert(snapshot_epoch_id.clone());
                     }
                 } else if snapshot_info.height < confirmed_snapshot_height {
-                    // We remove for older pivot snapshot one after another.
+                    // We remove for older pivot snapshot one after another,
+                    // except for those kept by the state retention policy.
                     if snapshot_epoch_id.eq(prev_snapshot_epoch_id) {
-                        old_pivot_snapshots_to_remove
-                            .push(snapshot_epoch_id.clone());
+                        if snapshot_info.height < retained_snapshot_height {
+                            old_pivot_snapshots_to_remove
+                                .push(snapshot_epoch_id.clone());
+                        }
                         prev_snapshot_epoch_id =
                             &snapshot_info.parent_snapshot_epoch_id;
                     } else {
@@ -950,7 +983,8 @@ impl StorageManager {
             || !old_pivot_snapshots_to_remove.is_empty()
         {
             {
-                // TODO: Archive node may do something different.
+                // States retained for an Archive Node below the boundary are
+                // accessed through `first_retained_state_height`.
                 let state_boundary = &mut *state_availability_boundary.write();
                 if first_available_state_height > state_boundary.lower_bound {
                     state_boundary
@@ -1000,6 +1034,13 @@ impl StorageManager {
             debug!("maintain_snapshots_pivot_chain_confirmed: finished");
         }
 
+        {
+            let retained_height = &mut *self.first_retained_state_height.lock();
+            if first_retained_state_height > *retained_height {
+                *retained_height = first_retained_state_height;
+            }
+        }
+
         // TODO: implement in_progress_snapshot cancellation.
         /*
         if !in_progress_snapshot_to_cancel.is_empty() {
@@ -1199,10 +1240,45 @@ impl StorageManager {
             }
         }
 
+        // The snapshots below the retained snapshot height are removed, so
+        // the oldest remaining snapshot other than the genesis snapshot
+        // gives the first retained state height. The states based on the
+        // genesis snapshot are treated as removed unless no other snapshot
+        // is available.
+        *self.first_retained_state_height.lock() = snapshot_info_map
+            .iter()
+            .filter(|(snapshot_epoch_id, _)| **snapshot_epoch_id != NULL_EPOCH)
+            .map(|(_, snapshot_info)| snapshot_info.height + 1)
+            .min()
+            .unwrap_or(0);
+
         Ok(())
     }
 }
 
+/// Returns the height of the oldest pivot snapshot to keep according to the
+/// state retention `policy`, when the snapshot at `confirmed_snapshot_height`
+/// is confirmed.
+pub fn retained_snapshot_height(
+    policy: StateRetentionPolicy, snapshot_epoch_count: u32,
+    confirmed_height: u64, confirmed_snapshot_height: u64,
+) -> u64
+{
+    match policy {
+        StateRetentionPolicy::Recent => confirmed_snapshot_height,
+        StateRetentionPolicy::Epochs(epoch_count) => {
+            // The state at height h is available if the snapshot at the
+            // largest multiple of snapshot_epoch_count below h is kept.
+            let snapshot_epoch_count = snapshot_epoch_count as u64;
+            let first_height = confirmed_height.saturating_sub(epoch_count);
+            let snapshot_height = first_height.saturating_sub(1)
+                / snapshot_epoch_count
+                * snapshot_epoch_count;
+            min(snapshot_height, confirmed_snapshot_height)
+        }
+    }
+}
+
 struct MaybeDeltaTrieDestroyErrors {
     delta_trie_destroy_error_1: Cell<Option<Error>>,
     delta_trie_destroy_error_2: Cell<Option<Error>>,
@@ -1287,7 +1363,8 @@ use crate::{
         },
         storage_dir,
         utils::guarded_value::GuardedValue,
-        KeyValueDbTrait, KvdbSqlite, StorageConfiguration,
+        KeyValueDbTrait, KvdbSqlite, StateRetentionPolicy,
+        StorageConfiguration,
     },
 };
 use fallible_iterator::FallibleIterator;
@@ -1298,6 +1375,7 @@ use rlp::{Decodable, DecoderError, Encodable, Rlp};
 use sqlite::Statement;
 use std::{
     cell::Cell,
+    cmp::min,
     collections::{HashMap, HashSet},
     fs,
     sync::{
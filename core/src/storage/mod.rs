@@ -45,6 +45,17 @@ pub struct ConsensusParam {
     pub snapshot_epoch_count: u32,
 }
 
+/// Which states of past epochs on the pivot chain are kept by the storage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StateRetentionPolicy {
+    /// Keep only the states required to execute new epochs and to serve
+    /// snapshot sync.
+    Recent,
+    /// Keep at least the states of the given number of epochs before the
+    /// confirmed epoch. States are removed a snapshot at a time.
+    Epochs(u64),
+}
+
 #[derive(Debug, Clone)]
 pub struct StorageConfiguration {
     pub consensus_param: ConsensusParam,
@@ -59,6 +70,7 @@ pub struct StorageConfiguration {
     pub path_storage_dir: PathBuf,
     pub path_snapshot_dir: PathBuf,
     pub path_snapshot_info_db: PathBuf,
+    pub state_retention: StateRetentionPolicy,
 }
 
 impl StorageConfiguration {
@@ -88,6 +100,7 @@ impl StorageConfiguration {
                 .join(&*storage_dir::SNAPSHOT_INFO_DB_PATH),
             path_storage_dir: conflux_data_path
                 .join(&*storage_dir::STORAGE_DIR),
+            state_retention: StateRetentionPolicy::Recent,
         }
     }
 }
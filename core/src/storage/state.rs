@@ -33,6 +33,10 @@ pub trait StateTrait {
     fn delete_all(
         &mut self, access_key_prefix: StorageKey,
     ) -> Result<Option<Vec<(Vec<u8>, Box<[u8]>)>>>;
+    // Read everything prefixed by access_key without changing the state.
+    fn read_all(
+        &mut self, access_key_prefix: StorageKey,
+    ) -> Result<Option<Vec<(Vec<u8>, Box<[u8]>)>>>;
 
     // Finalize
     /// It's costly to compute state root however it's only necessary to compute
@@ -9,6 +9,8 @@ pub use snapshot::FakeSnapshotMptDb;
 mod sharded_iter_merger;
 #[cfg(test)]
 mod state;
+#[cfg(test)]
+mod state_retention;
 
 #[cfg(test)]
 const TEST_NUMBER_OF_KEYS: usize = 100000;
@@ -97,6 +99,7 @@ impl FakeStateManager {
                         .join(&*storage_dir::SNAPSHOT_INFO_DB_PATH),
                     path_storage_dir: unit_test_data_path
                         .join(&*storage_dir::STORAGE_DIR),
+                    state_retention: StateRetentionPolicy::Recent,
                 })?),
             })
         }
@@ -233,7 +236,7 @@ pub fn print_mpt_key(key: &[u8]) {
 #[cfg(test)]
 use crate::storage::{
     defaults, impls::state_manager::StateManager, storage_dir, ConsensusParam,
-    StorageConfiguration,
+    StateRetentionPolicy, StorageConfiguration,
 };
 use crate::storage::{
     impls::{errors::*, merkle_patricia_trie::CompressedPathRaw},
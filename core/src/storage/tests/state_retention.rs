@@ -0,0 +1,89 @@
+// Copyright 2020 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+const SNAPSHOT_EPOCH_COUNT: u32 = 100;
+
+#[test]
+fn test_recent_retention_keeps_confirmed_snapshot() {
+    assert_eq!(
+        retained_snapshot_height(
+            StateRetentionPolicy::Recent,
+            SNAPSHOT_EPOCH_COUNT,
+            550,
+            400
+        ),
+        400
+    );
+}
+
+#[test]
+fn test_epochs_retention() {
+    // Epochs 250..=550 are retained, so the states above the snapshot at
+    // height 200 are kept.
+    assert_eq!(
+        retained_snapshot_height(
+            StateRetentionPolicy::Epochs(300),
+            SNAPSHOT_EPOCH_COUNT,
+            550,
+            400
+        ),
+        200
+    );
+    // The state at height 201 is based on the snapshot at height 200.
+    assert_eq!(
+        retained_snapshot_height(
+            StateRetentionPolicy::Epochs(350),
+            SNAPSHOT_EPOCH_COUNT,
+            551,
+            400
+        ),
+        200
+    );
+    assert_eq!(
+        retained_snapshot_height(
+            StateRetentionPolicy::Epochs(350),
+            SNAPSHOT_EPOCH_COUNT,
+            550,
+            400
+        ),
+        100
+    );
+}
+
+#[test]
+fn test_epochs_retention_bounds() {
+    // Never keep fewer states than the recent policy.
+    assert_eq!(
+        retained_snapshot_height(
+            StateRetentionPolicy::Epochs(0),
+            SNAPSHOT_EPOCH_COUNT,
+            550,
+            400
+        ),
+        400
+    );
+    // Keep everything when the retained epochs reach the genesis.
+    assert_eq!(
+        retained_snapshot_height(
+            StateRetentionPolicy::Epochs(1000),
+            SNAPSHOT_EPOCH_COUNT,
+            550,
+            400
+        ),
+        0
+    );
+    assert_eq!(
+        retained_snapshot_height(
+            StateRetentionPolicy::Epochs(std::u64::MAX),
+            SNAPSHOT_EPOCH_COUNT,
+            550,
+            400
+        ),
+        0
+    );
+}
+
+use crate::storage::{
+    impls::storage_manager::retained_snapshot_height, StateRetentionPolicy,
+};
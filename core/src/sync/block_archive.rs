@@ -0,0 +1,216 @@
+// Copyright 2020 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Offline export and import of blocks, to replay a chain into another node.
+//!
+//! An archive is a stream of RLP encoded blocks, with their headers and
+//! transactions. The blocks are written epoch by epoch, and in topological
+//! order within an epoch, so every block comes after its parent and referees
+//! that are in the archive. The importer verifies the blocks as if they were
+//! received from a peer.
+
+use crate::{
+    block_data_manager::BlockDataManager,
+    sync::{
+        error::{Error, ErrorKind},
+        state::snapshot_archive::read_record,
+        SynchronizationGraph,
+    },
+};
+use cfx_types::H256;
+use primitives::Block;
+use rlp::{Encodable, Rlp};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+    sync::Arc,
+};
+
+/// The outcome of `import_blocks`.
+#[derive(Debug, Default)]
+pub struct BlockImportStatistics {
+    /// Blocks new to the synchronization graph.
+    pub imported: u64,
+    /// Blocks already in the synchronization graph.
+    pub known: u64,
+    /// Imported blocks which wait for ancestors missing in the archive.
+    pub not_ready: u64,
+}
+
+/// Write the blocks of the epochs from `from_epoch` to `to_epoch` to `out`,
+/// and return the number of blocks written. `epoch_block_hashes` gives all
+/// the blocks of an epoch by height, including the skipped ones, so that the
+/// recent epochs whose epoch sets are not persisted yet can be exported from
+/// the consensus graph.
+pub fn export_blocks(
+    data_man: &BlockDataManager, from_epoch: u64, to_epoch: u64,
+    epoch_block_hashes: impl Fn(u64) -> Option<Vec<H256>>, out: &mut dyn Write,
+) -> Result<u64, Error> {
+    let mut count = 0;
+    for epoch in from_epoch..=to_epoch {
+        let hashes = match epoch_block_hashes(epoch) {
+            Some(hashes) => hashes,
+            None => bail!(ErrorKind::InternalError(format!(
+                "Epoch {} is not in the consensus graph",
+                epoch
+            ))),
+        };
+        let mut blocks = Vec::with_capacity(hashes.len());
+        for hash in &hashes {
+            match data_man.block_by_hash(hash, false) {
+                Some(block) => blocks.push(block),
+                None => bail!(ErrorKind::InternalError(format!(
+                    "Block {:?} of epoch {} is not in the database",
+                    hash, epoch
+                ))),
+            }
+        }
+        for block in topological_order(blocks) {
+            out.write_all(&block.rlp_bytes())?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Insert the blocks read from `input` into `graph`, with full verification.
+/// The graph should be recovered from the database before, so that the
+/// blocks can build on the local ones.
+///
+/// The import stops at the first invalid block.
+pub fn import_blocks(
+    graph: &SynchronizationGraph, input: &mut dyn Read,
+) -> Result<BlockImportStatistics, Error> {
+    let mut statistics = BlockImportStatistics::default();
+    while let Some(record) = read_record(input)? {
+        let mut block: Block = Rlp::new(&record).as_val()?;
+        let hash = block.hash();
+        if graph.contains_block(&hash) {
+            statistics.known += 1;
+            continue;
+        }
+        graph.data_man.recover_block(&mut block)?;
+
+        let (insert_result, _) = graph.insert_block_header(
+            &mut block.block_header,
+            true,  // need_to_verify
+            false, // bench_mode
+            false, // insert_into_consensus
+            true,  // persistent
+        );
+        if insert_result.is_invalid() {
+            warn!("Invalid header of block {:?} in archive", hash);
+            bail!(ErrorKind::InvalidBlock);
+        }
+        if !insert_result.should_process_body() {
+            statistics.known += 1;
+            continue;
+        }
+
+        let insert_result = graph.insert_block(
+            block, true,  /* need_to_verify */
+            true,  /* persistent */
+            false, /* recover_from_db */
+        );
+        if !insert_result.is_valid() {
+            warn!("Invalid block {:?} in archive", hash);
+            bail!(ErrorKind::InvalidBlock);
+        }
+        statistics.imported += 1;
+        if !insert_result.should_relay() {
+            statistics.not_ready += 1;
+        }
+    }
+    Ok(statistics)
+}
+
+/// Order `blocks` so that each one follows its parent and referees among
+/// them. Otherwise the order is kept.
+fn topological_order(blocks: Vec<Arc<Block>>) -> Vec<Arc<Block>> {
+    let index: HashMap<H256, usize> = blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| (block.hash(), i))
+        .collect();
+    let mut visited = HashSet::new();
+    let mut ordered = Vec::with_capacity(blocks.len());
+    for root in 0..blocks.len() {
+        // Depth first search, and emit a block after all its dependencies.
+        let mut stack = vec![(root, false)];
+        while let Some((i, expanded)) = stack.pop() {
+            if expanded {
+                ordered.push(blocks[i].clone());
+                continue;
+            }
+            if !visited.insert(i) {
+                continue;
+            }
+            stack.push((i, true));
+            let header = &blocks[i].block_header;
+            for dependency in std::iter::once(header.parent_hash())
+                .chain(header.referee_hashes().iter())
+                .rev()
+            {
+                if let Some(j) = index.get(dependency) {
+                    if !visited.contains(j) {
+                        stack.push((*j, false));
+                    }
+                }
+            }
+        }
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::topological_order;
+    use crate::sync::utils::create_simple_block_impl;
+    use cfx_types::{H256, U256};
+    use primitives::Block;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_topological_order() {
+        let new_block = |parent: H256, referees: Vec<H256>, nonce: u64| {
+            let (_, block) = create_simple_block_impl(
+                parent,
+                referees,
+                1,
+                nonce.into(),
+                U256::one(),
+                1,
+                false,
+            );
+            Arc::new(block)
+        };
+        // a <- b <- d, a <- c, and d refers to c.
+        let a = new_block(H256::zero(), vec![], 0);
+        let b = new_block(a.hash(), vec![], 1);
+        let c = new_block(a.hash(), vec![], 2);
+        let d = new_block(b.hash(), vec![c.hash()], 3);
+
+        let hashes = |blocks: Vec<Arc<Block>>| -> Vec<H256> {
+            blocks.iter().map(|block| block.hash()).collect()
+        };
+        assert_eq!(
+            hashes(topological_order(vec![
+                d.clone(),
+                c.clone(),
+                b.clone(),
+                a.clone()
+            ])),
+            vec![a.hash(), b.hash(), c.hash(), d.hash()]
+        );
+        assert_eq!(
+            hashes(topological_order(vec![
+                c.clone(),
+                a.clone(),
+                d.clone(),
+                b.clone()
+            ])),
+            vec![a.hash(), c.hash(), b.hash(), d.hash()]
+        );
+    }
+}
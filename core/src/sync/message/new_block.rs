@@ -7,6 +7,7 @@ use crate::sync::{
     Error, ErrorKind,
 };
 use cfx_types::H256;
+use network::PeerEvent;
 use primitives::Block;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
@@ -86,6 +87,12 @@ fn on_new_decoded_block(
             if insert_result.is_new_valid() {
                 need_to_relay.extend(to_relay);
             } else {
+                if insert_result.is_invalid() {
+                    ctx.io.report_peer(
+                        &ctx.node_id,
+                        PeerEvent::InvalidBlock(hash),
+                    );
+                }
                 return Err(Error::from_kind(ErrorKind::InvalidBlock));
             }
         }
@@ -97,6 +104,10 @@ fn on_new_decoded_block(
         persistent,
         false, // recover_from_db
     );
+    if insert_result.is_invalid() {
+        ctx.io
+            .report_peer(&ctx.node_id, PeerEvent::InvalidBlock(hash));
+    }
     if insert_result.should_relay() {
         need_to_relay.push(hash);
     }
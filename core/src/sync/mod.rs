@@ -1,6 +1,7 @@
 // Copyright 2019 Conflux Foundation. All rights reserved.
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
+pub mod block_archive;
 mod error;
 pub mod message;
 pub mod request_manager;
@@ -18,7 +19,7 @@ pub mod utils;
 
 pub use self::{
     error::{Error, ErrorKind},
-    state::{restore, StateSyncConfiguration},
+    state::{restore, snapshot_archive, StateSyncConfiguration},
     synchronization_graph::{
         SharedSynchronizationGraph, SyncGraphConfig, SyncGraphStatistics,
         SynchronizationGraph, SynchronizationGraphInner,
@@ -27,7 +28,7 @@ pub use self::{
     synchronization_phases::{
         CatchUpCheckpointPhase, CatchUpRecoverBlockFromDbPhase,
         CatchUpRecoverBlockHeaderFromDbPhase, CatchUpSyncBlockHeaderPhase,
-        CatchUpSyncBlockPhase, NormalSyncPhase, SyncPhaseType,
+        CatchUpSyncBlockPhase, NormalSyncPhase, SyncPhaseType, SyncStatus,
         SynchronizationPhaseManager, SynchronizationPhaseTrait,
     },
     synchronization_protocol_handler::{
@@ -14,7 +14,7 @@ use crate::{
 };
 use network::{
     node_table::NodeId, ErrorKind as NetworkErrorKind, NetworkContext,
-    UpdateNodeOperation,
+    PeerEvent, UpdateNodeOperation,
 };
 use parking_lot::Mutex;
 use std::{
@@ -159,6 +159,7 @@ impl RequestHandler {
     ) -> Vec<RequestMessage> {
         // Check if in-flight requests timeout
         let mut timeout_requests = Vec::new();
+        let mut timeout_peers = Vec::new();
         let mut peers_to_disconnect = HashSet::new();
         let mut peers_to_send_pending_requests = HashSet::new();
         for sync_req in self.get_timeout_sync_requests() {
@@ -166,6 +167,7 @@ impl RequestHandler {
                 self.match_request(&sync_req.peer_id, sync_req.request_id)
             {
                 let peer_id = sync_req.peer_id.clone();
+                timeout_peers.push(peer_id);
                 if let Some(request_container) =
                     self.peers.lock().get_mut(&peer_id)
                 {
@@ -188,6 +190,11 @@ impl RequestHandler {
         } else {
             Some(UpdateNodeOperation::Failure)
         };
+        // Like `disconnect_peer`, `report_peer` may disconnect the peer, so we
+        // must call it without locking `self.peers`.
+        for peer_id in &timeout_peers {
+            io.report_peer(peer_id, PeerEvent::Timeout);
+        }
         for peer_id in peers_to_disconnect {
             // Note `self.peers` will be used in `disconnect_peer`, so we must
             // call it without locking `self.peers`.
@@ -3,6 +3,7 @@
 // See http://www.gnu.org/licenses/
 
 pub mod restore;
+pub mod snapshot_archive;
 mod snapshot_chunk_request;
 mod snapshot_chunk_response;
 mod snapshot_chunk_sync;
@@ -62,6 +62,13 @@ impl Restorer {
         }
     }
 
+    /// Whether all the chunks of the manifest are restored.
+    pub fn is_completed(&self) -> bool {
+        self.verifier
+            .as_ref()
+            .map_or(false, |verifier| verifier.is_completed())
+    }
+
     /// Start to restore chunks asynchronously.
     pub fn finalize_restoration(
         &mut self, state_manager: Arc<StateManager>,
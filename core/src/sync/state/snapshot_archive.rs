@@ -0,0 +1,541 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Offline export and import of state snapshots, to bootstrap a node from a
+//! file instead of syncing the state from peers.
+//!
+//! An archive is a stream of RLP records. The first one is the
+//! `ArchiveManifest`, which carries everything a syncing node gets in a
+//! `SnapshotManifestResponse`, along with the pivot chain headers needed to
+//! check it against a trusted block. The chunks of the manifest follow in
+//! order. The importer trusts nothing in the archive but the hash of the
+//! trusted blame block, which is given by the operator.
+
+use crate::{
+    block_data_manager::{BlockDataManager, BlockExecutionResult},
+    parameters::{
+        consensus::DEFERRED_STATE_EPOCH_COUNT,
+        consensus_internal::REWARD_EPOCH_COUNT, sync::MAX_PACKET_SIZE,
+    },
+    storage::FullSyncVerifier,
+    sync::{
+        error::{Error, ErrorKind},
+        state::{
+            restore::Restorer,
+            snapshot_chunk_sync::SnapshotChunkSync,
+            snapshot_manifest_request::SnapshotManifestRequest,
+            storage::{Chunk, RangedManifest, SnapshotSyncCandidate},
+        },
+    },
+};
+use cfx_types::H256;
+use primitives::{BlockHeader, EpochId, StateRoot};
+use rlp::{DecoderError, Rlp};
+use rlp_derive::{RlpDecodable, RlpEncodable};
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
+
+/// Bump for incompatible changes of the archive format.
+const ARCHIVE_VERSION: u64 = 1;
+/// Records larger than a network packet are rejected before they are read,
+/// since the manifest, the chunks and the blocks are each sent in one packet.
+const MAX_RECORD_SIZE: usize = MAX_PACKET_SIZE;
+
+#[derive(RlpDecodable, RlpEncodable)]
+pub struct ArchiveManifest {
+    pub version: u64,
+    pub snapshot_epoch_id: EpochId,
+    pub trusted_blame_block: H256,
+    /// Pivot chain headers from the trusted blame block down to the oldest
+    /// epoch the blame states and receipts refer to.
+    pub block_headers: Vec<BlockHeader>,
+    pub state_root_vec: Vec<StateRoot>,
+    pub receipt_blame_vec: Vec<H256>,
+    pub bloom_blame_vec: Vec<H256>,
+    /// Executed blocks of the epochs whose receipts are archived, from the
+    /// snapshot epoch down.
+    pub epoch_sets: Vec<EpochSet>,
+    pub block_receipts: Vec<BlockExecutionResult>,
+    pub manifest: RangedManifest,
+}
+
+#[derive(RlpDecodable, RlpEncodable)]
+pub struct EpochSet {
+    pub block_hashes: Vec<H256>,
+}
+
+/// Export the snapshot of `snapshot_epoch_id` to `out`, and return the
+/// trusted blame block the archive must be imported with.
+///
+/// Without `trusted_blame_block`, the first pivot block which commits to the
+/// state root of the snapshot is used.
+pub fn export_snapshot(
+    data_man: &BlockDataManager, snapshot_epoch_id: &EpochId,
+    trusted_blame_block: Option<H256>, chunk_size: u64, out: &mut dyn Write,
+) -> Result<H256, Error>
+{
+    let snapshot_height = data_man
+        .block_header_by_hash(snapshot_epoch_id)
+        .ok_or_else(|| {
+            Error::from(ErrorKind::InternalError(format!(
+                "unknown snapshot epoch {:?}",
+                snapshot_epoch_id
+            )))
+        })?
+        .height();
+    let trusted_blame_block = match trusted_blame_block {
+        Some(hash) => hash,
+        None => {
+            let height = snapshot_height
+                + DEFERRED_STATE_EPOCH_COUNT
+                + data_man.get_snapshot_blame_plus_depth() as u64;
+            pivot_hash_at(data_man, height).ok_or_else(|| {
+                Error::from(ErrorKind::InternalError(format!(
+                    "epoch {} is not executed yet",
+                    height
+                )))
+            })?
+        }
+    };
+
+    let (state_root_vec, receipt_blame_vec, bloom_blame_vec) =
+        SnapshotManifestRequest::get_blame_states(
+            data_man,
+            snapshot_epoch_id,
+            &trusted_blame_block,
+            None, /* peer */
+        )
+        .ok_or_else(|| {
+            Error::from(ErrorKind::InternalError(
+                "blame states of the snapshot are not available".into(),
+            ))
+        })?;
+    let block_receipts = SnapshotManifestRequest::get_block_receipts(
+        data_man,
+        snapshot_epoch_id,
+        |height| data_man.executed_epoch_set_hashes_from_db(height),
+        None, /* peer */
+    )
+    .ok_or_else(|| {
+        Error::from(ErrorKind::InternalError(
+            "receipts before the snapshot are not available".into(),
+        ))
+    })?;
+    let epoch_sets = (0..REWARD_EPOCH_COUNT.min(snapshot_height + 1))
+        .map(|i| {
+            Some(EpochSet {
+                block_hashes: data_man
+                    .executed_epoch_set_hashes_from_db(snapshot_height - i)?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            Error::from(ErrorKind::InternalError(
+                "epoch sets before the snapshot are not available".into(),
+            ))
+        })?;
+
+    let lowest_height = lowest_required_height(
+        data_man,
+        snapshot_height,
+        trusted_height(data_man, &trusted_blame_block)?,
+        state_root_vec.len(),
+    );
+    let mut block_headers = Vec::new();
+    let mut hash = trusted_blame_block;
+    loop {
+        let header = data_man.block_header_by_hash(&hash).ok_or_else(|| {
+            Error::from(ErrorKind::InternalError(format!(
+                "missing header of block {:?}",
+                hash
+            )))
+        })?;
+        hash = *header.parent_hash();
+        block_headers.push((*header).clone());
+        if header.height() <= lowest_height {
+            break;
+        }
+    }
+    if !block_headers
+        .iter()
+        .any(|header| header.hash() == *snapshot_epoch_id)
+    {
+        bail!(ErrorKind::InternalError(format!(
+            "snapshot {:?} is not on the pivot chain of {:?}",
+            snapshot_epoch_id, trusted_blame_block
+        )));
+    }
+
+    let (manifest, _) = RangedManifest::load(
+        &SnapshotSyncCandidate::FullSync {
+            height: snapshot_height,
+            snapshot_epoch_id: *snapshot_epoch_id,
+        },
+        None,
+        &data_man.storage_manager,
+        chunk_size,
+    )?
+    .ok_or_else(|| {
+        Error::from(ErrorKind::InternalError(format!(
+            "snapshot {:?} is not available",
+            snapshot_epoch_id
+        )))
+    })?;
+    let chunk_keys = manifest.clone().into_chunks();
+
+    out.write_all(&rlp::encode(&ArchiveManifest {
+        version: ARCHIVE_VERSION,
+        snapshot_epoch_id: *snapshot_epoch_id,
+        trusted_blame_block,
+        block_headers,
+        state_root_vec,
+        receipt_blame_vec,
+        bloom_blame_vec,
+        epoch_sets,
+        block_receipts,
+        manifest,
+    }))?;
+    for chunk_key in &chunk_keys {
+        let chunk = Chunk::load(
+            snapshot_epoch_id,
+            chunk_key,
+            &data_man.storage_manager,
+        )?
+        .ok_or_else(|| {
+            Error::from(ErrorKind::InternalError(format!(
+                "snapshot {:?} is removed during export",
+                snapshot_epoch_id
+            )))
+        })?;
+        out.write_all(&rlp::encode(&chunk))?;
+    }
+    info!(
+        "Exported snapshot {:?} with {} chunks, trusted blame block {:?}",
+        snapshot_epoch_id,
+        chunk_keys.len(),
+        trusted_blame_block
+    );
+    Ok(trusted_blame_block)
+}
+
+/// Verify the archive in `input` against `trusted_blame_block`, and restore
+/// the snapshot in it with the execution results of the epochs before it.
+/// Return the epoch of the restored snapshot.
+///
+/// Once the snapshot is restored, the node skips state sync if it is the
+/// checkpoint to sync to.
+pub fn import_snapshot(
+    data_man: &BlockDataManager, trusted_blame_block: &H256,
+    input: &mut dyn Read,
+) -> Result<EpochId, Error>
+{
+    let manifest: ArchiveManifest = match read_record(input)? {
+        Some(record) => Rlp::new(&record).as_val()?,
+        None => bail!(invalid_manifest("empty archive")),
+    };
+    if manifest.version != ARCHIVE_VERSION {
+        bail!(invalid_manifest(&format!(
+            "unsupported archive version {}",
+            manifest.version
+        )));
+    }
+    if manifest.trusted_blame_block != *trusted_blame_block {
+        bail!(invalid_manifest(&format!(
+            "archive is exported for trusted block {:?}",
+            manifest.trusted_blame_block
+        )));
+    }
+    let snapshot_epoch_id = manifest.snapshot_epoch_id;
+    let snapshot_height = verify_headers(data_man, &manifest)?;
+
+    // The blame states and receipts are checked against the headers
+    // committed to by the trusted block, so the headers go to the db first.
+    for header in &manifest.block_headers {
+        data_man.insert_block_header(
+            header.hash(),
+            Arc::new(header.clone()),
+            true, /* persistent */
+        );
+    }
+    let (blame_vec_offset, state_root_with_aux_info, snapshot_info) =
+        SnapshotChunkSync::validate_blame_states(
+            data_man,
+            &snapshot_epoch_id,
+            trusted_blame_block,
+            &manifest.state_root_vec,
+            &manifest.receipt_blame_vec,
+            &manifest.bloom_blame_vec,
+        )
+        .ok_or_else(|| invalid_manifest("invalid blame states"))?;
+    let epoch_receipts = SnapshotChunkSync::validate_epoch_receipts(
+        data_man,
+        |height| {
+            if height > snapshot_height {
+                return None;
+            }
+            let hashes = &manifest
+                .epoch_sets
+                .get((snapshot_height - height) as usize)?
+                .block_hashes;
+            // The pivot block is executed last.
+            let pivot_hash = pivot_hash_of(data_man, &manifest, height)?;
+            if hashes.last() != Some(&pivot_hash) {
+                return None;
+            }
+            Some(hashes.clone())
+        },
+        blame_vec_offset,
+        &snapshot_epoch_id,
+        &manifest.receipt_blame_vec,
+        &manifest.bloom_blame_vec,
+        &manifest.block_receipts,
+    )
+    .ok_or_else(|| invalid_manifest("invalid epoch receipts"))?;
+    manifest
+        .manifest
+        .validate(&snapshot_info.merkle_root, &None)?;
+
+    let mut restorer = Restorer::new(snapshot_epoch_id);
+    restorer.snapshot_merkle_root = snapshot_info.merkle_root;
+    restorer.initialize_verifier(FullSyncVerifier::new(
+        manifest.manifest.chunk_boundaries.len() + 1,
+        manifest.manifest.chunk_boundaries.clone(),
+        manifest.manifest.chunk_boundary_proofs.clone(),
+        snapshot_info.merkle_root,
+        data_man
+            .storage_manager
+            .get_storage_manager()
+            .get_snapshot_manager()
+            .get_snapshot_db_manager(),
+        &snapshot_epoch_id,
+    )?);
+    let chunk_keys = manifest.manifest.clone().into_chunks();
+    for (index, chunk_key) in chunk_keys.iter().enumerate() {
+        let chunk: Chunk = match read_record(input)? {
+            Some(record) => Rlp::new(&record).as_val()?,
+            None => bail!(ErrorKind::InvalidSnapshotChunk(format!(
+                "archive ends after {} of {} chunks",
+                index,
+                chunk_keys.len()
+            ))),
+        };
+        chunk.validate(chunk_key)?;
+        if !restorer.append(chunk_key.clone(), chunk) {
+            bail!(ErrorKind::InvalidSnapshotChunk(format!(
+                "chunk {} does not match the manifest",
+                index
+            )));
+        }
+        debug!("restored chunk {} of {}", index + 1, chunk_keys.len());
+    }
+    if !restorer.is_completed() {
+        bail!(ErrorKind::InvalidSnapshotChunk(
+            "snapshot is incomplete".into()
+        ));
+    }
+    restorer.finalize_restoration(
+        data_man.storage_manager.clone(),
+        snapshot_info,
+    )?;
+
+    SnapshotChunkSync::insert_restored_execution_state(
+        data_man,
+        &snapshot_epoch_id,
+        blame_vec_offset,
+        &state_root_with_aux_info,
+        &manifest.receipt_blame_vec,
+        &manifest.bloom_blame_vec,
+        &epoch_receipts,
+    );
+    info!(
+        "Imported snapshot {:?} with {} chunks",
+        snapshot_epoch_id,
+        chunk_keys.len()
+    );
+    Ok(snapshot_epoch_id)
+}
+
+/// Check that the headers in the archive are a pivot chain from the trusted
+/// blame block through the snapshot, long enough for the validation of the
+/// blame states and receipts. Return the height of the snapshot.
+fn verify_headers(
+    data_man: &BlockDataManager, manifest: &ArchiveManifest,
+) -> Result<u64, Error> {
+    let headers = &manifest.block_headers;
+    match headers.first() {
+        Some(header) if header.hash() == manifest.trusted_blame_block => {}
+        _ => bail!(invalid_manifest("headers do not start at trusted block")),
+    }
+    for pair in headers.windows(2) {
+        if *pair[0].parent_hash() != pair[1].hash()
+            || pair[0].height() != pair[1].height() + 1
+        {
+            bail!(invalid_manifest("headers are not a chain"));
+        }
+    }
+    let lowest = headers.last().expect("not empty");
+    if lowest.height() == 0 && lowest.hash() != data_man.true_genesis.hash() {
+        bail!(invalid_manifest("archive is exported from another chain"));
+    }
+
+    let snapshot_height = headers
+        .iter()
+        .find(|header| header.hash() == manifest.snapshot_epoch_id)
+        .ok_or_else(|| invalid_manifest("snapshot is not on the pivot chain"))?
+        .height();
+    let trusted_height = headers[0].height();
+    if trusted_height
+        < snapshot_height
+            + DEFERRED_STATE_EPOCH_COUNT
+            + data_man.get_snapshot_blame_plus_depth() as u64
+    {
+        bail!(invalid_manifest("trusted block is too close to snapshot"));
+    }
+    if lowest.height()
+        > lowest_required_height(
+            data_man,
+            snapshot_height,
+            trusted_height,
+            manifest.state_root_vec.len(),
+        )
+    {
+        bail!(invalid_manifest("not enough headers"));
+    }
+    Ok(snapshot_height)
+}
+
+/// The height of the oldest pivot block used to validate the snapshot: the
+/// end of the blame states, the oldest epoch with archived receipts, or the
+/// parent snapshot.
+fn lowest_required_height(
+    data_man: &BlockDataManager, snapshot_height: u64, trusted_height: u64,
+    state_root_vec_len: usize,
+) -> u64
+{
+    (trusted_height + 1)
+        .saturating_sub(state_root_vec_len as u64)
+        .min((snapshot_height + 1).saturating_sub(REWARD_EPOCH_COUNT))
+        .min(
+            snapshot_height
+                .saturating_sub(data_man.get_snapshot_epoch_count() as u64),
+        )
+}
+
+fn trusted_height(
+    data_man: &BlockDataManager, trusted_blame_block: &H256,
+) -> Result<u64, Error> {
+    data_man
+        .block_header_by_hash(trusted_blame_block)
+        .map(|header| header.height())
+        .ok_or_else(|| {
+            ErrorKind::InternalError(format!(
+                "unknown trusted block {:?}",
+                trusted_blame_block
+            ))
+            .into()
+        })
+}
+
+fn pivot_hash_at(data_man: &BlockDataManager, height: u64) -> Option<H256> {
+    data_man
+        .executed_epoch_set_hashes_from_db(height)?
+        .last()
+        .cloned()
+}
+
+fn pivot_hash_of(
+    data_man: &BlockDataManager, manifest: &ArchiveManifest, height: u64,
+) -> Option<H256> {
+    if height == 0 {
+        return Some(data_man.true_genesis.hash());
+    }
+    manifest
+        .block_headers
+        .iter()
+        .find(|header| header.height() == height)
+        .map(|header| header.hash())
+}
+
+fn invalid_manifest(reason: &str) -> Error {
+    ErrorKind::InvalidSnapshotManifest(reason.into()).into()
+}
+
+/// Read the next RLP list from `input`, or `None` at the end of it. Records
+/// over `MAX_RECORD_SIZE` are an error.
+pub(crate) fn read_record(
+    input: &mut dyn Read,
+) -> Result<Option<Vec<u8>>, Error> {
+    let mut prefix = [0u8; 1];
+    if input.read(&mut prefix)? == 0 {
+        return Ok(None);
+    }
+    let mut record = prefix.to_vec();
+    let payload_len = match prefix[0] {
+        0xc0..=0xf7 => (prefix[0] - 0xc0) as usize,
+        0xf8..=0xff => {
+            let mut len_bytes = vec![0u8; (prefix[0] - 0xf7) as usize];
+            input.read_exact(&mut len_bytes)?;
+            record.extend_from_slice(&len_bytes);
+            len_bytes
+                .iter()
+                .try_fold(0usize, |len, byte| {
+                    len.checked_mul(256)?.checked_add(*byte as usize)
+                })
+                .ok_or(DecoderError::RlpIsTooBig)?
+        }
+        _ => return Err(DecoderError::RlpExpectedToBeList.into()),
+    };
+    let header_len = record.len();
+    match header_len.checked_add(payload_len) {
+        Some(len) if len <= MAX_RECORD_SIZE => record.resize(len, 0),
+        _ => return Err(DecoderError::RlpIsTooBig.into()),
+    }
+    input.read_exact(&mut record[header_len..])?;
+    Ok(Some(record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_record;
+    use crate::sync::state::storage::Chunk;
+    use rlp::Rlp;
+
+    #[test]
+    fn test_read_record() {
+        let chunks: Vec<Chunk> = [1usize, 100, 10000]
+            .iter()
+            .map(|size| Chunk {
+                keys: vec![vec![1; *size]],
+                values: vec![vec![2; *size]],
+            })
+            .collect();
+        let mut data = Vec::new();
+        for chunk in &chunks {
+            data.extend(rlp::encode(chunk));
+        }
+
+        let mut input = &data[..];
+        for chunk in &chunks {
+            let record = read_record(&mut input).unwrap().unwrap();
+            let decoded: Chunk = Rlp::new(&record).as_val().unwrap();
+            assert_eq!(decoded.keys, chunk.keys);
+            assert_eq!(decoded.values, chunk.values);
+        }
+        assert!(read_record(&mut input).unwrap().is_none());
+
+        // a truncated record is an error
+        let mut input = &data[..data.len() - 1];
+        read_record(&mut input).unwrap();
+        read_record(&mut input).unwrap();
+        assert!(read_record(&mut input).is_err());
+
+        // an oversized record is an error before its payload is read
+        let mut input = &[0xfb, 0xff, 0xff, 0xff, 0xff][..];
+        assert!(read_record(&mut input).is_err());
+        let mut input = &[0xff; 9][..];
+        assert!(read_record(&mut input).is_err());
+    }
+}
@@ -3,7 +3,7 @@
 // See http://www.gnu.org/licenses/
 
 use crate::{
-    block_data_manager::BlockExecutionResult,
+    block_data_manager::{BlockDataManager, BlockExecutionResult},
     parameters::{
         consensus::DEFERRED_STATE_EPOCH_COUNT,
         consensus_internal::REWARD_EPOCH_COUNT,
@@ -306,7 +306,7 @@ impl SnapshotChunkSync {
         // validate blame state if requested
         if request.is_initial_request() {
             match Self::validate_blame_states(
-                ctx,
+                &ctx.manager.graph.data_man,
                 inner
                     .current_sync_candidate
                     .as_ref()
@@ -351,7 +351,14 @@ impl SnapshotChunkSync {
                 }
             }
             match Self::validate_epoch_receipts(
-                ctx,
+                &ctx.manager.graph.data_man,
+                |height| {
+                    ctx.manager
+                        .graph
+                        .consensus
+                        .get_block_hashes_by_epoch(EpochNumber::Number(height))
+                        .ok()
+                },
                 inner.blame_vec_offset,
                 inner
                     .current_sync_candidate
@@ -550,42 +557,57 @@ impl SnapshotChunkSync {
         &self, sync_handler: &SynchronizationProtocolHandler,
     ) {
         let inner = self.inner.read();
-        let mut deferred_block_hash = inner
-            .current_sync_candidate
-            .as_ref()
-            .unwrap()
-            .get_snapshot_epoch_id()
-            .clone();
+        Self::insert_restored_execution_state(
+            &sync_handler.graph.data_man,
+            inner
+                .current_sync_candidate
+                .as_ref()
+                .unwrap()
+                .get_snapshot_epoch_id(),
+            inner.blame_vec_offset,
+            &inner.true_state_root_by_blame_info,
+            &inner.receipt_blame_vec,
+            &inner.bloom_blame_vec,
+            &inner.epoch_receipts,
+        );
+    }
+
+    /// Persist the execution commitments and receipts of the epochs before
+    /// a restored snapshot, which are validated along with the snapshot
+    /// manifest.
+    pub fn insert_restored_execution_state(
+        data_man: &BlockDataManager, snapshot_epoch_id: &EpochId,
+        blame_vec_offset: usize,
+        true_state_root_by_blame_info: &StateRootWithAuxInfo,
+        receipt_blame_vec: &Vec<H256>, bloom_blame_vec: &Vec<H256>,
+        epoch_receipts: &Vec<(H256, H256, Arc<BlockReceipts>)>,
+    )
+    {
+        let mut deferred_block_hash = snapshot_epoch_id.clone();
         // FIXME: Because state_root_aux_info can't be computed for state block
         // FIXME: before snapshot, for the reward epoch count, maybe
         // FIXME: save it to a dedicated place for reward computation.
-        for i in inner.blame_vec_offset
-            ..(inner.blame_vec_offset + REWARD_EPOCH_COUNT as usize)
+        for i in
+            blame_vec_offset..(blame_vec_offset + REWARD_EPOCH_COUNT as usize)
         {
             info!(
                 "insert_epoch_execution_commitment for block hash {:?}",
                 &deferred_block_hash
             );
-            sync_handler
-                .graph
-                .data_man
-                .insert_epoch_execution_commitment(
-                    deferred_block_hash,
-                    // FIXME: the state root is wrong for epochs before sync
-                    // FIXME: point. but these information won't be used.
-                    inner.true_state_root_by_blame_info.clone(),
-                    inner.receipt_blame_vec[i],
-                    inner.bloom_blame_vec[i],
-                );
-            let block = sync_handler
-                .graph
-                .data_man
-                .block_header_by_hash(&deferred_block_hash)
-                .unwrap();
+            data_man.insert_epoch_execution_commitment(
+                deferred_block_hash,
+                // FIXME: the state root is wrong for epochs before sync
+                // FIXME: point. but these information won't be used.
+                true_state_root_by_blame_info.clone(),
+                receipt_blame_vec[i],
+                bloom_blame_vec[i],
+            );
+            let block =
+                data_man.block_header_by_hash(&deferred_block_hash).unwrap();
             deferred_block_hash = *block.parent_hash();
         }
-        for (block_hash, epoch_hash, receipts) in &inner.epoch_receipts {
-            sync_handler.graph.data_man.insert_block_execution_result(
+        for (block_hash, epoch_hash, receipts) in epoch_receipts {
+            data_man.insert_block_execution_result(
                 *block_hash,
                 *epoch_hash,
                 receipts.clone(),
@@ -594,25 +616,19 @@ impl SnapshotChunkSync {
         }
     }
 
-    fn validate_blame_states(
-        ctx: &Context, snapshot_epoch_id: &H256, trusted_blame_block: &H256,
-        state_root_vec: &Vec<StateRoot>, receipt_blame_vec: &Vec<H256>,
-        bloom_blame_vec: &Vec<H256>,
+    pub fn validate_blame_states(
+        data_man: &BlockDataManager, snapshot_epoch_id: &H256,
+        trusted_blame_block: &H256, state_root_vec: &Vec<StateRoot>,
+        receipt_blame_vec: &Vec<H256>, bloom_blame_vec: &Vec<H256>,
     ) -> Option<(usize, StateRootWithAuxInfo, SnapshotInfo)>
     {
         let mut state_blame_vec = vec![];
 
         // these two header must exist in disk, it's safe to unwrap
-        let snapshot_block_header = ctx
-            .manager
-            .graph
-            .data_man
+        let snapshot_block_header = data_man
             .block_header_by_hash(snapshot_epoch_id)
             .expect("block header must exist for snapshot to sync");
-        let trusted_blame_block = ctx
-            .manager
-            .graph
-            .data_man
+        let trusted_blame_block = data_man
             .block_header_by_hash(trusted_blame_block)
             .expect("trusted_blame_block header must exist");
 
@@ -646,10 +662,7 @@ impl SnapshotChunkSync {
         // verify the length of vector.
         loop {
             vec_len += 1;
-            let block = ctx
-                .manager
-                .graph
-                .data_man
+            let block = data_man
                 .block_header_by_hash(&block_hash)
                 .expect("block header must exist");
             // We've jump to another trusted block.
@@ -725,18 +738,16 @@ impl SnapshotChunkSync {
             slice_begin = slice_end;
         }
 
-        let (parent_snapshot_epoch, pivot_chain_parts) =
-            ctx.manager.graph.data_man.get_parent_epochs_for(
+        let (parent_snapshot_epoch, pivot_chain_parts) = data_man
+            .get_parent_epochs_for(
                 snapshot_epoch_id.clone(),
-                ctx.manager.graph.data_man.get_snapshot_epoch_count() as u64,
+                data_man.get_snapshot_epoch_count() as u64,
             );
 
         let parent_snapshot_height = if parent_snapshot_epoch == NULL_EPOCH {
             0
         } else {
-            ctx.manager
-                .graph
-                .data_man
+            data_man
                 .block_header_by_hash(&parent_snapshot_epoch)
                 .unwrap()
                 .height()
@@ -776,12 +787,8 @@ impl SnapshotChunkSync {
                 serve_one_step_sync: false,
                 // We need the extra -1 to get a state root that points to the
                 // snapshot we want.
-                merkle_root: state_root_vec[offset
-                    - ctx
-                        .manager
-                        .graph
-                        .data_man
-                        .get_snapshot_blame_plus_depth()]
+                merkle_root: state_root_vec
+                    [offset - data_man.get_snapshot_blame_plus_depth()]
                 .snapshot_root,
                 height: snapshot_block_header.height(),
                 parent_snapshot_epoch_id: parent_snapshot_epoch,
@@ -791,17 +798,18 @@ impl SnapshotChunkSync {
         ))
     }
 
-    fn validate_epoch_receipts(
-        ctx: &Context, blame_vec_offset: usize, snapshot_epoch_id: &EpochId,
+    /// Validate the receipts of the epochs before the snapshot, where
+    /// `epoch_block_hashes` gives the executed blocks of an epoch by height.
+    pub fn validate_epoch_receipts(
+        data_man: &BlockDataManager,
+        epoch_block_hashes: impl Fn(u64) -> Option<Vec<H256>>,
+        blame_vec_offset: usize, snapshot_epoch_id: &EpochId,
         receipt_blame_vec: &Vec<H256>, bloom_blame_vec: &Vec<H256>,
         block_receipts: &Vec<BlockExecutionResult>,
     ) -> Option<Vec<(H256, H256, Arc<BlockReceipts>)>>
     {
         let mut epoch_hash = snapshot_epoch_id.clone();
-        let checkpoint = ctx
-            .manager
-            .graph
-            .data_man
+        let checkpoint = data_man
             .block_header_by_hash(snapshot_epoch_id)
             .expect("checkpoint header must exist");
         let epoch_receipts_count = if checkpoint.height() == 0 {
@@ -812,20 +820,20 @@ impl SnapshotChunkSync {
         let mut receipts_vec_offset = 0;
         let mut result = Vec::new();
         for idx in 0..epoch_receipts_count {
-            let block_header = ctx
-                .manager
-                .graph
-                .data_man
+            let block_header = data_man
                 .block_header_by_hash(&epoch_hash)
                 .expect("block header must exist");
-            let ordered_executable_epoch_blocks = ctx
-                .manager
-                .graph
-                .consensus
-                .get_block_hashes_by_epoch(EpochNumber::Number(
-                    block_header.height(),
-                ))
-                .expect("ordered executable epoch blocks must exist");
+            let ordered_executable_epoch_blocks =
+                match epoch_block_hashes(block_header.height()) {
+                    Some(hashes) => hashes,
+                    None => {
+                        debug!(
+                            "cannot get block hashes for epoch {}",
+                            block_header.height()
+                        );
+                        return None;
+                    }
+                };
             let mut epoch_receipts = Vec::new();
             for i in 0..ordered_executable_epoch_blocks.len() {
                 if let Some(block_receipt) =
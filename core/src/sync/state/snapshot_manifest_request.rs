@@ -3,7 +3,7 @@
 // See http://www.gnu.org/licenses/
 
 use crate::{
-    block_data_manager::BlockExecutionResult,
+    block_data_manager::{BlockDataManager, BlockExecutionResult},
     message::{
         GetMaybeRequestId, Message, MessageProtocolVersionBound, MsgId,
         RequestId, SetRequestId,
@@ -25,7 +25,7 @@ use crate::{
     },
 };
 use cfx_types::H256;
-use network::service::ProtocolVersion;
+use network::{node_table::NodeId, service::ProtocolVersion};
 use primitives::{EpochNumber, StateRoot};
 use rlp_derive::{RlpDecodable, RlpEncodable};
 use std::{any::Any, time::Duration};
@@ -68,9 +68,32 @@ impl Handleable for SnapshotManifestRequest {
             }
         };
 
-        let (state_root_vec, receipt_blame_vec, bloom_blame_vec) =
-            self.get_blame_states(ctx).unwrap_or_default();
-        let block_receipts = self.get_block_receipts(ctx).unwrap_or_default();
+        let data_man = &ctx.manager.graph.data_man;
+        let snapshot_epoch_id = self.snapshot_to_sync.get_snapshot_epoch_id();
+        let (state_root_vec, receipt_blame_vec, bloom_blame_vec) = self
+            .trusted_blame_block
+            .and_then(|trusted_blame_block| {
+                Self::get_blame_states(
+                    data_man,
+                    snapshot_epoch_id,
+                    &trusted_blame_block,
+                    Some(&ctx.node_id),
+                )
+            })
+            .unwrap_or_default();
+        let block_receipts = Self::get_block_receipts(
+            data_man,
+            snapshot_epoch_id,
+            |height| {
+                ctx.manager
+                    .graph
+                    .consensus
+                    .get_block_hashes_by_epoch(EpochNumber::Number(height))
+                    .ok()
+            },
+            Some(&ctx.node_id),
+        )
+        .unwrap_or_default();
 
         debug!("handle SnapshotManifestRequest {:?}", self,);
         ctx.send_response(&SnapshotManifestResponse {
@@ -103,25 +126,23 @@ impl SnapshotManifestRequest {
         self.trusted_blame_block.is_some()
     }
 
-    fn get_block_receipts(
-        &self, ctx: &Context,
-    ) -> Option<Vec<BlockExecutionResult>> {
+    /// Load the receipts of the epochs before the snapshot, where
+    /// `epoch_block_hashes` gives the executed blocks of an epoch by height.
+    /// `peer` is the requester, if the request is from a peer.
+    pub fn get_block_receipts(
+        data_man: &BlockDataManager, snapshot_epoch_id: &H256,
+        epoch_block_hashes: impl Fn(u64) -> Option<Vec<H256>>,
+        peer: Option<&NodeId>,
+    ) -> Option<Vec<BlockExecutionResult>>
+    {
         let mut epoch_receipts = Vec::new();
-        let mut epoch_hash =
-            self.snapshot_to_sync.get_snapshot_epoch_id().clone();
+        let mut epoch_hash = snapshot_epoch_id.clone();
         for _ in 0..REWARD_EPOCH_COUNT {
-            if let Some(block) =
-                ctx.manager.graph.data_man.block_header_by_hash(&epoch_hash)
-            {
-                match ctx.manager.graph.consensus.get_block_hashes_by_epoch(
-                    EpochNumber::Number(block.height()),
-                ) {
-                    Ok(ordered_executable_epoch_blocks) => {
+            if let Some(block) = data_man.block_header_by_hash(&epoch_hash) {
+                match epoch_block_hashes(block.height()) {
+                    Some(ordered_executable_epoch_blocks) => {
                         for hash in &ordered_executable_epoch_blocks {
-                            match ctx
-                                .manager
-                                .graph
-                                .data_man
+                            match data_man
                                 .block_execution_result_by_hash_with_epoch(
                                     hash,
                                     &epoch_hash,
@@ -140,7 +161,7 @@ impl SnapshotManifestRequest {
                             }
                         }
                     }
-                    Err(_) => {
+                    None => {
                         debug!(
                             "Cannot get block hashes for epoch {}",
                             block.height()
@@ -154,10 +175,7 @@ impl SnapshotManifestRequest {
                 }
                 epoch_hash = block.parent_hash().clone();
             } else {
-                warn!(
-                    "failed to find block={} in db, peer={}",
-                    epoch_hash, ctx.node_id
-                );
+                warn_missing_block(&epoch_hash, peer);
                 return None;
             }
         }
@@ -166,24 +184,29 @@ impl SnapshotManifestRequest {
 
     /// return an empty vec if some information not exist in db, caller may find
     /// another peer to send the request; otherwise return a state_blame_vec
-    /// of the requested block
-    fn get_blame_states(
-        &self, ctx: &Context,
-    ) -> Option<(Vec<StateRoot>, Vec<H256>, Vec<H256>)> {
-        let trusted_block = ctx
-            .manager
-            .graph
-            .data_man
-            .block_header_by_hash(&self.trusted_blame_block?)?;
+    /// of the requested block. `peer` is the requester, if the request is
+    /// from a peer.
+    pub fn get_blame_states(
+        data_man: &BlockDataManager, snapshot_epoch_id: &H256,
+        trusted_blame_block: &H256, peer: Option<&NodeId>,
+    ) -> Option<(Vec<StateRoot>, Vec<H256>, Vec<H256>)>
+    {
+        let trusted_block =
+            data_man.block_header_by_hash(trusted_blame_block)?;
         let snapshot_epoch_block =
-            ctx.manager.graph.data_man.block_header_by_hash(
-                self.snapshot_to_sync.get_snapshot_epoch_id(),
-            )?;
+            data_man.block_header_by_hash(snapshot_epoch_id)?;
         if trusted_block.height() < snapshot_epoch_block.height() {
-            warn!(
-                "receive invalid snapshot manifest request from peer={}",
-                ctx.node_id
-            );
+            match peer {
+                Some(peer) => warn!(
+                    "receive invalid snapshot manifest request from peer={}",
+                    peer
+                ),
+                None => warn!(
+                    "invalid snapshot manifest request, trusted block {:?} \
+                     is before snapshot {:?}",
+                    trusted_blame_block, snapshot_epoch_id
+                ),
+            }
             return None;
         }
         let mut block_hash = trusted_block.hash();
@@ -191,10 +214,7 @@ impl SnapshotManifestRequest {
         let mut blame_count = trusted_block.blame();
         let mut deferred_block_hash = block_hash;
         for _ in 0..DEFERRED_STATE_EPOCH_COUNT {
-            deferred_block_hash = *ctx
-                .manager
-                .graph
-                .data_man
+            deferred_block_hash = *data_man
                 .block_header_by_hash(&deferred_block_hash)
                 .expect("All headers exist")
                 .parent_hash();
@@ -217,9 +237,7 @@ impl SnapshotManifestRequest {
 
         // loop until we have enough length of `state_root_vec`
         loop {
-            if let Some(block) =
-                ctx.manager.graph.data_man.block_header_by_hash(&block_hash)
-            {
+            if let Some(block) = data_man.block_header_by_hash(&block_hash) {
                 // We've jumped to another trusted block.
                 if block.height() + blame_count as u64 + 1
                     == trusted_block_height
@@ -227,10 +245,7 @@ impl SnapshotManifestRequest {
                     trusted_block_height = block.height();
                     blame_count = block.blame()
                 }
-                if let Some(commitment) = ctx
-                    .manager
-                    .graph
-                    .data_man
+                if let Some(commitment) = data_man
                     .get_epoch_execution_commitment_with_db(
                         &deferred_block_hash,
                     )
@@ -241,10 +256,7 @@ impl SnapshotManifestRequest {
                     receipt_blame_vec.push(commitment.receipts_root);
                     bloom_blame_vec.push(commitment.logs_bloom_hash);
                 } else {
-                    warn!(
-                        "failed to find block={} in db, peer={}",
-                        block_hash, ctx.node_id
-                    );
+                    warn_missing_block(&block_hash, peer);
                     return None;
                 }
                 // We've collected enough states.
@@ -254,18 +266,12 @@ impl SnapshotManifestRequest {
                     break;
                 }
                 block_hash = *block.parent_hash();
-                deferred_block_hash = *ctx
-                    .manager
-                    .graph
-                    .data_man
+                deferred_block_hash = *data_man
                     .block_header_by_hash(&deferred_block_hash)
                     .expect("All headers received")
                     .parent_hash();
             } else {
-                warn!(
-                    "failed to find block={} in db, peer={}",
-                    block_hash, ctx.node_id
-                );
+                warn_missing_block(&block_hash, peer);
                 return None;
             }
         }
@@ -280,6 +286,15 @@ impl AsAny for SnapshotManifestRequest {
     fn as_any_mut(&mut self) -> &mut dyn Any { self }
 }
 
+fn warn_missing_block(block_hash: &H256, peer: Option<&NodeId>) {
+    match peer {
+        Some(peer) => {
+            warn!("failed to find block={} in db, peer={}", block_hash, peer)
+        }
+        None => warn!("failed to find block={} in db", block_hash),
+    }
+}
+
 impl Request for SnapshotManifestRequest {
     fn timeout(&self, conf: &ProtocolConfiguration) -> Duration {
         conf.snapshot_manifest_request_timeout
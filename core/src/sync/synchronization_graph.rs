@@ -12,6 +12,7 @@ use crate::{
     pow::ProofOfWorkConfig,
     state_exposer::{SyncGraphBlockState, STATE_EXPOSER},
     statistics::SharedStatistics,
+    sync::SyncStatus,
     verification::*,
     ConsensusGraph, Notifications,
 };
@@ -28,7 +29,7 @@ use primitives::{
 };
 use slab::Slab;
 use std::{
-    cmp::max,
+    cmp::{max, min},
     collections::{BinaryHeap, HashMap, HashSet, VecDeque},
     mem, panic,
     sync::{
@@ -1039,9 +1040,18 @@ pub struct SynchronizationGraph {
     /// Each element is <block_hash, ignore_body>
     new_block_hashes: Arc<Channel<(H256, bool)>>,
 
+    /// Channel used to send sync phase changes and catch-up progress to
+    /// PubSub.
+    pub sync_status: Arc<Channel<SyncStatus>>,
+
     /// whether it is a archive node or full node
     is_full_node: bool,
     machine: Arc<Machine>,
+
+    /// The height below which the execution commitments of the pivot blocks
+    /// are removed because their states are no longer retained by the
+    /// storage. It's only used by archive nodes retaining old states.
+    pruned_commitment_height: Mutex<u64>,
 }
 
 impl MallocSizeOf for SynchronizationGraph {
@@ -1107,8 +1117,16 @@ impl SynchronizationGraph {
             statistics: consensus.get_statistics().clone(),
             consensus_unprocessed_count: consensus_unprocessed_count.clone(),
             new_block_hashes: notifications.new_block_hashes.clone(),
+            sync_status: notifications.sync_status.clone(),
             is_full_node,
             machine,
+            pruned_commitment_height: Mutex::new(
+                data_man
+                    .storage_manager
+                    .get_storage_manager()
+                    .get_first_retained_state_height()
+                    .unwrap_or(0),
+            ),
         };
 
         // It receives `BLOCK_GRAPH_READY` blocks in order and handles them in
@@ -1233,6 +1251,11 @@ impl SynchronizationGraph {
     }
 
     pub fn try_remove_old_era_blocks_from_disk(&self) {
+        let first_retained_state_height = self
+            .data_man
+            .storage_manager
+            .get_storage_manager()
+            .get_first_retained_state_height();
         let mut num_of_blocks_to_remove = OLD_ERA_BLOCK_GC_BATCH_SIZE;
         while let Some(hash) = self.consensus.retrieve_old_era_blocks() {
             // only full node should remove blocks and receipts in old eras
@@ -1243,18 +1266,75 @@ impl SynchronizationGraph {
                 self.data_man
                     .remove_block_result(&hash, true /* remove_db */);
             }
-            // All nodes will not maintain old era states, so related data can
-            // be removed safely. The in-memory data is already
-            // removed in `make_checkpoint`.
+            // Old era epochs will not be executed again, so related data can
+            // be removed safely. The execution commitments of the pivot
+            // blocks are kept to read the old era states retained by the
+            // storage, until the states are removed. The in-memory data is
+            // already removed in `make_checkpoint`.
             // TODO Only call remove for executed epochs.
-            self.data_man
-                .remove_epoch_execution_commitment_from_db(&hash);
+            if !self.is_retained_pivot_block(&hash, first_retained_state_height)
+            {
+                self.data_man
+                    .remove_epoch_execution_commitment_from_db(&hash);
+            }
             self.data_man.remove_epoch_execution_context_from_db(&hash);
             num_of_blocks_to_remove -= 1;
             if num_of_blocks_to_remove == 0 {
                 break;
             }
         }
+        if let Some(first_retained_state_height) = first_retained_state_height
+        {
+            self.remove_unretained_execution_commitments(
+                first_retained_state_height,
+            );
+        }
+    }
+
+    /// Return `true` if `hash` is a pivot block whose state is still retained
+    /// by the storage.
+    fn is_retained_pivot_block(
+        &self, hash: &H256, first_retained_state_height: Option<u64>,
+    ) -> bool {
+        let first_retained_state_height = match first_retained_state_height {
+            Some(height) => height,
+            None => return false,
+        };
+        let height = match self.data_man.block_header_by_hash(hash) {
+            Some(header) => header.height(),
+            None => return false,
+        };
+        height >= first_retained_state_height
+            && self
+                .data_man
+                .executed_epoch_set_hashes_from_db(height)
+                .map_or(false, |epoch_set| epoch_set.last() == Some(hash))
+    }
+
+    /// Remove the execution commitments kept for the old era pivot blocks
+    /// whose states have been removed by the storage.
+    fn remove_unretained_execution_commitments(
+        &self, first_retained_state_height: u64,
+    ) {
+        let era_genesis_height = self
+            .data_man
+            .block_header_by_hash(
+                &self.data_man.get_cur_consensus_era_genesis_hash(),
+            )
+            .map_or(0, |header| header.height());
+        let end_height = min(first_retained_state_height, era_genesis_height);
+        let pruned_height = &mut *self.pruned_commitment_height.lock();
+        while *pruned_height < end_height {
+            if let Some(pivot_hash) = self
+                .data_man
+                .executed_epoch_set_hashes_from_db(*pruned_height)
+                .and_then(|epoch_set| epoch_set.last().cloned())
+            {
+                self.data_man
+                    .remove_epoch_execution_commitment_from_db(&pivot_hash);
+            }
+            *pruned_height += 1;
+        }
     }
 
     /// In full/archive node, this function can be invoked during
@@ -1436,6 +1516,9 @@ impl SynchronizationGraph {
             .notify_new_best_info(self.consensus.best_info())
             // FIXME: propogate error.
             .expect(&concat!(file!(), ":", line!(), ":", column!()));
+        if !header_only {
+            self.consensus.get_tx_pool().replay_journal();
+        }
         info!("Finish reconstructing the pivot chain of length {}, start to sync from peers", self.consensus.best_epoch_number());
     }
 
@@ -4,6 +4,7 @@
 
 use crate::{
     block_data_manager::StateAvailabilityBoundary,
+    channel::Channel,
     consensus::{ConsensusGraph, ConsensusGraphInner},
     parameters::{consensus::NULL, sync::CATCH_UP_EPOCH_LAG_THRESHOLD},
     sync::{
@@ -15,7 +16,7 @@ use crate::{
     },
 };
 use network::NetworkContext;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::{
     collections::HashMap,
     sync::{
@@ -45,6 +46,19 @@ pub enum SyncPhaseType {
     Normal = 5,
 }
 
+/// The sync progress pushed to the subscribers of
+/// `Notifications::sync_status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncStatus {
+    /// The name of the current phase.
+    pub phase: &'static str,
+    pub catch_up_mode: bool,
+    /// The best epoch number of the local consensus graph.
+    pub current_epoch: u64,
+    /// The median epoch number of the peers, if it is known.
+    pub highest_epoch: Option<u64>,
+}
+
 pub trait SynchronizationPhaseTrait: Send + Sync {
     fn name(&self) -> &'static str;
     fn phase_type(&self) -> SyncPhaseType;
@@ -105,6 +119,9 @@ impl SynchronizationPhaseManagerInner {
 
 pub struct SynchronizationPhaseManager {
     inner: RwLock<SynchronizationPhaseManagerInner>,
+    sync_status: Arc<Channel<SyncStatus>>,
+    /// The last status sent to `sync_status`.
+    last_status: Mutex<Option<SyncStatus>>,
 }
 
 impl SynchronizationPhaseManager {
@@ -119,6 +136,8 @@ impl SynchronizationPhaseManager {
             inner: RwLock::new(SynchronizationPhaseManagerInner::new(
                 initial_phase_type,
             )),
+            sync_status: sync_graph.sync_status.clone(),
+            last_status: Mutex::new(None),
         };
 
         sync_manager.register_phase(Arc::new(
@@ -179,6 +198,34 @@ impl SynchronizationPhaseManager {
             current_phase.start(io, sync_handler);
         }
     }
+
+    /// Sends the current status to the `sync_status` subscribers if the
+    /// phase has changed, or if the local epoch has moved while catching up.
+    /// Epoch updates in the normal phase are left to the `epochs`
+    /// subscriptions.
+    pub fn notify_sync_status(
+        &self, sync_handler: &SynchronizationProtocolHandler,
+    ) {
+        let current_phase = self.get_current_phase();
+        let status = SyncStatus {
+            phase: current_phase.name(),
+            catch_up_mode: current_phase.phase_type() != SyncPhaseType::Normal,
+            current_epoch: sync_handler.graph.consensus.best_epoch_number(),
+            highest_epoch: sync_handler.syn.median_epoch_from_normal_peers(),
+        };
+        let mut last_status = self.last_status.lock();
+        let changed = match &*last_status {
+            None => true,
+            Some(last) => {
+                last.phase != status.phase
+                    || (status.catch_up_mode && *last != status)
+            }
+        };
+        if changed {
+            self.sync_status.send(status.clone());
+            *last_status = Some(status);
+        }
+    }
 }
 
 pub struct CatchUpRecoverBlockHeaderFromDbPhase {
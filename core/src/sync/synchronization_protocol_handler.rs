@@ -34,7 +34,7 @@ use metrics::{register_meter_with_group, Meter, MeterTimer};
 use network::{
     node_table::NodeId, service::ProtocolVersion,
     throttling::THROTTLING_SERVICE, Error as NetworkError, HandlerWorkType,
-    NetworkContext, NetworkProtocolHandler, UpdateNodeOperation,
+    NetworkContext, NetworkProtocolHandler, PeerEvent, UpdateNodeOperation,
 };
 use parking_lot::{Mutex, RwLock};
 use primitives::{Block, BlockHeader, EpochId, SignedTransaction};
@@ -964,6 +964,12 @@ impl SynchronizationProtocolHandler {
                         false, // insert_into_consensus
                         true,  // persistent
                     );
+                    if insert_result.is_invalid() {
+                        io.report_peer(
+                            &task.failed_peer,
+                            PeerEvent::InvalidBlock(hash),
+                        );
+                    }
                     if !insert_result.should_process_body() {
                         // If the header is invalid or the block has been
                         // processed in consensus, we do not need to request the
@@ -996,6 +1002,11 @@ impl SynchronizationProtocolHandler {
             if insert_result.is_valid() {
                 // The requested block is correctly received
                 received_blocks.insert(hash);
+            } else if insert_result.is_invalid() {
+                io.report_peer(
+                    &task.failed_peer,
+                    PeerEvent::InvalidBlock(hash),
+                );
             }
             if insert_result.should_relay() {
                 need_to_relay.push(hash);
@@ -1478,6 +1489,7 @@ impl SynchronizationProtocolHandler {
                 self.phase_manager
                     .change_phase_to(next_phase_type, io, self);
             }
+            self.phase_manager.notify_sync_status(self);
         }
 
         let catch_up_mode = self.catch_up_mode();
@@ -135,6 +135,8 @@ pub fn initialize_data_manager(
         DataManagerConfiguration::new(
             false,                          /* do not persist transaction
                                              * address */
+            false,                          /* do not persist block traces */
+            false,                          /* do not persist log blooms */
             Duration::from_millis(300_000), /* max cached tx count */
             dbtype,
         ),
@@ -155,12 +157,14 @@ pub fn initialize_synchronization_graph_with_data_manager(
     );
 
     let machine = Arc::new(new_machine_with_builtin());
+    let notifications = Notifications::init();
 
     let txpool = Arc::new(TransactionPool::new(
         TxPoolConfig::default(),
         verification_config.clone(),
         data_man.clone(),
         machine.clone(),
+        notifications.clone(),
     ));
     let statistics = Arc::new(Statistics::new());
 
@@ -177,7 +181,6 @@ pub fn initialize_synchronization_graph_with_data_manager(
         enable_state_expose: false,
         is_consortium: false,
     };
-    let notifications = Notifications::init();
     let consensus = Arc::new(ConsensusGraph::new(
         ConsensusConfig {
             chain_id: ChainIdParams { chain_id: 0 },
@@ -0,0 +1,180 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::{
+    trace::{
+        Action, Call, CallResult, Create, CreateResult, FlatTrace, Res,
+        Suicide, TraceError,
+    },
+    Tracer,
+};
+use crate::vm::ActionParams;
+use cfx_types::{Address, U256};
+
+/// Tracer which does nothing. It is used when tracing is disabled.
+pub struct NoopTracer;
+
+impl Tracer for NoopTracer {
+    fn prepare_trace_call(&mut self, _: &ActionParams, _: bool) {}
+
+    fn prepare_trace_create(&mut self, _: &ActionParams) {}
+
+    fn done_trace_call(&mut self, _: U256, _: &[u8]) {}
+
+    fn done_trace_create(&mut self, _: U256, _: &[u8], _: Address) {}
+
+    fn done_trace_failed(&mut self, _: TraceError) {}
+
+    fn traces_suicide(&self) -> bool { false }
+
+    fn trace_suicide(&mut self, _: Address, _: U256, _: Address) {}
+}
+
+/// Tracer which records the call tree of a transaction as a list of
+/// `FlatTrace`.
+#[derive(Default)]
+pub struct ExecutiveTracer {
+    traces: Vec<FlatTrace>,
+    /// Indices in `traces` of the frames which are not finished yet.
+    index_stack: Vec<usize>,
+}
+
+impl ExecutiveTracer {
+    /// Returns the recorded traces.
+    pub fn drain(self) -> Vec<FlatTrace> { self.traces }
+
+    fn push_trace(&mut self, action: Action) -> usize {
+        let trace_address = match self.index_stack.last() {
+            Some(&parent) => {
+                let parent = &mut self.traces[parent];
+                let mut trace_address = parent.trace_address.clone();
+                trace_address.push(parent.subtraces);
+                parent.subtraces += 1;
+                trace_address
+            }
+            None => vec![],
+        };
+        self.traces.push(FlatTrace {
+            action,
+            result: Res::None,
+            subtraces: 0,
+            trace_address,
+        });
+        self.traces.len() - 1
+    }
+
+    fn pop_trace(&mut self) -> &mut FlatTrace {
+        let index = self
+            .index_stack
+            .pop()
+            .expect("prepare_trace_* is called before done_trace_*; qed");
+        &mut self.traces[index]
+    }
+}
+
+impl Tracer for ExecutiveTracer {
+    fn prepare_trace_call(
+        &mut self, params: &ActionParams, is_internal_contract: bool,
+    ) {
+        let call = Call::from(params);
+        let action = if is_internal_contract {
+            Action::InternalCall(call)
+        } else {
+            Action::Call(call)
+        };
+        let index = self.push_trace(action);
+        self.index_stack.push(index);
+    }
+
+    fn prepare_trace_create(&mut self, params: &ActionParams) {
+        let index = self.push_trace(Action::Create(Create::from(params)));
+        self.index_stack.push(index);
+    }
+
+    fn done_trace_call(&mut self, gas_used: U256, output: &[u8]) {
+        self.pop_trace().result = Res::Call(CallResult {
+            gas_used,
+            output: output.to_vec(),
+        });
+    }
+
+    fn done_trace_create(
+        &mut self, gas_used: U256, code: &[u8], address: Address,
+    ) {
+        self.pop_trace().result = Res::Create(CreateResult {
+            gas_used,
+            code: code.to_vec(),
+            address,
+        });
+    }
+
+    fn done_trace_failed(&mut self, error: TraceError) {
+        let trace = self.pop_trace();
+        trace.result = match trace.action {
+            Action::Create(_) => Res::FailedCreate(error),
+            _ => Res::FailedCall(error),
+        };
+    }
+
+    fn trace_suicide(
+        &mut self, address: Address, balance: U256, refund_address: Address,
+    ) {
+        self.push_trace(Action::Suicide(Suicide {
+            address,
+            balance,
+            refund_address,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{ActionValue, CallType};
+
+    fn call_params(to: u64) -> ActionParams {
+        let mut params = ActionParams::default();
+        params.address = Address::from_low_u64_be(to);
+        params.value = ActionValue::Transfer(1.into());
+        params.call_type = CallType::Call;
+        params
+    }
+
+    #[test]
+    fn test_trace_address() {
+        let mut tracer = ExecutiveTracer::default();
+        tracer.prepare_trace_call(&call_params(1), false);
+        tracer.prepare_trace_call(&call_params(2), false);
+        tracer.trace_suicide(
+            Address::from_low_u64_be(2),
+            1.into(),
+            Address::from_low_u64_be(1),
+        );
+        tracer.done_trace_call(10.into(), &[]);
+        tracer.prepare_trace_create(&ActionParams::default());
+        tracer.done_trace_failed(TraceError::Error("Out of gas".into()));
+        tracer.done_trace_call(100.into(), &[1]);
+
+        let traces = tracer.drain();
+        assert_eq!(traces.len(), 4);
+        assert_eq!(traces[0].trace_address, Vec::<usize>::new());
+        assert_eq!(traces[0].subtraces, 2);
+        assert_eq!(
+            traces[0].result,
+            Res::Call(CallResult {
+                gas_used: 100.into(),
+                output: vec![1],
+            })
+        );
+        assert_eq!(traces[1].trace_address, vec![0]);
+        assert_eq!(traces[1].subtraces, 1);
+        assert_eq!(traces[2].trace_address, vec![0, 0]);
+        assert_eq!(traces[2].result, Res::None);
+        assert_eq!(traces[3].trace_address, vec![1]);
+        assert_eq!(
+            traces[3].result,
+            Res::FailedCreate(TraceError::Error("Out of gas".into()))
+        );
+    }
+}
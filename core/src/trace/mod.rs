@@ -0,0 +1,84 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+mod executive_tracer;
+mod struct_logger;
+pub mod trace;
+
+pub use self::{
+    executive_tracer::{ExecutiveTracer, NoopTracer},
+    struct_logger::{
+        StructLog, StructLogger, StructLoggerConfig, StructLoggerResult,
+    },
+    trace::{BlockExecTraces, FlatTrace, TransactionExecTraces},
+};
+
+use self::trace::TraceError;
+use crate::vm::ActionParams;
+use cfx_types::{Address, U256};
+
+/// This trait is used by the executive to build the call tree of a
+/// transaction. Every `prepare_trace_*` is matched by exactly one
+/// `done_trace_*` once the frame is finished.
+///
+/// The instruction hooks are called by the interpreter for each instruction
+/// executed in a frame, see `trace_next_instruction`.
+pub trait Tracer {
+    /// Prepares a trace for a call or a value transfer.
+    fn prepare_trace_call(
+        &mut self, params: &ActionParams, is_internal_contract: bool,
+    );
+
+    /// Prepares a trace for a contract creation.
+    fn prepare_trace_create(&mut self, params: &ActionParams);
+
+    /// Finishes the innermost unfinished frame, which is a successful call.
+    fn done_trace_call(&mut self, gas_used: U256, output: &[u8]);
+
+    /// Finishes the innermost unfinished frame, which is a successful
+    /// creation.
+    fn done_trace_create(
+        &mut self, gas_used: U256, code: &[u8], address: Address,
+    );
+
+    /// Finishes the innermost unfinished frame, which failed or reverted.
+    fn done_trace_failed(&mut self, error: TraceError);
+
+    /// Decides if `trace_suicide` should be called. The balance of a
+    /// suicided contract is only read from the state for a tracer which
+    /// records it.
+    fn traces_suicide(&self) -> bool { true }
+
+    /// Records a suicide in the innermost unfinished frame.
+    fn trace_suicide(
+        &mut self, address: Address, balance: U256, refund_address: Address,
+    );
+
+    /// Decides if the instruction at `pc` should be traced. Once it returns
+    /// `false`, the interpreter stops calling the instruction hooks for the
+    /// rest of the frame, so a tracer which doesn't override it costs nothing
+    /// per instruction.
+    fn trace_next_instruction(
+        &mut self, _pc: usize, _instruction: u8, _current_gas: U256,
+    ) -> bool {
+        false
+    }
+
+    /// Called before the execution of a traced instruction, with the stack
+    /// and the memory at that time. `store_written` is the storage slot
+    /// written by `SSTORE`.
+    fn trace_prepare_execute(
+        &mut self, _pc: usize, _instruction: u8, _gas_cost: U256,
+        _stack: &[U256], _mem: &[u8], _store_written: Option<(U256, U256)>,
+    )
+    {
+    }
+
+    /// Called after the execution of a traced instruction, with the items it
+    /// pushed to the stack.
+    fn trace_executed(
+        &mut self, _gas_left: U256, _stack_push: &[U256], _mem: &[u8],
+    ) {
+    }
+}
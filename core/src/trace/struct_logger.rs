@@ -0,0 +1,280 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::{trace::TraceError, Tracer};
+use crate::{bytes::Bytes, evm::Instruction, vm::ActionParams};
+use cfx_types::{Address, U256};
+use std::collections::{BTreeMap, HashMap};
+
+/// Options of the `StructLogger`. They match the ones of the geth struct
+/// logger.
+#[derive(Debug, Clone, Default)]
+pub struct StructLoggerConfig {
+    pub disable_stack: bool,
+    pub disable_memory: bool,
+    pub disable_storage: bool,
+    /// The maximum number of instructions to log, 0 means no limit.
+    pub limit: usize,
+}
+
+/// The state of the vm before the execution of an instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructLog {
+    pub pc: usize,
+    /// The mnemonic name of the instruction.
+    pub op: String,
+    /// The gas left before the execution.
+    pub gas: U256,
+    pub gas_cost: U256,
+    /// The depth of the frame, starting from 1.
+    pub depth: usize,
+    /// The vm error if the instruction failed the frame.
+    pub error: Option<String>,
+    /// The stack, with the top item last.
+    pub stack: Option<Vec<U256>>,
+    pub memory: Option<Bytes>,
+    /// The storage slots of the current contract accessed so far by `SLOAD`
+    /// and `SSTORE`. Only set for these two instructions.
+    pub storage: Option<BTreeMap<U256, U256>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StructLoggerResult {
+    /// Whether the top-level frame failed or reverted.
+    pub failed: bool,
+    /// The output of the top-level frame, which is the deployed code for a
+    /// contract creation.
+    pub return_value: Bytes,
+    pub struct_logs: Vec<StructLog>,
+}
+
+/// Tracer which logs the vm state at every instruction, in the format of the
+/// geth struct logger.
+#[derive(Default)]
+pub struct StructLogger {
+    config: StructLoggerConfig,
+    result: StructLoggerResult,
+    /// The storage owners of the frames which are not finished yet.
+    frames: Vec<Address>,
+    /// The storage slots accessed so far, for each contract.
+    storage: HashMap<Address, BTreeMap<U256, U256>>,
+    /// The key read by the `SLOAD` being executed. The value is only known
+    /// after the execution.
+    pending_sload: Option<U256>,
+}
+
+impl StructLogger {
+    pub fn new(config: StructLoggerConfig) -> Self {
+        StructLogger {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the recorded logs and the outcome of the top-level frame.
+    pub fn drain(self) -> StructLoggerResult { self.result }
+
+    fn record_storage(&mut self, key: U256, value: U256) {
+        let address = match self.frames.last() {
+            Some(address) => *address,
+            None => return,
+        };
+        let storage = self.storage.entry(address).or_default();
+        storage.insert(key, value);
+        if let Some(log) = self.result.struct_logs.last_mut() {
+            log.storage = Some(storage.clone());
+        }
+    }
+
+    fn finish_frame(&mut self, failed: bool, output: &[u8]) {
+        self.frames.pop();
+        if self.frames.is_empty() {
+            self.result.failed = failed;
+            self.result.return_value = output.to_vec();
+        }
+    }
+}
+
+impl Tracer for StructLogger {
+    fn prepare_trace_call(&mut self, params: &ActionParams, _: bool) {
+        self.frames.push(params.address);
+    }
+
+    fn prepare_trace_create(&mut self, params: &ActionParams) {
+        self.frames.push(params.address);
+    }
+
+    fn done_trace_call(&mut self, _: U256, output: &[u8]) {
+        self.finish_frame(false, output);
+    }
+
+    fn done_trace_create(&mut self, _: U256, code: &[u8], _: Address) {
+        self.finish_frame(false, code);
+    }
+
+    fn done_trace_failed(&mut self, error: TraceError) {
+        let depth = self.frames.len();
+        match error {
+            TraceError::Reverted { output, .. } => {
+                self.finish_frame(true, &output)
+            }
+            TraceError::Error(message) => {
+                // The failing instruction is the last one logged in the
+                // frame.
+                if let Some(log) = self.result.struct_logs.last_mut() {
+                    if log.depth == depth && log.error.is_none() {
+                        log.error = Some(message);
+                    }
+                }
+                self.finish_frame(true, &[]);
+            }
+        }
+    }
+
+    fn traces_suicide(&self) -> bool { false }
+
+    fn trace_suicide(&mut self, _: Address, _: U256, _: Address) {}
+
+    fn trace_next_instruction(
+        &mut self, pc: usize, instruction: u8, current_gas: U256,
+    ) -> bool {
+        self.pending_sload = None;
+        if self.config.limit != 0
+            && self.result.struct_logs.len() >= self.config.limit
+        {
+            return false;
+        }
+        let op = match Instruction::from_u8(instruction) {
+            Some(instruction) => instruction.info().name.into(),
+            None => format!("opcode {:#x} not defined", instruction),
+        };
+        self.result.struct_logs.push(StructLog {
+            pc,
+            op,
+            gas: current_gas,
+            gas_cost: U256::zero(),
+            depth: self.frames.len(),
+            error: None,
+            stack: None,
+            memory: None,
+            storage: None,
+        });
+        true
+    }
+
+    fn trace_prepare_execute(
+        &mut self, _pc: usize, instruction: u8, gas_cost: U256,
+        stack: &[U256], mem: &[u8], store_written: Option<(U256, U256)>,
+    )
+    {
+        let log = match self.result.struct_logs.last_mut() {
+            Some(log) => log,
+            None => return,
+        };
+        log.gas_cost = gas_cost;
+        if !self.config.disable_stack {
+            log.stack = Some(stack.to_vec());
+        }
+        if !self.config.disable_memory {
+            log.memory = Some(mem.to_vec());
+        }
+        if self.config.disable_storage {
+            return;
+        }
+        match Instruction::from_u8(instruction) {
+            Some(Instruction::SLOAD) => {
+                self.pending_sload = stack.last().cloned();
+            }
+            Some(Instruction::SSTORE) => {
+                if let Some((key, value)) = store_written {
+                    self.record_storage(key, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn trace_executed(&mut self, _: U256, stack_push: &[U256], _: &[u8]) {
+        if let Some(key) = self.pending_sload.take() {
+            if let Some(value) = stack_push.first() {
+                self.record_storage(key, *value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_struct_logger() {
+        let mut logger = StructLogger::new(StructLoggerConfig {
+            disable_memory: true,
+            ..Default::default()
+        });
+        let mut params = ActionParams::default();
+        params.address = Address::from_low_u64_be(1);
+        logger.prepare_trace_call(&params, false);
+        // PUSH1 0x2a PUSH1 0x00 SSTORE PUSH1 0x00 SLOAD
+        let stack = [U256::from(0x2a), U256::zero()];
+        assert!(logger.trace_next_instruction(4, 0x55, 1000.into()));
+        logger.trace_prepare_execute(
+            4,
+            0x55,
+            20000.into(),
+            &stack,
+            &[],
+            Some((U256::zero(), 0x2a.into())),
+        );
+        logger.trace_executed(980.into(), &[], &[]);
+        assert!(logger.trace_next_instruction(7, 0x54, 980.into()));
+        logger.trace_prepare_execute(
+            7,
+            0x54,
+            200.into(),
+            &[0.into()],
+            &[],
+            None,
+        );
+        logger.trace_executed(780.into(), &[0x2a.into()], &[]);
+        assert!(logger.trace_next_instruction(8, 0xfe, 780.into()));
+        logger.done_trace_failed(TraceError::Error("Bad instruction".into()));
+
+        let result = logger.drain();
+        assert!(result.failed);
+        assert_eq!(result.struct_logs.len(), 3);
+        let sstore = &result.struct_logs[0];
+        assert_eq!(sstore.op, "SSTORE");
+        assert_eq!(sstore.depth, 1);
+        assert_eq!(sstore.gas_cost, 20000.into());
+        assert_eq!(sstore.stack, Some(stack.to_vec()));
+        assert_eq!(sstore.memory, None);
+        let sload = &result.struct_logs[1];
+        assert_eq!(
+            sload.storage.as_ref().and_then(|s| s.get(&U256::zero())),
+            Some(&U256::from(0x2a))
+        );
+        let invalid = &result.struct_logs[2];
+        assert_eq!(invalid.op, "opcode 0xfe not defined");
+        assert_eq!(invalid.error, Some("Bad instruction".into()));
+    }
+
+    #[test]
+    fn test_struct_logger_limit() {
+        let mut logger = StructLogger::new(StructLoggerConfig {
+            limit: 1,
+            ..Default::default()
+        });
+        logger.prepare_trace_call(&ActionParams::default(), false);
+        assert!(logger.trace_next_instruction(0, 0x00, 0.into()));
+        assert!(!logger.trace_next_instruction(1, 0x00, 0.into()));
+        logger.done_trace_call(0.into(), &[1]);
+
+        let result = logger.drain();
+        assert!(!result.failed);
+        assert_eq!(result.return_value, vec![1]);
+        assert_eq!(result.struct_logs.len(), 1);
+    }
+}
@@ -0,0 +1,357 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Trace types recorded while executing transactions.
+
+use crate::{
+    bytes::Bytes,
+    vm::{ActionParams, CallType},
+};
+use cfx_types::{Address, U256};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use rlp_derive::{RlpDecodable, RlpEncodable};
+
+/// Function selector of the solidity `Error(string)` revert payload.
+const REVERT_REASON_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Description of a call action, either `CALL`, `CALLCODE`, `DELEGATECALL` or
+/// `STATICCALL`. Plain value transfers are also recorded as calls.
+#[derive(Debug, Clone, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct Call {
+    /// The sending account.
+    pub from: Address,
+    /// The destination account.
+    pub to: Address,
+    /// The value transferred to the destination account.
+    pub value: U256,
+    /// The gas available for executing the call.
+    pub gas: U256,
+    /// The input data provided to the call.
+    pub input: Bytes,
+    /// The type of the call.
+    pub call_type: CallType,
+}
+
+impl From<&ActionParams> for Call {
+    fn from(p: &ActionParams) -> Self {
+        Call {
+            from: p.sender,
+            to: p.address,
+            value: p.value.value(),
+            gas: p.gas,
+            input: p.data.clone().unwrap_or_default(),
+            call_type: p.call_type.clone(),
+        }
+    }
+}
+
+/// Description of a contract creation.
+#[derive(Debug, Clone, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct Create {
+    /// The address of the creator.
+    pub from: Address,
+    /// The value with which the new account is endowed.
+    pub value: U256,
+    /// The gas available for the creation init code.
+    pub gas: U256,
+    /// The init code.
+    pub init: Bytes,
+}
+
+impl From<&ActionParams> for Create {
+    fn from(p: &ActionParams) -> Self {
+        Create {
+            from: p.sender,
+            value: p.value.value(),
+            gas: p.gas,
+            init: p
+                .code
+                .as_ref()
+                .map_or_else(Vec::new, |c| c.as_ref().clone()),
+        }
+    }
+}
+
+/// Description of a `SUICIDE` action.
+#[derive(Debug, Clone, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct Suicide {
+    /// The destroyed contract.
+    pub address: Address,
+    /// The balance of the contract just before it was destroyed.
+    pub balance: U256,
+    /// The address receiving the remaining balance.
+    pub refund_address: Address,
+}
+
+/// The action of a single trace frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Call(Call),
+    Create(Create),
+    /// A call to one of the internal contracts, e.g. the staking contract.
+    InternalCall(Call),
+    Suicide(Suicide),
+}
+
+impl Encodable for Action {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        match self {
+            Action::Call(call) => s.append(&0u8).append(call),
+            Action::Create(create) => s.append(&1u8).append(create),
+            Action::InternalCall(call) => s.append(&2u8).append(call),
+            Action::Suicide(suicide) => s.append(&3u8).append(suicide),
+        };
+    }
+}
+
+impl Decodable for Action {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        match rlp.val_at::<u8>(0)? {
+            0 => Ok(Action::Call(rlp.val_at(1)?)),
+            1 => Ok(Action::Create(rlp.val_at(1)?)),
+            2 => Ok(Action::InternalCall(rlp.val_at(1)?)),
+            3 => Ok(Action::Suicide(rlp.val_at(1)?)),
+            _ => Err(DecoderError::Custom("Invalid trace action type.")),
+        }
+    }
+}
+
+/// Result of a successful call.
+#[derive(Debug, Clone, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct CallResult {
+    pub gas_used: U256,
+    pub output: Bytes,
+}
+
+/// Result of a successful contract creation.
+#[derive(Debug, Clone, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct CreateResult {
+    pub gas_used: U256,
+    /// The deployed code.
+    pub code: Bytes,
+    pub address: Address,
+}
+
+/// The reason why a call or create frame failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceError {
+    /// The frame executed `REVERT`.
+    Reverted { gas_used: U256, output: Bytes },
+    /// The frame failed with a vm error, all gas is consumed.
+    Error(String),
+}
+
+impl TraceError {
+    /// Returns the decoded revert reason if the output is an ABI encoded
+    /// `Error(string)`.
+    pub fn revert_reason(&self) -> Option<String> {
+        match self {
+            TraceError::Reverted { output, .. } => decode_revert_reason(output),
+            TraceError::Error(_) => None,
+        }
+    }
+}
+
+impl Encodable for TraceError {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            TraceError::Reverted { gas_used, output } => {
+                s.begin_list(3).append(&0u8).append(gas_used).append(output);
+            }
+            TraceError::Error(message) => {
+                s.begin_list(2).append(&1u8).append(message);
+            }
+        }
+    }
+}
+
+impl Decodable for TraceError {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        match rlp.val_at::<u8>(0)? {
+            0 => Ok(TraceError::Reverted {
+                gas_used: rlp.val_at(1)?,
+                output: rlp.val_at(2)?,
+            }),
+            1 => Ok(TraceError::Error(rlp.val_at(1)?)),
+            _ => Err(DecoderError::Custom("Invalid trace error type.")),
+        }
+    }
+}
+
+/// The result of a single trace frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Res {
+    Call(CallResult),
+    Create(CreateResult),
+    FailedCall(TraceError),
+    FailedCreate(TraceError),
+    /// Used for suicides, and for frames whose execution is not finished.
+    None,
+}
+
+impl Encodable for Res {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            Res::Call(call) => {
+                s.begin_list(2).append(&0u8).append(call);
+            }
+            Res::Create(create) => {
+                s.begin_list(2).append(&1u8).append(create);
+            }
+            Res::FailedCall(err) => {
+                s.begin_list(2).append(&2u8).append(err);
+            }
+            Res::FailedCreate(err) => {
+                s.begin_list(2).append(&3u8).append(err);
+            }
+            Res::None => {
+                s.begin_list(1).append(&4u8);
+            }
+        }
+    }
+}
+
+impl Decodable for Res {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        match rlp.val_at::<u8>(0)? {
+            0 => Ok(Res::Call(rlp.val_at(1)?)),
+            1 => Ok(Res::Create(rlp.val_at(1)?)),
+            2 => Ok(Res::FailedCall(rlp.val_at(1)?)),
+            3 => Ok(Res::FailedCreate(rlp.val_at(1)?)),
+            4 => Ok(Res::None),
+            _ => Err(DecoderError::Custom("Invalid trace result type.")),
+        }
+    }
+}
+
+/// A trace frame in the flattened call tree of a transaction. Frames are
+/// ordered as they are entered, and `trace_address` gives the position of the
+/// frame in the tree: the top-level frame has an empty address and the `i`-th
+/// sub frame of a frame with address `a` has address `a ++ [i]`.
+#[derive(Debug, Clone, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct FlatTrace {
+    pub action: Action,
+    pub result: Res,
+    /// The number of direct sub frames.
+    pub subtraces: usize,
+    pub trace_address: Vec<usize>,
+}
+
+/// The traces of all transactions in a block, in execution order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockExecTraces(pub Vec<TransactionExecTraces>);
+
+/// The traces of a single transaction. It's empty when the transaction is not
+/// executed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransactionExecTraces(pub Vec<FlatTrace>);
+
+impl From<Vec<FlatTrace>> for TransactionExecTraces {
+    fn from(traces: Vec<FlatTrace>) -> Self { TransactionExecTraces(traces) }
+}
+
+impl From<Vec<TransactionExecTraces>> for BlockExecTraces {
+    fn from(traces: Vec<TransactionExecTraces>) -> Self {
+        BlockExecTraces(traces)
+    }
+}
+
+impl Encodable for TransactionExecTraces {
+    fn rlp_append(&self, s: &mut RlpStream) { s.append_list(&self.0); }
+}
+
+impl Decodable for TransactionExecTraces {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(TransactionExecTraces(rlp.as_list()?))
+    }
+}
+
+impl Encodable for BlockExecTraces {
+    fn rlp_append(&self, s: &mut RlpStream) { s.append_list(&self.0); }
+}
+
+impl Decodable for BlockExecTraces {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(BlockExecTraces(rlp.as_list()?))
+    }
+}
+
+/// Decodes the message of a solidity `revert("...")` or failed `require`.
+pub fn decode_revert_reason(output: &[u8]) -> Option<String> {
+    // selector ++ offset ++ length ++ data.
+    if output.len() < 4 + 32 + 32 || output[..4] != REVERT_REASON_SELECTOR {
+        return None;
+    }
+    let data = &output[4..];
+    let offset = U256::from_big_endian(&data[..32]);
+    if offset > U256::from(data.len() - 32) {
+        return None;
+    }
+    let offset = offset.as_usize();
+    let len = U256::from_big_endian(&data[offset..offset + 32]);
+    if len > U256::from(data.len() - offset - 32) {
+        return None;
+    }
+    let start = offset + 32;
+    String::from_utf8(data[start..start + len.as_usize()].to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hex::FromHex;
+
+    #[test]
+    fn test_decode_revert_reason() {
+        // revert("Not enough Ether provided.")
+        let output: Vec<u8> = "08c379a0\
+             0000000000000000000000000000000000000000000000000000000000000020\
+             000000000000000000000000000000000000000000000000000000000000001a\
+             4e6f7420656e6f7567682045746865722070726f76696465642e000000000000"
+            .from_hex()
+            .unwrap();
+        assert_eq!(
+            decode_revert_reason(&output),
+            Some("Not enough Ether provided.".into())
+        );
+        assert_eq!(decode_revert_reason(&output[..40]), None);
+        assert_eq!(decode_revert_reason(&[]), None);
+    }
+
+    #[test]
+    fn test_rlp_round_trip() {
+        let traces = TransactionExecTraces(vec![
+            FlatTrace {
+                action: Action::Call(Call {
+                    from: Address::from_low_u64_be(1),
+                    to: Address::from_low_u64_be(2),
+                    value: 10.into(),
+                    gas: 100000.into(),
+                    input: vec![1, 2, 3],
+                    call_type: CallType::Call,
+                }),
+                result: Res::FailedCall(TraceError::Reverted {
+                    gas_used: 500.into(),
+                    output: vec![4, 5],
+                }),
+                subtraces: 1,
+                trace_address: vec![],
+            },
+            FlatTrace {
+                action: Action::Suicide(Suicide {
+                    address: Address::from_low_u64_be(2),
+                    balance: 3.into(),
+                    refund_address: Address::from_low_u64_be(1),
+                }),
+                result: Res::None,
+                subtraces: 0,
+                trace_address: vec![0],
+            },
+        ]);
+        let decoded: TransactionExecTraces =
+            rlp::decode(&rlp::encode(&traces)).unwrap();
+        assert_eq!(decoded, traces);
+    }
+}
@@ -0,0 +1,363 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Journal of the transactions submitted to this node, so that they are not
+//! lost when the node restarts before they are packed.
+//!
+//! The journal is a file of RLP encoded transactions, appended to when local
+//! transactions enter the pool. Since the transactions stay in the journal
+//! after they leave the pool, the journal is rotated periodically, or when it
+//! grows over its size limit, by rewriting it with only the local
+//! transactions still in the pool. If they do not fit either, the ones with
+//! the highest nonces of each sender are dropped first, so that the ones kept
+//! are still executable after a restart.
+
+use cfx_types::{Address, H256};
+use primitives::{SignedTransaction, TransactionWithSignature};
+use rlp::{Encodable, Rlp};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Name of the journal file in the data directory.
+pub const TX_POOL_JOURNAL_FILE: &str = "txpool_journal.rlp";
+
+/// A rotated journal which is trimmed is kept below this percentage of the
+/// size limit, so that it is not rotated again on the next append.
+const ROTATION_TARGET_PERCENT: u64 = 80;
+
+pub struct TransactionJournal {
+    path: PathBuf,
+    /// Size in bytes over which the journal is rotated.
+    max_size: u64,
+    rotate_interval: Duration,
+    file: File,
+    size: u64,
+    last_rotation: Instant,
+    /// Hashes of the journaled transactions, oldest first.
+    hashes: Vec<H256>,
+}
+
+impl TransactionJournal {
+    /// Open the journal at `path`, and return the transactions in it to
+    /// replay. An incomplete record at the end, e.g. from a crash in the
+    /// middle of a write, is discarded.
+    pub fn open(
+        path: PathBuf, max_size: u64, rotate_interval: Duration,
+    ) -> io::Result<(Self, Vec<TransactionWithSignature>)> {
+        let mut data = Vec::new();
+        match File::open(&path) {
+            Ok(mut file) => {
+                file.read_to_end(&mut data)?;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut transactions = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let rlp = Rlp::new(&data[offset..]);
+            let len = match rlp.payload_info() {
+                Ok(info) if offset + info.total() <= data.len() => info.total(),
+                _ => {
+                    warn!(
+                        "Discard the incomplete end of tx pool journal {:?}",
+                        path
+                    );
+                    break;
+                }
+            };
+            match Rlp::new(&data[offset..offset + len]).as_val() {
+                Ok(tx) => transactions.push(tx),
+                Err(e) => warn!("Invalid transaction in journal: {:?}", e),
+            }
+            offset += len;
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.set_len(offset as u64)?;
+        info!(
+            "Loaded {} transactions from tx pool journal {:?}",
+            transactions.len(),
+            path
+        );
+
+        let journal = TransactionJournal {
+            path,
+            max_size,
+            rotate_interval,
+            file,
+            size: offset as u64,
+            last_rotation: Instant::now(),
+            hashes: transactions.iter().map(|tx| tx.hash()).collect(),
+        };
+        Ok((journal, transactions))
+    }
+
+    /// Hashes of the journaled transactions, oldest first.
+    pub fn hashes(&self) -> &[H256] { &self.hashes }
+
+    pub fn append(
+        &mut self, transactions: &[TransactionWithSignature],
+    ) -> io::Result<()> {
+        let mut data = Vec::new();
+        for tx in transactions {
+            data.extend(tx.rlp_bytes());
+        }
+        self.file.write_all(&data)?;
+        self.size += data.len() as u64;
+        self.hashes.extend(transactions.iter().map(|tx| tx.hash()));
+        Ok(())
+    }
+
+    pub fn needs_rotation(&self) -> bool {
+        self.size > self.max_size
+            || self.last_rotation.elapsed() >= self.rotate_interval
+    }
+
+    /// Replace the journal with `transactions`, the ones still in the pool
+    /// oldest first. If they do not fit in the size limit, the ones with the
+    /// highest nonces of each sender are dropped until they fit in
+    /// `ROTATION_TARGET_PERCENT` of the limit.
+    pub fn rotate(
+        &mut self, transactions: Vec<Arc<SignedTransaction>>,
+    ) -> io::Result<()> {
+        let mut seen = HashSet::new();
+        let transactions: Vec<Arc<SignedTransaction>> = transactions
+            .into_iter()
+            .filter(|tx| seen.insert(tx.hash()))
+            .collect();
+        let records: Vec<Vec<u8>> = transactions
+            .iter()
+            .map(|tx| tx.transaction.rlp_bytes())
+            .collect();
+        let total_size: u64 =
+            records.iter().map(|record| record.len() as u64).sum();
+
+        let mut keep = vec![true; transactions.len()];
+        if total_size > self.max_size {
+            let target =
+                self.max_size.saturating_mul(ROTATION_TARGET_PERCENT) / 100;
+            // Take the transactions of every sender in the order of nonces,
+            // round by round, so that all the senders keep their lowest
+            // nonces once the target is reached.
+            let mut by_sender: HashMap<Address, Vec<usize>> = HashMap::new();
+            for (i, tx) in transactions.iter().enumerate() {
+                by_sender.entry(tx.sender()).or_default().push(i);
+            }
+            let mut by_round: Vec<Vec<usize>> = Vec::new();
+            for indices in by_sender.values_mut() {
+                indices.sort_by_key(|&i| transactions[i].nonce());
+                for (round, &i) in indices.iter().enumerate() {
+                    if by_round.len() <= round {
+                        by_round.push(Vec::new());
+                    }
+                    by_round[round].push(i);
+                }
+            }
+
+            keep = vec![false; transactions.len()];
+            let mut size = 0;
+            let mut truncated = HashSet::new();
+            for mut indices in by_round {
+                // Older transactions are preferred within a round.
+                indices.sort();
+                for i in indices {
+                    let sender = transactions[i].sender();
+                    if truncated.contains(&sender) {
+                        continue;
+                    }
+                    let record_size = records[i].len() as u64;
+                    if size + record_size > target {
+                        truncated.insert(sender);
+                        continue;
+                    }
+                    size += record_size;
+                    keep[i] = true;
+                }
+            }
+            warn!(
+                "Drop {} transactions over the size limit of tx pool journal",
+                keep.iter().filter(|kept| !**kept).count()
+            );
+        }
+
+        let records: Vec<(H256, Vec<u8>)> = transactions
+            .iter()
+            .zip(records)
+            .zip(keep)
+            .filter(|(_, kept)| *kept)
+            .map(|((tx, record), _)| (tx.hash(), record))
+            .collect();
+        let size: u64 =
+            records.iter().map(|(_, record)| record.len() as u64).sum();
+
+        let new_path = self.path.with_extension("new");
+        let mut file = File::create(&new_path)?;
+        for (_, record) in &records {
+            file.write_all(record)?;
+        }
+        file.sync_all()?;
+        fs::rename(&new_path, &self.path)?;
+
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.size = size;
+        self.last_rotation = Instant::now();
+        self.hashes = records.into_iter().map(|(hash, _)| hash).collect();
+        debug!(
+            "Rotated tx pool journal, {} transactions kept",
+            self.hashes.len()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cfx_types::U256;
+    use keylib::{Generator, Random};
+    use primitives::{transaction::Action, Transaction};
+    use tempdir::TempDir;
+
+    fn new_signed_transactions(count: usize) -> Vec<Arc<SignedTransaction>> {
+        let keypair = Random.generate().unwrap();
+        (0..count)
+            .map(|nonce| {
+                Transaction {
+                    nonce: nonce.into(),
+                    gas_price: U256::one(),
+                    gas: 21000.into(),
+                    action: Action::Call(Default::default()),
+                    value: U256::zero(),
+                    storage_limit: U256::zero(),
+                    epoch_height: 0,
+                    chain_id: 0,
+                    data: vec![],
+                }
+                .sign(keypair.secret())
+            })
+            .map(Arc::new)
+            .collect()
+    }
+
+    fn new_transactions(count: usize) -> Vec<TransactionWithSignature> {
+        new_signed_transactions(count)
+            .iter()
+            .map(|tx| tx.transaction.clone())
+            .collect()
+    }
+
+    fn unsigned(
+        txs: &[Arc<SignedTransaction>],
+    ) -> Vec<TransactionWithSignature> {
+        txs.iter().map(|tx| tx.transaction.clone()).collect()
+    }
+
+    fn open(
+        path: &PathBuf, max_size: u64,
+    ) -> (TransactionJournal, Vec<TransactionWithSignature>) {
+        TransactionJournal::open(
+            path.clone(),
+            max_size,
+            Duration::from_secs(3600),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_journal_replay() {
+        let dir = TempDir::new("txpool_journal").unwrap();
+        let path = dir.path().join(TX_POOL_JOURNAL_FILE);
+        let txs = new_transactions(5);
+
+        let (mut journal, replay) = open(&path, 1 << 20);
+        assert!(replay.is_empty());
+        journal.append(&txs[..3]).unwrap();
+        journal.append(&txs[3..]).unwrap();
+        drop(journal);
+
+        let (journal, replay) = open(&path, 1 << 20);
+        assert_eq!(replay, txs);
+        assert_eq!(journal.hashes().len(), 5);
+        drop(journal);
+
+        // a partially written record is dropped
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 10)
+            .unwrap();
+        let (mut journal, replay) = open(&path, 1 << 20);
+        assert_eq!(replay, &txs[..4]);
+        journal.append(&txs[4..]).unwrap();
+        drop(journal);
+        assert_eq!(open(&path, 1 << 20).1, txs);
+    }
+
+    #[test]
+    fn test_journal_rotate() {
+        let dir = TempDir::new("txpool_journal").unwrap();
+        let path = dir.path().join(TX_POOL_JOURNAL_FILE);
+        let txs = new_signed_transactions(10);
+        let max_size = 1 << 20;
+
+        let (mut journal, _) = open(&path, max_size);
+        journal.append(&unsigned(&txs)).unwrap();
+        assert!(!journal.needs_rotation());
+
+        // only the transactions still in the pool are kept
+        journal.rotate(txs[2..].to_vec()).unwrap();
+        let hashes: Vec<H256> = txs[2..].iter().map(|tx| tx.hash()).collect();
+        assert_eq!(journal.hashes(), &hashes[..]);
+        drop(journal);
+
+        assert_eq!(open(&path, max_size).1, unsigned(&txs[2..]));
+    }
+
+    #[test]
+    fn test_journal_rotate_over_size_limit() {
+        let dir = TempDir::new("txpool_journal").unwrap();
+        let path = dir.path().join(TX_POOL_JOURNAL_FILE);
+        let first = new_signed_transactions(8);
+        let second = new_signed_transactions(4);
+        let txs: Vec<Arc<SignedTransaction>> =
+            first.iter().chain(&second).cloned().collect();
+        // room for 10 transactions, while the rotated journal is trimmed
+        // to 8 of them
+        let max_record_size = txs
+            .iter()
+            .map(|tx| tx.transaction.rlp_bytes().len() as u64)
+            .max()
+            .unwrap();
+        let max_size = max_record_size * 10;
+
+        let (mut journal, _) = open(&path, max_size);
+        journal.append(&unsigned(&txs)).unwrap();
+        assert!(journal.needs_rotation());
+
+        // the highest nonces of each sender are dropped
+        journal.rotate(txs.clone()).unwrap();
+        assert!(!journal.needs_rotation());
+        assert!(journal.size <= max_size * ROTATION_TARGET_PERCENT / 100);
+        let kept: Vec<Arc<SignedTransaction>> =
+            first[..4].iter().chain(&second[..4]).cloned().collect();
+        let hashes: Vec<H256> = kept.iter().map(|tx| tx.hash()).collect();
+        assert_eq!(journal.hashes(), &hashes[..]);
+        drop(journal);
+
+        assert_eq!(open(&path, max_size).1, unsigned(&kept));
+    }
+}
@@ -9,23 +9,27 @@ mod test_treap;
 
 mod account_cache;
 mod garbage_collector;
+mod journal;
 mod nonce_pool;
 mod transaction_pool_inner;
 
 extern crate rand;
 
-pub use self::impls::TreapMap;
+pub use self::{impls::TreapMap, journal::TX_POOL_JOURNAL_FILE};
 use crate::{
     block_data_manager::BlockDataManager,
+    channel::Channel,
     consensus::BestInformation,
     machine::Machine,
     parameters::block::DEFAULT_TARGET_BLOCK_GAS_LIMIT,
     statedb::{Result as StateDbResult, StateDb},
     storage::{Result as StorageResult, StateIndex, StorageManagerTrait},
     verification::VerificationConfig,
+    Notifications,
 };
 use account_cache::AccountCache;
 use cfx_types::{Address, H256, U256};
+use journal::TransactionJournal;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use metrics::{
     register_meter_with_group, Gauge, GaugeUsize, Lock, Meter, MeterTimer,
@@ -38,7 +42,9 @@ use std::{
     collections::hash_map::HashMap,
     mem,
     ops::DerefMut,
+    path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 use transaction_pool_inner::TransactionPoolInner;
 
@@ -87,6 +93,14 @@ pub struct TxPoolConfig {
     pub tx_weight_scaling: u64,
     pub tx_weight_exp: u8,
     pub target_block_gas_limit: u64,
+    /// File to journal the local transactions to, which are replayed on
+    /// restart. `None` disables the journal.
+    pub journal_path: Option<PathBuf>,
+    /// Size in bytes over which the journal is rotated.
+    pub journal_max_size: u64,
+    /// Interval after which the journal is rotated even if under its size
+    /// limit, to drop the transactions no longer in the pool.
+    pub journal_rotate_interval: Duration,
 }
 
 impl MallocSizeOf for TxPoolConfig {
@@ -104,6 +118,9 @@ impl Default for TxPoolConfig {
             tx_weight_scaling: 1,
             tx_weight_exp: 1,
             target_block_gas_limit: DEFAULT_TARGET_BLOCK_GAS_LIMIT,
+            journal_path: None,
+            journal_max_size: 16 * 1024 * 1024,
+            journal_rotate_interval: Duration::from_secs(3600),
         }
     }
 }
@@ -119,6 +136,11 @@ pub struct TransactionPool {
     set_tx_requests: Mutex<Vec<Arc<SignedTransaction>>>,
     recycle_tx_requests: Mutex<Vec<Arc<SignedTransaction>>>,
     machine: Arc<Machine>,
+    notifications: Arc<Notifications>,
+    journal: Option<Mutex<TransactionJournal>>,
+    /// Transactions loaded from the journal, to insert once the consensus
+    /// graph is recovered.
+    journal_replay: Mutex<Vec<TransactionWithSignature>>,
 }
 
 impl MallocSizeOf for TransactionPool {
@@ -148,6 +170,7 @@ impl TransactionPool {
     pub fn new(
         config: TxPoolConfig, verification_config: VerificationConfig,
         data_man: Arc<BlockDataManager>, machine: Arc<Machine>,
+        notifications: Arc<Notifications>,
     ) -> Self
     {
         let genesis_hash = data_man.true_genesis.hash();
@@ -156,6 +179,20 @@ impl TransactionPool {
             config.tx_weight_scaling,
             config.tx_weight_exp,
         );
+        let (journal, journal_replay) = match config.journal_path {
+            Some(ref path) => match TransactionJournal::open(
+                path.clone(),
+                config.journal_max_size,
+                config.journal_rotate_interval,
+            ) {
+                Ok((journal, replay)) => (Some(Mutex::new(journal)), replay),
+                Err(e) => {
+                    warn!("Failed to open tx pool journal {:?}: {}", path, e);
+                    (None, Vec::new())
+                }
+            },
+            None => (None, Vec::new()),
+        };
         TransactionPool {
             config,
             verification_config,
@@ -182,11 +219,21 @@ impl TransactionPool {
             set_tx_requests: Mutex::new(Default::default()),
             recycle_tx_requests: Mutex::new(Default::default()),
             machine,
+            notifications,
+            journal,
+            journal_replay: Mutex::new(journal_replay),
         }
     }
 
     pub fn machine(&self) -> Arc<Machine> { self.machine.clone() }
 
+    /// The channel of the transactions accepted by the pool.
+    pub fn new_pending_transactions(
+        &self,
+    ) -> &Arc<Channel<Vec<Arc<SignedTransaction>>>> {
+        &self.notifications.new_pending_transactions
+    }
+
     pub fn get_transaction(
         &self, tx_hash: &H256,
     ) -> Option<Arc<SignedTransaction>> {
@@ -310,9 +357,80 @@ impl TransactionPool {
         INSERT_TXS_SUCCESS_TPS.mark(passed_transactions.len());
         INSERT_TXS_FAILURE_TPS.mark(failure.len());
 
+        if !passed_transactions.is_empty() {
+            self.notifications
+                .new_pending_transactions
+                .send(passed_transactions.clone());
+        }
+
         (passed_transactions, failure)
     }
 
+    /// Insert the transactions submitted to this node, like
+    /// `insert_new_transactions`, and journal the ones inserted.
+    pub fn insert_local_transactions(
+        &self, transactions: Vec<TransactionWithSignature>,
+    ) -> (Vec<Arc<SignedTransaction>>, HashMap<H256, String>) {
+        let (passed, failure) = self.insert_new_transactions(transactions);
+        if let Some(journal) = &self.journal {
+            let journaled: Vec<TransactionWithSignature> =
+                passed.iter().map(|tx| tx.transaction.clone()).collect();
+            if let Err(e) = journal.lock().append(&journaled) {
+                warn!("Failed to journal local transactions: {}", e);
+            }
+        }
+        (passed, failure)
+    }
+
+    /// Insert the transactions loaded from the journal, which are verified
+    /// again against the current best state. Only the first call replays the
+    /// journal.
+    pub fn replay_journal(&self) {
+        let transactions = mem::take(&mut *self.journal_replay.lock());
+        if transactions.is_empty() {
+            return;
+        }
+        let total = transactions.len();
+        let (passed, _) = self.insert_new_transactions(transactions);
+        info!(
+            "Replayed {} of {} transactions in tx pool journal",
+            passed.len(),
+            total
+        );
+        self.rotate_journal();
+    }
+
+    /// Rotate the journal if it is over its size limit or rotation interval.
+    /// It is called periodically from a background thread, since the rotation
+    /// reads the pool and rewrites the whole journal.
+    pub fn maybe_rotate_journal(&self) {
+        let needs_rotation = match &self.journal {
+            Some(journal) => journal.lock().needs_rotation(),
+            None => false,
+        };
+        if needs_rotation {
+            self.rotate_journal();
+        }
+    }
+
+    /// Rewrite the journal with the local transactions still in the pool.
+    fn rotate_journal(&self) {
+        if let Some(journal) = &self.journal {
+            let mut journal = journal.lock();
+            let transactions = {
+                let inner = self.inner.read();
+                journal
+                    .hashes()
+                    .iter()
+                    .filter_map(|hash| inner.get(hash))
+                    .collect()
+            };
+            if let Err(e) = journal.rotate(transactions) {
+                warn!("Failed to rotate tx pool journal: {}", e);
+            }
+        }
+    }
+
     /// verify transactions based on the rules that have nothing to do with
     /// readiness
     fn verify_transaction_tx_pool(
@@ -530,6 +648,13 @@ impl TransactionPool {
         inner.content()
     }
 
+    /// The gas prices of the ready transactions with the total gas limit of
+    /// each price, by descending price, until `max_gas` is reached.
+    pub fn ready_gas_prices(&self, max_gas: U256) -> Vec<(U256, U256)> {
+        let inner = self.inner.read();
+        inner.ready_gas_prices(max_gas)
+    }
+
     pub fn notify_new_best_info(
         &self, best_info: Arc<BestInformation>,
     ) -> StateDbResult<()> {
@@ -15,7 +15,7 @@ use primitives::{
 };
 use rlp::*;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
@@ -141,6 +141,9 @@ impl DeferredPool {
 #[derive(DeriveMallocSizeOf)]
 struct ReadyAccountPool {
     treap: TreapMap<Address, Arc<SignedTransaction>, WeightType>,
+    /// Total gas limit of the transactions in `treap` by gas price.
+    #[ignore_malloc_size_of = "insignificant"]
+    gas_by_price: BTreeMap<U256, U256>,
     tx_weight_scaling: u64,
     tx_weight_exp: u8,
 }
@@ -149,6 +152,7 @@ impl ReadyAccountPool {
     fn new(tx_weight_scaling: u64, tx_weight_exp: u8) -> Self {
         ReadyAccountPool {
             treap: TreapMap::new(),
+            gas_by_price: BTreeMap::new(),
             tx_weight_scaling,
             tx_weight_exp,
         }
@@ -167,7 +171,35 @@ impl ReadyAccountPool {
     }
 
     fn remove(&mut self, address: &Address) -> Option<Arc<SignedTransaction>> {
-        self.treap.remove(address)
+        let removed = self.treap.remove(address);
+        if let Some(tx) = &removed {
+            self.remove_gas(tx);
+        }
+        removed
+    }
+
+    fn remove_gas(&mut self, tx: &SignedTransaction) {
+        if let Some(gas) = self.gas_by_price.get_mut(&tx.gas_price) {
+            *gas -= tx.gas;
+            if gas.is_zero() {
+                self.gas_by_price.remove(&tx.gas_price);
+            }
+        }
+    }
+
+    /// The gas prices with their total gas limit, by descending price, until
+    /// `max_gas` is reached.
+    fn gas_prices(&self, max_gas: U256) -> Vec<(U256, U256)> {
+        let mut prices = Vec::new();
+        let mut sum = U256::zero();
+        for (price, gas) in self.gas_by_price.iter().rev() {
+            if sum >= max_gas {
+                break;
+            }
+            prices.push((*price, *gas));
+            sum = sum.saturating_add(*gas);
+        }
+        prices
     }
 
     fn update(
@@ -201,7 +233,12 @@ impl ReadyAccountPool {
             weight *= base_weight;
         }
 
-        self.treap.insert(tx.sender(), tx.clone(), weight)
+        *self.gas_by_price.entry(tx.gas_price).or_default() += tx.gas;
+        let replaced = self.treap.insert(tx.sender(), tx.clone(), weight);
+        if let Some(replaced) = &replaced {
+            self.remove_gas(replaced);
+        }
+        replaced
     }
 
     fn pop(&mut self) -> Option<Arc<SignedTransaction>> {
@@ -717,6 +754,10 @@ impl TransactionPoolInner {
         (ready_txs, deferred_txs)
     }
 
+    pub fn ready_gas_prices(&self, max_gas: U256) -> Vec<(U256, U256)> {
+        self.ready_account_pool.gas_prices(max_gas)
+    }
+
     // Add transaction into deferred pool and maintain its readiness
     // the packed tag provided
     // if force tag is true, the replacement in nonce pool must be happened
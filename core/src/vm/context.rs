@@ -164,10 +164,11 @@ pub trait Context {
         false
     }
 
-    /// Prepare to trace an operation. Passthrough for the VM trace.
+    /// Prepare to trace an operation. Passthrough for the VM trace. `stack`
+    /// and `mem` are the stack and the memory before the execution.
     fn trace_prepare_execute(
         &mut self, _pc: usize, _instruction: u8, _gas_cost: U256,
-        _mem_written: Option<(usize, usize)>,
+        _stack: &[U256], _mem: &[u8], _mem_written: Option<(usize, usize)>,
         _store_written: Option<(U256, U256)>,
     )
     {
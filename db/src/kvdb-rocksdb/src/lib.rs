@@ -661,6 +661,17 @@ impl Database {
             .unwrap_or(0)
     }
 
+    /// The number of keys in a column estimated by RocksDB.
+    pub fn estimate_num_keys(&self, col: u32) -> Option<u64> {
+        match *self.db.read() {
+            Some(ref cfs) => cfs.db.get_property_int_cf(
+                cfs.get_cf(col as usize),
+                "rocksdb.estimate-num-keys",
+            ),
+            None => None,
+        }
+    }
+
     /// Drop a column family.
     pub fn drop_column(&self) -> io::Result<()> {
         match *self.db.write() {
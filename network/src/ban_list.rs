@@ -0,0 +1,377 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Bans of peers by node id or IP subnet. Unlike the blacklisted nodes, which
+//! are only refused while they are known in the node database, bans apply to
+//! any node, and are saved to disk on every change so that they survive a
+//! restart. The file is written in a background thread, since the bans are
+//! changed and checked while the node database is locked.
+
+use crate::node_table::NodeId;
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    io::{BufWriter, Write},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::mpsc::{channel, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const BANNED_PEERS_FILE: &str = "banned_peers.json";
+/// The ban list is written to this file first, and then renamed, so that
+/// a crash while saving does not leave a truncated file behind.
+const BANNED_PEERS_TMP_FILE: &str = "banned_peers.json.tmp";
+/// A ban list file which cannot be parsed is moved here instead of being
+/// overwritten by the next save.
+const BANNED_PEERS_INVALID_FILE: &str = "banned_peers.json.invalid";
+
+/// The peers a ban applies to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BanTarget {
+    Node(NodeId),
+    Subnet(IpNetwork),
+}
+
+impl BanTarget {
+    fn matches(&self, id: &NodeId, ip: Option<&IpAddr>) -> bool {
+        match (self, ip) {
+            (BanTarget::Node(banned), _) => banned == id,
+            (BanTarget::Subnet(subnet), Some(ip)) => subnet.contains(*ip),
+            (BanTarget::Subnet(_), None) => false,
+        }
+    }
+}
+
+impl Display for BanTarget {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            BanTarget::Node(id) => write!(f, "{:x}", id),
+            BanTarget::Subnet(subnet) => write!(f, "{}", subnet),
+        }
+    }
+}
+
+impl FromStr for BanTarget {
+    type Err = String;
+
+    /// Parse a hex encoded node id, or an IP subnet in CIDR notation. A
+    /// single IP address is parsed as a subnet of one address.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(subnet) = IpNetwork::from_str(s) {
+            return Ok(BanTarget::Subnet(subnet));
+        }
+
+        let hex = s.trim_start_matches("0x");
+        NodeId::from_str(hex).map(BanTarget::Node).map_err(|_| {
+            format!("invalid ban target {:?}, node id or subnet expected", s)
+        })
+    }
+}
+
+impl Serialize for BanTarget {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for BanTarget {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        BanTarget::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ban {
+    pub target: BanTarget,
+    /// Seconds since the unix epoch when the ban expires. Permanent if
+    /// `None`.
+    pub until: Option<u64>,
+    pub reason: String,
+}
+
+impl Ban {
+    fn expired(&self, now: u64) -> bool {
+        self.until.map_or(false, |until| until <= now)
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Bans backed by disk file.
+pub struct BanList {
+    bans: Vec<Ban>,
+    writer: Option<BanListWriter>,
+}
+
+impl BanList {
+    pub fn new(dir: Option<String>) -> Self {
+        let dir = match dir {
+            Some(dir) => PathBuf::from(dir),
+            None => {
+                return BanList {
+                    bans: Vec::new(),
+                    writer: None,
+                }
+            }
+        };
+
+        BanList {
+            bans: load_from_file(&dir),
+            writer: Some(BanListWriter::new(dir)),
+        }
+    }
+
+    /// Ban `target` for `duration`, or permanently if `duration` is `None`.
+    /// Replaces any existing ban of the same target.
+    pub fn ban(
+        &mut self, target: BanTarget, duration: Option<Duration>,
+        reason: String,
+    )
+    {
+        let until = duration.map(|d| now_secs().saturating_add(d.as_secs()));
+        self.bans.retain(|ban| ban.target != target);
+        self.bans.push(Ban {
+            target,
+            until,
+            reason,
+        });
+        self.save();
+    }
+
+    /// Lift the ban of `target`. Returns whether it was banned.
+    pub fn unban(&mut self, target: &BanTarget) -> bool {
+        let count = self.bans.len();
+        self.bans.retain(|ban| ban.target != *target);
+        let removed = self.bans.len() != count;
+        if removed {
+            self.save();
+        }
+        removed
+    }
+
+    /// Check if the node `id`, or the subnet of `ip` if specified, is banned.
+    /// Expired bans are removed.
+    pub fn is_banned(&mut self, id: &NodeId, ip: Option<&IpAddr>) -> bool {
+        self.remove_expired();
+        self.bans.iter().any(|ban| ban.target.matches(id, ip))
+    }
+
+    /// All the bans in effect.
+    pub fn bans(&mut self) -> Vec<Ban> {
+        self.remove_expired();
+        self.bans.clone()
+    }
+
+    /// Expired bans are only removed in memory. They are dropped from the
+    /// file with the next change, and ignored when loaded before that.
+    fn remove_expired(&mut self) {
+        let now = now_secs();
+        self.bans.retain(|ban| !ban.expired(now));
+    }
+
+    fn save(&self) {
+        if let Some(ref writer) = self.writer {
+            writer.save(self.bans.clone());
+        }
+    }
+}
+
+fn load_from_file(dir: &Path) -> Vec<Ban> {
+    let path = dir.join(BANNED_PEERS_FILE);
+    let file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            debug!("ban list file not found: {:?}", e);
+            return Vec::new();
+        }
+    };
+    let res: Result<Vec<Ban>, _> = serde_json::from_reader(file);
+    match res {
+        Ok(bans) => {
+            let now = now_secs();
+            bans.into_iter().filter(|ban| !ban.expired(now)).collect()
+        }
+        Err(e) => {
+            // Keep the file for inspection, otherwise it is overwritten by
+            // the next ban and the bans in it are lost silently.
+            let invalid_path = dir.join(BANNED_PEERS_INVALID_FILE);
+            match fs::rename(&path, &invalid_path) {
+                Ok(()) => warn!(
+                    "Error reading ban list file {:?}: {:?}, moved to {:?}",
+                    path, e, invalid_path
+                ),
+                Err(rename_err) => warn!(
+                    "Error reading ban list file {:?}: {:?}, failed to move \
+                     it to {:?}: {:?}",
+                    path, e, invalid_path, rename_err
+                ),
+            }
+            Vec::new()
+        }
+    }
+}
+
+/// Saves the bans in a background thread. Only the latest bans are written
+/// if they are changed again before the previous save completes.
+struct BanListWriter {
+    sender: Option<Sender<Vec<Ban>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BanListWriter {
+    fn new(dir: PathBuf) -> Self {
+        let (sender, receiver) = channel::<Vec<Ban>>();
+        let thread = thread::Builder::new()
+            .name("Ban List Writer".into())
+            .spawn(move || {
+                while let Ok(mut bans) = receiver.recv() {
+                    while let Ok(newer) = receiver.try_recv() {
+                        bans = newer;
+                    }
+                    if let Err(e) = save_to_file(&dir, &bans) {
+                        warn!("Error writing ban list file: {:?}", e);
+                    }
+                }
+            })
+            .expect("Failed to spawn the ban list writer");
+
+        BanListWriter {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    fn save(&self, bans: Vec<Ban>) {
+        if let Some(ref sender) = self.sender {
+            // The writer only exits once the sender is dropped.
+            sender.send(bans).ok();
+        }
+    }
+}
+
+impl Drop for BanListWriter {
+    /// Wait for the pending bans to be written.
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+fn save_to_file(dir: &Path, bans: &[Ban]) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let tmp_path = dir.join(BANNED_PEERS_TMP_FILE);
+    let file = fs::File::create(&tmp_path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, bans)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    fs::rename(&tmp_path, dir.join(BANNED_PEERS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    #[test]
+    fn test_parse_target() {
+        let id = NodeId::random();
+        let target = BanTarget::Node(id);
+        assert_eq!(BanTarget::from_str(&target.to_string()), Ok(target));
+        assert_eq!(
+            BanTarget::from_str(&format!("0x{:x}", id)),
+            Ok(BanTarget::Node(id))
+        );
+
+        let subnet = BanTarget::from_str("10.1.0.0/16").unwrap();
+        assert_eq!(subnet.to_string(), "10.1.0.0/16");
+        assert!(BanTarget::from_str("10.1.0.0/33").is_err());
+        assert!(BanTarget::from_str("0x1234").is_err());
+    }
+
+    #[test]
+    fn test_ban() {
+        let mut list = BanList::new(None);
+        let id = NodeId::random();
+        let ip = IpAddr::from_str("10.1.2.3").unwrap();
+        assert!(!list.is_banned(&id, Some(&ip)));
+
+        list.ban(BanTarget::Node(id), None, "test".into());
+        assert!(list.is_banned(&id, None));
+        assert!(!list.is_banned(&NodeId::random(), Some(&ip)));
+
+        let subnet = BanTarget::from_str("10.1.0.0/16").unwrap();
+        list.ban(subnet, Some(Duration::from_secs(60)), "test".into());
+        assert!(list.is_banned(&NodeId::random(), Some(&ip)));
+        let other_ip = IpAddr::from_str("10.2.2.3").unwrap();
+        assert!(!list.is_banned(&NodeId::random(), Some(&other_ip)));
+        assert_eq!(list.bans().len(), 2);
+
+        assert!(list.unban(&BanTarget::Node(id)));
+        assert!(!list.unban(&BanTarget::Node(id)));
+        assert!(!list.is_banned(&id, None));
+    }
+
+    #[test]
+    fn test_ban_expired() {
+        let mut list = BanList::new(None);
+        let id = NodeId::random();
+        list.ban(BanTarget::Node(id), Some(Duration::from_secs(0)), "".into());
+        assert!(!list.is_banned(&id, None));
+        assert!(list.bans().is_empty());
+    }
+
+    #[test]
+    fn test_persistence() {
+        let dir = temp_dir().join(format!("ban_list_{:x}", NodeId::random()));
+        let dir = dir.to_str().unwrap().to_string();
+        let id = NodeId::random();
+
+        let mut list = BanList::new(Some(dir.clone()));
+        list.ban(BanTarget::Node(id), None, "test".into());
+        drop(list);
+
+        let mut list = BanList::new(Some(dir.clone()));
+        assert!(list.is_banned(&id, None));
+        assert_eq!(list.bans()[0].reason, "test");
+        drop(list);
+        assert!(!PathBuf::from(&dir).join(BANNED_PEERS_TMP_FILE).exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_invalid_file_preserved() {
+        let dir = temp_dir().join(format!("ban_list_{:x}", NodeId::random()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(BANNED_PEERS_FILE), "not json").unwrap();
+        let id = NodeId::random();
+
+        let mut list = BanList::new(Some(dir.to_str().unwrap().into()));
+        assert!(list.bans().is_empty());
+        list.ban(BanTarget::Node(id), None, "test".into());
+        drop(list);
+
+        assert_eq!(
+            fs::read_to_string(dir.join(BANNED_PEERS_INVALID_FILE)).unwrap(),
+            "not json"
+        );
+        let mut list = BanList::new(Some(dir.to_str().unwrap().into()));
+        assert!(list.is_banned(&id, None));
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Snappy compression of the protocol packets of a session.
+//!
+//! Peers advertise the compression version in the Hello packet, and compress
+//! the payloads of protocol packets only if both of them support it. Small
+//! payloads and payloads which do not shrink are sent as is, so every packet
+//! is flagged in its header as compressed or not.
+
+use crate::{Error, ErrorKind};
+use lazy_static::lazy_static;
+use metrics::{register_meter_with_group, Meter};
+use std::sync::Arc;
+
+lazy_static! {
+    static ref COMPRESS_RAW_METER: Arc<dyn Meter> =
+        register_meter_with_group("network_compression", "compress_raw");
+    static ref COMPRESS_COMPRESSED_METER: Arc<dyn Meter> =
+        register_meter_with_group("network_compression", "compress_compressed");
+    static ref DECOMPRESS_RAW_METER: Arc<dyn Meter> =
+        register_meter_with_group("network_compression", "decompress_raw");
+    static ref DECOMPRESS_COMPRESSED_METER: Arc<dyn Meter> =
+        register_meter_with_group(
+            "network_compression",
+            "decompress_compressed"
+        );
+}
+
+/// The version of the packet compression advertised in the Hello packet.
+/// Version 0 means packets are never compressed, which is what peers that do
+/// not advertise any version support.
+pub const COMPRESSION_VERSION: u8 = 1;
+
+/// Payloads smaller than this are not worth compressing.
+const MIN_COMPRESS_SIZE: usize = 512;
+
+/// The size of a decompressed payload is bounded by the size of a packet.
+const MAX_DECOMPRESSED_SIZE: usize = 1 << 24;
+
+/// Compress `data` if it is large enough and the compression saves space.
+/// Returns the payload to send and whether it is compressed.
+pub fn compress(data: Vec<u8>) -> (Vec<u8>, bool) {
+    if data.len() < MIN_COMPRESS_SIZE {
+        return (data, false);
+    }
+
+    match snap::raw::Encoder::new().compress_vec(&data) {
+        Ok(compressed) if compressed.len() < data.len() => {
+            COMPRESS_RAW_METER.mark(data.len());
+            COMPRESS_COMPRESSED_METER.mark(compressed.len());
+            (compressed, true)
+        }
+        Ok(_) => (data, false),
+        Err(e) => {
+            debug!("failed to compress packet: {:?}", e);
+            (data, false)
+        }
+    }
+}
+
+/// Decompress a payload flagged as compressed by the remote peer.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let decompressed_len = snap::raw::decompress_len(data).map_err(|e| {
+        debug!("failed to decompress packet: {:?}", e);
+        Error::from(ErrorKind::BadProtocol)
+    })?;
+    if decompressed_len > MAX_DECOMPRESSED_SIZE {
+        debug!(
+            "failed to decompress packet, oversized: {}",
+            decompressed_len
+        );
+        bail!(ErrorKind::OversizedPacket);
+    }
+
+    let decompressed = snap::raw::Decoder::new()
+        .decompress_vec(data)
+        .map_err(|e| {
+            debug!("failed to decompress packet: {:?}", e);
+            Error::from(ErrorKind::BadProtocol)
+        })?;
+    DECOMPRESS_COMPRESSED_METER.mark(data.len());
+    DECOMPRESS_RAW_METER.mark(decompressed.len());
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress() {
+        // small payloads are not compressed
+        let data = vec![0; MIN_COMPRESS_SIZE - 1];
+        assert_eq!(compress(data.clone()), (data, false));
+
+        let data = vec![7; 4096];
+        let (payload, compressed) = compress(data.clone());
+        assert!(compressed);
+        assert!(payload.len() < data.len());
+        assert_eq!(decompress(&payload).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decompress_invalid() {
+        // the decompressed length is too large
+        assert!(decompress(&[0xff, 0xff, 0xff, 0xff, 0x0f]).is_err());
+        // the literal is longer than the remaining input
+        assert!(decompress(&[0x10, 0x3c, 1, 2]).is_err());
+    }
+}
@@ -4,6 +4,7 @@
 
 use crate::{
     io::{IoContext, StreamToken},
+    session_cipher::{SessionCipher, MAC_SIZE},
     throttling::THROTTLING_SERVICE,
     Error, ErrorKind,
 };
@@ -110,6 +111,8 @@ struct Packet {
     data: Vec<u8>,
     // current data position to write to socket.
     sending_pos: usize,
+    // whether to seal the data with the session cipher before sending.
+    seal: bool,
     original_is_high_priority: bool,
     throttling_size: usize,
     creation_time: Instant,
@@ -128,6 +131,7 @@ impl Packet {
         Ok(Packet {
             data,
             sending_pos: 0,
+            seal: false,
             original_is_high_priority: is_high_priority,
             throttling_size,
             creation_time: Instant::now(),
@@ -186,11 +190,23 @@ pub struct GenericConnection<Socket: GenericSocket> {
     registered: AtomicBool,
     /// Assemble packet with extra information before sending out.
     assembler: Box<dyn PacketAssembler>,
+    /// Cipher of the frames, set once both peers agree to encrypt the
+    /// session.
+    cipher: Option<SessionCipher>,
 }
 
 impl<Socket: GenericSocket> GenericConnection<Socket> {
+    /// Encrypt the packets received from now on, and the packets queued from
+    /// now on before sending them out. Packets already queued are still sent
+    /// in plaintext.
+    pub fn enable_cipher(&mut self, cipher: SessionCipher) {
+        self.cipher = Some(cipher);
+    }
+
+    pub fn is_encrypted(&self) -> bool { self.cipher.is_some() }
+
     /// Readable IO handler. Called when there is some data to be read.
-    pub fn readable(&mut self) -> io::Result<Option<Bytes>> {
+    pub fn readable(&mut self) -> Result<Option<Bytes>, Error> {
         let mut buf: [u8; 1024] = [0; 1024];
 
         // Read until the socket has no data to read.
@@ -219,13 +235,18 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
                     io::ErrorKind::WouldBlock => break,
                     _ => {
                         debug!("Failed to read socket data, token = {}, err = {:?}", self.token, e);
-                        return Err(e);
+                        return Err(e.into());
                     }
                 },
             }
         }
 
-        let packet = self.assembler.load(&mut self.recv_buf);
+        let mut packet = self.assembler.load(&mut self.recv_buf);
+
+        if let (Some(p), Some(cipher)) = (packet.as_mut(), self.cipher.as_mut())
+        {
+            cipher.open(p)?;
+        }
 
         if let Some(ref p) = packet {
             trace!(
@@ -249,6 +270,9 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
             data
         );
 
+        if let Some(ref mut cipher) = self.cipher {
+            cipher.seal(&mut data)?;
+        }
         self.assembler.assemble(&mut data)?;
         let size = self.socket.write(&data)?;
 
@@ -273,6 +297,13 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
                 None => return Ok(WriteStatus::Complete),
             };
 
+            if packet.seal {
+                self.cipher
+                    .as_mut()
+                    .expect("cipher should be enabled to seal packet")
+                    .seal(&mut packet.data)?;
+            }
+
             // assemble packet to send, e.g. prefix length to packet
             self.assembler.assemble(&mut packet.data)?;
 
@@ -337,13 +368,16 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
     {
         if !data.is_empty() {
             let size = data.len();
-            if self.assembler.is_oversized(size) {
+            let seal = self.cipher.is_some();
+            let frame_size = if seal { size + MAC_SIZE } else { size };
+            if self.assembler.is_oversized(frame_size) {
                 return Err(ErrorKind::OversizedPacket.into());
             }
 
             trace!("Sending packet, token = {}, size = {}", self.token, size);
 
-            let packet = Packet::new(data, priority)?;
+            let mut packet = Packet::new(data, priority)?;
+            packet.seal = seal;
             self.send_queue.push_back(packet, priority);
 
             SEND_METER.mark(size);
@@ -386,6 +420,7 @@ impl Connection {
             interest: Ready::hup() | Ready::readable(),
             registered: AtomicBool::new(false),
             assembler: Box::new(PacketWithLenAssembler::default()),
+            cipher: None,
         }
     }
 
@@ -478,6 +513,7 @@ impl Connection {
                 .len_by_priority(SendQueuePriority::High),
             interest: format!("{:?}", self.interest),
             registered: self.registered.load(AtomicOrdering::SeqCst),
+            encrypted: self.is_encrypted(),
         }
     }
 }
@@ -493,6 +529,7 @@ pub struct ConnectionDetails {
     pub priority_queue_high: usize,
     pub interest: String,
     pub registered: bool,
+    pub encrypted: bool,
 }
 
 /// Assembler that prefix packet with length information.
@@ -674,6 +711,7 @@ mod tests {
                 interest: Ready::hup() | Ready::readable(),
                 registered: AtomicBool::new(false),
                 assembler: Box::new(PacketWithLenAssembler::new(1, None)),
+                cipher: None,
             }
         }
     }
@@ -738,6 +776,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn connection_encrypted() {
+        let secret = cfx_types::H256::random();
+        let mut sender = TestConnection::new();
+        let mut receiver = TestConnection::new();
+
+        // queued before the cipher is enabled, so sent in plaintext
+        sender
+            .send(&test_io(), vec![1, 2, 3], SendQueuePriority::High)
+            .unwrap();
+        sender.enable_cipher(SessionCipher::new(&secret, true).unwrap());
+        receiver.enable_cipher(SessionCipher::new(&secret, false).unwrap());
+        sender
+            .send(&test_io(), vec![4, 5, 6], SendQueuePriority::High)
+            .unwrap();
+        sender.writable(&test_io()).unwrap();
+        sender.writable(&test_io()).unwrap();
+
+        let plaintext_len = 1 + 3;
+        let wire = sender.socket.write_buf.clone();
+        assert_eq!(&wire[..plaintext_len], &[3, 2, 3, 1]);
+        assert_eq!(wire.len(), plaintext_len + 1 + 3 + MAC_SIZE);
+
+        receiver.socket.read_buf = wire[plaintext_len..].to_vec();
+        assert_eq!(&receiver.readable().unwrap().unwrap()[..], &[4, 5, 6]);
+
+        // a tampered frame is rejected
+        let mut tampered = wire[plaintext_len..].to_vec();
+        tampered[1] ^= 1;
+        let mut receiver = TestConnection::new();
+        receiver.enable_cipher(SessionCipher::new(&secret, false).unwrap());
+        receiver.socket.read_buf = tampered;
+        assert!(receiver.readable().is_err());
+    }
+
     #[test]
     fn test_assembler_oversized() {
         let assembler = PacketWithLenAssembler::default();
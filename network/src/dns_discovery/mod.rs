@@ -0,0 +1,447 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Node discovery via DNS, in the style of EIP-1459.
+//!
+//! A node list is published as a Merkle tree of TXT records under a domain,
+//! whose root is signed by the publisher. The list is configured with the URL
+//! `enrtree://<base32 public key of the publisher>@<domain>`, and may link to
+//! the lists of other publishers. The nodes resolved are added to the trusted
+//! node table, in the same way as the boot nodes.
+//!
+//! The sequence number of every tree resolved is remembered, and a tree whose
+//! root has a lower sequence number is refused, so that a stale or replayed
+//! root cannot roll back a node list.
+
+mod resolver;
+mod tree;
+
+pub use self::{
+    resolver::{StaticResolver, SystemResolver, TxtResolver},
+    tree::{entry_hash, Entry, LinkEntry, RootEntry, Tree},
+};
+
+use crate::{node_table::NodeEntry, Error};
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    mem,
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Maximum number of trees resolved from a URL, including the linked trees.
+const MAX_TREES: usize = 16;
+
+/// Maximum number of entries resolved from a tree.
+const MAX_TREE_ENTRIES: usize = 4096;
+
+/// Interval to resolve the trees again, to pick up the updated lists.
+pub const DNS_DISCOVERY_REFRESH_TIMEOUT: Duration = Duration::from_secs(1800);
+
+/// Resolve the root of the tree at `link`, and check its signature.
+fn resolve_root<R: TxtResolver + ?Sized>(
+    resolver: &R, link: &LinkEntry,
+) -> Result<RootEntry, Error> {
+    for txt in resolver.resolve_txt(&link.domain)? {
+        if let Ok(Entry::Root(root)) = txt.parse::<Entry>() {
+            if !root.verify(&link.public) {
+                bail!("invalid signature of tree root at {}", link.domain);
+            }
+            return Ok(root);
+        }
+    }
+    bail!("no tree root at {}", link.domain)
+}
+
+/// Resolve the entry with `hash` under `domain`, and check its hash.
+fn resolve_entry<R: TxtResolver + ?Sized>(
+    resolver: &R, domain: &str, hash: &str,
+) -> Result<Entry, Error> {
+    let name = format!("{}.{}", hash, domain);
+    for txt in resolver.resolve_txt(&name)? {
+        if entry_hash(&txt).eq_ignore_ascii_case(hash) {
+            return txt.parse();
+        }
+    }
+    bail!("no tree entry with matching hash at {}", name)
+}
+
+/// Resolve the leaves of the subtree with root `hash` under `domain`.
+fn resolve_subtree<R: TxtResolver + ?Sized>(
+    resolver: &R, domain: &str, hash: &str,
+) -> Result<Vec<Entry>, Error> {
+    let mut leaves = Vec::new();
+    let mut pending = vec![hash.to_string()];
+    let mut resolved = 0;
+    while let Some(hash) = pending.pop() {
+        resolved += 1;
+        if resolved > MAX_TREE_ENTRIES {
+            bail!("too many entries in tree at {}", domain);
+        }
+
+        match resolve_entry(resolver, domain, &hash)? {
+            Entry::Branch(children) => pending.extend(children),
+            Entry::Root(_) => bail!("unexpected root entry at {}", domain),
+            leaf => leaves.push(leaf),
+        }
+    }
+    Ok(leaves)
+}
+
+/// The latest sequence numbers of the trees resolved, by domain.
+pub type TreeSeqs = HashMap<String, u64>;
+
+/// Resolve the nodes and the links of the tree at `link`. Fails if its
+/// sequence number is lower than the one in `seqs`, which is updated once the
+/// tree is resolved.
+fn resolve_tree<R: TxtResolver + ?Sized>(
+    resolver: &R, link: &LinkEntry, seqs: &mut TreeSeqs,
+) -> Result<(Vec<NodeEntry>, Vec<LinkEntry>), Error> {
+    let root = resolve_root(resolver, link)?;
+    if let Some(&seq) = seqs.get(&link.domain) {
+        if root.seq < seq {
+            bail!(
+                "tree at {} rolled back from seq {} to {}",
+                link.domain,
+                seq,
+                root.seq
+            );
+        }
+    }
+
+    let mut nodes = Vec::new();
+    for entry in resolve_subtree(resolver, &link.domain, &root.node_root)? {
+        match entry {
+            Entry::Node(node) => nodes.push(node),
+            _ => bail!("unexpected link in node list at {}", link.domain),
+        }
+    }
+
+    let mut links = Vec::new();
+    for entry in resolve_subtree(resolver, &link.domain, &root.link_root)? {
+        match entry {
+            Entry::Link(link) => links.push(link),
+            _ => bail!("unexpected node in link list at {}", link.domain),
+        }
+    }
+
+    debug!(
+        "Resolved tree at {}, seq = {}, {} nodes, {} links",
+        link.domain,
+        root.seq,
+        nodes.len(),
+        links.len()
+    );
+    seqs.insert(link.domain.clone(), root.seq);
+    Ok((nodes, links))
+}
+
+/// Resolve the nodes of the tree at `url`, and of the trees it links to.
+/// Fails if the tree at `url` is invalid or rolled back, while such linked
+/// trees are skipped.
+pub fn resolve_nodes<R: TxtResolver + ?Sized>(
+    resolver: &R, url: &str, seqs: &mut TreeSeqs,
+) -> Result<Vec<NodeEntry>, Error> {
+    let link: LinkEntry = url.parse()?;
+    let (mut nodes, links) = resolve_tree(resolver, &link, seqs)?;
+
+    let mut visited: HashSet<String> = HashSet::new();
+    visited.insert(link.domain);
+    let mut pending: VecDeque<LinkEntry> = links.into();
+    while let Some(link) = pending.pop_front() {
+        if visited.len() >= MAX_TREES {
+            debug!("Too many linked trees in {}", url);
+            break;
+        }
+        if !visited.insert(link.domain.clone()) {
+            continue;
+        }
+
+        match resolve_tree(resolver, &link, seqs) {
+            Ok((linked_nodes, links)) => {
+                nodes.extend(linked_nodes);
+                pending.extend(links);
+            }
+            Err(e) => debug!("Failed to resolve linked tree: {}", e),
+        }
+    }
+
+    let mut ids = HashSet::new();
+    nodes.retain(|node| ids.insert(node.id));
+    Ok(nodes)
+}
+
+/// Resolves the node lists periodically in a background thread, since the
+/// DNS queries block. The nodes resolved are kept until taken.
+pub struct DnsDiscovery {
+    nodes: Arc<Mutex<Vec<NodeEntry>>>,
+    stop: Option<Sender<()>>,
+}
+
+impl DnsDiscovery {
+    pub fn start(
+        urls: Vec<String>, resolver: Box<dyn TxtResolver>, refresh: Duration,
+    ) -> Self {
+        let nodes = Arc::new(Mutex::new(Vec::new()));
+        let resolved = nodes.clone();
+        let (stop, stopped) = channel();
+        thread::Builder::new()
+            .name("DNS Discovery".into())
+            .spawn(move || {
+                let mut seqs = TreeSeqs::new();
+                loop {
+                    for url in &urls {
+                        if is_stopped(&stopped) {
+                            return;
+                        }
+                        match resolve_nodes(&*resolver, url, &mut seqs) {
+                            Ok(nodes) => {
+                                info!(
+                                    "Resolved {} nodes from {}",
+                                    nodes.len(),
+                                    url
+                                );
+                                resolved.lock().extend(nodes);
+                            }
+                            Err(e) => warn!(
+                                "Failed to resolve nodes from {}: {}",
+                                url, e
+                            ),
+                        }
+                    }
+
+                    match stopped.recv_timeout(refresh) {
+                        Err(RecvTimeoutError::Timeout) => {}
+                        _ => return,
+                    }
+                }
+            })
+            .expect("Failed to spawn the DNS discovery thread");
+
+        DnsDiscovery {
+            nodes,
+            stop: Some(stop),
+        }
+    }
+
+    /// Take the nodes resolved since the last call.
+    pub fn take_nodes(&self) -> Vec<NodeEntry> {
+        mem::replace(&mut *self.nodes.lock(), Vec::new())
+    }
+}
+
+fn is_stopped(stopped: &Receiver<()>) -> bool {
+    match stopped.try_recv() {
+        Err(TryRecvError::Empty) => false,
+        _ => true,
+    }
+}
+
+impl Drop for DnsDiscovery {
+    /// Signal the thread to stop without waiting for it, since it may be
+    /// blocked on a DNS query for long. It exits once the query returns.
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node_table::{Node, NodeId};
+    use keylib::{Generator, KeyPair, Random};
+    use std::str::FromStr;
+
+    fn new_nodes(count: usize) -> Vec<NodeEntry> {
+        (0..count)
+            .map(|i| {
+                let node = Node::from_str(&format!(
+                    "cfxnode://{:x}@10.0.{}.{}:32323",
+                    NodeId::random(),
+                    i / 256,
+                    i % 256
+                ))
+                .unwrap();
+                NodeEntry {
+                    id: node.id,
+                    endpoint: node.endpoint,
+                }
+            })
+            .collect()
+    }
+
+    fn publish(
+        resolver: &mut StaticResolver, key: &KeyPair, domain: &str,
+        nodes: &[NodeEntry], links: &[LinkEntry], seq: u64,
+    ) -> String
+    {
+        let tree = Tree::sign(nodes, links, seq, key.secret()).unwrap();
+        for (name, txt) in tree.txt_records(domain) {
+            resolver.insert(name, txt);
+        }
+        LinkEntry {
+            public: *key.public(),
+            domain: domain.into(),
+        }
+        .to_string()
+    }
+
+    #[test]
+    fn test_resolve_nodes() {
+        let mut resolver = StaticResolver::new();
+        let key = Random.generate().unwrap();
+        let linked_key = Random.generate().unwrap();
+
+        let linked_nodes = new_nodes(5);
+        let linked_url = publish(
+            &mut resolver,
+            &linked_key,
+            "linked.example.org",
+            &linked_nodes,
+            &[],
+            1,
+        );
+
+        let nodes = new_nodes(100);
+        let url = publish(
+            &mut resolver,
+            &key,
+            "nodes.example.org",
+            &nodes,
+            &[linked_url.parse().unwrap()],
+            1,
+        );
+
+        let mut seqs = TreeSeqs::new();
+        let resolved = resolve_nodes(&resolver, &url, &mut seqs).unwrap();
+        assert_eq!(resolved.len(), 105);
+        let ids: HashSet<NodeId> = resolved.iter().map(|n| n.id).collect();
+        assert!(nodes
+            .iter()
+            .chain(&linked_nodes)
+            .all(|n| ids.contains(&n.id)));
+
+        assert_eq!(seqs.len(), 2);
+
+        // the linked tree alone
+        let resolved =
+            resolve_nodes(&resolver, &linked_url, &mut TreeSeqs::new());
+        assert_eq!(resolved.unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_resolve_rolled_back() {
+        let key = Random.generate().unwrap();
+        let linked_key = Random.generate().unwrap();
+        let publish_with_seq = |seq| {
+            let mut resolver = StaticResolver::new();
+            let linked_url = publish(
+                &mut resolver,
+                &linked_key,
+                "linked.example.org",
+                &new_nodes(5),
+                &[],
+                seq,
+            );
+            let url = publish(
+                &mut resolver,
+                &key,
+                "nodes.example.org",
+                &new_nodes(3),
+                &[linked_url.parse().unwrap()],
+                seq,
+            );
+            (resolver, url)
+        };
+        let (old, url) = publish_with_seq(1);
+        let (new, _) = publish_with_seq(2);
+
+        let mut seqs = TreeSeqs::new();
+        assert_eq!(resolve_nodes(&new, &url, &mut seqs).unwrap().len(), 8);
+        assert_eq!(resolve_nodes(&new, &url, &mut seqs).unwrap().len(), 8);
+        assert!(resolve_nodes(&old, &url, &mut seqs).is_err());
+        assert_eq!(seqs["nodes.example.org"], 2);
+
+        // a rolled back linked tree is skipped
+        seqs.remove("nodes.example.org");
+        assert_eq!(resolve_nodes(&old, &url, &mut seqs).unwrap().len(), 3);
+        assert_eq!(seqs["nodes.example.org"], 1);
+        assert_eq!(seqs["linked.example.org"], 2);
+    }
+
+    #[test]
+    fn test_resolve_invalid() {
+        let mut resolver = StaticResolver::new();
+        let key = Random.generate().unwrap();
+        let url = publish(
+            &mut resolver,
+            &key,
+            "nodes.example.org",
+            &new_nodes(3),
+            &[],
+            1,
+        );
+
+        // signed by another key
+        let other = LinkEntry {
+            public: *Random.generate().unwrap().public(),
+            domain: "nodes.example.org".into(),
+        };
+        let mut seqs = TreeSeqs::new();
+        assert!(
+            resolve_nodes(&resolver, &other.to_string(), &mut seqs).is_err()
+        );
+
+        // missing entries
+        let missing = url.replace("nodes", "none");
+        assert!(resolve_nodes(&resolver, &missing, &mut seqs).is_err());
+
+        // tampered entry
+        let mut tampered = StaticResolver::new();
+        let tree = Tree::sign(&new_nodes(3), &[], 1, key.secret()).unwrap();
+        for (name, txt) in tree.txt_records("nodes.example.org") {
+            let txt = txt.replace("10.0.0.", "10.0.1.");
+            tampered.insert(name, txt);
+        }
+        assert!(resolve_nodes(&tampered, &url, &mut seqs).is_err());
+        assert!(seqs.is_empty());
+    }
+
+    #[test]
+    fn test_dns_discovery() {
+        let mut resolver = StaticResolver::new();
+        let key = Random.generate().unwrap();
+        let url = publish(
+            &mut resolver,
+            &key,
+            "nodes.example.org",
+            &new_nodes(3),
+            &[],
+            1,
+        );
+
+        let discovery = DnsDiscovery::start(
+            vec![url],
+            Box::new(resolver),
+            Duration::from_secs(60),
+        );
+        let mut nodes = Vec::new();
+        for _ in 0..100 {
+            nodes.extend(discovery.take_nodes());
+            if !nodes.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(nodes.len(), 3);
+        assert!(discovery.take_nodes().is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::Error;
+use std::collections::HashMap;
+use trust_dns_resolver::Resolver;
+
+/// Resolves the TXT records of a domain name. Node trees are resolved with
+/// the system DNS configuration by default, and with a `StaticResolver` in
+/// tests.
+pub trait TxtResolver: Send {
+    fn resolve_txt(&self, name: &str) -> Result<Vec<String>, Error>;
+}
+
+pub struct SystemResolver {
+    resolver: Resolver,
+}
+
+impl SystemResolver {
+    pub fn new() -> Result<Self, Error> {
+        let resolver = Resolver::from_system_conf().map_err(|e| {
+            format!("failed to load system DNS configuration: {}", e)
+        })?;
+        Ok(SystemResolver { resolver })
+    }
+}
+
+impl TxtResolver for SystemResolver {
+    fn resolve_txt(&self, name: &str) -> Result<Vec<String>, Error> {
+        // resolve as a fully qualified name, without the search domains
+        let fqdn = if name.ends_with('.') {
+            name.to_string()
+        } else {
+            format!("{}.", name)
+        };
+        let lookup = self
+            .resolver
+            .txt_lookup(fqdn.as_str())
+            .map_err(|e| format!("failed to resolve TXT of {}: {}", name, e))?;
+
+        // a TXT record is split into strings of at most 255 bytes
+        Ok(lookup
+            .iter()
+            .map(|txt| {
+                txt.txt_data()
+                    .iter()
+                    .map(|data| String::from_utf8_lossy(data))
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+/// Resolves the TXT records from memory.
+#[derive(Default)]
+pub struct StaticResolver {
+    records: HashMap<String, Vec<String>>,
+}
+
+impl StaticResolver {
+    pub fn new() -> Self { Default::default() }
+
+    pub fn insert(&mut self, name: String, txt: String) {
+        self.records.entry(name).or_insert_with(Vec::new).push(txt);
+    }
+}
+
+impl TxtResolver for StaticResolver {
+    fn resolve_txt(&self, name: &str) -> Result<Vec<String>, Error> {
+        match self.records.get(name) {
+            Some(txts) => Ok(txts.clone()),
+            None => bail!("no TXT record of {}", name),
+        }
+    }
+}
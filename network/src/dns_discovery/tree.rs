@@ -0,0 +1,371 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Entries of a node tree, and building of signed trees.
+//!
+//! The TXT record of the root entry is published at the domain of the tree:
+//!
+//! `enrtree-root:v1 e=<node root> l=<link root> seq=<seq> sig=<signature>`
+//!
+//! where the signature is a base64 encoded secp256k1 signature of the keccak
+//! hash of the text before ` sig=`. Every other entry is published at the
+//! subdomain named by its hash, which is the base32 encoding of the first 16
+//! bytes of the keccak hash of its text. Entries are either:
+//! - branches, `enrtree-branch:<hash>,<hash>,...`;
+//! - links to other trees, `enrtree://<base32 public key>@<domain>`;
+//! - node URLs, `cfxnode://<node id>@<ip>:<port>`.
+
+use crate::{
+    node_table::{Node, NodeEntry, NodeId},
+    Error,
+};
+use data_encoding::{BASE32_NOPAD, BASE64URL_NOPAD};
+use keccak_hash::keccak;
+use keylib::{recover, sign, Secret, Signature};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+const ROOT_PREFIX: &str = "enrtree-root:v1";
+const BRANCH_PREFIX: &str = "enrtree-branch:";
+const LINK_PREFIX: &str = "enrtree://";
+const NODE_PREFIX: &str = "cfxnode://";
+
+/// Branches have at most this many children, so that their TXT records fit
+/// in a DNS response over UDP.
+const MAX_CHILDREN: usize = 13;
+
+/// The subdomain of the entry with the specified text.
+pub fn entry_hash(text: &str) -> String {
+    BASE32_NOPAD.encode(&keccak(text.as_bytes())[..16])
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootEntry {
+    /// Hash of the root of the subtree of node URLs.
+    pub node_root: String,
+    /// Hash of the root of the subtree of links to other trees.
+    pub link_root: String,
+    /// Sequence number, increased on every update of the tree.
+    pub seq: u64,
+    pub signature: Signature,
+}
+
+impl RootEntry {
+    fn signed_text(&self) -> String {
+        format!(
+            "{} e={} l={} seq={}",
+            ROOT_PREFIX, self.node_root, self.link_root, self.seq
+        )
+    }
+
+    /// Check if the root is signed by the specified key.
+    pub fn verify(&self, public: &NodeId) -> bool {
+        match recover(&self.signature, &keccak(self.signed_text())) {
+            Ok(signer) => signer == *public,
+            Err(_) => false,
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self, Error> {
+        let mut node_root = None;
+        let mut link_root = None;
+        let mut seq = None;
+        let mut signature = None;
+
+        for field in s.split_whitespace().skip(1) {
+            let (key, value) = match field.find('=') {
+                Some(pos) => (&field[..pos], &field[pos + 1..]),
+                None => bail!("invalid field {:?} of tree root", field),
+            };
+            match key {
+                "e" => node_root = Some(value.to_string()),
+                "l" => link_root = Some(value.to_string()),
+                "seq" => seq = value.parse().ok(),
+                "sig" => {
+                    signature = BASE64URL_NOPAD
+                        .decode(value.as_bytes())
+                        .ok()
+                        .filter(|sig| sig.len() == 65)
+                        .map(|sig| {
+                            let mut bytes = [0u8; 65];
+                            bytes.copy_from_slice(&sig);
+                            Signature::from(bytes)
+                        })
+                }
+                _ => {}
+            }
+        }
+
+        match (node_root, link_root, seq, signature) {
+            (Some(node_root), Some(link_root), Some(seq), Some(signature)) => {
+                Ok(RootEntry {
+                    node_root,
+                    link_root,
+                    seq,
+                    signature,
+                })
+            }
+            _ => bail!("invalid tree root {:?}", s),
+        }
+    }
+}
+
+impl Display for RootEntry {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} sig={}",
+            self.signed_text(),
+            BASE64URL_NOPAD.encode(&self.signature[..])
+        )
+    }
+}
+
+/// Link to the tree at `domain`, signed by `public`. The URL of a tree to
+/// resolve is also a link.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkEntry {
+    pub public: NodeId,
+    pub domain: String,
+}
+
+impl Display for LinkEntry {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}@{}",
+            LINK_PREFIX,
+            BASE32_NOPAD.encode(self.public.as_bytes()),
+            self.domain
+        )
+    }
+}
+
+impl FromStr for LinkEntry {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.starts_with(LINK_PREFIX) {
+            bail!("invalid tree link {:?}", s);
+        }
+
+        let mut parts = s[LINK_PREFIX.len()..].splitn(2, '@');
+        let key = parts.next().unwrap_or_default();
+        let domain = match parts.next() {
+            Some(domain) if !domain.is_empty() => domain,
+            _ => bail!("missing domain of tree link {:?}", s),
+        };
+        let public = match BASE32_NOPAD.decode(key.as_bytes()) {
+            Ok(ref key) if key.len() == NodeId::len_bytes() => {
+                NodeId::from_slice(key)
+            }
+            _ => bail!("invalid public key of tree link {:?}", s),
+        };
+
+        Ok(LinkEntry {
+            public,
+            domain: domain.to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Entry {
+    Root(RootEntry),
+    Branch(Vec<String>),
+    Link(LinkEntry),
+    Node(NodeEntry),
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Entry::Root(root) => write!(f, "{}", root),
+            Entry::Branch(children) => {
+                write!(f, "{}{}", BRANCH_PREFIX, children.join(","))
+            }
+            Entry::Link(link) => write!(f, "{}", link),
+            Entry::Node(node) => {
+                write!(f, "{}", Node::new(node.id, node.endpoint.clone()))
+            }
+        }
+    }
+}
+
+impl FromStr for Entry {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with(ROOT_PREFIX) {
+            Ok(Entry::Root(RootEntry::parse(s)?))
+        } else if s.starts_with(BRANCH_PREFIX) {
+            let children = s[BRANCH_PREFIX.len()..]
+                .split(',')
+                .filter(|hash| !hash.is_empty())
+                .map(str::to_string)
+                .collect();
+            Ok(Entry::Branch(children))
+        } else if s.starts_with(LINK_PREFIX) {
+            Ok(Entry::Link(s.parse()?))
+        } else if s.starts_with(NODE_PREFIX) {
+            let node = Node::from_str(s)?;
+            Ok(Entry::Node(NodeEntry {
+                id: node.id,
+                endpoint: node.endpoint,
+            }))
+        } else {
+            bail!("unknown tree entry {:?}", s)
+        }
+    }
+}
+
+/// A signed tree of node URLs and links to other trees.
+pub struct Tree {
+    root: RootEntry,
+    /// Texts of the entries other than the root, by hash.
+    entries: BTreeMap<String, String>,
+}
+
+impl Tree {
+    pub fn sign(
+        nodes: &[NodeEntry], links: &[LinkEntry], seq: u64, secret: &Secret,
+    ) -> Result<Tree, Error> {
+        let mut entries = BTreeMap::new();
+        let node_root = Self::build_subtree(
+            nodes.iter().map(|n| Entry::Node(n.clone()).to_string()),
+            &mut entries,
+        );
+        let link_root = Self::build_subtree(
+            links.iter().map(|l| l.to_string()),
+            &mut entries,
+        );
+
+        let mut root = RootEntry {
+            node_root,
+            link_root,
+            seq,
+            signature: Signature::default(),
+        };
+        root.signature = sign(secret, &keccak(root.signed_text()))
+            .map_err(|e| format!("failed to sign tree root: {}", e))?;
+
+        Ok(Tree { root, entries })
+    }
+
+    /// Add the leaves to `entries` under branches of at most `MAX_CHILDREN`
+    /// children, and return the hash of the subtree root.
+    fn build_subtree<I>(
+        leaves: I, entries: &mut BTreeMap<String, String>,
+    ) -> String
+    where I: Iterator<Item = String> {
+        let mut add = |text: String| {
+            let hash = entry_hash(&text);
+            entries.insert(hash.clone(), text);
+            hash
+        };
+
+        let mut level: Vec<String> = leaves.map(&mut add).collect();
+        if level.len() == 1 {
+            return level.pop().expect("one leaf");
+        }
+        while level.len() > MAX_CHILDREN {
+            level = level
+                .chunks(MAX_CHILDREN)
+                .map(|children| {
+                    add(Entry::Branch(children.to_vec()).to_string())
+                })
+                .collect();
+        }
+        add(Entry::Branch(level).to_string())
+    }
+
+    pub fn root(&self) -> &RootEntry { &self.root }
+
+    /// The TXT records to publish the tree at `domain`, by domain name.
+    pub fn txt_records(&self, domain: &str) -> BTreeMap<String, String> {
+        let mut records: BTreeMap<String, String> = self
+            .entries
+            .iter()
+            .map(|(hash, text)| (format!("{}.{}", hash, domain), text.clone()))
+            .collect();
+        records.insert(domain.to_string(), self.root.to_string());
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keylib::{Generator, Random};
+
+    #[test]
+    fn test_parse_entries() {
+        let key = Random.generate().unwrap();
+        let link = LinkEntry {
+            public: *key.public(),
+            domain: "nodes.example.org".into(),
+        };
+        let parsed: LinkEntry = link.to_string().parse().unwrap();
+        assert_eq!(parsed, link);
+        assert!(LinkEntry::from_str("enrtree://AAAA@example.org").is_err());
+        assert!(LinkEntry::from_str("enrtree://nodes.example.org").is_err());
+
+        match "enrtree-branch:AAAA,BBBB".parse::<Entry>().unwrap() {
+            Entry::Branch(children) => {
+                assert_eq!(children, vec!["AAAA", "BBBB"])
+            }
+            e => panic!("unexpected entry {:?}", e),
+        }
+        match "enrtree-branch:".parse::<Entry>().unwrap() {
+            Entry::Branch(children) => assert!(children.is_empty()),
+            e => panic!("unexpected entry {:?}", e),
+        }
+        assert!("enr:-abcd".parse::<Entry>().is_err());
+        assert!("enrtree-root:v1 e=AAAA l=BBBB".parse::<Entry>().is_err());
+    }
+
+    #[test]
+    fn test_sign() {
+        let key = Random.generate().unwrap();
+        let nodes: Vec<NodeEntry> = (0..30)
+            .map(|i| {
+                let node = Node::from_str(&format!(
+                    "cfxnode://{:x}@10.0.0.{}:32323",
+                    NodeId::random(),
+                    i
+                ))
+                .unwrap();
+                NodeEntry {
+                    id: node.id,
+                    endpoint: node.endpoint,
+                }
+            })
+            .collect();
+        let tree = Tree::sign(&nodes, &[], 3, key.secret()).unwrap();
+        assert!(tree.root().verify(key.public()));
+        assert!(!tree.root().verify(&NodeId::random()));
+
+        let records = tree.txt_records("nodes.example.org");
+        let root: Entry = records["nodes.example.org"].parse().unwrap();
+        match root {
+            Entry::Root(root) => {
+                assert_eq!(&root, tree.root());
+                assert_eq!(root.seq, 3);
+            }
+            e => panic!("unexpected entry {:?}", e),
+        }
+
+        // 30 nodes, 3 branches of nodes, the branch of branches and the
+        // empty branch of links
+        assert_eq!(records.len(), 1 + 30 + 3 + 1 + 1);
+        for (name, text) in &records {
+            if name != "nodes.example.org" {
+                assert!(name.starts_with(&entry_hash(text)));
+            }
+        }
+    }
+}
@@ -14,6 +14,7 @@ pub enum DisconnectReason {
     IpLimited,
     UpdateNodeIdFailed,
     Blacklisted,
+    NotReserved,
     Custom(String),
     Unknown,
 }
@@ -27,6 +28,7 @@ impl DisconnectReason {
             DisconnectReason::IpLimited => 3,
             DisconnectReason::UpdateNodeIdFailed => 4,
             DisconnectReason::Blacklisted => 5,
+            DisconnectReason::NotReserved => 6,
             DisconnectReason::Custom(_) => 100,
             DisconnectReason::Unknown => 0xff,
         }
@@ -60,6 +62,7 @@ impl Decodable for DisconnectReason {
             3 => Ok(DisconnectReason::IpLimited),
             4 => Ok(DisconnectReason::UpdateNodeIdFailed),
             5 => Ok(DisconnectReason::Blacklisted),
+            6 => Ok(DisconnectReason::NotReserved),
             100 => match std::str::from_utf8(&raw[1..]) {
                 Err(_) => {
                     Err(DecoderError::Custom("Unable to decode message part"))
@@ -80,6 +83,7 @@ impl fmt::Display for DisconnectReason {
             DisconnectReason::IpLimited => "IP limited",
             DisconnectReason::UpdateNodeIdFailed => "Update node id failed",
             DisconnectReason::Blacklisted => "blacklisted",
+            DisconnectReason::NotReserved => "not a reserved peer",
             DisconnectReason::Custom(ref msg) => &msg[..],
             DisconnectReason::Unknown => "unknown",
         };
@@ -224,6 +228,12 @@ impl From<keylib::crypto::Error> for Error {
     fn from(_err: keylib::crypto::Error) -> Self { ErrorKind::Auth.into() }
 }
 
+impl From<parity_crypto::error::SymmError> for Error {
+    fn from(_err: parity_crypto::error::SymmError) -> Self {
+        ErrorKind::Auth.into()
+    }
+}
+
 impl From<net::AddrParseError> for Error {
     fn from(_err: net::AddrParseError) -> Self { ErrorKind::BadAddr.into() }
 }
@@ -24,6 +24,7 @@ use crate::{
 };
 use cfx_types::{Public, H256};
 use io::{IoContext, StreamToken};
+use keccak_hash::keccak;
 use keylib::{crypto::ecies, Secret};
 use mio::tcp::TcpStream;
 use priority_send_queue::SendQueuePriority;
@@ -64,6 +65,10 @@ pub struct Handshake {
     state: HandshakeState,
     /// nonce for verification
     nonce: H256,
+    /// nonce of the remote node, known once its auth or ack of auth is read
+    remote_nonce: Option<H256>,
+    /// Whether this node started the handshake
+    originated: bool,
 }
 
 impl Handshake {
@@ -76,6 +81,8 @@ impl Handshake {
             connection: Connection::new(token, socket),
             state: HandshakeState::New,
             nonce: H256::random(),
+            remote_nonce: None,
+            originated: id.is_some(),
         }
     }
 
@@ -98,6 +105,25 @@ impl Handshake {
     /// Check if handshake is complete
     pub fn done(&self) -> bool { self.state == HandshakeState::StartSession }
 
+    /// The secret shared with the remote node after the handshake completes,
+    /// derived from the nonces of both nodes. Only the two nodes know the
+    /// nonces, as they are always sent encrypted, so the secret authenticates
+    /// the messages exchanged to set up the session cipher. Returns `None` if
+    /// the cryptography is bypassed.
+    pub fn handshake_secret(&self) -> Option<H256> {
+        let remote_nonce = self.remote_nonce?;
+        let (initiator_nonce, recipient_nonce) = if self.originated {
+            (self.nonce, remote_nonce)
+        } else {
+            (remote_nonce, self.nonce)
+        };
+
+        let mut nonces = Vec::with_capacity(2 * H256::len_bytes());
+        nonces.extend_from_slice(initiator_nonce.as_bytes());
+        nonces.extend_from_slice(recipient_nonce.as_bytes());
+        Some(keccak(&nonces))
+    }
+
     /// Readable IO handler. Drives the state change.
     pub fn readable<Message>(
         &mut self, io: &IoContext<Message>, host: &HostMetadata,
@@ -189,6 +215,7 @@ impl Handshake {
 
         let (remote_public, remote_nonce) = auth.split_at(NodeId::len_bytes());
         self.id.assign_from_slice(remote_public);
+        self.remote_nonce = Some(H256::from_slice(remote_nonce));
 
         self.write_ack_of_auth(io, remote_nonce)
     }
@@ -264,6 +291,7 @@ impl Handshake {
             debug!("failed to read ack of auth, nonce mismatch");
             return Err(ErrorKind::BadProtocol.into());
         }
+        self.remote_nonce = Some(H256::from_slice(remote_nonce));
 
         self.write_ack_of_ack(io, remote_nonce)
     }
@@ -20,26 +20,25 @@
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-use crate::{node_table::NodeEndpoint, NatType};
+use crate::{
+    nat_pmp::{Mapping, MappingProtocol, NatPmpClient, NatPmpLease},
+    node_table::NodeEndpoint,
+    NatType,
+};
 use igd::{search_gateway, PortMappingProtocol, SearchOptions};
 use ipnetwork::IpNetwork;
-use natpmp::{Natpmp, Protocol, Response};
 use std::{
     io,
     net::{
         IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6,
         TcpStream,
     },
-    thread,
     time::Duration,
 };
 
-const NAT_PMP_PORT_MAPPING_LIFETIME: u32 = 3600 * 24;
-// Waiting duration in milliseconds for response from router after sending port
-// mapping request. 50 milliseconds might be enough for low RTT.
-const NAT_PMP_PORT_MAPPING_WAITING_DURATION: u64 = 50;
-const NAT_PMP_PORT_MAPPING_MAX_RETRY: u64 = 5;
-const NAT_PMP_PORT_MAPPING_RETRY_WAITING_DURATION_MS: u64 = 250;
+/// The lifetime in seconds requested for NAT-PMP mappings, which are renewed
+/// at half of the lifetime granted.
+const NAT_PMP_PORT_MAPPING_LIFETIME: u32 = 7200;
 
 /// Socket address extension for rustc beta. To be replaces with now unstable
 /// API
@@ -410,118 +409,77 @@ fn search_upnp(local: &NodeEndpoint) -> Option<NodeEndpoint> {
     None
 }
 
-fn search_natpmp(local: &NodeEndpoint) -> Option<NodeEndpoint> {
+fn search_natpmp(local: &NodeEndpoint) -> Option<(NodeEndpoint, NatPmpLease)> {
     if let SocketAddr::V4(ref local_addr) = local.address {
         let local_port = local_addr.port();
         let local_udp_port = local.udp_port;
 
-        let search_gateway_child = ::std::thread::spawn(move || {
-            let mut n = Natpmp::new()?;
-
-            // this function call want to receive `Response::Gateway` response
-            // from router, if other then it is an Error.
-            n.send_public_address_request()?;
-            ::std::thread::sleep(Duration::from_millis(
-                NAT_PMP_PORT_MAPPING_WAITING_DURATION,
-            ));
-            let mut retry = NAT_PMP_PORT_MAPPING_MAX_RETRY;
-            let gw = loop {
-                break match n.read_response_or_retry() {
-                    Ok(Response::Gateway(gw)) => Ok(gw),
-                    Err(natpmp::Error::NATPMP_TRYAGAIN) => {
-                        if retry == 0 {
-                            debug!("natpmp retry timeout");
-                            return Err(
-                                natpmp::Error::NATPMP_ERR_UNDEFINEDERROR.into(),
-                            );
-                        } else {
-                            retry -= 1;
-                            thread::sleep(Duration::from_millis(
-                                NAT_PMP_PORT_MAPPING_RETRY_WAITING_DURATION_MS,
-                            ));
-                            continue;
-                        }
-                    }
-                    Err(e) => {
-                        debug!("IP request error: {}", e);
-                        Err(e)
-                    }
-                    _ => Err(natpmp::Error::NATPMP_ERR_UNDEFINEDERROR.into()),
-                }?;
-            };
-
-            // this function call want to receive `Response::TCP` response from
-            // router, if other then it is an Error.
-            n.send_port_mapping_request(
-                Protocol::TCP,
+        let map = || -> io::Result<(NatPmpClient, Mapping, Mapping)> {
+            let mut client = NatPmpClient::new()?;
+            let tcp = client.map(
+                MappingProtocol::Tcp,
                 local_port,
                 local_port,
                 NAT_PMP_PORT_MAPPING_LIFETIME,
             )?;
-            ::std::thread::sleep(Duration::from_millis(
-                NAT_PMP_PORT_MAPPING_WAITING_DURATION,
-            ));
-            let tcp_r = match n.read_response_or_retry() {
-                Ok(Response::TCP(tcp)) => Ok(tcp),
-                Err(e) => {
-                    debug!("Port mapping for TCP error: {}", e);
-                    Err(e)
-                }
-                _ => Err(natpmp::Error::NATPMP_ERR_UNDEFINEDERROR.into()),
-            }?;
-
-            // this function call want to receive `Response::UDP` response from
-            // router, if other then it is an Error.
-            n.send_port_mapping_request(
-                Protocol::UDP,
+            let udp = match client.map(
+                MappingProtocol::Udp,
                 local_udp_port,
                 local_udp_port,
                 NAT_PMP_PORT_MAPPING_LIFETIME,
-            )?;
-            ::std::thread::sleep(Duration::from_millis(
-                NAT_PMP_PORT_MAPPING_WAITING_DURATION,
-            ));
-            let udp_r = match n.read_response_or_retry() {
-                Ok(Response::UDP(udp)) => Ok(udp),
+            ) {
+                Ok(udp) => udp,
                 Err(e) => {
-                    debug!("Port mapping for UDP error: {}", e);
-                    Err(e)
+                    let _ = client.unmap(&tcp);
+                    return Err(e);
                 }
-                _ => Err(natpmp::Error::NATPMP_ERR_UNDEFINEDERROR.into()),
-            }?;
-
-            Ok(NodeEndpoint {
-                address: SocketAddr::V4(SocketAddrV4::new(
-                    *gw.public_address(),
-                    tcp_r.public_port(),
-                )),
-                udp_port: udp_r.public_port(),
-            })
-        });
+            };
+            Ok((client, tcp, udp))
+        };
 
-        return search_gateway_child
-            .join()
-            .ok()?
-            .map_err(|e: natpmp::Error| {
-                debug!("NAT PMP port mapping error: {:?}", e)
-            })
-            .ok();
+        return match map() {
+            Ok((client, tcp, udp)) => {
+                debug!(
+                    "NAT-PMP gets tcp port: {}, udp port: {}",
+                    tcp.external.port(),
+                    udp.external.port()
+                );
+                let endpoint = NodeEndpoint {
+                    address: SocketAddr::V4(tcp.external),
+                    udp_port: udp.external.port(),
+                };
+                let lease = NatPmpLease::start(
+                    client,
+                    vec![tcp, udp],
+                    NAT_PMP_PORT_MAPPING_LIFETIME,
+                );
+                Some((endpoint, lease))
+            }
+            Err(e) => {
+                debug!("NAT-PMP port mapping error: {}", e);
+                None
+            }
+        };
     }
     None
 }
 
-/// Port mapping using ether UPnP or Nat-PMP.
-/// NAT PMP has higher priority than UPnP.
+/// Port mapping using ether UPnP or NAT-PMP/PCP.
+/// UPnP has higher priority than NAT-PMP. NAT-PMP mappings are leased, and
+/// are kept alive until the returned lease is dropped.
 pub fn map_external_address(
     local: &NodeEndpoint, nat_type: &NatType,
-) -> Option<NodeEndpoint> {
+) -> Option<(NodeEndpoint, Option<NatPmpLease>)> {
+    let natpmp = || {
+        search_natpmp(local).map(|(endpoint, lease)| (endpoint, Some(lease)))
+    };
     match *nat_type {
         NatType::Any => match search_upnp(local) {
-            Some(end_point) => Some(end_point),
-            None => search_natpmp(local),
+            Some(end_point) => Some((end_point, None)),
+            None => natpmp(),
         },
-        NatType::NatPMP => search_natpmp(local),
-        NatType::UPnP => search_upnp(local),
+        NatType::NatPMP => natpmp(),
+        NatType::UPnP => search_upnp(local).map(|end_point| (end_point, None)),
         _ => {
             trace!("Can't map external address using NAT");
             None
@@ -37,23 +37,32 @@ pub type ProtocolId = [u8; PROTOCOL_ID_SIZE];
 pub type HandlerWorkType = u8;
 pub type PeerId = usize;
 
+mod ban_list;
+mod compression;
 mod connection;
 mod discovery;
+pub mod dns_discovery;
 mod error;
 mod handshake;
 mod ip;
 mod ip_utils;
+mod nat_pmp;
 mod node_database;
 pub mod node_table;
+mod reputation;
+mod reserved_peers;
 pub mod service;
 mod session;
+mod session_cipher;
 mod session_manager;
 pub mod throttling;
 
 pub use crate::{
+    ban_list::{Ban, BanTarget},
     error::{DisconnectReason, Error, ErrorKind, ThrottlingReason},
     ip::SessionIpLimitConfig,
     node_table::Node,
+    reputation::{PeerEvent, PeerReputation, ReputationConfig},
     service::NetworkService,
     session::SessionDetails,
 };
@@ -101,6 +110,9 @@ pub struct NetworkConfiguration {
     /// Enable discovery
     pub discovery_enabled: bool,
     pub boot_nodes: Vec<String>,
+    /// URLs of the signed node lists to resolve from DNS, e.g.
+    /// `enrtree://<public key>@nodes.example.org`.
+    pub dns_discovery_urls: Vec<String>,
     /// Use provided node key instead of default
     pub use_secret: Option<Secret>,
     /// Maximum number of outgoing peers
@@ -115,6 +127,10 @@ pub struct NetworkConfiguration {
     pub max_handshakes: usize,
     /// List of reserved node addresses.
     pub reserved_nodes: Vec<String>,
+    /// File of reserved node addresses, which can be reloaded at runtime.
+    pub reserved_peers_file: Option<String>,
+    /// Only allow sessions with the reserved nodes.
+    pub reserved_only: bool,
     /// IP filter
     pub ip_filter: IpFilter,
     /// Timeout duration for initiating peer connection management
@@ -135,6 +151,17 @@ pub struct NetworkConfiguration {
     /// Maximum number of P2P nodes for subnet B (ip/16).
     pub subnet_quota: usize,
     pub session_ip_limit_config: SessionIpLimitConfig,
+    /// Encrypt and authenticate the packets of the sessions with peers that
+    /// support it.
+    pub encrypt_sessions: bool,
+    /// Disconnect the peers which do not encrypt the session, which requires
+    /// `encrypt_sessions`.
+    pub require_session_encryption: bool,
+    /// Compress the payloads of protocol packets sent to peers that support
+    /// it.
+    pub compression_enabled: bool,
+    /// Decay and thresholds of the reputation of peers.
+    pub reputation_config: ReputationConfig,
 }
 
 impl NetworkConfiguration {
@@ -149,12 +176,15 @@ impl NetworkConfiguration {
             nat_enabled: true,
             discovery_enabled: false,
             boot_nodes: Vec::new(),
+            dns_discovery_urls: Vec::new(),
             use_secret: None,
             max_outgoing_peers: 0,
             max_outgoing_peers_archive: 0,
             max_incoming_peers: 0,
             max_handshakes: 0,
             reserved_nodes: Vec::new(),
+            reserved_peers_file: None,
+            reserved_only: false,
             ip_filter: IpFilter::default(),
             housekeeping_timeout: DEFAULT_HOUSEKEEPING_TIMEOUT,
             discovery_refresh_timeout: DEFAULT_DISCOVERY_REFRESH_TIMEOUT,
@@ -167,6 +197,10 @@ impl NetworkConfiguration {
             test_mode: false,
             subnet_quota: 32,
             session_ip_limit_config: SessionIpLimitConfig::default(),
+            encrypt_sessions: false,
+            require_session_encryption: false,
+            compression_enabled: true,
+            reputation_config: ReputationConfig::default(),
         }
     }
 
@@ -276,6 +310,10 @@ pub trait NetworkContext {
         &self, node_id: &NodeId, op: Option<UpdateNodeOperation>, reason: &str,
     );
 
+    /// Report an event affecting the reputation of a peer, which may
+    /// throttle or ban the peer.
+    fn report_peer(&self, node_id: &NodeId, event: PeerEvent);
+
     /// Register a new IO timer. 'IoHandler::timeout' will be called with the
     /// token.
     fn register_timer(
@@ -0,0 +1,590 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Port mapping with NAT-PMP (RFC 6886) and its successor PCP (RFC 6887).
+//!
+//! The client first speaks PCP to the gateway, and falls back to NAT-PMP if
+//! the gateway replies that it does not support PCP, or does not reply at all.
+//! Mappings are leased for a limited lifetime, so `NatPmpLease` renews them in
+//! the background until it is dropped, and then removes them from the gateway.
+
+use rand::random;
+use std::{
+    cmp::max,
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The port of the NAT-PMP/PCP server on the gateway.
+pub const SERVER_PORT: u16 = 5351;
+
+const NAT_PMP_VERSION: u8 = 0;
+const PCP_VERSION: u8 = 2;
+const NAT_PMP_OPCODE_PUBLIC_ADDRESS: u8 = 0;
+const PCP_OPCODE_MAP: u8 = 1;
+const RESPONSE_FLAG: u8 = 0x80;
+const RESULT_SUCCESS: u16 = 0;
+const RESULT_UNSUPPORTED_VERSION: u16 = 1;
+
+const PCP_HEADER_SIZE: usize = 24;
+const PCP_MAP_SIZE: usize = PCP_HEADER_SIZE + 36;
+const NAT_PMP_PUBLIC_ADDRESS_RESPONSE_SIZE: usize = 12;
+const NAT_PMP_MAP_SIZE: usize = 12;
+const NAT_PMP_MAP_RESPONSE_SIZE: usize = 16;
+
+/// Requests are retransmitted with the timeout doubled each time, as
+/// recommended by both RFCs.
+const INITIAL_TIMEOUT: Duration = Duration::from_millis(250);
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Mappings are not renewed more often than this, even if the gateway grants
+/// a shorter lifetime.
+const MIN_RENEWAL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long to wait for the mappings to be removed when a lease is dropped.
+/// The removal goes on in the background after that.
+const REMOVAL_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MappingProtocol {
+    Tcp,
+    Udp,
+}
+
+impl MappingProtocol {
+    fn nat_pmp_opcode(self) -> u8 {
+        match self {
+            MappingProtocol::Udp => 1,
+            MappingProtocol::Tcp => 2,
+        }
+    }
+
+    fn ip_protocol(self) -> u8 {
+        match self {
+            MappingProtocol::Tcp => 6,
+            MappingProtocol::Udp => 17,
+        }
+    }
+}
+
+/// A port mapped on the gateway.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mapping {
+    pub protocol: MappingProtocol,
+    pub internal_port: u16,
+    pub external: SocketAddrV4,
+    pub lifetime: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Version {
+    Pcp,
+    NatPmp,
+}
+
+/// Client of the NAT-PMP/PCP server of a gateway.
+pub struct NatPmpClient {
+    socket: UdpSocket,
+    client_ip: Ipv4Addr,
+    version: Version,
+    /// Identifies the PCP mappings of this client.
+    nonce: [u8; 12],
+    /// The external address reported by a NAT-PMP gateway.
+    external_ip: Option<Ipv4Addr>,
+}
+
+impl NatPmpClient {
+    /// Create a client of the default gateway.
+    pub fn new() -> io::Result<Self> {
+        let gateway = natpmp::get_default_gateway().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("failed to get default gateway: {:?}", e),
+            )
+        })?;
+        Self::with_gateway(SocketAddr::V4(SocketAddrV4::new(
+            gateway,
+            SERVER_PORT,
+        )))
+    }
+
+    /// Create a client of the NAT-PMP/PCP server at `gateway`.
+    pub fn with_gateway(gateway: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.connect(gateway)?;
+        let client_ip = match socket.local_addr()?.ip() {
+            IpAddr::V4(ip) => ip,
+            IpAddr::V6(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "gateway should have an IPv4 address",
+                ))
+            }
+        };
+
+        Ok(NatPmpClient {
+            socket,
+            client_ip,
+            version: Version::Pcp,
+            nonce: random(),
+            external_ip: None,
+        })
+    }
+
+    /// Map `internal_port` to `external_port` on the gateway for `lifetime`
+    /// seconds. The gateway may map another external port.
+    pub fn map(
+        &mut self, protocol: MappingProtocol, internal_port: u16,
+        external_port: u16, lifetime: u32,
+    ) -> io::Result<Mapping>
+    {
+        if self.version == Version::Pcp {
+            match self.pcp_map(protocol, internal_port, external_port, lifetime)
+            {
+                Ok(Some(mapping)) => return Ok(mapping),
+                Ok(None) => {
+                    debug!(
+                        "PCP not supported by gateway, fall back to NAT-PMP"
+                    );
+                    self.version = Version::NatPmp;
+                }
+                // Some NAT-PMP gateways silently drop the requests of
+                // unknown versions.
+                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
+                    debug!("PCP request timed out, fall back to NAT-PMP");
+                    self.version = Version::NatPmp;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.nat_pmp_map(protocol, internal_port, external_port, lifetime)
+    }
+
+    /// Remove a mapping from the gateway.
+    pub fn unmap(&mut self, mapping: &Mapping) -> io::Result<()> {
+        self.map(mapping.protocol, mapping.internal_port, 0, 0)
+            .map(|_| ())
+    }
+
+    /// Returns `None` if the gateway does not support PCP.
+    fn pcp_map(
+        &self, protocol: MappingProtocol, internal_port: u16,
+        external_port: u16, lifetime: u32,
+    ) -> io::Result<Option<Mapping>>
+    {
+        let mut request = Vec::with_capacity(PCP_MAP_SIZE);
+        request.extend_from_slice(&[PCP_VERSION, PCP_OPCODE_MAP, 0, 0]);
+        request.extend_from_slice(&lifetime.to_be_bytes());
+        request.extend_from_slice(&self.client_ip.to_ipv6_mapped().octets());
+        request.extend_from_slice(&self.nonce);
+        request.extend_from_slice(&[protocol.ip_protocol(), 0, 0, 0]);
+        request.extend_from_slice(&internal_port.to_be_bytes());
+        request.extend_from_slice(&external_port.to_be_bytes());
+        request.extend_from_slice(
+            &Ipv4Addr::UNSPECIFIED.to_ipv6_mapped().octets(),
+        );
+
+        let response = self.request(&request, PCP_OPCODE_MAP)?;
+        if response[0] == NAT_PMP_VERSION {
+            return match read_u16(&response, 2) {
+                RESULT_UNSUPPORTED_VERSION => Ok(None),
+                result => Err(result_error(result)),
+            };
+        }
+
+        // The result code is a single byte in PCP.
+        let result = response[3] as u16;
+        if result == RESULT_UNSUPPORTED_VERSION {
+            return Ok(None);
+        } else if result != RESULT_SUCCESS {
+            return Err(result_error(result));
+        }
+        if response.len() < PCP_MAP_SIZE
+            || response[PCP_HEADER_SIZE..PCP_HEADER_SIZE + 12] != self.nonce
+        {
+            return Err(invalid_response());
+        }
+
+        let data = &response[PCP_HEADER_SIZE..];
+        let mut external_ip = [0; 4];
+        external_ip.copy_from_slice(&data[32..36]);
+        Ok(Some(Mapping {
+            protocol,
+            internal_port,
+            external: SocketAddrV4::new(external_ip.into(), read_u16(data, 18)),
+            lifetime: Duration::from_secs(read_u32(&response, 4) as u64),
+        }))
+    }
+
+    fn nat_pmp_map(
+        &mut self, protocol: MappingProtocol, internal_port: u16,
+        external_port: u16, lifetime: u32,
+    ) -> io::Result<Mapping>
+    {
+        let external_ip = match self.external_ip {
+            Some(ip) => ip,
+            None => {
+                let ip = self.nat_pmp_external_ip()?;
+                self.external_ip = Some(ip);
+                ip
+            }
+        };
+
+        let opcode = protocol.nat_pmp_opcode();
+        let mut request = Vec::with_capacity(NAT_PMP_MAP_SIZE);
+        request.extend_from_slice(&[NAT_PMP_VERSION, opcode, 0, 0]);
+        request.extend_from_slice(&internal_port.to_be_bytes());
+        request.extend_from_slice(&external_port.to_be_bytes());
+        request.extend_from_slice(&lifetime.to_be_bytes());
+
+        let response = self.nat_pmp_request(&request, opcode)?;
+        if response.len() < NAT_PMP_MAP_RESPONSE_SIZE
+            || read_u16(&response, 8) != internal_port
+        {
+            return Err(invalid_response());
+        }
+
+        Ok(Mapping {
+            protocol,
+            internal_port,
+            external: SocketAddrV4::new(external_ip, read_u16(&response, 10)),
+            lifetime: Duration::from_secs(read_u32(&response, 12) as u64),
+        })
+    }
+
+    fn nat_pmp_external_ip(&self) -> io::Result<Ipv4Addr> {
+        let opcode = NAT_PMP_OPCODE_PUBLIC_ADDRESS;
+        let response =
+            self.nat_pmp_request(&[NAT_PMP_VERSION, opcode], opcode)?;
+        if response.len() < NAT_PMP_PUBLIC_ADDRESS_RESPONSE_SIZE {
+            return Err(invalid_response());
+        }
+
+        let mut ip = [0; 4];
+        ip.copy_from_slice(&response[8..12]);
+        Ok(ip.into())
+    }
+
+    /// Send a NAT-PMP request and check the result code of the response.
+    fn nat_pmp_request(
+        &self, request: &[u8], opcode: u8,
+    ) -> io::Result<Vec<u8>> {
+        let response = self.request(request, opcode)?;
+        match read_u16(&response, 2) {
+            RESULT_SUCCESS => Ok(response),
+            result => Err(result_error(result)),
+        }
+    }
+
+    /// Send a request until a response to `opcode` is received.
+    fn request(&self, request: &[u8], opcode: u8) -> io::Result<Vec<u8>> {
+        let mut timeout = INITIAL_TIMEOUT;
+        for _ in 0..MAX_ATTEMPTS {
+            self.socket.send(request)?;
+            let deadline = Instant::now() + timeout;
+            loop {
+                let remaining =
+                    deadline.saturating_duration_since(Instant::now());
+                if remaining == Duration::from_secs(0) {
+                    break;
+                }
+                self.socket.set_read_timeout(Some(remaining))?;
+
+                let mut buf = [0u8; 1100];
+                match self.socket.recv(&mut buf) {
+                    // Ignore the responses to previous requests.
+                    Ok(len) if len >= 4 && buf[1] == opcode | RESPONSE_FLAG => {
+                        return Ok(buf[..len].to_vec());
+                    }
+                    Ok(_) => continue,
+                    Err(ref e)
+                        if e.kind() == io::ErrorKind::WouldBlock
+                            || e.kind() == io::ErrorKind::TimedOut =>
+                    {
+                        break
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            timeout *= 2;
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "no response from gateway",
+        ))
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    u16::from_be_bytes([data[pos], data[pos + 1]])
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&data[pos..pos + 4]);
+    u32::from_be_bytes(bytes)
+}
+
+fn result_error(result: u16) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        format!("gateway refused request, result code {}", result),
+    )
+}
+
+fn invalid_response() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid response from gateway")
+}
+
+/// Keeps renewing port mappings until dropped, then removes them.
+pub struct NatPmpLease {
+    stop: Option<Sender<()>>,
+    /// Disconnected when the renewal thread exits.
+    stopped: Receiver<()>,
+}
+
+impl NatPmpLease {
+    /// Renew `mappings` with `lifetime` seconds whenever half of the
+    /// shortest lifetime granted by the gateway has elapsed.
+    pub fn start(
+        mut client: NatPmpClient, mut mappings: Vec<Mapping>, lifetime: u32,
+    ) -> Self {
+        let (stop, stop_signal) = channel();
+        let (exit_signal, stopped) = channel::<()>();
+        thread::spawn(move || {
+            let _exit_signal = exit_signal;
+            loop {
+                let renew_after = mappings
+                    .iter()
+                    .map(|m| m.lifetime / 2)
+                    .min()
+                    .unwrap_or_else(|| Duration::from_secs(lifetime as u64));
+                let renew_after = max(renew_after, MIN_RENEWAL_INTERVAL);
+                match stop_signal.recv_timeout(renew_after) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => break,
+                }
+
+                for mapping in &mut mappings {
+                    match client.map(
+                        mapping.protocol,
+                        mapping.internal_port,
+                        mapping.external.port(),
+                        lifetime,
+                    ) {
+                        Ok(renewed) => {
+                            if renewed.external != mapping.external {
+                                warn!(
+                                    "NAT-PMP mapping of port {} moved from {} to {}",
+                                    mapping.internal_port,
+                                    mapping.external,
+                                    renewed.external
+                                );
+                            }
+                            *mapping = renewed;
+                        }
+                        Err(e) => warn!(
+                            "Failed to renew NAT-PMP mapping of port {}: {}",
+                            mapping.internal_port, e
+                        ),
+                    }
+                }
+            }
+
+            for mapping in &mappings {
+                if let Err(e) = client.unmap(mapping) {
+                    debug!(
+                        "Failed to remove NAT-PMP mapping of port {}: {}",
+                        mapping.internal_port, e
+                    );
+                }
+            }
+        });
+
+        NatPmpLease {
+            stop: Some(stop),
+            stopped,
+        }
+    }
+}
+
+impl Drop for NatPmpLease {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        // The renewal thread may be waiting for the gateway, so do not block
+        // for long.
+        if let Err(RecvTimeoutError::Timeout) =
+            self.stopped.recv_timeout(REMOVAL_TIMEOUT)
+        {
+            debug!("NAT-PMP mappings are still being removed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTERNAL_IP: [u8; 4] = [203, 0, 113, 7];
+
+    /// A fake gateway which only speaks NAT-PMP, maps every internal port to
+    /// the next port, and reports the requests it receives. PCP requests are
+    /// ignored unless `reply_to_pcp`.
+    fn nat_pmp_gateway(reply_to_pcp: bool) -> (SocketAddr, Receiver<Vec<u8>>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        let (requests, received) = channel();
+        thread::spawn(move || loop {
+            let mut buf = [0u8; 1100];
+            let (len, from) = socket.recv_from(&mut buf).unwrap();
+            let request = buf[..len].to_vec();
+            if request[0] != NAT_PMP_VERSION && !reply_to_pcp {
+                if requests.send(request).is_err() {
+                    break;
+                }
+                continue;
+            }
+            let mut response = vec![0, request[1] | RESPONSE_FLAG, 0, 0];
+            response.extend_from_slice(&[0, 0, 0, 1]);
+            if request[0] != NAT_PMP_VERSION {
+                response[2..4]
+                    .copy_from_slice(&RESULT_UNSUPPORTED_VERSION.to_be_bytes());
+            } else if request[1] == NAT_PMP_OPCODE_PUBLIC_ADDRESS {
+                response.extend_from_slice(&EXTERNAL_IP);
+            } else {
+                let internal_port = read_u16(&request, 4);
+                let lifetime = read_u32(&request, 8);
+                let external_port =
+                    if lifetime == 0 { 0 } else { internal_port + 1 };
+                response.extend_from_slice(&internal_port.to_be_bytes());
+                response.extend_from_slice(&external_port.to_be_bytes());
+                response.extend_from_slice(&lifetime.to_be_bytes());
+            }
+            socket.send_to(&response, from).unwrap();
+            if requests.send(request).is_err() {
+                break;
+            }
+        });
+        (address, received)
+    }
+
+    #[test]
+    fn test_fall_back_to_nat_pmp() {
+        let (gateway, requests) = nat_pmp_gateway(true);
+        let mut client = NatPmpClient::with_gateway(gateway).unwrap();
+
+        let mapping =
+            client.map(MappingProtocol::Tcp, 32323, 32323, 60).unwrap();
+        assert_eq!(
+            mapping,
+            Mapping {
+                protocol: MappingProtocol::Tcp,
+                internal_port: 32323,
+                external: SocketAddrV4::new(EXTERNAL_IP.into(), 32324),
+                lifetime: Duration::from_secs(60),
+            }
+        );
+        // PCP request, then NAT-PMP public address and mapping requests.
+        assert_eq!(requests.recv().unwrap()[0], PCP_VERSION);
+        assert_eq!(requests.recv().unwrap(), vec![0, 0]);
+        assert_eq!(requests.recv().unwrap()[..2], [0, 2]);
+
+        // Only NAT-PMP is used from now on.
+        client.unmap(&mapping).unwrap();
+        let request = requests.recv().unwrap();
+        assert_eq!(request[..2], [0, 2]);
+        assert_eq!(read_u32(&request, 8), 0);
+    }
+
+    #[test]
+    fn test_fall_back_to_nat_pmp_on_pcp_timeout() {
+        let (gateway, requests) = nat_pmp_gateway(false);
+        let mut client = NatPmpClient::with_gateway(gateway).unwrap();
+
+        let mapping =
+            client.map(MappingProtocol::Udp, 32323, 32323, 60).unwrap();
+        assert_eq!(
+            mapping.external,
+            SocketAddrV4::new(EXTERNAL_IP.into(), 32324)
+        );
+        // Retransmitted PCP requests, then NAT-PMP requests.
+        for _ in 0..MAX_ATTEMPTS {
+            assert_eq!(requests.recv().unwrap()[0], PCP_VERSION);
+        }
+        assert_eq!(requests.recv().unwrap(), vec![0, 0]);
+        assert_eq!(requests.recv().unwrap()[..2], [0, 1]);
+    }
+
+    #[test]
+    fn test_lease_removes_mappings_on_drop() {
+        let (gateway, requests) = nat_pmp_gateway(true);
+        let mut client = NatPmpClient::with_gateway(gateway).unwrap();
+        let mapping =
+            client.map(MappingProtocol::Udp, 32323, 32323, 60).unwrap();
+        while requests.try_recv().is_ok() {}
+
+        drop(NatPmpLease::start(client, vec![mapping], 60));
+        let request = requests.recv().unwrap();
+        assert_eq!(request[..2], [0, 1]);
+        assert_eq!(read_u16(&request, 4), 32323);
+        assert_eq!(read_u32(&request, 8), 0);
+    }
+
+    #[test]
+    fn test_lease_renews_mappings() {
+        let (gateway, requests) = nat_pmp_gateway(true);
+        let mut client = NatPmpClient::with_gateway(gateway).unwrap();
+        let mut mapping =
+            client.map(MappingProtocol::Tcp, 32323, 32323, 60).unwrap();
+        while requests.try_recv().is_ok() {}
+
+        mapping.lifetime = Duration::from_millis(100);
+        let lease = NatPmpLease::start(client, vec![mapping], 60);
+        let request = requests.recv().unwrap();
+        assert_eq!(request[..2], [0, 2]);
+        assert_eq!(read_u32(&request, 8), 60);
+        drop(lease);
+    }
+
+    #[test]
+    fn test_lease_with_zero_lifetime() {
+        let (gateway, requests) = nat_pmp_gateway(true);
+        let mut client = NatPmpClient::with_gateway(gateway).unwrap();
+        let mut mapping =
+            client.map(MappingProtocol::Tcp, 32323, 32323, 60).unwrap();
+        while requests.try_recv().is_ok() {}
+
+        // The gateway grants the renewals a lifetime of 0 as requested.
+        mapping.lifetime = Duration::from_secs(0);
+        let lease = NatPmpLease::start(client, vec![mapping], 0);
+        thread::sleep(MIN_RENEWAL_INTERVAL + MIN_RENEWAL_INTERVAL / 2);
+        assert_eq!(requests.try_iter().count(), 1);
+        drop(lease);
+    }
+
+    #[test]
+    fn test_lease_drop_does_not_wait_for_gateway() {
+        // A gateway which never replies.
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client =
+            NatPmpClient::with_gateway(socket.local_addr().unwrap()).unwrap();
+        let mapping = Mapping {
+            protocol: MappingProtocol::Tcp,
+            internal_port: 32323,
+            external: SocketAddrV4::new(EXTERNAL_IP.into(), 32323),
+            lifetime: Duration::from_secs(60),
+        };
+
+        let lease = NatPmpLease::start(client, vec![mapping], 60);
+        let start = Instant::now();
+        drop(lease);
+        assert!(start.elapsed() < REMOVAL_TIMEOUT + Duration::from_secs(1));
+    }
+}
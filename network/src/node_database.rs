@@ -3,6 +3,7 @@
 // See http://www.gnu.org/licenses/
 
 use crate::{
+    ban_list::{Ban, BanList, BanTarget},
     ip::{NodeIpLimit, NodeTagIndex, ValidateInsertResult},
     node_table::{Node, NodeContact, NodeEntry, NodeId, NodeTable},
     IpFilter,
@@ -76,6 +77,12 @@ const BLACKLISTED_NODES_FILE: &str = "blacklisted_nodes.json";
 /// following: 1. Select untrusted node prior to trusted node.
 /// 2. Select node that has been contacted long time ago.
 /// 3. Randomly select one without "fresher" bias.
+///
+/// # Bans
+///
+/// Nodes could be banned by node id or IP subnet, either manually via RPC or
+/// due to low reputation. Banned nodes are removed from database, and are
+/// refused to add again until the ban is lifted or expired.
 pub struct NodeDatabase {
     // Trusted nodes to establish outgoing connections.
     // A trusted node comes in 2 ways:
@@ -92,6 +99,9 @@ pub struct NodeDatabase {
     // Maximum duration to blacklist a node since last contact.
     blacklisted_lifetime: Duration,
 
+    // Banned node ids and subnets, persisted on every change.
+    ban_list: BanList,
+
     // IP address/subnet index for trusted and untrusted nodes.
     ip_limit: NodeIpLimit,
 
@@ -116,8 +126,12 @@ impl NodeDatabase {
         let mut db = NodeDatabase {
             trusted_nodes,
             untrusted_nodes,
-            blacklisted_nodes: NodeTable::new(path, BLACKLISTED_NODES_FILE),
+            blacklisted_nodes: NodeTable::new(
+                path.clone(),
+                BLACKLISTED_NODES_FILE,
+            ),
             blacklisted_lifetime: Duration::from_secs(7 * 24 * 3600),
+            ban_list: BanList::new(path),
             ip_limit,
             trusted_node_tag_index,
         };
@@ -133,7 +147,7 @@ impl NodeDatabase {
     pub fn insert_with_token(
         &mut self, entry: NodeEntry, stream_token: StreamToken,
     ) {
-        if self.evaluate_blacklisted(&entry.id) {
+        if self.is_refused(&entry) {
             return;
         }
 
@@ -187,7 +201,7 @@ impl NodeDatabase {
     /// node with the specified `entry`, and promote the node to trusted if it
     /// is untrusted.
     pub fn insert_with_conditional_promotion(&mut self, entry: NodeEntry) {
-        if self.evaluate_blacklisted(&entry.id) {
+        if self.is_refused(&entry) {
             return;
         }
 
@@ -245,7 +259,7 @@ impl NodeDatabase {
     /// Add a new trusted node if not exists, or promote the existing untrusted
     /// node.
     pub fn insert_trusted(&mut self, entry: NodeEntry) {
-        if self.evaluate_blacklisted(&entry.id) {
+        if self.is_refused(&entry) {
             return;
         }
 
@@ -606,12 +620,66 @@ impl NodeDatabase {
 
         true
     }
+
+    /// Check if the specified node is blacklisted or banned.
+    fn is_refused(&mut self, entry: &NodeEntry) -> bool {
+        self.evaluate_blacklisted(&entry.id)
+            || self
+                .ban_list
+                .is_banned(&entry.id, Some(&entry.endpoint.address.ip()))
+    }
+
+    /// Check if the specified node, or the subnet of `ip` if specified, is
+    /// banned.
+    pub fn is_banned(&mut self, id: &NodeId, ip: Option<&IpAddr>) -> bool {
+        self.ban_list.is_banned(id, ip)
+    }
+
+    /// Ban the specified node or subnet for `duration`, or permanently if
+    /// `duration` is `None`, and remove the banned nodes from database.
+    pub fn ban(
+        &mut self, target: BanTarget, duration: Option<Duration>,
+        reason: String,
+    )
+    {
+        let banned: Vec<NodeId> = match &target {
+            BanTarget::Node(id) => vec![*id],
+            BanTarget::Subnet(subnet) => self
+                .trusted_nodes
+                .all()
+                .into_iter()
+                .chain(self.untrusted_nodes.all())
+                .filter(|id| {
+                    self.get(id, false /* trusted_only */).map_or(false, |n| {
+                        subnet.contains(n.endpoint.address.ip())
+                    })
+                })
+                .collect(),
+        };
+        for id in banned {
+            self.remove(&id);
+        }
+
+        self.ban_list.ban(target, duration, reason);
+    }
+
+    /// Lift the ban of the specified node or subnet. Returns whether it was
+    /// banned.
+    pub fn unban(&mut self, target: &BanTarget) -> bool {
+        self.ban_list.unban(target)
+    }
+
+    /// Get all the bans in effect.
+    pub fn bans(&mut self) -> Vec<Ban> { self.ban_list.bans() }
 }
 
 #[cfg(test)]
 mod tests {
     use super::NodeDatabase;
-    use crate::node_table::{NodeEndpoint, NodeEntry, NodeId};
+    use crate::{
+        ban_list::BanTarget,
+        node_table::{NodeEndpoint, NodeEntry, NodeId},
+    };
     use std::{str::FromStr, time::Duration};
 
     fn new_entry(addr: &str) -> NodeEntry {
@@ -793,4 +861,34 @@ mod tests {
         assert_eq!(db.evaluate_blacklisted(&n.id), false);
         assert_eq!(db.get(&n.id, false), None);
     }
+
+    #[test]
+    fn test_banned() {
+        let mut db = NodeDatabase::new(None, 2);
+
+        let n1 = new_entry("127.0.0.1:999");
+        let n2 = new_entry("10.0.0.1:999");
+        db.insert_trusted(n1.clone());
+        db.insert_trusted(n2.clone());
+
+        // banned node is removed and refused to add again
+        db.ban(BanTarget::Node(n1.id), None, "test".into());
+        assert_eq!(db.get(&n1.id, false), None);
+        db.insert_trusted(n1.clone());
+        assert_eq!(db.get(&n1.id, false), None);
+
+        // nodes in banned subnet are removed and refused to add
+        let subnet = BanTarget::from_str("10.0.0.0/8").unwrap();
+        db.ban(subnet.clone(), None, "test".into());
+        assert_eq!(db.get(&n2.id, false), None);
+        let n3 = new_entry("10.1.0.1:999");
+        db.insert_with_token(n3.clone(), 3);
+        assert_eq!(db.get(&n3.id, false), None);
+
+        // unban subnet
+        assert!(db.unban(&subnet));
+        db.insert_with_token(n3.clone(), 3);
+        assert!(db.get(&n3.id, false).is_some());
+        assert_eq!(db.bans().len(), 1);
+    }
 }
@@ -0,0 +1,259 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Reputation of peers, driven by the events reported by protocol handlers.
+//!
+//! Every peer starts with a score of 0, and each reported misbehavior lowers
+//! it by the penalty of the event. The score decays back to 0 exponentially,
+//! so that occasional errors are forgiven while persistent misbehaviors add
+//! up. A peer whose score falls below the throttle threshold is demoted, so
+//! that it is not sampled for outgoing connections anymore, and a peer whose
+//! score falls below the ban threshold is disconnected and banned.
+
+use crate::node_table::NodeId;
+use cfx_types::H256;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+/// Scores closer to 0 than this are forgotten.
+const FORGIVEN_SCORE: f64 = 1.0;
+
+/// An event which affects the reputation of a peer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerEvent {
+    /// The peer sent the block with the given hash, which failed
+    /// verification. Each block is only penalized once per peer.
+    InvalidBlock(H256),
+    /// A request sent to the peer timed out.
+    Timeout,
+    /// The peer responded with items which were not requested, or which
+    /// were already received from other peers.
+    UselessResponse,
+}
+
+impl PeerEvent {
+    fn penalty(self) -> f64 {
+        match self {
+            PeerEvent::InvalidBlock(_) => 100.0,
+            PeerEvent::Timeout => 10.0,
+            PeerEvent::UselessResponse => 5.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReputationConfig {
+    /// The time for a score to decay to half of its value.
+    pub half_life: Duration,
+    /// Peers with a lower score are demoted.
+    pub throttle_threshold: f64,
+    /// Peers with a lower score are banned.
+    pub ban_threshold: f64,
+    /// How long a peer is banned for once its score falls below
+    /// `ban_threshold`.
+    pub ban_duration: Duration,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        ReputationConfig {
+            half_life: Duration::from_secs(600),
+            throttle_threshold: -100.0,
+            ban_threshold: -300.0,
+            ban_duration: Duration::from_secs(24 * 3600),
+        }
+    }
+}
+
+/// What to do with a peer after its score changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReputationAction {
+    None,
+    Throttle,
+    Ban,
+}
+
+/// The score of a peer, as reported via RPC.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerReputation {
+    pub node_id: NodeId,
+    pub score: f64,
+}
+
+struct Score {
+    value: f64,
+    updated: Instant,
+    /// The invalid blocks which are already penalized.
+    invalid_blocks: HashSet<H256>,
+}
+
+/// The scores of the peers with events reported recently.
+pub struct ReputationTable {
+    config: ReputationConfig,
+    scores: HashMap<NodeId, Score>,
+}
+
+impl ReputationTable {
+    pub fn new(config: ReputationConfig) -> Self {
+        ReputationTable {
+            config,
+            scores: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &ReputationConfig { &self.config }
+
+    fn decayed(&self, score: &Score, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(score.updated);
+        let half_lives =
+            elapsed.as_secs_f64() / self.config.half_life.as_secs_f64();
+        score.value * 0.5f64.powf(half_lives)
+    }
+
+    /// Apply the penalty of `event` to the score of `id`, and return what to
+    /// do with the peer given its new score.
+    pub fn report(
+        &mut self, id: &NodeId, event: PeerEvent,
+    ) -> ReputationAction {
+        let now = Instant::now();
+        let value = match self.scores.get(id) {
+            Some(score) => self.decayed(score, now),
+            None => 0.0,
+        };
+        let score = self.scores.entry(*id).or_insert_with(|| Score {
+            value,
+            updated: now,
+            invalid_blocks: HashSet::new(),
+        });
+        if let PeerEvent::InvalidBlock(hash) = event {
+            if !score.invalid_blocks.insert(hash) {
+                // The peer is already penalized for this block, e.g. when it
+                // is received again in a response to a later request.
+                return ReputationAction::None;
+            }
+        }
+        let value = value - event.penalty();
+        score.value = value;
+        score.updated = now;
+
+        if value < self.config.ban_threshold {
+            ReputationAction::Ban
+        } else if value < self.config.throttle_threshold {
+            ReputationAction::Throttle
+        } else {
+            ReputationAction::None
+        }
+    }
+
+    /// The current score of `id`.
+    pub fn score(&self, id: &NodeId) -> f64 {
+        match self.scores.get(id) {
+            Some(score) => self.decayed(score, Instant::now()),
+            None => 0.0,
+        }
+    }
+
+    /// The current scores of the peers with events reported recently.
+    pub fn scores(&self) -> Vec<PeerReputation> {
+        let now = Instant::now();
+        self.scores
+            .iter()
+            .map(|(id, score)| PeerReputation {
+                node_id: *id,
+                score: self.decayed(score, now),
+            })
+            .collect()
+    }
+
+    pub fn remove(&mut self, id: &NodeId) { self.scores.remove(id); }
+
+    /// Forget the peers whose score decayed back to almost 0.
+    pub fn clear_forgiven(&mut self) {
+        let now = Instant::now();
+        let forgiven: Vec<NodeId> = self
+            .scores
+            .iter()
+            .filter(|(_, score)| self.decayed(score, now) > -FORGIVEN_SCORE)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in forgiven {
+            self.scores.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+
+    #[test]
+    fn test_report() {
+        let mut table = ReputationTable::new(ReputationConfig::default());
+        let id = NodeId::random();
+
+        assert_eq!(
+            table.report(&id, PeerEvent::Timeout),
+            ReputationAction::None
+        );
+        assert_eq!(
+            table.report(&id, PeerEvent::InvalidBlock(H256::random())),
+            ReputationAction::Throttle
+        );
+        assert_eq!(
+            table.report(&id, PeerEvent::InvalidBlock(H256::random())),
+            ReputationAction::Throttle
+        );
+        assert_eq!(
+            table.report(&id, PeerEvent::InvalidBlock(H256::random())),
+            ReputationAction::Ban
+        );
+        assert!(table.score(&id) < -300.0);
+
+        // other peers are not affected
+        assert_eq!(table.score(&NodeId::random()), 0.0);
+    }
+
+    #[test]
+    fn test_report_invalid_block_once() {
+        let mut table = ReputationTable::new(ReputationConfig::default());
+        let id = NodeId::random();
+        let hash = H256::random();
+
+        table.report(&id, PeerEvent::InvalidBlock(hash));
+        let score = table.score(&id);
+        assert_eq!(
+            table.report(&id, PeerEvent::InvalidBlock(hash)),
+            ReputationAction::None
+        );
+        assert!(table.score(&id) >= score);
+
+        // the same block sent by another peer is penalized
+        let other = NodeId::random();
+        table.report(&other, PeerEvent::InvalidBlock(hash));
+        assert!(table.score(&other) < 0.0);
+    }
+
+    #[test]
+    fn test_decay() {
+        let mut table = ReputationTable::new(ReputationConfig {
+            half_life: Duration::from_millis(10),
+            ..Default::default()
+        });
+        let id = NodeId::random();
+
+        table.report(&id, PeerEvent::InvalidBlock(H256::random()));
+        sleep(Duration::from_millis(20));
+        let score = table.score(&id);
+        assert!(score > -50.0 && score < 0.0);
+
+        // the score decays to 0 and is forgotten eventually
+        sleep(Duration::from_millis(100));
+        table.clear_forgiven();
+        assert!(table.scores().is_empty());
+    }
+}
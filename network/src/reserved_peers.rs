@@ -0,0 +1,80 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! The reserved peers file lists the node URLs of the peers to always keep
+//! connected, one per line, e.g. `cfxnode://<node id>@127.0.0.1:32323`.
+//! Empty lines and lines starting with `#` are ignored.
+
+use crate::{
+    node_table::{Node, NodeEndpoint, NodeId},
+    Error, ErrorKind,
+};
+use std::{collections::HashMap, fs, io, path::Path, str::FromStr};
+
+/// Parse the node URLs of the reserved peers. Node URLs without node id are
+/// refused, since reserved peers are identified by their node id.
+pub fn parse_reserved_peers(
+    content: &str,
+) -> Result<HashMap<NodeId, NodeEndpoint>, Error> {
+    let mut peers = HashMap::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let node = Node::from_str(line)?;
+        if node.id.is_zero() {
+            bail!(ErrorKind::InvalidNodeId);
+        }
+        peers.insert(node.id, node.endpoint);
+    }
+    Ok(peers)
+}
+
+/// Load the reserved peers from `path`. A missing file is treated as empty,
+/// so that it could be created later and reloaded via RPC.
+pub fn load_reserved_peers(
+    path: &Path,
+) -> Result<HashMap<NodeId, NodeEndpoint>, Error> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            warn!("Reserved peers file {:?} not found, no peers loaded", path);
+            return Ok(HashMap::new());
+        }
+        Err(e) => {
+            bail!("failed to read reserved peers file {:?}: {}", path, e)
+        }
+    };
+    parse_reserved_peers(&content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reserved_peers() {
+        let id = NodeId::random();
+        let content = format!(
+            "# reserved peers\n\ncfxnode://{:x}@127.0.0.1:32323\n  \n",
+            id
+        );
+        let peers = parse_reserved_peers(&content).unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[&id].address.to_string(), "127.0.0.1:32323");
+
+        // node id is required
+        assert!(parse_reserved_peers("127.0.0.1:32323").is_err());
+        assert!(parse_reserved_peers("cfxnode://1234@127.0.0.1:32323").is_err());
+    }
+
+    #[test]
+    fn test_load_missing_reserved_peers() {
+        let path = std::env::temp_dir()
+            .join(format!("reserved_peers_{:x}", NodeId::random()));
+        assert!(load_reserved_peers(&path).unwrap().is_empty());
+    }
+}
@@ -4,13 +4,22 @@
 
 use super::DisconnectReason;
 use crate::{
+    ban_list::{Ban, BanTarget},
     discovery::{Discovery, DISCOVER_NODES_COUNT},
+    dns_discovery::{
+        DnsDiscovery, SystemResolver, DNS_DISCOVERY_REFRESH_TIMEOUT,
+    },
     handshake::BYPASS_CRYPTOGRAPHY,
     io::*,
     ip_utils::{map_external_address, select_public_address},
+    nat_pmp::NatPmpLease,
     node_database::NodeDatabase,
     node_table::*,
     parse_msg_id_leb128_2_bytes_at_most,
+    reputation::{
+        PeerEvent, PeerReputation, ReputationAction, ReputationTable,
+    },
+    reserved_peers::load_reserved_peers,
     session::{self, Session, SessionData, SessionDetails},
     session_manager::SessionManager,
     Error, ErrorKind, HandlerWorkType, IpFilter, NatType, NetworkConfiguration,
@@ -384,6 +393,52 @@ impl NetworkService {
         true
     }
 
+    /// Get the scores of the peers with events reported recently.
+    pub fn get_reputations(&self) -> Option<Vec<PeerReputation>> {
+        let inner = self.inner.as_ref()?;
+        let scores = inner.reputation.lock().scores();
+        Some(scores)
+    }
+
+    /// Get all the bans in effect.
+    pub fn get_bans(&self) -> Option<Vec<Ban>> {
+        let inner = self.inner.as_ref()?;
+        let bans = inner.node_db.write().bans();
+        Some(bans)
+    }
+
+    /// Ban the specified node or subnet for `duration`, or permanently if
+    /// `duration` is `None`, and disconnect the banned peers.
+    pub fn ban(&self, target: BanTarget, duration: Option<Duration>) -> bool {
+        if self.inner.is_none() || self.io_service.is_none() {
+            return false;
+        }
+        let inner = self.inner.as_ref().unwrap();
+        let io = IoContext::new(self.io_service.as_ref().unwrap().channel(), 0);
+        inner.ban(target, duration, "banned by RPC".into(), &io);
+        true
+    }
+
+    /// Reload the reserved peers file. Returns the number of reserved peers.
+    pub fn reload_reserved_peers(&self) -> Result<usize, Error> {
+        match (&self.inner, &self.io_service) {
+            (Some(inner), Some(io_service)) => {
+                let io = IoContext::new(io_service.channel(), 0);
+                inner.reload_reserved_nodes(&io)
+            }
+            _ => Err("Network service not started yet!".into()),
+        }
+    }
+
+    /// Lift the ban of the specified node or subnet. Returns whether it was
+    /// banned.
+    pub fn unban(&self, target: &BanTarget) -> bool {
+        match self.inner {
+            Some(ref inner) => inner.node_db.write().unban(target),
+            None => false,
+        }
+    }
+
     pub fn save_node_db(&self) {
         if let Some(inner) = &self.inner {
             inner.node_db.write().save();
@@ -391,6 +446,16 @@ impl NetworkService {
     }
 }
 
+impl Drop for NetworkService {
+    fn drop(&mut self) {
+        // The inner service may be still referenced by the IO handlers, so
+        // the port mappings are removed explicitly.
+        if let Some(inner) = &self.inner {
+            inner.remove_port_mappings();
+        }
+    }
+}
+
 type SharedSession = Arc<RwLock<Session>>;
 
 pub struct HostMetadata {
@@ -433,13 +498,25 @@ pub struct NetworkServiceInner {
     timers: RwLock<HashMap<TimerToken, ProtocolTimer>>,
     timer_counter: RwLock<usize>,
     pub node_db: RwLock<NodeDatabase>,
-    reserved_nodes: RwLock<HashSet<NodeId>>,
+    /// Nodes always kept connected, whether trusted or not.
+    reserved_nodes: RwLock<HashMap<NodeId, NodeEndpoint>>,
     dropped_nodes: RwLock<HashSet<NodeId>>,
 
     is_consortium: bool,
 
     /// Delayed message queue and corresponding latency
     delayed_queue: Option<DelayedQueue>,
+
+    /// Scores of the peers, driven by the events reported by protocol
+    /// handlers.
+    reputation: Mutex<ReputationTable>,
+
+    /// Renews the NAT-PMP port mappings of the public endpoint until they
+    /// are removed in `remove_port_mappings`.
+    nat_pmp_lease: Mutex<Option<NatPmpLease>>,
+
+    /// Resolves the node lists published via DNS.
+    dns_discovery: Mutex<Option<DnsDiscovery>>,
 }
 
 struct DelayedQueue {
@@ -547,6 +624,7 @@ impl NetworkServiceInner {
         let udp_socket =
             UdpSocket::bind(&udp_addr).expect("Error binding UDP socket");
 
+        let mut nat_pmp_lease = None;
         let public_address = config.public_address;
         let public_endpoint = match public_address {
             None => {
@@ -558,11 +636,12 @@ impl NetworkServiceInner {
                 };
                 if config.nat_enabled {
                     match map_external_address(&local_endpoint, &NatType::Any) {
-                        Some(endpoint) => {
+                        Some((endpoint, lease)) => {
                             info!(
                                 "NAT mapped to external address {}",
                                 endpoint.address
                             );
+                            nat_pmp_lease = lease;
                             endpoint
                         }
                         None => public_endpoint,
@@ -586,9 +665,25 @@ impl NetworkServiceInner {
             }
         };
 
+        let dns_discovery = if config.dns_discovery_urls.is_empty() {
+            None
+        } else {
+            match SystemResolver::new() {
+                Ok(resolver) => Some(DnsDiscovery::start(
+                    config.dns_discovery_urls.clone(),
+                    Box::new(resolver),
+                    DNS_DISCOVERY_REFRESH_TIMEOUT,
+                )),
+                Err(e) => {
+                    warn!("DNS discovery disabled: {}", e);
+                    None
+                }
+            }
+        };
+
         let nodes_path = config.config_path.clone();
 
-        let mut inner = NetworkServiceInner {
+        let inner = NetworkServiceInner {
             metadata: HostMetadata {
                 network_id: config.id,
                 keys,
@@ -616,22 +711,23 @@ impl NetworkServiceInner {
                 nodes_path,
                 config.subnet_quota,
             )),
-            reserved_nodes: RwLock::new(HashSet::new()),
+            reserved_nodes: RwLock::new(HashMap::new()),
             dropped_nodes: RwLock::new(HashSet::new()),
             is_consortium: config.is_consortium,
             delayed_queue: None,
+            reputation: Mutex::new(ReputationTable::new(
+                config.reputation_config.clone(),
+            )),
+            nat_pmp_lease: Mutex::new(nat_pmp_lease),
+            dns_discovery: Mutex::new(dns_discovery),
         };
 
         for n in &config.boot_nodes {
             inner.add_boot_node(n);
         }
 
-        let reserved_nodes = config.reserved_nodes.clone();
-        for n in reserved_nodes {
-            if let Err(e) = inner.add_reserved_node(&n) {
-                debug!("Error parsing node id: {}: {:?}", n, e);
-            }
-        }
+        let reserved_nodes = inner.load_reserved_nodes()?;
+        inner.set_reserved_nodes(reserved_nodes);
 
         Ok(inner)
     }
@@ -667,6 +763,15 @@ impl NetworkServiceInner {
 
     pub fn get_ip_filter(&self) -> &IpFilter { &self.config.ip_filter }
 
+    /// Stop renewing the NAT-PMP port mappings of the public endpoint, and
+    /// remove them from the gateway.
+    pub fn remove_port_mappings(&self) {
+        if let Some(lease) = self.nat_pmp_lease.lock().take() {
+            debug!("Removing NAT-PMP port mappings");
+            drop(lease);
+        }
+    }
+
     fn add_boot_node(&self, id: &str) {
         match Node::from_str(id) {
             Err(e) => {
@@ -681,14 +786,73 @@ impl NetworkServiceInner {
         }
     }
 
-    fn add_reserved_node(&mut self, id: &str) -> Result<(), Error> {
-        let n = Node::from_str(id)?;
-        self.node_db.write().insert_trusted(NodeEntry {
-            id: n.id.clone(),
-            endpoint: n.endpoint.clone(),
-        });
-        self.reserved_nodes.write().insert(n.id);
-        Ok(())
+    /// Load the reserved nodes from the configuration and the reserved peers
+    /// file.
+    fn load_reserved_nodes(
+        &self,
+    ) -> Result<HashMap<NodeId, NodeEndpoint>, Error> {
+        let mut nodes = HashMap::new();
+        for n in &self.config.reserved_nodes {
+            match Node::from_str(n) {
+                Ok(node) => {
+                    nodes.insert(node.id, node.endpoint);
+                }
+                Err(e) => debug!("Error parsing node id: {}: {:?}", n, e),
+            }
+        }
+
+        if let Some(ref path) = self.config.reserved_peers_file {
+            nodes.extend(load_reserved_peers(Path::new(path))?);
+        }
+
+        Ok(nodes)
+    }
+
+    /// Replace the reserved nodes, which are also added to the trusted node
+    /// table. In reserved only mode, sessions with other nodes are refused.
+    fn set_reserved_nodes(&self, nodes: HashMap<NodeId, NodeEndpoint>) {
+        {
+            let mut node_db = self.node_db.write();
+            for (id, endpoint) in &nodes {
+                node_db.insert_trusted(NodeEntry {
+                    id: *id,
+                    endpoint: endpoint.clone(),
+                });
+            }
+        }
+
+        if self.config.reserved_only {
+            self.sessions
+                .set_allowed_nodes(Some(nodes.keys().cloned().collect()));
+        }
+
+        *self.reserved_nodes.write() = nodes;
+    }
+
+    /// Reload the reserved nodes, and in reserved only mode, disconnect the
+    /// peers which are not reserved anymore.
+    fn reload_reserved_nodes(
+        &self, io: &IoContext<NetworkIoMessage>,
+    ) -> Result<usize, Error> {
+        let nodes = self.load_reserved_nodes()?;
+        let count = nodes.len();
+        self.set_reserved_nodes(nodes);
+        info!("Reserved peers reloaded, {} reserved peers", count);
+
+        if self.config.reserved_only {
+            let disallowed: Vec<NodeId> = self
+                .sessions
+                .all()
+                .iter()
+                .filter_map(|session| session.read().id().cloned())
+                .filter(|id| !self.sessions.is_node_allowed(id))
+                .collect();
+            for id in disallowed {
+                self.kill_connection(&id, io, true, None, "not reserved");
+            }
+        }
+
+        Ok(count)
     }
 
     fn initialize_udp_protocols(
@@ -774,6 +938,7 @@ impl NetworkServiceInner {
     }
 
     fn on_housekeeping(&self, io: &IoContext<NetworkIoMessage>) {
+        self.add_dns_discovered_nodes();
         if self.is_consortium {
             unimplemented!();
         } else {
@@ -782,6 +947,23 @@ impl NetworkServiceInner {
         self.drop_peers(io);
     }
 
+    /// Add the nodes resolved from DNS to the trusted node table, in the same
+    /// way as the boot nodes.
+    fn add_dns_discovered_nodes(&self) {
+        let nodes = match *self.dns_discovery.lock() {
+            Some(ref dns_discovery) => dns_discovery.take_nodes(),
+            None => return,
+        };
+        if nodes.is_empty() {
+            return;
+        }
+
+        let mut node_db = self.node_db.write();
+        for node in nodes {
+            node_db.insert_trusted(node);
+        }
+    }
+
     // Connect to all reserved and trusted peers if not yet
     fn connect_peers(&self, io: &IoContext<NetworkIoMessage>) {
         assert!(
@@ -791,46 +973,57 @@ impl NetworkServiceInner {
 
         let self_id = self.metadata.id().clone();
 
-        let sampled_archive_nodes = self.sample_archive_nodes();
-
         let (handshake_count, egress_count, ingress_count) =
             self.sessions.stat();
-        let samples;
-        {
-            let egress_attempt_count = if self.config.max_outgoing_peers
-                > egress_count + sampled_archive_nodes.len()
-            {
-                self.config.max_outgoing_peers
-                    - egress_count
-                    - sampled_archive_nodes.len()
-            } else {
-                0
-            };
-            samples = self.node_db.read().sample_trusted_node_ids(
-                egress_attempt_count as u32,
-                &self.config.ip_filter,
-            );
-        }
 
-        let reserved_nodes = self.reserved_nodes.read();
-        // Try to connect all reserved peers and trusted peers
-        let nodes = reserved_nodes
-            .iter()
+        // Reserved peers are always connected, regardless of the limits of
+        // outgoing connections and handshakes.
+        let reserved_nodes: Vec<NodeId> = self
+            .reserved_nodes
+            .read()
+            .keys()
+            .filter(|id| !self.sessions.contains_node(id) && **id != self_id)
             .cloned()
-            .chain(sampled_archive_nodes)
-            .chain(samples);
-
-        let max_handshakes_per_round = self.config.max_handshakes / 2;
-        let mut started: usize = 0;
-        for id in nodes
-            .filter(|id| !self.sessions.contains_node(id) && *id != self_id)
-            .take(min(
-                max_handshakes_per_round,
-                self.config.max_handshakes - handshake_count,
-            ))
-        {
-            self.connect_peer(&id, io);
-            started += 1;
+            .collect();
+        for id in &reserved_nodes {
+            self.connect_peer(id, io);
+        }
+        let mut started = reserved_nodes.len();
+
+        // Only reserved peers are connected in reserved only mode.
+        if !self.config.reserved_only {
+            let sampled_archive_nodes = self.sample_archive_nodes();
+            let samples;
+            {
+                let egress_attempt_count = if self.config.max_outgoing_peers
+                    > egress_count + sampled_archive_nodes.len()
+                {
+                    self.config.max_outgoing_peers
+                        - egress_count
+                        - sampled_archive_nodes.len()
+                } else {
+                    0
+                };
+                samples = self.node_db.read().sample_trusted_node_ids(
+                    egress_attempt_count as u32,
+                    &self.config.ip_filter,
+                );
+            }
+
+            // Try to connect trusted peers
+            let nodes = sampled_archive_nodes.into_iter().chain(samples);
+
+            let max_handshakes_per_round = self.config.max_handshakes / 2;
+            for id in nodes
+                .filter(|id| !self.sessions.contains_node(id) && *id != self_id)
+                .take(min(
+                    max_handshakes_per_round,
+                    self.config.max_handshakes - handshake_count,
+                ))
+            {
+                self.connect_peer(&id, io);
+                started += 1;
+            }
         }
         debug!(
             "Connecting peers: {} sessions, {} pending + {} started",
@@ -902,8 +1095,13 @@ impl NetworkServiceInner {
 
         let (socket, address) = {
             let address = {
-                // outgoing connection must pick node from trusted node table
-                if let Some(node) = self.node_db.read().get(id, true) {
+                // outgoing connection must pick node from reserved nodes or
+                // trusted node table
+                let reserved =
+                    self.reserved_nodes.read().get(id).map(|e| e.address);
+                if let Some(address) = reserved {
+                    address
+                } else if let Some(node) = self.node_db.read().get(id, true) {
                     node.endpoint.address
                 } else {
                     debug!("Abort connect. Node expired");
@@ -1350,6 +1548,75 @@ impl NetworkServiceInner {
         deregister
     }
 
+    /// Update the score of a peer with a reported event, and throttle or
+    /// ban the peer if its score is too low.
+    fn report_peer(
+        &self, node_id: &NodeId, event: PeerEvent,
+        io: &IoContext<NetworkIoMessage>,
+    )
+    {
+        let (action, ban_duration) = {
+            let mut reputation = self.reputation.lock();
+            let action = reputation.report(node_id, event);
+            if action == ReputationAction::Ban {
+                // The peer starts over once the ban expires.
+                reputation.remove(node_id);
+            }
+            (action, reputation.config().ban_duration)
+        };
+        match action {
+            ReputationAction::None => {}
+            ReputationAction::Throttle => {
+                debug!("Demote peer {:?} due to low reputation", node_id);
+                self.node_db.write().demote(node_id);
+            }
+            ReputationAction::Ban => {
+                info!("Ban peer {:?} due to low reputation", node_id);
+                self.ban(
+                    BanTarget::Node(*node_id),
+                    Some(ban_duration),
+                    format!("low reputation, last event {:?}", event),
+                    io,
+                );
+            }
+        }
+    }
+
+    /// Ban the specified node or subnet, and disconnect the banned peers.
+    fn ban(
+        &self, target: BanTarget, duration: Option<Duration>, reason: String,
+        io: &IoContext<NetworkIoMessage>,
+    )
+    {
+        self.node_db.write().ban(target, duration, reason);
+
+        // Do not lock the node database while locking sessions, which is the
+        // reverse order of handling the Hello packet.
+        let peers: Vec<(NodeId, SocketAddr)> = self
+            .sessions
+            .all()
+            .iter()
+            .filter_map(|session| {
+                let sess = session.read();
+                Some((*sess.id()?, sess.address()))
+            })
+            .collect();
+        let banned: Vec<NodeId> = {
+            let mut node_db = self.node_db.write();
+            peers
+                .into_iter()
+                .filter(|(id, address)| {
+                    node_db.is_banned(id, Some(&address.ip()))
+                })
+                .map(|(id, _)| id)
+                .collect()
+        };
+
+        for id in banned {
+            self.kill_connection(&id, io, true, None, "banned");
+        }
+    }
+
     pub fn with_context<F, R>(
         &self, handler: Arc<dyn NetworkProtocolHandler + Sync>,
         protocol: ProtocolId, io: &IoContext<NetworkIoMessage>, action: F,
@@ -1587,6 +1854,7 @@ impl IoHandler<NetworkIoMessage> for NetworkServiceInner {
                 trace!("Refreshing node table");
                 self.try_promote_untrusted();
                 self.node_db.write().save();
+                self.reputation.lock().clear_forgiven();
             }
             CHECK_SESSIONS => self.on_check_sessions(io),
             SEND_DELAYED_MESSAGES => {
@@ -1996,6 +2264,10 @@ impl<'a> NetworkContextTrait for NetworkContext<'a> {
             .kill_connection(node_id, self.io, true, op, reason);
     }
 
+    fn report_peer(&self, node_id: &NodeId, event: PeerEvent) {
+        self.network_service.report_peer(node_id, event, self.io);
+    }
+
     fn register_timer(
         &self, token: TimerToken, delay: Duration,
     ) -> Result<(), Error> {
@@ -3,16 +3,23 @@
 // See http://www.gnu.org/licenses/
 
 use crate::{
+    compression::{compress, decompress, COMPRESSION_VERSION},
     connection::{Connection, ConnectionDetails, SendQueueStatus, WriteStatus},
     handshake::Handshake,
     node_table::{NodeEndpoint, NodeEntry, NodeId},
     parse_msg_id_leb128_2_bytes_at_most,
     service::{NetworkServiceInner, ProtocolVersion},
+    session_cipher::{
+        hello_mac, session_secret, verify_hello_mac, SessionCipher,
+        SESSION_CIPHER_VERSION,
+    },
     DisconnectReason, Error, ErrorKind, ProtocolId, ProtocolInfo,
     SessionMetadata, UpdateNodeOperation, PROTOCOL_ID_SIZE,
 };
 use bytes::Bytes;
+use cfx_types::{Public, H256};
 use io::*;
+use keylib::{Generator, KeyPair, Random};
 use mio::{tcp::*, *};
 use priority_send_queue::SendQueuePriority;
 use rlp::{Rlp, RlpStream};
@@ -31,9 +38,14 @@ use std::{
 /// packet to exchange the supported protocols. Then, session is ready to send
 /// and receive protocol packets.
 ///
-/// Conflux do not use AES based encrypted connection to send protocol packets.
-/// This is because that Conflux has high TPS, and the encryption/decryption
-/// workloads are very heavy (about 20% CPU time in 3000 TPS).
+/// Peers also advertise the version of the session cipher in the HELLO packet,
+/// along with an ephemeral public key, and authenticate the packet with the
+/// secret derived from the handshake nonces. If both peers support it, all the
+/// packets after HELLO are encrypted and authenticated with keys derived from
+/// the ephemeral ECDH agreement. Otherwise, e.g. with peers of older versions
+/// or with encryption disabled in configuration, which is the default to save
+/// CPU time at high TPS, packets are sent in plaintext. Likewise, the
+/// payloads of protocol packets are compressed if both peers support it.
 pub struct Session {
     /// Session information
     pub metadata: SessionMetadata,
@@ -41,6 +53,15 @@ pub struct Session {
     address: SocketAddr,
     /// Session state
     state: State,
+    /// Secret shared with the remote peer after handshake, used to
+    /// authenticate the HELLO packets. It's dropped once HELLO is received.
+    handshake_secret: Option<H256>,
+    /// Ephemeral key pair advertised in the HELLO packet to agree on the
+    /// session cipher. It's dropped once HELLO is received.
+    ephemeral: Option<KeyPair>,
+    /// Whether to compress the payloads of protocol packets, agreed on in the
+    /// Hello packets.
+    compress: bool,
     /// Timestamp of when Hello packet sent, which is used to measure timeout.
     sent_hello: Instant,
     /// Session ready flag that set after successful Hello packet received.
@@ -92,6 +113,12 @@ pub const PACKET_USER: u8 = 0x10;
 pub const PACKET_HEADER_VERSION: u8 = 0;
 /// The header version where extension is introduced.
 const HEADER_VERSION_WITH_EXTENSION: u8 = 0;
+/// Flag in the header byte of packets whose data is compressed.
+const COMPRESSED_FLAG: u8 = 0x20;
+/// Index of the ephemeral public key of the session cipher in Hello packet.
+const HELLO_EPHEMERAL_PUBLIC_INDEX: usize = 5;
+/// Index of the mac of Hello packet, which signs all the items before it.
+const HELLO_MAC_INDEX: usize = 6;
 
 impl Session {
     /// Create a new instance of `Session`, which starts to handshake with
@@ -116,6 +143,9 @@ impl Session {
             },
             address,
             state: State::Handshake(MovableWrapper::new(handshake)),
+            handshake_secret: None,
+            ephemeral: None,
+            compress: false,
             sent_hello: Instant::now(),
             had_hello: None,
             expired: None,
@@ -192,8 +222,10 @@ impl Session {
 
     /// Complete the handshake process:
     /// 1. For incoming session, check if the remote peer is blacklisted.
-    /// 2. Change the session state to `State::Session`.
-    /// 3. Send Hello packet to remote peer.
+    /// 2. Check if the remote peer or its subnet is banned.
+    /// 3. In reserved only mode, check if the remote peer is reserved.
+    /// 4. Change the session state to `State::Session`.
+    /// 5. Send Hello packet to remote peer.
     fn complete_handshake<Message>(
         &mut self, io: &IoContext<Message>, host: &NetworkServiceInner,
     ) -> Result<(), Error>
@@ -215,8 +247,27 @@ impl Session {
             self.metadata.id = Some(id);
         }
 
+        // refuse session if the node or its subnet is banned
+        let id = self
+            .metadata
+            .id
+            .expect("should have node ID after handshake");
+        if host
+            .node_db
+            .write()
+            .is_banned(&id, Some(&self.address.ip()))
+        {
+            return Err(self.send_disconnect(DisconnectReason::Blacklisted));
+        }
+
+        if !host.sessions.is_node_allowed(&id) {
+            return Err(self.send_disconnect(DisconnectReason::NotReserved));
+        }
+
         // write HELLO packet to remote peer
-        self.state = State::Session(wrapper.take().connection);
+        let handshake = wrapper.take();
+        self.handshake_secret = handshake.handshake_secret();
+        self.state = State::Session(handshake.connection);
         self.write_hello(io, host)?;
 
         Ok(())
@@ -446,6 +497,82 @@ impl Session {
             host.node_db.write().insert_with_token(entry, self.token());
         }
 
+        // Peers of older versions do not advertise any session cipher.
+        let peer_cipher_version: u8 = if rlp.item_count()? > 3 {
+            rlp.val_at(3)?
+        } else {
+            0
+        };
+        // Drop the secrets once the session cipher is agreed on.
+        let handshake_secret = self.handshake_secret.take();
+        let ephemeral = self.ephemeral.take();
+        if peer_cipher_version >= SESSION_CIPHER_VERSION {
+            // The peer can only support the cipher if the cryptography is not
+            // bypassed during handshake.
+            let handshake_secret = match handshake_secret {
+                Some(secret) => secret,
+                None => bail!(ErrorKind::Auth),
+            };
+            let mac: H256 = rlp.val_at(HELLO_MAC_INDEX)?;
+            if !verify_hello_mac(
+                &handshake_secret,
+                !self.metadata.originated,
+                &signed_hello_payload(rlp)?,
+                &mac,
+            ) {
+                debug!(
+                    "failed to read hello, mac mismatch, session = {:?}",
+                    self
+                );
+                return Err(self.send_disconnect(DisconnectReason::Custom(
+                    "hello mac mismatch".into(),
+                )));
+            }
+
+            if let Some(ephemeral) = ephemeral {
+                let remote_ephemeral_public: Public =
+                    rlp.val_at(HELLO_EPHEMERAL_PUBLIC_INDEX)?;
+                let secret = session_secret(
+                    SESSION_CIPHER_VERSION,
+                    &handshake_secret,
+                    ephemeral.secret(),
+                    &remote_ephemeral_public,
+                )?;
+                let cipher =
+                    SessionCipher::new(&secret, self.metadata.originated)?;
+                self.connection_mut().enable_cipher(cipher);
+            }
+        }
+        if self.connection().is_encrypted() {
+            debug!("Session is encrypted, session = {:?}", self);
+        } else if host.config.require_session_encryption {
+            debug!(
+                "Peer does not encrypt the session, cipher version = {}, \
+                 session = {:?}",
+                peer_cipher_version, self
+            );
+            return Err(self.send_disconnect(DisconnectReason::Custom(
+                "session encryption required".into(),
+            )));
+        } else if host.config.encrypt_sessions {
+            // The Hello of the peer may have been altered to remove the
+            // session cipher.
+            info!(
+                "Session is not encrypted, cipher version = {}, session = {:?}",
+                peer_cipher_version, self
+            );
+        } else {
+            debug!("Session is not encrypted, session = {:?}", self);
+        }
+
+        let peer_compression_version: u8 = if rlp.item_count()? > 4 {
+            rlp.val_at(4)?
+        } else {
+            0
+        };
+        self.compress = peer_compression_version >= COMPRESSION_VERSION
+            && host.config.compression_enabled;
+
         self.had_hello = Some(Instant::now());
 
         Ok(())
@@ -473,11 +600,18 @@ impl Session {
             return Err(ErrorKind::Expired.into());
         }
 
+        let (data, compressed) = if self.compress && protocol.is_some() {
+            compress(data)
+        } else {
+            (data, false)
+        };
+
         Ok(SessionPacket::assemble(
             packet_id,
             self.metadata.peer_header_version,
             protocol,
             data,
+            compressed,
         ))
     }
 
@@ -559,16 +693,55 @@ impl Session {
         &mut self, io: &IoContext<Message>, host: &NetworkServiceInner,
     ) -> Result<(), Error> {
         debug!("Sending Hello, session = {:?}", self);
-        let mut rlp = RlpStream::new_list(3);
+        let handshake_secret = match self.handshake_secret {
+            Some(secret) if host.config.encrypt_sessions => Some(secret),
+            _ => None,
+        };
+        let cipher_version = if handshake_secret.is_some() {
+            SESSION_CIPHER_VERSION
+        } else {
+            0
+        };
+        let compression_version = if host.config.compression_enabled {
+            COMPRESSION_VERSION
+        } else {
+            0
+        };
+
+        let mut rlp = RlpStream::new_list(HELLO_EPHEMERAL_PUBLIC_INDEX);
         rlp.append(&host.metadata.network_id);
         rlp.append_list(&*host.metadata.protocols.read());
         host.metadata.public_endpoint.to_rlp_list(&mut rlp);
+        rlp.append(&cipher_version);
+        rlp.append(&compression_version);
+        let mut hello = rlp.out();
+
+        if let Some(handshake_secret) = handshake_secret {
+            let ephemeral = Random.generate()?;
+            let mut rlp = RlpStream::new_list(HELLO_MAC_INDEX);
+            append_hello_items(&mut rlp, &Rlp::new(&hello));
+            rlp.append(ephemeral.public());
+            self.ephemeral = Some(ephemeral);
+            let payload = rlp.out();
+
+            // Sign all the items with the handshake secret, which is only
+            // known to the remote peer.
+            let mut rlp = RlpStream::new_list(HELLO_MAC_INDEX + 1);
+            append_hello_items(&mut rlp, &Rlp::new(&payload));
+            rlp.append(&hello_mac(
+                &handshake_secret,
+                self.metadata.originated,
+                &payload,
+            ));
+            hello = rlp.out();
+        }
+
         self.send_packet(
             io,
             None,
             ProtocolVersion::default(),
             PACKET_HELLO,
-            rlp.drain(),
+            hello,
             SendQueuePriority::High,
         )
         .map(|_| ())
@@ -629,6 +802,23 @@ impl Session {
     }
 }
 
+/// Append all the items of the Hello packet `hello` to `rlp`.
+fn append_hello_items(rlp: &mut RlpStream, hello: &Rlp) {
+    for item in hello.iter() {
+        rlp.append_raw(item.as_raw(), 1);
+    }
+}
+
+/// The payload of a Hello packet signed by its mac, which is the list of all
+/// the items before the mac.
+fn signed_hello_payload(hello: &Rlp) -> Result<Vec<u8>, Error> {
+    let mut rlp = RlpStream::new_list(HELLO_MAC_INDEX);
+    for index in 0..HELLO_MAC_INDEX {
+        rlp.append_raw(hello.at(index)?.as_raw(), 1);
+    }
+    Ok(rlp.out())
+}
+
 impl fmt::Debug for Session {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Session {{ token: {}, id: {:?}, originated: {}, address: {:?}, had_hello: {}, expired: {} }}",
@@ -691,8 +881,8 @@ impl<T> MovableWrapper<T> {
 ///
 /// The header format is:
 /// [  extensions (0 to more bytes) || protocol (0 or 3 bytes if protocol_flag)
-///   || reserved (3 bit), has_extension (1 bit), header_version (3 bit),
-///      protocol_flag (1 bit)
+///   || reserved (2 bit), compressed (1 bit), has_extension (1 bit),
+///      header_version (3 bit), protocol_flag (1 bit)
 ///   || packet_id]
 ///
 /// The protocol format is:
@@ -702,6 +892,9 @@ impl<T> MovableWrapper<T> {
 /// [ extention data (0 to more bytes)
 ///   || extension data length (7 bit) | has_next_extension (1 bit)
 /// ]
+///
+/// Only the data of packets with protocol may be compressed, and it is
+/// decompressed when the packet is parsed.
 #[derive(Eq, PartialEq)]
 struct SessionPacket {
     pub id: u8,
@@ -715,7 +908,7 @@ impl SessionPacket {
     // data + Option<protocol> + protocol_flag + packet_id
     fn assemble(
         id: u8, header_version: u8, protocol: Option<ProtocolId>,
-        mut data: Vec<u8>,
+        mut data: Vec<u8>, compressed: bool,
     ) -> Vec<u8>
     {
         let mut protocol_flag = 0;
@@ -724,7 +917,10 @@ impl SessionPacket {
             protocol_flag = 1;
         }
 
-        let header_byte = (header_version << 1) + protocol_flag;
+        let mut header_byte = (header_version << 1) + protocol_flag;
+        if compressed {
+            header_byte |= COMPRESSED_FLAG;
+        }
         data.push(header_byte);
         data.push(id);
 
@@ -754,6 +950,7 @@ impl SessionPacket {
             return Err(ErrorKind::BadProtocol.into());
         }
         let has_extension = (header_byte & 0x10) >> 4;
+        let compressed = header_byte & COMPRESSED_FLAG != 0;
 
         // without protocol
         if protocol_flag == 0 {
@@ -790,9 +987,13 @@ impl SessionPacket {
         protocol.copy_from_slice(&protocol_bytes);
 
         // extensions
-        let (data, extensions) =
+        let (mut data, extensions) =
             Self::parse_extensions(data, has_extension != 0)?;
 
+        if compressed {
+            data = decompress(&data)?.into();
+        }
+
         Ok(SessionPacket {
             id: packet_id,
             protocol: Some(protocol),
@@ -840,8 +1041,13 @@ mod tests {
 
     #[test]
     fn test_packet_assemble() {
-        let packet =
-            SessionPacket::assemble(5, PACKET_HEADER_VERSION, None, vec![1, 3]);
+        let packet = SessionPacket::assemble(
+            5,
+            PACKET_HEADER_VERSION,
+            None,
+            vec![1, 3],
+            false,
+        );
         assert_eq!(packet, vec![1, 3, 0, 5]);
 
         let packet = SessionPacket::assemble(
@@ -849,10 +1055,52 @@ mod tests {
             PACKET_HEADER_VERSION,
             Some([8; 3]),
             vec![2, 4],
+            false,
         );
         assert_eq!(packet, vec![2, 4, 8, 8, 8, 1, 6]);
     }
 
+    #[test]
+    fn test_compressed_packet() {
+        let data = vec![9; 4096];
+        let (payload, compressed) = compress(data.clone());
+        assert!(compressed);
+        let packet = SessionPacket::assemble(
+            PACKET_USER,
+            PACKET_HEADER_VERSION,
+            Some([3; 3]),
+            payload,
+            true,
+        );
+        assert!(packet.len() < data.len());
+
+        let packet = SessionPacket::parse(packet.into()).unwrap();
+        assert_eq!(packet.protocol, Some([3; 3]));
+        assert_eq!(&packet.data[..], &data[..]);
+    }
+
+    #[test]
+    fn test_signed_hello_payload() {
+        let mut rlp = RlpStream::new_list(HELLO_MAC_INDEX);
+        for item in 0..HELLO_MAC_INDEX {
+            rlp.append(&(item as u64));
+        }
+        let payload = rlp.out();
+
+        let mut rlp = RlpStream::new_list(HELLO_MAC_INDEX + 1);
+        append_hello_items(&mut rlp, &Rlp::new(&payload));
+        rlp.append(&H256::random());
+        let hello = rlp.out();
+        assert_eq!(signed_hello_payload(&Rlp::new(&hello)).unwrap(), payload);
+
+        // Hello of peers without session cipher is not signed.
+        let mut rlp = RlpStream::new_list(HELLO_EPHEMERAL_PUBLIC_INDEX);
+        for item in 0..HELLO_EPHEMERAL_PUBLIC_INDEX {
+            rlp.append(&(item as u64));
+        }
+        assert!(signed_hello_payload(&Rlp::new(&rlp.out())).is_err());
+    }
+
     #[test]
     fn test_packet_parse() {
         // packet id missed
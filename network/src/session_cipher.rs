@@ -0,0 +1,286 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Authenticated encryption of the frames of a session.
+//!
+//! The nonces exchanged during the handshake are only ever sent encrypted with
+//! ECIES, so the handshake secret derived from them is known to the two peers
+//! only. Each peer authenticates its Hello packet, including the advertised
+//! cipher version and an ephemeral public key, with a mac keyed by the
+//! handshake secret. The session secret is then derived from the ephemeral
+//! ECDH agreement and the handshake secret, so that recorded sessions can not
+//! be decrypted even if the node keys leak later.
+//!
+//! Each direction of the connection has its own AES-256-CTR key stream and
+//! HMAC-SHA256 key, so that a frame can not be reflected back to its sender.
+//!
+//! A frame is `[ciphertext || mac]`, where the mac covers the number of
+//! frames sent before in the same direction and the ciphertext. As the key
+//! stream and the frame number both advance with every frame, dropped,
+//! reordered or replayed frames fail the mac check.
+
+use crate::{Error, ErrorKind};
+use bytes::BytesMut;
+use cfx_types::{Public, H256};
+use keccak_hash::keccak;
+use keylib::{crypto::ecdh, Secret};
+use parity_crypto::{
+    aes::AesCtr256,
+    digest::Sha256,
+    hmac::{SigKey, Signer},
+    is_equal,
+};
+
+/// The version of the session cipher advertised in the Hello packet. Version
+/// 0 means frames are sent in plaintext, which is what peers that do not
+/// advertise any version support.
+pub const SESSION_CIPHER_VERSION: u8 = 1;
+
+/// The size of the mac appended to each frame.
+pub const MAC_SIZE: usize = 32;
+
+const NULL_IV: [u8; 16] = [0; 16];
+
+/// The purposes of the keys derived from a secret.
+const STREAM_KEY: u8 = 0;
+const FRAME_MAC_KEY: u8 = 1;
+const HELLO_MAC_KEY: u8 = 2;
+
+/// Derive the key for `purpose` in the direction from the peer which started
+/// the handshake if `originated`, or the other way round.
+fn derive_key(secret: &H256, originated: bool, purpose: u8) -> H256 {
+    let direction = if originated { 0 } else { 1 };
+    let mut material = secret.as_bytes().to_vec();
+    material.extend_from_slice(&[direction, purpose]);
+    keccak(&material)
+}
+
+/// Compute the mac of the signed payload of a Hello packet. `originated` is
+/// whether the sender of the packet started the handshake.
+pub fn hello_mac(
+    handshake_secret: &H256, originated: bool, payload: &[u8],
+) -> H256 {
+    let key = derive_key(handshake_secret, originated, HELLO_MAC_KEY);
+    let mut signer = Signer::with(&SigKey::sha256(key.as_bytes()));
+    signer.update(payload);
+    H256::from_slice(&*signer.sign())
+}
+
+/// Check the mac of the signed payload of a Hello packet in constant time.
+pub fn verify_hello_mac(
+    handshake_secret: &H256, originated: bool, payload: &[u8], mac: &H256,
+) -> bool {
+    is_equal(
+        hello_mac(handshake_secret, originated, payload).as_bytes(),
+        mac.as_bytes(),
+    )
+}
+
+/// Derive the session secret of `version` from the ephemeral key pair of this
+/// peer and the ephemeral public key of the remote peer, both authenticated by
+/// the handshake secret.
+pub fn session_secret(
+    version: u8, handshake_secret: &H256, ephemeral_secret: &Secret,
+    remote_ephemeral_public: &Public,
+) -> Result<H256, Error>
+{
+    let shared = ecdh::agree(ephemeral_secret, remote_ephemeral_public)?;
+    let mut material = Vec::with_capacity(1 + 2 * H256::len_bytes());
+    material.push(version);
+    material.extend_from_slice(shared.as_bytes());
+    material.extend_from_slice(handshake_secret.as_bytes());
+    Ok(keccak(&material))
+}
+
+/// The key stream and mac key of one direction of a session.
+struct FrameKeys {
+    stream: AesCtr256,
+    mac_key: SigKey<Sha256>,
+    frame_number: u64,
+}
+
+impl FrameKeys {
+    fn new(secret: &H256, originated: bool) -> Result<Self, Error> {
+        Ok(FrameKeys {
+            stream: AesCtr256::new(
+                derive_key(secret, originated, STREAM_KEY).as_bytes(),
+                &NULL_IV,
+            )?,
+            mac_key: SigKey::sha256(
+                derive_key(secret, originated, FRAME_MAC_KEY).as_bytes(),
+            ),
+            frame_number: 0,
+        })
+    }
+
+    fn mac(&self, ciphertext: &[u8]) -> Vec<u8> {
+        let mut signer = Signer::with(&self.mac_key);
+        signer.update(&self.frame_number.to_be_bytes());
+        signer.update(ciphertext);
+        signer.sign().to_vec()
+    }
+}
+
+/// The ciphers of both directions of a session.
+pub struct SessionCipher {
+    egress: FrameKeys,
+    ingress: FrameKeys,
+}
+
+impl SessionCipher {
+    /// Create the ciphers of a session from the session secret.
+    /// `originated` is whether this peer started the handshake.
+    pub fn new(secret: &H256, originated: bool) -> Result<Self, Error> {
+        Ok(SessionCipher {
+            egress: FrameKeys::new(secret, originated)?,
+            ingress: FrameKeys::new(secret, !originated)?,
+        })
+    }
+
+    /// Encrypt a frame in place and append its mac.
+    pub fn seal(&mut self, frame: &mut Vec<u8>) -> Result<(), Error> {
+        self.egress.stream.encrypt(frame)?;
+        let mac = self.egress.mac(frame);
+        frame.extend_from_slice(&mac);
+        self.egress.frame_number += 1;
+        Ok(())
+    }
+
+    /// Check the mac of a sealed frame, then decrypt it in place.
+    pub fn open(&mut self, frame: &mut BytesMut) -> Result<(), Error> {
+        if frame.len() < MAC_SIZE {
+            debug!("failed to open frame, mac missed");
+            return Err(ErrorKind::Auth.into());
+        }
+
+        let mac = frame.split_off(frame.len() - MAC_SIZE);
+        if !is_equal(&self.ingress.mac(frame), &mac) {
+            debug!("failed to open frame, mac mismatch");
+            return Err(ErrorKind::Auth.into());
+        }
+
+        self.ingress.stream.decrypt(frame)?;
+        self.ingress.frame_number += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keylib::{Generator, Random};
+
+    fn session_pair() -> (SessionCipher, SessionCipher) {
+        let secret = H256::random();
+        (
+            SessionCipher::new(&secret, true).unwrap(),
+            SessionCipher::new(&secret, false).unwrap(),
+        )
+    }
+
+    fn open(cipher: &mut SessionCipher, frame: &[u8]) -> Option<Vec<u8>> {
+        let mut frame = BytesMut::from(frame);
+        cipher.open(&mut frame).ok().map(|_| frame.to_vec())
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let (mut initiator, mut recipient) = session_pair();
+
+        for data in vec![vec![], vec![1, 2, 3], vec![7; 100]] {
+            let mut frame = data.clone();
+            initiator.seal(&mut frame).unwrap();
+            assert_eq!(frame.len(), data.len() + MAC_SIZE);
+            assert_eq!(open(&mut recipient, &frame), Some(data.clone()));
+
+            let mut frame = data.clone();
+            recipient.seal(&mut frame).unwrap();
+            assert_eq!(open(&mut initiator, &frame), Some(data));
+        }
+    }
+
+    #[test]
+    fn test_session_secret() {
+        let handshake_secret = H256::random();
+        let initiator = Random.generate().unwrap();
+        let recipient = Random.generate().unwrap();
+
+        let secret = session_secret(
+            SESSION_CIPHER_VERSION,
+            &handshake_secret,
+            initiator.secret(),
+            recipient.public(),
+        )
+        .unwrap();
+        assert_eq!(
+            session_secret(
+                SESSION_CIPHER_VERSION,
+                &handshake_secret,
+                recipient.secret(),
+                initiator.public(),
+            )
+            .unwrap(),
+            secret
+        );
+
+        // Both the ephemeral keys and the handshake secret are required.
+        let other = Random.generate().unwrap();
+        assert_ne!(
+            session_secret(
+                SESSION_CIPHER_VERSION,
+                &handshake_secret,
+                other.secret(),
+                recipient.public(),
+            )
+            .unwrap(),
+            secret
+        );
+        assert_ne!(
+            session_secret(
+                SESSION_CIPHER_VERSION,
+                &H256::random(),
+                initiator.secret(),
+                recipient.public(),
+            )
+            .unwrap(),
+            secret
+        );
+    }
+
+    #[test]
+    fn test_hello_mac() {
+        let handshake_secret = H256::random();
+        let mac = hello_mac(&handshake_secret, true, &[1, 2, 3]);
+        assert!(verify_hello_mac(&handshake_secret, true, &[1, 2, 3], &mac));
+        // tampered payload
+        assert_ne!(hello_mac(&handshake_secret, true, &[1, 2, 4]), mac);
+        // Hello reflected to its sender
+        assert_ne!(hello_mac(&handshake_secret, false, &[1, 2, 3]), mac);
+        // unknown handshake secret
+        assert_ne!(hello_mac(&H256::random(), true, &[1, 2, 3]), mac);
+    }
+
+    #[test]
+    fn test_tampered_frames() {
+        let (mut initiator, mut recipient) = session_pair();
+        let mut first = vec![1, 2, 3, 4];
+        initiator.seal(&mut first).unwrap();
+        let mut second = vec![5, 6, 7, 8];
+        initiator.seal(&mut second).unwrap();
+
+        // flipped bit
+        let mut tampered = first.clone();
+        tampered[0] ^= 1;
+        assert_eq!(open(&mut recipient, &tampered), None);
+        // frame reflected to its sender
+        assert_eq!(open(&mut initiator, &first), None);
+        // reordered frames
+        assert_eq!(open(&mut recipient, &second), None);
+
+        assert_eq!(open(&mut recipient, &first), Some(vec![1, 2, 3, 4]));
+        // replayed frame
+        assert_eq!(open(&mut recipient, &first), None);
+        assert_eq!(open(&mut recipient, &second), Some(vec![5, 6, 7, 8]));
+    }
+}
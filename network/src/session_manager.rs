@@ -31,6 +31,8 @@ use std::{
 ///
 /// The session manager also limits the maximum number of incoming TCP
 /// connections, so as to establish some trusted outgoing connections.
+///
+/// In reserved only mode, sessions are only allowed with the reserved nodes.
 pub struct SessionManager {
     sessions: RwLock<Slab<Arc<RwLock<Session>>>>,
     capacity: usize,
@@ -48,6 +50,9 @@ pub struct SessionManager {
     node_id_index: RwLock<HashMap<NodeId, usize>>,
     ip_limit: RwLock<Box<dyn SessionIpLimit>>,
     tag_index: RwLock<SessionTagIndex>,
+
+    /// Node ids allowed to create sessions, or `None` to allow any node.
+    allowed_nodes: RwLock<Option<HashSet<NodeId>>>,
 }
 
 impl SessionManager {
@@ -66,6 +71,7 @@ impl SessionManager {
             node_id_index: RwLock::new(HashMap::new()),
             ip_limit: RwLock::new(new_session_ip_limit(ip_limit_config)),
             tag_index: Default::default(),
+            allowed_nodes: RwLock::new(None),
         }
     }
 
@@ -141,6 +147,20 @@ impl SessionManager {
         self.ip_limit.read().is_allowed(ip)
     }
 
+    /// Only allow sessions with the specified nodes, or with any node if
+    /// `None`. Existing sessions are not affected.
+    pub fn set_allowed_nodes(&self, nodes: Option<HashSet<NodeId>>) {
+        *self.allowed_nodes.write() = nodes;
+    }
+
+    /// Check if the specified node is allowed to create a session.
+    pub fn is_node_allowed(&self, id: &NodeId) -> bool {
+        match *self.allowed_nodes.read() {
+            Some(ref nodes) => nodes.contains(id),
+            None => true,
+        }
+    }
+
     /// Creates a new session with specified TCP socket. It is egress connection
     /// if the `id` is not `None`, otherwise it is ingress connection.
     pub fn create(
@@ -167,8 +187,16 @@ impl SessionManager {
             ));
         }
 
-        // ensure the node id is unique if specified.
+        // ensure the node id is allowed and unique if specified.
         if let Some(node_id) = id {
+            if !self.is_node_allowed(node_id) {
+                debug!("SessionManager.create: leave on node_id not allowed");
+                return Err(format!(
+                    "node not allowed, nodeId = {:?}",
+                    node_id
+                ));
+            }
+
             if node_id_index.contains_key(node_id) {
                 debug!(
                     "SessionManager.create: leave on node_id already exists"
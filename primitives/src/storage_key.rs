@@ -240,6 +240,10 @@ impl<'a> StorageKey<'a> {
                 } else {
                     StorageKey::CodeRootKey(address_bytes)
                 }
+            } else if bytes.starts_with(Self::DEPOSIT_LIST_PREFIX) {
+                StorageKey::DepositListKey(address_bytes)
+            } else if bytes.starts_with(Self::VOTE_LIST_PREFIX) {
+                StorageKey::VoteListKey(address_bytes)
             } else {
                 unsafe { unreachable_unchecked() }
                 /*
@@ -659,4 +663,28 @@ mod tests {
         let key2 = StorageKey::from_delta_mpt_key(&bytes[..]);
         assert_eq!(key, key2);
     }
+
+    #[test]
+    fn test_key_bytes() {
+        let address = "0f572e5295c57f15886f9b263e2f6d2d6c7b5ec6"
+            .parse::<Address>()
+            .unwrap();
+        let code_hash =
+            "0f572e5295c57f15886f9b263e2f6d2d6c7b5ec66d2d6c7b5ec66d2d6c7b5ec6"
+                .parse::<H256>()
+                .unwrap();
+
+        for key in &[
+            StorageKey::new_account_key(&address),
+            StorageKey::new_storage_root_key(&address),
+            StorageKey::new_storage_key(&address, b"0123"),
+            StorageKey::new_code_root_key(&address),
+            StorageKey::new_code_key(&address, &code_hash),
+            StorageKey::new_deposit_list_key(&address),
+            StorageKey::new_vote_list_key(&address),
+        ] {
+            let bytes = key.to_key_bytes();
+            assert_eq!(*key, StorageKey::from_key_bytes(&bytes[..]));
+        }
+    }
 }
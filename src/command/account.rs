@@ -20,19 +20,25 @@
 
 extern crate cfxcore_accounts;
 
-use super::helpers::{password_from_file, password_prompt};
+use super::helpers::{
+    input_password, mnemonic_prompt, password_from_file, password_prompt,
+};
+use cfxkey::{Address, DerivationPath};
 use cfxstore::{
     accounts_dir::RootDiskDirectory, import_account, import_accounts,
+    Derivation,
 };
 use clap;
 use client::accounts::{account_provider, keys_dir, keys_path};
-use std::path::PathBuf;
+use std::{fs, path::PathBuf, str::FromStr};
 
 #[derive(Debug, PartialEq)]
 pub enum AccountCmd {
     New(NewAccount),
     List(ListAccounts),
     Import(ImportAccounts),
+    ImportMnemonic(ImportMnemonic),
+    Derive(DeriveAccount),
 }
 
 #[derive(Debug, PartialEq)]
@@ -87,11 +93,63 @@ impl ImportAccounts {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct ImportMnemonic {
+    pub path: Option<String>,
+    pub mnemonic_file: Option<String>,
+    pub passphrase_file: Option<String>,
+    pub derivation_path: String,
+    pub password_file: Option<String>,
+}
+
+impl ImportMnemonic {
+    pub fn new(matches: &clap::ArgMatches) -> Self {
+        let file = |name| matches.value_of(name).map(|x| x.to_string());
+        Self {
+            path: None,
+            mnemonic_file: file("mnemonic"),
+            passphrase_file: file("passphrase"),
+            derivation_path: matches
+                .value_of("derivation-path")
+                .expect("CLI argument has default value; qed")
+                .to_string(),
+            password_file: file("password"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DeriveAccount {
+    pub path: Option<String>,
+    pub address: String,
+    pub derivation_path: String,
+    pub password_file: Option<String>,
+}
+
+impl DeriveAccount {
+    pub fn new(matches: &clap::ArgMatches) -> Self {
+        Self {
+            path: None,
+            address: matches
+                .value_of("address")
+                .expect("CLI argument is required; qed")
+                .to_string(),
+            derivation_path: matches
+                .value_of("derivation-path")
+                .expect("CLI argument is required; qed")
+                .to_string(),
+            password_file: matches.value_of("password").map(|x| x.to_string()),
+        }
+    }
+}
+
 pub fn execute(cmd: AccountCmd) -> Result<String, String> {
     match cmd {
         AccountCmd::New(new_cmd) => new(new_cmd),
         AccountCmd::List(list_cmd) => list(list_cmd),
         AccountCmd::Import(import_cmd) => import(import_cmd),
+        AccountCmd::ImportMnemonic(import_cmd) => import_mnemonic(import_cmd),
+        AccountCmd::Derive(derive_cmd) => derive(derive_cmd),
     }
 }
 
@@ -144,3 +202,59 @@ fn import(import_cmd: ImportAccounts) -> Result<String, String> {
 
     Ok(format!("{} account(s) imported", imported))
 }
+
+fn read_trimmed(path: &str) -> Result<String, String> {
+    fs::read_to_string(path)
+        .map(|content| content.trim().to_string())
+        .map_err(|e| format!("Unable to read file {}: {}", path, e))
+}
+
+fn import_mnemonic(import_cmd: ImportMnemonic) -> Result<String, String> {
+    let derivation_path: DerivationPath = import_cmd
+        .derivation_path
+        .parse()
+        .map_err(|e| format!("{}", e))?;
+    let phrase = match import_cmd.mnemonic_file {
+        Some(file) => read_trimmed(&file)?,
+        None => mnemonic_prompt()?,
+    };
+    let passphrase = match import_cmd.passphrase_file {
+        Some(file) => read_trimmed(&file)?,
+        None => String::new(),
+    };
+    let password = match import_cmd.password_file {
+        Some(file) => password_from_file(file)?,
+        None => password_prompt()?,
+    };
+
+    let acc_provider = account_provider(import_cmd.path, None)?;
+    let account = acc_provider
+        .import_mnemonic(&phrase, &passphrase, &derivation_path, &password)
+        .map_err(|e| format!("Could not import account: {}", e))?;
+    Ok(format!("0x{:x}", account))
+}
+
+fn derive(derive_cmd: DeriveAccount) -> Result<String, String> {
+    let address =
+        Address::from_str(derive_cmd.address.trim_start_matches("0x"))
+            .map_err(|e| format!("Invalid address: {:?}", e))?;
+    let derivation_path: DerivationPath = derive_cmd
+        .derivation_path
+        .parse()
+        .map_err(|e| format!("{}", e))?;
+    let password = match derive_cmd.password_file {
+        Some(file) => password_from_file(file)?,
+        None => input_password()?,
+    };
+
+    let acc_provider = account_provider(derive_cmd.path, None)?;
+    let account = acc_provider
+        .derive_account(
+            &address,
+            Some(password),
+            Derivation::from(&derivation_path),
+            true, /* save */
+        )
+        .map_err(|e| format!("Could not derive account: {}", e))?;
+    Ok(format!("0x{:x}", account))
+}
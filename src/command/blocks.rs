@@ -0,0 +1,138 @@
+// Copyright 2020 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::helpers::{recover_graph, wait_for_consensus};
+use cfxcore::{
+    sync::block_archive::{export_blocks, import_blocks},
+    BlockDataManager, ConsensusGraphTrait,
+};
+use clap;
+use client::configuration::Configuration;
+use primitives::EpochNumber;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    sync::Arc,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum BlocksCmd {
+    Export(ExportBlocks),
+    Import(ImportBlocks),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ExportBlocks {
+    pub from: u64,
+    pub to: u64,
+    pub output: String,
+}
+
+impl ExportBlocks {
+    pub fn new(matches: &clap::ArgMatches) -> Result<Self, String> {
+        Ok(Self {
+            from: parse_epoch(
+                matches.value_of("from").expect("CLI argument has default"),
+            )?,
+            to: parse_epoch(
+                matches.value_of("to").expect("CLI argument is required"),
+            )?,
+            output: matches
+                .value_of("output")
+                .expect("CLI argument is required")
+                .to_string(),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ImportBlocks {
+    pub file: String,
+}
+
+impl ImportBlocks {
+    pub fn new(matches: &clap::ArgMatches) -> Self {
+        Self {
+            file: matches
+                .value_of("file")
+                .expect("CLI argument is required")
+                .to_string(),
+        }
+    }
+}
+
+fn parse_epoch(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|e| format!("Invalid epoch number {}: {}", value, e))
+}
+
+pub fn execute(
+    cmd: BlocksCmd, conf: &Configuration, data_man: Arc<BlockDataManager>,
+    is_full_node: bool,
+) -> Result<String, String>
+{
+    match cmd {
+        BlocksCmd::Export(export_cmd) => {
+            if export_cmd.from > export_cmd.to {
+                return Err(format!(
+                    "Invalid epoch range {}..{}",
+                    export_cmd.from, export_cmd.to
+                ));
+            }
+            let file = File::create(&export_cmd.output).map_err(|e| {
+                format!("Unable to create {}: {}", export_cmd.output, e)
+            })?;
+            let mut out = BufWriter::new(file);
+            let (consensus, _) =
+                recover_graph(conf, data_man.clone(), is_full_node);
+            let count = export_blocks(
+                &data_man,
+                export_cmd.from,
+                export_cmd.to,
+                |epoch| {
+                    let epoch = EpochNumber::Number(epoch);
+                    let mut hashes = consensus
+                        .get_skipped_block_hashes_by_epoch(epoch.clone())
+                        .ok()?;
+                    hashes.extend(
+                        consensus.get_block_hashes_by_epoch(epoch).ok()?,
+                    );
+                    Some(hashes)
+                },
+                &mut out,
+            )
+            .map_err(|e| format!("Failed to export blocks: {}", e))?;
+            out.flush().map_err(|e| {
+                format!("Unable to write {}: {}", export_cmd.output, e)
+            })?;
+            Ok(format!(
+                "Exported {} blocks of epochs {}..{} to {}",
+                count, export_cmd.from, export_cmd.to, export_cmd.output
+            ))
+        }
+        BlocksCmd::Import(import_cmd) => {
+            let file = File::open(&import_cmd.file).map_err(|e| {
+                format!("Unable to open {}: {}", import_cmd.file, e)
+            })?;
+            let (consensus, sync_graph) =
+                recover_graph(conf, data_man, is_full_node);
+            let statistics =
+                import_blocks(&sync_graph, &mut BufReader::new(file))
+                    .map_err(|e| format!("Failed to import blocks: {}", e))?;
+
+            // Let the consensus graph process and execute the blocks before
+            // exiting.
+            wait_for_consensus(&consensus, &sync_graph);
+            Ok(format!(
+                "Imported {} blocks, {} already known, {} waiting for \
+                 missing ancestors, best epoch {}",
+                statistics.imported,
+                statistics.known,
+                statistics.not_ready,
+                consensus.best_epoch_number()
+            ))
+        }
+    }
+}
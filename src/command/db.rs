@@ -0,0 +1,224 @@
+// Copyright 2020 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::helpers::parse_hash;
+use cfx_types::H256;
+use cfxcore::{
+    block_data_manager::consistency_check::{
+        check_execution_commitments, truncate_inconsistent_commitments,
+        CommitmentCheck,
+    },
+    BlockDataManager,
+};
+use clap;
+use client::configuration::Configuration;
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq)]
+pub enum DbCmd {
+    Stats,
+    Get(GetItem),
+    Verify(VerifyCommitments),
+    Truncate,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ItemKind {
+    Header,
+    Block,
+    ExecutionResult,
+    EpochSet,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GetItem {
+    pub kind: ItemKind,
+    pub hash: H256,
+}
+
+impl GetItem {
+    pub fn new(matches: &clap::ArgMatches) -> Result<Self, String> {
+        let kind =
+            match matches.value_of("kind").expect("CLI argument is required") {
+                "header" => ItemKind::Header,
+                "block" => ItemKind::Block,
+                "execution-result" => ItemKind::ExecutionResult,
+                "epoch-set" => ItemKind::EpochSet,
+                _ => unreachable!(),
+            };
+        Ok(Self {
+            kind,
+            hash: parse_hash(
+                matches.value_of("hash").expect("CLI argument is required"),
+            )?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VerifyCommitments {
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+impl VerifyCommitments {
+    pub fn new(matches: &clap::ArgMatches) -> Result<Self, String> {
+        let parse_epoch = |name: &str| match matches.value_of(name) {
+            Some(value) => value
+                .parse::<u64>()
+                .map(Some)
+                .map_err(|e| format!("Invalid epoch number {}: {}", value, e)),
+            None => Ok(None),
+        };
+        Ok(Self {
+            from: parse_epoch("from")?,
+            to: parse_epoch("to")?,
+        })
+    }
+}
+
+pub fn execute(
+    cmd: DbCmd, conf: &Configuration, data_man: Arc<BlockDataManager>,
+    _is_full_node: bool,
+) -> Result<String, String>
+{
+    match cmd {
+        DbCmd::Stats => {
+            let stats = data_man.db_manager.table_stats().ok_or_else(|| {
+                "Statistics are only available for RocksDB".to_string()
+            })?;
+            let lines: Vec<String> = stats
+                .into_iter()
+                .map(|(table, column_family, keys)| match keys {
+                    Some(keys) => {
+                        format!("{} ({}): ~{} keys", table, column_family, keys)
+                    }
+                    None => format!("{} ({}): unknown", table, column_family),
+                })
+                .collect();
+            Ok(lines.join("\n"))
+        }
+        DbCmd::Get(get_cmd) => get_item(&data_man, &get_cmd),
+        DbCmd::Verify(verify_cmd) => {
+            let from = match verify_cmd.from {
+                Some(from) => from,
+                None => checkpoint_height(&data_man)?,
+            };
+            let to = verify_cmd.to.unwrap_or(std::u64::MAX);
+            let results = check_execution_commitments(&data_man, from, to);
+            if results.is_empty() {
+                return Err(format!("No executed epoch from epoch {}", from));
+            }
+            let mut lines = Vec::new();
+            let (mut consistent, mut not_executed) = (0, 0);
+            for (epoch, pivot_hash, result) in &results {
+                match result {
+                    CommitmentCheck::Consistent => consistent += 1,
+                    CommitmentCheck::NotExecuted => not_executed += 1,
+                    CommitmentCheck::Inconsistent(reason) => lines.push(
+                        format!("Epoch {} {:?}: {}", epoch, pivot_hash, reason),
+                    ),
+                }
+            }
+            let inconsistent = lines.len();
+            lines.push(format!(
+                "Checked epochs {}..{}: {} consistent, {} not executed, {} \
+                 inconsistent",
+                from,
+                results.last().expect("not empty").0,
+                consistent,
+                not_executed,
+                inconsistent
+            ));
+            if inconsistent == 0 {
+                Ok(lines.join("\n"))
+            } else {
+                Err(lines.join("\n"))
+            }
+        }
+        DbCmd::Truncate => {
+            let truncation = match truncate_inconsistent_commitments(
+                &data_man,
+                conf.raw_conf.era_epoch_count,
+            )? {
+                None => {
+                    return Ok("The execution commitments are consistent".into())
+                }
+                Some(truncation) => truncation,
+            };
+            let mut lines = Vec::new();
+            if let Some(checkpoint) = truncation.checkpoint {
+                lines.push(format!(
+                    "Moved the checkpoint back to epoch {}",
+                    checkpoint
+                ));
+            }
+            lines.push(format!(
+                "Removed {} execution commitments from epoch {}, which will \
+                 be executed again when the node starts",
+                truncation.removed, truncation.from_epoch
+            ));
+            Ok(lines.join("\n"))
+        }
+    }
+}
+
+fn get_item(
+    data_man: &BlockDataManager, get_cmd: &GetItem,
+) -> Result<String, String> {
+    let hash = &get_cmd.hash;
+    let not_found = || format!("{:?} is not found", hash);
+    match get_cmd.kind {
+        ItemKind::Header => {
+            let header =
+                data_man.block_header_by_hash(hash).ok_or_else(not_found)?;
+            Ok(format!("{:#?}", header))
+        }
+        ItemKind::Block => {
+            let block = data_man
+                .block_by_hash(hash, false /* update_cache */)
+                .ok_or_else(not_found)?;
+            Ok(format!("{:#?}", block))
+        }
+        ItemKind::ExecutionResult => {
+            let result = data_man
+                .block_execution_result_by_hash_from_db(hash)
+                .ok_or_else(not_found)?;
+            Ok(format!("Executed in epoch {:?}\n{:#?}", result.0, result.1))
+        }
+        ItemKind::EpochSet => {
+            let height = data_man
+                .block_header_by_hash(hash)
+                .ok_or_else(not_found)?
+                .height();
+            let executed =
+                match data_man.executed_epoch_set_hashes_from_db(height) {
+                    Some(executed) => executed,
+                    None => return Err(format!("No epoch set at {}", height)),
+                };
+            let skipped = data_man
+                .skipped_epoch_set_hashes_from_db(height)
+                .unwrap_or_default();
+            let mut output = format!("Epoch {}", height);
+            if executed.last() != Some(hash) {
+                output += &format!(", whose pivot block is not {:?}", hash);
+            }
+            Ok(format!(
+                "{}\nExecuted blocks: {:#?}\nSkipped blocks: {:#?}",
+                output, executed, skipped
+            ))
+        }
+    }
+}
+
+/// The height of the stable block of the current era checkpoint.
+fn checkpoint_height(data_man: &BlockDataManager) -> Result<u64, String> {
+    let stable_hash = data_man.get_cur_consensus_era_stable_hash();
+    data_man
+        .block_header_by_hash(&stable_hash)
+        .map(|header| header.height())
+        .ok_or_else(|| {
+            format!("The header of checkpoint {:?} is missing", stable_hash)
+        })
+}
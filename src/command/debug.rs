@@ -0,0 +1,238 @@
+// Copyright 2020 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::helpers::{parse_hash, recover_graph};
+use cfx_types::H256;
+use cfxcore::{
+    consensus::debug::debug_recompute::{
+        describe_storage_key, describe_storage_value, EpochRecomputation,
+    },
+    BlockDataManager,
+};
+use clap;
+use client::configuration::Configuration;
+use primitives::Receipt;
+use std::{fs::File, io::Write, sync::Arc};
+
+#[derive(Debug, PartialEq)]
+pub enum DebugCmd {
+    RecomputeEpoch(RecomputeEpoch),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RecomputeEpoch {
+    pub hash: H256,
+    pub output: Option<String>,
+}
+
+impl RecomputeEpoch {
+    pub fn new(matches: &clap::ArgMatches) -> Result<Self, String> {
+        Ok(Self {
+            hash: parse_hash(
+                matches.value_of("hash").expect("CLI argument is required"),
+            )?,
+            output: matches.value_of("output").map(str::to_string),
+        })
+    }
+}
+
+pub fn execute(
+    cmd: DebugCmd, conf: &Configuration, data_man: Arc<BlockDataManager>,
+    is_full_node: bool,
+) -> Result<String, String>
+{
+    match cmd {
+        DebugCmd::RecomputeEpoch(recompute_cmd) => {
+            let (consensus, _) = recover_graph(conf, data_man, is_full_node);
+            let recomputation =
+                consensus.recompute_epoch(&recompute_cmd.hash)?;
+            if let Some(output) = &recompute_cmd.output {
+                let json =
+                    serde_json::to_string(&recomputation.debug_record)
+                        .map_err(|e| format!("Failed to serialize: {}", e))?;
+                File::create(output)
+                    .and_then(|mut file| file.write_all(json.as_bytes()))
+                    .map_err(|e| {
+                        format!("Unable to write {}: {}", output, e)
+                    })?;
+            }
+            Ok(format_recomputation(&recomputation))
+        }
+    }
+}
+
+fn format_recomputation(recomputation: &EpochRecomputation) -> String {
+    let record = &recomputation.debug_record;
+    let committed = recomputation.committed.as_ref();
+    let deferred_block = recomputation.deferred_block.as_ref();
+    let mut lines = vec![format!(
+        "Recomputed epoch {} {:?} with {} blocks and {} transactions",
+        record.block_height,
+        record.block_hash,
+        record.block_hashes.len(),
+        record.transactions.len()
+    )];
+    match deferred_block {
+        Some(header) => lines
+            .push(format!("Expected roots are from block {:?}", header.hash())),
+        None => lines.push(
+            "No pivot block without blame commits to the epoch yet".into(),
+        ),
+    }
+    lines.push(compare_roots(
+        "State root",
+        recomputation
+            .state_root
+            .state_root
+            .compute_state_root_hash(),
+        committed.map(|commitment| {
+            commitment
+                .state_root_with_aux_info
+                .state_root
+                .compute_state_root_hash()
+        }),
+        deferred_block.map(|header| *header.deferred_state_root()),
+    ));
+    lines.push(compare_roots(
+        "Receipts root",
+        recomputation.receipts_root,
+        committed.map(|commitment| commitment.receipts_root),
+        deferred_block.map(|header| *header.deferred_receipts_root()),
+    ));
+    lines.push(compare_roots(
+        "Logs bloom hash",
+        recomputation.logs_bloom_hash,
+        committed.map(|commitment| commitment.logs_bloom_hash),
+        deferred_block.map(|header| *header.deferred_logs_bloom_hash()),
+    ));
+
+    // Changes which differ from the committed state are marked with "!".
+    let differs = |recomputed: &Option<Box<[u8]>>,
+                   committed_value: &Option<Box<[u8]>>| {
+        committed.is_some() && recomputed != committed_value
+    };
+    let changed_keys = recomputation
+        .state_changes
+        .iter()
+        .filter(|change| {
+            differs(&change.recomputed_value, &change.committed_value)
+        })
+        .count();
+    lines.push(format!(
+        "{} keys are changed, {} of which differ from the committed state",
+        recomputation.state_changes.len(),
+        changed_keys
+    ));
+    for change in &recomputation.state_changes {
+        let marker =
+            if differs(&change.recomputed_value, &change.committed_value) {
+                "!"
+            } else {
+                " "
+            };
+        let describe = |value: &Option<Box<[u8]>>| match value {
+            Some(value) => describe_storage_value(&change.key, value),
+            None => "none".into(),
+        };
+        lines.push(format!(
+            "{} {} {}",
+            marker,
+            change.op_name,
+            describe_storage_key(&change.key)
+        ));
+        lines.push(format!(
+            "    parent:     {}",
+            describe(&change.parent_value)
+        ));
+        lines.push(format!(
+            "    recomputed: {}",
+            describe(&change.recomputed_value)
+        ));
+        if committed.is_some() {
+            lines.push(format!(
+                "    committed:  {}",
+                describe(&change.committed_value)
+            ));
+        }
+    }
+
+    for block in &recomputation.blocks {
+        lines.push(format!(
+            "Block {:?} with {} transactions, secondary reward {}",
+            block.block_hash,
+            block.transaction_hashes.len(),
+            block.receipts.secondary_reward
+        ));
+        let committed_receipts = block
+            .committed_receipts
+            .as_ref()
+            .map(|block_receipts| &block_receipts.receipts);
+        for (index, (tx_hash, receipt)) in block
+            .transaction_hashes
+            .iter()
+            .zip(&block.receipts.receipts)
+            .enumerate()
+        {
+            let committed_receipt =
+                committed_receipts.and_then(|receipts| receipts.get(index));
+            match committed_receipt {
+                Some(committed_receipt) if committed_receipt != receipt => {
+                    lines.push(format!(
+                        "! tx {} {:?}: {}",
+                        index,
+                        tx_hash,
+                        describe_receipt(receipt)
+                    ));
+                    lines.push(format!(
+                        "    committed: {}",
+                        describe_receipt(committed_receipt)
+                    ));
+                }
+                _ => lines.push(format!(
+                    "  tx {} {:?}: {}",
+                    index,
+                    tx_hash,
+                    describe_receipt(receipt)
+                )),
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+fn compare_roots(
+    name: &str, recomputed: H256, committed: Option<H256>,
+    expected: Option<H256>,
+) -> String
+{
+    let mut line = format!("{}: recomputed {:?}", name, recomputed);
+    match committed {
+        Some(committed) if committed == recomputed => {
+            line += ", same as committed"
+        }
+        Some(committed) => line += &format!(", committed {:?}", committed),
+        None => line += ", not committed",
+    }
+    match expected {
+        Some(expected) if expected == recomputed => {
+            line += ", same as expected"
+        }
+        Some(expected) => line += &format!(", expected {:?}", expected),
+        None => {}
+    }
+    line
+}
+
+fn describe_receipt(receipt: &Receipt) -> String {
+    format!(
+        "outcome {}, accumulated gas used {}, gas fee {}, {} logs, {} \
+         storage collateralized, {} storage released",
+        receipt.outcome_status,
+        receipt.accumulated_gas_used,
+        receipt.gas_fee,
+        receipt.logs.len(),
+        receipt.storage_collateralized.len(),
+        receipt.storage_released.len()
+    )
+}
@@ -18,11 +18,26 @@
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
+use cfx_types::H256;
+use cfxcore::{
+    BlockDataManager, ConsensusGraph, ConsensusGraphTrait, Notifications,
+    SynchronizationGraph,
+};
 use cfxkey::Password;
+use clap::ArgMatches;
+use client::{
+    common::{initialize_data_manager, initialize_synchronization_graph},
+    configuration::Configuration,
+};
 use rpassword::read_password;
+use secret_store::SecretStore;
 use std::{
     fs::File,
     io::{self, BufRead, BufReader, Write},
+    str::FromStr,
+    sync::Arc,
+    thread,
+    time::Duration,
 };
 
 pub use dir::helpers::{replace_home, replace_home_and_local};
@@ -70,6 +85,14 @@ pub fn input_password() -> Result<Password, String> {
     Ok(password)
 }
 
+/// Prompts user for a mnemonic phrase, without echoing it.
+pub fn mnemonic_prompt() -> Result<String, String> {
+    print!("Type mnemonic phrase: ");
+    flush_stdout();
+
+    read_password().map_err(|_| PASSWORD_STDIN_ERROR.to_owned())
+}
+
 /// Read a password from password file.
 pub fn password_from_file(path: String) -> Result<Password, String> {
     let passwords = passwords_from_files(&[path])?;
@@ -93,3 +116,60 @@ pub fn passwords_from_files(files: &[String]) -> Result<Vec<Password>, String> {
 	}).collect::<Result<Vec<Vec<Password>>, String>>();
     Ok(passwords?.into_iter().flatten().collect())
 }
+
+/// Parse a hash, with or without the `0x` prefix.
+pub fn parse_hash(value: &str) -> Result<H256, String> {
+    let hex = value.trim_start_matches("0x");
+    if hex.len() != 64 {
+        return Err(format!("Invalid hash {}", value));
+    }
+    H256::from_str(hex).map_err(|e| format!("Invalid hash {}: {:?}", value, e))
+}
+
+/// Run `execute` with the data manager of the data directory configured by
+/// `matches`. The node must not be running.
+pub fn execute_offline<Cmd>(
+    cmd: Cmd, matches: &ArgMatches,
+    execute: fn(
+        Cmd,
+        &Configuration,
+        Arc<BlockDataManager>,
+        bool,
+    ) -> Result<String, String>,
+) -> Result<String, String>
+{
+    let conf = Configuration::parse(matches)?;
+    let is_full_node = matches.is_present("full");
+    let data_man =
+        initialize_data_manager(&conf, &SecretStore::new(), is_full_node)?;
+    execute(cmd, &conf, data_man, is_full_node)
+}
+
+/// Recover the consensus graph from the database of `data_man`, without the
+/// transaction pool journal of the node, and wait for the epochs executed
+/// again during the recovery.
+pub fn recover_graph(
+    conf: &Configuration, data_man: Arc<BlockDataManager>, is_full_node: bool,
+) -> (Arc<ConsensusGraph>, Arc<SynchronizationGraph>) {
+    let (_, _, consensus, sync_graph) = initialize_synchronization_graph(
+        conf,
+        data_man,
+        Notifications::init(),
+        is_full_node,
+        false, /* use_tx_pool_journal */
+    );
+    sync_graph.recover_graph_from_db(false /* header_only */);
+    wait_for_consensus(&consensus, &sync_graph);
+    (consensus, sync_graph)
+}
+
+/// Wait until the consensus graph has processed and executed the blocks
+/// inserted into `sync_graph`.
+pub fn wait_for_consensus(
+    consensus: &ConsensusGraph, sync_graph: &SynchronizationGraph,
+) {
+    while sync_graph.is_consensus_worker_busy() {
+        thread::sleep(Duration::from_millis(100));
+    }
+    consensus.wait_for_generation(&consensus.best_block_hash());
+}
@@ -0,0 +1,113 @@
+// Copyright 2020 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::helpers::parse_hash;
+use cfx_types::H256;
+use cfxcore::{
+    sync::snapshot_archive::{export_snapshot, import_snapshot},
+    BlockDataManager,
+};
+use clap;
+use client::configuration::Configuration;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    sync::Arc,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum SnapshotCmd {
+    Export(ExportSnapshot),
+    Import(ImportSnapshot),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ExportSnapshot {
+    pub epoch: H256,
+    pub output: String,
+    pub trusted_block: Option<H256>,
+}
+
+impl ExportSnapshot {
+    pub fn new(matches: &clap::ArgMatches) -> Result<Self, String> {
+        Ok(Self {
+            epoch: parse_hash(
+                matches.value_of("epoch").expect("CLI argument is required"),
+            )?,
+            output: matches
+                .value_of("output")
+                .expect("CLI argument is required")
+                .to_string(),
+            trusted_block: match matches.value_of("trusted-block") {
+                Some(hash) => Some(parse_hash(hash)?),
+                None => None,
+            },
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ImportSnapshot {
+    pub file: String,
+    pub trusted_block: H256,
+}
+
+impl ImportSnapshot {
+    pub fn new(matches: &clap::ArgMatches) -> Result<Self, String> {
+        Ok(Self {
+            file: matches
+                .value_of("file")
+                .expect("CLI argument is required")
+                .to_string(),
+            trusted_block: parse_hash(
+                matches
+                    .value_of("trusted-block")
+                    .expect("CLI argument is required"),
+            )?,
+        })
+    }
+}
+
+pub fn execute(
+    cmd: SnapshotCmd, conf: &Configuration, data_man: Arc<BlockDataManager>,
+    _is_full_node: bool,
+) -> Result<String, String>
+{
+    match cmd {
+        SnapshotCmd::Export(export_cmd) => {
+            let file = File::create(&export_cmd.output).map_err(|e| {
+                format!("Unable to create {}: {}", export_cmd.output, e)
+            })?;
+            let mut out = BufWriter::new(file);
+            let trusted_block = export_snapshot(
+                &data_man,
+                &export_cmd.epoch,
+                export_cmd.trusted_block,
+                conf.raw_conf.chunk_size_byte,
+                &mut out,
+            )
+            .map_err(|e| format!("Failed to export snapshot: {}", e))?;
+            out.flush().map_err(|e| {
+                format!("Unable to write {}: {}", export_cmd.output, e)
+            })?;
+            Ok(format!(
+                "Exported snapshot of epoch {:?} to {}, import it with \
+                 --trusted-block {:?}",
+                export_cmd.epoch, export_cmd.output, trusted_block
+            ))
+        }
+        SnapshotCmd::Import(import_cmd) => {
+            let file = File::open(&import_cmd.file).map_err(|e| {
+                format!("Unable to open {}: {}", import_cmd.file, e)
+            })?;
+            let epoch = import_snapshot(
+                &data_man,
+                &import_cmd.trusted_block,
+                &mut BufReader::new(file),
+            )
+            .map_err(|e| format!("Failed to import snapshot: {}", e))?;
+            Ok(format!("Imported snapshot of epoch {:?}", epoch))
+        }
+    }
+}
@@ -13,7 +13,17 @@ use client::{
     full::FullClient,
     light::LightClient,
 };
-use command::account::{AccountCmd, ImportAccounts, ListAccounts, NewAccount};
+use command::{
+    account::{
+        AccountCmd, DeriveAccount, ImportAccounts, ImportMnemonic,
+        ListAccounts, NewAccount,
+    },
+    blocks::{BlocksCmd, ExportBlocks, ImportBlocks},
+    db::{DbCmd, GetItem, VerifyCommitments},
+    debug::{DebugCmd, RecomputeEpoch},
+    helpers::execute_offline,
+    snapshot::{ExportSnapshot, ImportSnapshot, SnapshotCmd},
+};
 use log::{info, LevelFilter};
 use log4rs::{
     append::{console::ConsoleAppender, file::FileAppender},
@@ -191,12 +201,83 @@ fn handle_sub_command(matches: &ArgMatches) -> Result<Option<String>, String> {
             ("import", Some(import_acc_matches)) => {
                 AccountCmd::Import(ImportAccounts::new(import_acc_matches))
             }
+            ("import-mnemonic", Some(import_matches)) => {
+                AccountCmd::ImportMnemonic(ImportMnemonic::new(import_matches))
+            }
+            ("derive", Some(derive_matches)) => {
+                AccountCmd::Derive(DeriveAccount::new(derive_matches))
+            }
             _ => unreachable!(),
         };
         let execute_output = command::account::execute(account_cmd)?;
         return Ok(Some(execute_output));
     }
 
+    // snapshot sub-commands
+    if let ("snapshot", Some(snapshot_matches)) = matches.subcommand() {
+        let snapshot_cmd = match snapshot_matches.subcommand() {
+            ("export", Some(export_matches)) => {
+                SnapshotCmd::Export(ExportSnapshot::new(export_matches)?)
+            }
+            ("import", Some(import_matches)) => {
+                SnapshotCmd::Import(ImportSnapshot::new(import_matches)?)
+            }
+            _ => unreachable!(),
+        };
+        let execute_output =
+            execute_offline(snapshot_cmd, matches, command::snapshot::execute)?;
+        return Ok(Some(execute_output));
+    }
+
+    // blocks sub-commands
+    if let ("blocks", Some(blocks_matches)) = matches.subcommand() {
+        let blocks_cmd = match blocks_matches.subcommand() {
+            ("export", Some(export_matches)) => {
+                BlocksCmd::Export(ExportBlocks::new(export_matches)?)
+            }
+            ("import", Some(import_matches)) => {
+                BlocksCmd::Import(ImportBlocks::new(import_matches))
+            }
+            _ => unreachable!(),
+        };
+        let execute_output =
+            execute_offline(blocks_cmd, matches, command::blocks::execute)?;
+        return Ok(Some(execute_output));
+    }
+
+    // db sub-commands
+    if let ("db", Some(db_matches)) = matches.subcommand() {
+        let db_cmd = match db_matches.subcommand() {
+            ("stats", Some(_)) => DbCmd::Stats,
+            ("get", Some(get_matches)) => {
+                DbCmd::Get(GetItem::new(get_matches)?)
+            }
+            ("verify", Some(verify_matches)) => {
+                DbCmd::Verify(VerifyCommitments::new(verify_matches)?)
+            }
+            ("truncate", Some(_)) => DbCmd::Truncate,
+            _ => unreachable!(),
+        };
+        let execute_output =
+            execute_offline(db_cmd, matches, command::db::execute)?;
+        return Ok(Some(execute_output));
+    }
+
+    // debug sub-commands
+    if let ("debug", Some(debug_matches)) = matches.subcommand() {
+        let debug_cmd = match debug_matches.subcommand() {
+            ("recompute-epoch", Some(recompute_matches)) => {
+                DebugCmd::RecomputeEpoch(RecomputeEpoch::new(
+                    recompute_matches,
+                )?)
+            }
+            _ => unreachable!(),
+        };
+        let execute_output =
+            execute_offline(debug_cmd, matches, command::debug::execute)?;
+        return Ok(Some(execute_output));
+    }
+
     // general RPC commands
     let mut subcmd_matches = matches;
     while let Some(m) = subcmd_matches.subcommand().1 {
@@ -0,0 +1,59 @@
+#!/usr/bin/env python3
+import os
+
+from conflux.rpc import RpcClient
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import assert_equal, run_node_command
+
+
+class BlockArchiveTest(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 2
+
+    def setup_network(self):
+        self.add_nodes(self.num_nodes)
+        self.start_node(0)
+
+    def run_test(self):
+        num_blocks = 200
+        # The latest epochs are exported from the consensus graph.
+        last_epoch = num_blocks
+
+        client = RpcClient(self.nodes[0])
+        nonce = client.get_nonce(client.GENESIS_ADDR)
+        for _ in range(num_blocks):
+            txs = []
+            for _ in range(3):
+                txs.append(client.new_tx(receiver=client.rand_addr(), nonce=nonce, value=21000))
+                nonce += 1
+            client.generate_block_with_fake_txs(txs)
+        hashes = [client.block_by_epoch(client.EPOCH_NUM(i))["hash"] for i in range(last_epoch + 1)]
+        balances = [client.get_balance(client.GENESIS_ADDR, client.EPOCH_NUM(i)) for i in range(last_epoch - 10)]
+        self.stop_node(0)
+
+        archive = os.path.join(self.options.tmpdir, "blocks.rlp")
+        result = run_node_command(self.nodes[0], ["blocks", "export", "--to", str(last_epoch), "--output", archive])
+        assert_equal(result.returncode, 0)
+        assert result.stdout.startswith("Exported %d blocks" % (last_epoch + 1)), result.stdout
+
+        result = run_node_command(self.nodes[1], ["blocks", "import", archive])
+        assert_equal(result.returncode, 0)
+        # The genesis block is already known.
+        assert "Imported %d blocks, 1 already known, 0 waiting" % last_epoch in result.stdout, result.stdout
+
+        # Importing again changes nothing.
+        result = run_node_command(self.nodes[1], ["blocks", "import", archive])
+        assert_equal(result.returncode, 0)
+        assert "Imported 0 blocks, %d already known" % (last_epoch + 1) in result.stdout, result.stdout
+        self.log.info("Imported the blocks of %d epochs", last_epoch)
+
+        self.start_node(1)
+        client = RpcClient(self.nodes[1])
+        for i in range(last_epoch + 1):
+            assert_equal(client.block_by_epoch(client.EPOCH_NUM(i))["hash"], hashes[i])
+        for i in range(len(balances)):
+            assert_equal(client.get_balance(client.GENESIS_ADDR, client.EPOCH_NUM(i)), balances[i])
+
+
+if __name__ == "__main__":
+    BlockArchiveTest().main()
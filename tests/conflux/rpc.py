@@ -122,6 +122,24 @@ class RpcClient:
         logs = self.node.cfx_getLogs(filter.__dict__)
         return logs
 
+    def new_filter(self, filter: Filter) -> str:
+        return self.node.cfx_newFilter(filter.__dict__)
+
+    def new_block_filter(self) -> str:
+        return self.node.cfx_newBlockFilter()
+
+    def new_pending_transaction_filter(self) -> str:
+        return self.node.cfx_newPendingTransactionFilter()
+
+    def get_filter_changes(self, filter_id: str) -> list:
+        return self.node.cfx_getFilterChanges(filter_id)
+
+    def get_filter_logs(self, filter_id: str) -> list:
+        return self.node.cfx_getFilterLogs(filter_id)
+
+    def uninstall_filter(self, filter_id: str) -> bool:
+        return self.node.cfx_uninstallFilter(filter_id)
+
     def get_storage_at(self, addr: str, pos: str, epoch: str = None) -> str:
         assert_is_hash_string(addr, length=40)
         assert_is_hash_string(pos)
@@ -143,6 +161,16 @@ class RpcClient:
 
         return res
 
+    def get_proof(self, addr: str, keys: list, epoch: str = None) -> dict:
+        assert_is_hash_string(addr, length=40)
+        for key in keys:
+            assert_is_hash_string(key)
+
+        if epoch is None:
+            return self.node.cfx_getProof(addr, keys)
+        else:
+            return self.node.cfx_getProof(addr, keys, epoch)
+
     def get_code(self, address: str, epoch: str = None) -> str:
         if epoch is None:
             code = self.node.cfx_getCode(address)
@@ -154,6 +182,13 @@ class RpcClient:
     def gas_price(self) -> int:
         return int(self.node.cfx_gasPrice(), 0)
 
+    def gas_price_suggestion(self) -> dict:
+        suggestion = self.node.cfx_gasPriceSuggestion()
+        return {k: int(v, 0) for (k, v) in suggestion.items()}
+
+    def fee_history(self, epoch_count: int, newest_epoch: str, percentiles: list) -> dict:
+        return self.node.cfx_feeHistory(hex(epoch_count), newest_epoch, percentiles)
+
     def get_block_reward_info(self, epoch: str):
         return self.node.cfx_getBlockRewardInfo(epoch)
 
@@ -253,6 +288,20 @@ class RpcClient:
     def block_by_epoch(self, epoch: str, include_txs: bool = False) -> dict:
         return self.node.cfx_getBlockByEpochNumber(epoch, include_txs)
 
+    def block_tx_count_by_hash(self, block_hash: str) -> int:
+        count = self.node.cfx_getBlockTransactionCountByHash(block_hash)
+        return None if count is None else int(count, 0)
+
+    def block_tx_count_by_epoch(self, epoch: str) -> int:
+        count = self.node.cfx_getBlockTransactionCountByEpochNumber(epoch)
+        return None if count is None else int(count, 0)
+
+    def get_tx_by_block_hash_and_index(self, block_hash: str, index: int) -> dict:
+        return self.node.cfx_getTransactionByBlockHashAndIndex(block_hash, hex(index))
+
+    def get_tx_by_epoch_and_index(self, epoch: str, index: int) -> dict:
+        return self.node.cfx_getTransactionByEpochNumberAndIndex(epoch, hex(index))
+
     def best_block_hash(self) -> str:
         return self.node.cfx_getBestBlockHash()
 
@@ -323,6 +372,30 @@ class RpcClient:
     def disconnect_peer(self, node_id: str, node_op:str=None) -> int:
         return self.node.net_disconnect_node(node_id, node_op)
 
+    def get_reputations(self) -> list:
+        return self.node.net_reputations()
+
+    def get_bans(self) -> list:
+        return self.node.net_bans()
+
+    def ban(self, target: str, duration_secs: int = None) -> bool:
+        return self.node.net_ban(target, duration_secs)
+
+    def unban(self, target: str) -> bool:
+        return self.node.net_unban(target)
+
+    def reload_reserved_peers(self) -> int:
+        return self.node.net_reload_reserved_peers()
+
+    def new_account(self, password: str) -> str:
+        return self.node.new_account(password)
+
+    def sign_typed_data(self, address: str, typed_data: dict, password: str = None) -> str:
+        if password is None:
+            return self.node.cfx_signTypedData(address, typed_data)
+        else:
+            return self.node.cfx_signTypedData(address, typed_data, password)
+
     def chain(self) -> list:
         return self.node.cfx_getChain()
 
@@ -330,6 +403,13 @@ class RpcClient:
         assert_is_hash_string(tx_hash)
         return self.node.cfx_getTransactionReceipt(tx_hash)
 
+    def get_epoch_receipts(self, epoch: str) -> list:
+        return self.node.cfx_getEpochReceipts(epoch)
+
+    def get_block_receipts(self, block_hash: str) -> list:
+        assert_is_hash_string(block_hash)
+        return self.node.cfx_getBlockReceipts(block_hash)
+
     def txpool_status(self) -> (int, int):
         status = self.node.txpool_status()
         return (status["deferred"], status["ready"])
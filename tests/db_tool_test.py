@@ -0,0 +1,104 @@
+#!/usr/bin/env python3
+import os
+import re
+import shutil
+
+from conflux.rpc import RpcClient
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import assert_equal, assert_greater_than, run_node_command
+
+
+class DbToolTest(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 1
+
+    def setup_network(self):
+        self.setup_nodes()
+
+    def verify(self):
+        result = run_node_command(self.nodes[0], ["db", "verify"])
+        # The inconsistent epochs are listed before the status line.
+        match = re.search(
+            r"Checked epochs 0\.\.(\d+): (\d+) consistent, (\d+) not executed, (\d+) inconsistent",
+            result.stdout if result.returncode == 0 else result.stderr)
+        assert match, result.stdout + result.stderr
+        last_epoch, consistent, not_executed, inconsistent = map(int, match.groups())
+        assert_equal(result.returncode == 0, inconsistent == 0)
+        return last_epoch, consistent, not_executed, inconsistent
+
+    def run_test(self):
+        client = RpcClient(self.nodes[0])
+        client.generate_empty_blocks(150)
+        block = client.block_by_epoch(client.EPOCH_NUM(20))
+        self.stop_node(0)
+
+        result = run_node_command(self.nodes[0], ["db", "stats"])
+        assert_equal(result.returncode, 0)
+        assert "blocks (col1)" in result.stdout, result.stdout
+
+        result = run_node_command(self.nodes[0], ["db", "get", "header", block["hash"]])
+        assert_equal(result.returncode, 0)
+        assert "height: 20" in result.stdout, result.stdout
+
+        result = run_node_command(self.nodes[0], ["db", "get", "epoch-set", block["hash"]])
+        assert_equal(result.returncode, 0)
+        assert result.stdout.startswith("Epoch 20\n"), result.stdout
+
+        result = run_node_command(self.nodes[0], ["db", "get", "header", "0x" + "00" * 32])
+        assert result.returncode != 0
+        assert "is not found" in result.stderr, result.stderr
+
+        result = run_node_command(self.nodes[0], ["db", "verify", "--from", "1", "--to", "40"])
+        assert_equal(result.returncode, 0)
+        assert "Checked epochs 1..40: 40 consistent, 0 not executed, 0 inconsistent" in result.stdout.splitlines(), \
+            result.stdout
+
+        # The epochs after the persisted epoch sets are checked too.
+        last_epoch, consistent, _, _ = self.verify()
+        assert_greater_than(last_epoch, 140)
+        assert_equal(consistent, last_epoch + 1)
+
+        result = run_node_command(self.nodes[0], ["db", "truncate"])
+        assert_equal(result.returncode, 0)
+        assert "The execution commitments are consistent" in result.stdout.splitlines(), result.stdout
+
+        # Roll the state storage back, so that the commitments of the epochs
+        # executed since then refer to missing states.
+        storage_dir = os.path.join(self.nodes[0].datadir, "storage_db")
+        backup_dir = os.path.join(self.options.tmpdir, "storage_db_backup")
+        shutil.copytree(storage_dir, backup_dir)
+        self.start_node(0)
+        client = RpcClient(self.nodes[0])
+        client.generate_empty_blocks(20)
+        self.stop_node(0)
+        shutil.rmtree(storage_dir)
+        shutil.copytree(backup_dir, storage_dir)
+
+        _, _, _, inconsistent = self.verify()
+        assert_greater_than(inconsistent, 0)
+
+        result = run_node_command(self.nodes[0], ["db", "truncate"])
+        assert_equal(result.returncode, 0)
+        match = re.search(r"^Removed (\d+) execution commitments from epoch (\d+),", result.stdout, re.MULTILINE)
+        assert match, result.stdout
+        removed, from_epoch = map(int, match.groups())
+        assert_greater_than(removed, 0)
+        assert from_epoch > last_epoch, result.stdout
+
+        _, _, _, inconsistent = self.verify()
+        assert_equal(inconsistent, 0)
+
+        # The removed epochs are executed again.
+        self.start_node(0)
+        client = RpcClient(self.nodes[0])
+        assert_equal(client.epoch_number(), 170)
+        client.generate_empty_blocks(10)
+        assert_greater_than(client.epoch_number("latest_state"), 170)
+        self.stop_node(0)
+        last_epoch, _, _, inconsistent = self.verify()
+        assert_greater_than(last_epoch, 170)
+        assert_equal(inconsistent, 0)
+
+
+if __name__ == "__main__":
+    DbToolTest().main()
@@ -0,0 +1,53 @@
+#!/usr/bin/env python3
+import json
+import os
+
+from conflux.rpc import RpcClient
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import assert_equal, run_node_command
+
+
+class DebugRecomputeTest(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 1
+
+    def setup_network(self):
+        self.setup_nodes()
+
+    def run_test(self):
+        client = RpcClient(self.nodes[0])
+        client.generate_empty_blocks(20)
+        receiver = client.rand_addr()
+        tx = client.new_tx(receiver=receiver, value=10 ** 18)
+        block_hash = client.generate_block_with_fake_txs([tx])
+        client.generate_empty_blocks(20)
+        self.stop_node(0)
+
+        output = os.path.join(self.options.tmpdir, "record.json")
+        result = run_node_command(self.nodes[0], ["debug", "recompute-epoch", block_hash, "--output", output])
+        assert_equal(result.returncode, 0)
+        assert "with 1 blocks and 1 transactions" in result.stdout, result.stdout
+        assert "State root: recomputed" in result.stdout, result.stdout
+        assert "same as committed, same as expected" in result.stdout, result.stdout
+        assert "0 of which differ from the committed state" in result.stdout, result.stdout
+        assert "unwritten" not in result.stdout, result.stdout
+        assert "account " + receiver.lower() in result.stdout.lower(), result.stdout
+        assert "  tx 0 " + tx.hash_hex() in result.stdout, result.stdout
+        with open(output) as f:
+            record = json.load(f)
+        assert_equal(record["block_hash"], block_hash)
+        assert len(record["state_ops"]) > 0
+
+        result = run_node_command(self.nodes[0], ["debug", "recompute-epoch", "0x" + "00" * 32])
+        assert result.returncode != 0
+        assert "is not in the consensus graph" in result.stderr, result.stderr
+
+        # The database is not changed.
+        self.start_node(0)
+        client = RpcClient(self.nodes[0])
+        assert_equal(client.epoch_number(), 41)
+        assert_equal(client.get_balance(receiver), 10 ** 18)
+
+
+if __name__ == "__main__":
+    DebugRecomputeTest().main()
@@ -0,0 +1,80 @@
+#!/usr/bin/env python3
+import json
+import threading
+from http.server import BaseHTTPRequestHandler, HTTPServer
+
+import eth_utils
+
+from conflux.config import default_config
+from conflux.rpc import RpcClient
+from conflux.utils import ecsign, priv_to_addr
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import assert_equal, assert_raises_rpc_error
+
+
+class SignerHandler(BaseHTTPRequestHandler):
+    """JSON-RPC endpoint of an external signer holding the genesis key."""
+
+    key = default_config["GENESIS_PRI_KEY"]
+    address = eth_utils.encode_hex(priv_to_addr(key))
+
+    def do_POST(self):
+        request = json.loads(self.rfile.read(int(self.headers["Content-Length"])))
+        response = {"jsonrpc": "2.0", "id": request["id"]}
+        method, params = request["method"], request["params"]
+        if method == "account_list":
+            response["result"] = [self.address]
+        elif method == "account_signHash" and params[0] == self.address:
+            v, r, s = ecsign(eth_utils.decode_hex(params[1]), self.key)
+            signature = r.to_bytes(32, "big") + s.to_bytes(32, "big") + bytes([v - 27])
+            response["result"] = eth_utils.encode_hex(signature)
+        else:
+            response["error"] = {"code": -32000, "message": "request denied"}
+
+        body = json.dumps(response).encode()
+        self.send_response(200)
+        self.send_header("Content-Type", "application/json")
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+    def log_message(self, format, *args):
+        pass
+
+
+class ExternalSignerTest(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 1
+        self.signer = HTTPServer(("127.0.0.1", 0), SignerHandler)
+        self.conf_parameters["external_signer"] = '"http://127.0.0.1:{}"'.format(self.signer.server_port)
+
+    def setup_network(self):
+        threading.Thread(target=self.signer.serve_forever, daemon=True).start()
+        self.setup_nodes()
+
+    def run_test(self):
+        client = RpcClient(self.nodes[0])
+        assert_equal(self.nodes[0].accounts(), [SignerHandler.address])
+
+        receiver = client.rand_addr()
+        tx = {
+            "from": SignerHandler.address,
+            "to": receiver,
+            "value": hex(10 ** 18),
+            "gas": hex(client.DEFAULT_TX_GAS),
+            "gasPrice": hex(client.DEFAULT_TX_GAS_PRICE),
+        }
+        tx_hash = self.nodes[0].send_transaction(tx)
+        client.wait_for_receipt(tx_hash)
+        assert_equal(client.get_balance(receiver), 10 ** 18)
+        self.log.info("Sent transaction signed by the external signer")
+
+        # accounts unknown to the signer are rejected
+        tx["from"] = client.rand_addr()
+        assert_raises_rpc_error(-32602, "Invalid parameters", self.nodes[0].send_transaction, tx)
+
+        self.signer.shutdown()
+
+
+if __name__ == "__main__":
+    ExternalSignerTest().main()
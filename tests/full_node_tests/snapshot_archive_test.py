@@ -0,0 +1,84 @@
+#!/usr/bin/env python3
+import os
+import sys
+import time
+
+sys.path.insert(1, os.path.dirname(sys.path[0]))
+
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import assert_equal, connect_nodes, sync_blocks, run_node_command
+from conflux.rpc import RpcClient
+
+
+class SnapshotArchiveTest(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 3
+        self.conf_parameters = {
+            "dev_snapshot_epoch_count": "10",
+            "adaptive_weight_beta": "1",
+            "timer_chain_block_difficulty_ratio": "2",
+            "timer_chain_beta": "6",
+            "era_epoch_count": "50",
+            "chunk_size_byte": "1000",
+            "anticone_penalty_ratio": "5"
+        }
+
+    def setup_network(self):
+        self.add_nodes(self.num_nodes)
+        self.start_node(0)
+        self.start_node(1)
+        connect_nodes(self.nodes, 0, 1)
+
+    def run_test(self):
+        num_blocks = 200
+        snapshot_epoch = 150
+        # The first pivot block which commits to the state root of the snapshot.
+        trusted_epoch = snapshot_epoch + 5 + 11
+
+        client = RpcClient(self.nodes[0])
+        nonce = client.get_nonce(client.GENESIS_ADDR)
+        for _ in range(num_blocks):
+            txs = []
+            for _ in range(5):
+                txs.append(client.new_tx(receiver=client.rand_addr(), nonce=nonce, value=21000))
+                nonce += 1
+            client.generate_block_with_fake_txs(txs)
+        sync_blocks(self.nodes[:-1])
+
+        snapshot_hash = client.block_by_epoch(client.EPOCH_NUM(snapshot_epoch))["hash"]
+        trusted_hash = client.block_by_epoch(client.EPOCH_NUM(trusted_epoch))["hash"]
+        self.stop_node(1)
+        archive = os.path.join(self.options.tmpdir, "snapshot.rlp")
+        result = run_node_command(self.nodes[1], ["snapshot", "export", "--epoch", snapshot_hash, "--output", archive])
+        assert_equal(result.returncode, 0)
+        assert trusted_hash[2:] in result.stdout, result.stdout
+        self.log.info("Exported snapshot of epoch %d", snapshot_epoch)
+
+        # The archive is rejected for any other trusted block.
+        full_node_index = self.num_nodes - 1
+        result = run_node_command(
+            self.nodes[full_node_index], ["--full", "snapshot", "import", archive, "--trusted-block", snapshot_hash])
+        assert result.returncode != 0
+        assert "invalid snapshot manifest" in result.stderr, result.stderr
+
+        result = run_node_command(
+            self.nodes[full_node_index], ["--full", "snapshot", "import", archive, "--trusted-block", trusted_hash])
+        assert_equal(result.returncode, 0)
+        self.log.info("Imported snapshot of epoch %d", snapshot_epoch)
+
+        # The full node skips state sync with the imported snapshot.
+        self.start_node(full_node_index, ["--full"], phase_to_wait=None)
+        connect_nodes(self.nodes, full_node_index, 0)
+        self.nodes[full_node_index].wait_for_phase(["NormalSyncPhase"], wait_time=60)
+        sync_blocks([self.nodes[0], self.nodes[full_node_index]], sync_count=False)
+        time.sleep(1)
+
+        full_node_client = RpcClient(self.nodes[full_node_index])
+        for i in range(snapshot_epoch + 1, full_node_client.epoch_number() - 3):
+            assert_equal(
+                full_node_client.get_balance(full_node_client.GENESIS_ADDR, full_node_client.EPOCH_NUM(i)),
+                client.get_balance(client.GENESIS_ADDR, client.EPOCH_NUM(i)))
+
+
+if __name__ == "__main__":
+    SnapshotArchiveTest().main()
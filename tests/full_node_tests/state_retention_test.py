@@ -0,0 +1,67 @@
+#!/usr/bin/env python3
+import os, sys, time
+
+sys.path.insert(1, os.path.dirname(sys.path[0]))
+
+from conflux.rpc import RpcClient
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import assert_equal, assert_raises_rpc_error
+
+ERA_EPOCH_COUNT = 100
+RETENTION_EPOCH_COUNT = 300
+STATE_PRUNED = -32073
+
+class ArchiveStateRetentionTest(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 1
+
+        # set era and snapshot length
+        self.conf_parameters["era_epoch_count"] = str(ERA_EPOCH_COUNT)
+        self.conf_parameters["dev_snapshot_epoch_count"] = str(ERA_EPOCH_COUNT // 2)
+        self.conf_parameters["storage_archive_state_retention_epochs"] = str(RETENTION_EPOCH_COUNT)
+
+        # set other params so that nodes won't crash
+        self.conf_parameters["adaptive_weight_beta"] = "1"
+        self.conf_parameters["anticone_penalty_ratio"] = "10"
+        self.conf_parameters["timer_chain_beta"] = "20"
+        self.conf_parameters["timer_chain_block_difficulty_ratio"] = "3"
+
+        # make sure GC is run often
+        self.conf_parameters["block_cache_gc_period_ms"] = "10"
+
+    def setup_network(self):
+        self.add_nodes(self.num_nodes)
+        self.start_node(0, ["--archive"])
+
+    def check_states(self, client, best_epoch):
+        # The states of the first eras are removed.
+        for epoch in range(1, 2 * ERA_EPOCH_COUNT):
+            assert_raises_rpc_error(STATE_PRUNED, "State pruned", client.get_balance, client.GENESIS_ADDR, client.EPOCH_NUM(epoch))
+
+        # The states within the retention are kept.
+        for epoch in range(best_epoch - RETENTION_EPOCH_COUNT // 2, best_epoch + 1):
+            client.get_balance(client.GENESIS_ADDR, client.EPOCH_NUM(epoch))
+
+    def run_test(self):
+        client = RpcClient(self.nodes[0])
+        num_block = 10 * ERA_EPOCH_COUNT
+
+        self.log.info(f"generating {num_block} blocks...")
+        client.generate_empty_blocks(num_block)
+        best_epoch = client.epoch_number()
+
+        # make sure GC has enough time
+        time.sleep(1)
+        self.check_states(client, best_epoch)
+
+        # The retained states are known right after a restart.
+        self.stop_node(0)
+        self.start_node(0, ["--archive"])
+        client = RpcClient(self.nodes[0])
+        assert_equal(client.epoch_number(), best_epoch)
+        self.check_states(client, best_epoch)
+
+        self.log.info("Pass")
+
+if __name__ == "__main__":
+    ArchiveStateRetentionTest().main()
@@ -97,6 +97,10 @@ class LogFilteringTest(ConfluxTestFramework):
         self.log.info("retrieving contract code...")
         self.check_code(contractAddr, contract_epoch)
 
+        # execute a virtual call against the contract
+        self.log.info("testing virtual calls...")
+        self.check_call(contractAddr, encode_hex_0x(keccak(b"foo()")), call_epoch)
+
         # apply filter, we expect a single log with 2 topics
         self.log.info("testing filter range...")
         self.check_filter(Filter(from_epoch="earliest", to_epoch=contract_epoch))
@@ -131,6 +135,10 @@ class LogFilteringTest(ConfluxTestFramework):
     def check_code(self, address, epoch):
         assert_equal(self.rpc[LIGHTNODE].get_code(address, epoch), self.rpc[FULLNODE0].get_code(address, epoch))
 
+    def check_call(self, address, data_hex, epoch):
+        assert_equal(self.rpc[LIGHTNODE].call(address, data_hex, epoch=epoch), self.rpc[FULLNODE0].call(address, data_hex, epoch=epoch))
+        assert_equal(self.rpc[LIGHTNODE].estimate_gas(address, data_hex), self.rpc[FULLNODE0].estimate_gas(address, data_hex))
+
     def address_to_topic(self, address):
         return "0x" + address[2:].zfill(64)
 
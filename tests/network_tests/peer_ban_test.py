@@ -0,0 +1,81 @@
+#!/usr/bin/env python3
+import os
+import sys
+import time
+
+sys.path.insert(1, os.path.dirname(sys.path[0]))
+
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import assert_equal, connect_nodes, get_peer_addr, wait_until
+from conflux.rpc import RpcClient
+
+class PeerBanTests(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 3
+
+        # try to create more outgoing connections timely
+        self.test_house_keeping_ms = 300
+
+        self.conf_parameters = {
+            "discovery_housekeeping_timeout_ms": str(self.test_house_keeping_ms),
+        }
+
+    def setup_network(self):
+        self.setup_nodes()
+
+    def run_test(self):
+        client0 = RpcClient(self.nodes[0])
+        assert_equal(client0.get_bans(), [])
+        assert_equal(client0.get_reputations(), [])
+
+        self.test_ban_node(client0)
+        self.test_ban_subnet(client0)
+
+    def test_ban_node(self, client0: RpcClient):
+        connect_nodes(self.nodes, 0, 1)
+        assert client0.get_peer(self.nodes[1].key) is not None
+
+        # the banned node is disconnected and removed from the node table
+        assert client0.ban(self.nodes[1].key)
+        wait_until(lambda: client0.get_peer(self.nodes[1].key) is None)
+        assert client0.get_node(self.nodes[1].key) is None
+        bans = client0.get_bans()
+        assert_equal(len(bans), 1)
+        assert_equal(bans[0]["target"], self.nodes[1].key[2:])
+        assert bans[0]["until"] is None
+
+        # the banned node cannot connect again
+        client1 = RpcClient(self.nodes[1])
+        self.nodes[1].addnode(self.nodes[0].key, get_peer_addr(self.nodes[0]))
+        time.sleep((self.test_house_keeping_ms + 100) / 1000)
+        peer0 = client1.get_peer(self.nodes[0].key)
+        # refused during handshake or not handshaked yet
+        assert peer0 is None or len(peer0["caps"]) == 0
+
+        # bans survive a restart
+        self.stop_node(0)
+        self.start_node(0)
+        client0 = RpcClient(self.nodes[0])
+        assert_equal(len(client0.get_bans()), 1)
+
+        assert client0.unban(self.nodes[1].key)
+        assert not client0.unban(self.nodes[1].key)
+        assert_equal(client0.get_bans(), [])
+        connect_nodes(self.nodes, 0, 1)
+
+    def test_ban_subnet(self, client0: RpcClient):
+        connect_nodes(self.nodes, 0, 2)
+
+        # all the test nodes run on localhost
+        assert client0.ban("127.0.0.0/8", 3600)
+        wait_until(lambda: len(client0.get_peers()) == 0)
+        bans = client0.get_bans()
+        assert_equal(len(bans), 1)
+        assert_equal(bans[0]["target"], "127.0.0.0/8")
+        assert bans[0]["until"] is not None
+
+        assert client0.unban("127.0.0.0/8")
+        connect_nodes(self.nodes, 0, 2)
+
+if __name__ == "__main__":
+    PeerBanTests().main()
@@ -0,0 +1,71 @@
+#!/usr/bin/env python3
+import os
+import sys
+import time
+
+sys.path.insert(1, os.path.dirname(sys.path[0]))
+
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import assert_equal, get_peer_addr, wait_until
+from conflux.rpc import RpcClient
+
+RESERVED_PEERS_FILE = "reserved_peers"
+
+class ReservedPeersTests(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 3
+
+        # try to create more outgoing connections timely
+        self.test_house_keeping_ms = 300
+
+        self.conf_parameters = {
+            "discovery_housekeeping_timeout_ms": str(self.test_house_keeping_ms),
+            "reserved_peers_file": "\"{}\"".format(RESERVED_PEERS_FILE),
+            "reserved_peers_only": "true",
+        }
+        self.extra_conf_files = {
+            RESERVED_PEERS_FILE: "# no reserved peers\n",
+        }
+
+    def setup_network(self):
+        self.setup_nodes()
+
+    def node_url(self, index: int) -> str:
+        node = self.nodes[index]
+        return "cfxnode://{}@{}".format(node.key[2:], get_peer_addr(node))
+
+    def set_reserved_peers(self, index: int, peers: list) -> int:
+        path = os.path.join(self.nodes[index].datadir, RESERVED_PEERS_FILE)
+        with open(path, "w") as f:
+            for peer in peers:
+                f.write(self.node_url(peer) + "\n")
+        return RpcClient(self.nodes[index]).reload_reserved_peers()
+
+    def run_test(self):
+        client0 = RpcClient(self.nodes[0])
+
+        # non-reserved nodes are not connected
+        self.nodes[0].addnode(self.nodes[1].key, get_peer_addr(self.nodes[1]))
+        time.sleep((self.test_house_keeping_ms + 100) / 1000)
+        assert_equal(client0.get_peers(), [])
+
+        # reserved peers are connected after reloading
+        assert_equal(self.set_reserved_peers(0, [1]), 1)
+        assert_equal(self.set_reserved_peers(1, [0]), 1)
+        wait_until(lambda: client0.get_peer(self.nodes[1].key) is not None)
+
+        # handshakes from nodes not reserved are rejected
+        assert_equal(self.set_reserved_peers(2, [0]), 1)
+        client2 = RpcClient(self.nodes[2])
+        time.sleep((self.test_house_keeping_ms + 100) / 1000)
+        peer0 = client2.get_peer(self.nodes[0].key)
+        # refused during handshake or not handshaked yet
+        assert peer0 is None or len(peer0["caps"]) == 0
+        assert client0.get_peer(self.nodes[2].key) is None
+
+        # peers not reserved anymore are disconnected
+        assert_equal(self.set_reserved_peers(0, []), 0)
+        wait_until(lambda: client0.get_peer(self.nodes[1].key) is None)
+
+if __name__ == "__main__":
+    ReservedPeersTests().main()
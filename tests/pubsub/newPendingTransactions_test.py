@@ -0,0 +1,64 @@
+#!/usr/bin/env python3
+
+# allow imports from parent directory
+# source: https://stackoverflow.com/a/11158224
+import os, sys
+sys.path.insert(1, os.path.join(sys.path[0], '..'))
+
+import asyncio
+
+from conflux.rpc import RpcClient
+from conflux.pubsub import PubSubClient
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import assert_equal
+
+NUM_TXS = 10
+
+class PubSubTest(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 1
+
+    def setup_network(self):
+        self.setup_nodes()
+        self.rpc = RpcClient(self.nodes[0])
+        self.pubsub = PubSubClient(self.nodes[0])
+
+    async def run_async(self):
+        receiver0 = self.rpc.rand_addr()
+        receiver1 = self.rpc.rand_addr()
+
+        # subscribe
+        sub_all = await self.pubsub.subscribe("newPendingTransactions")
+        sub_to = await self.pubsub.subscribe("newPendingTransactions", {"to": [receiver1]})
+
+        # send transactions to both receivers
+        nonce = self.rpc.get_nonce(self.rpc.GENESIS_ADDR)
+        hashes = [[], []]
+
+        for ii in range(NUM_TXS):
+            receiver = [receiver0, receiver1][ii % 2]
+            tx = self.rpc.new_tx(receiver=receiver, nonce=nonce + ii)
+            hashes[ii % 2].append(self.rpc.send_tx(tx))
+
+        # -------- 1. receive all transactions --------
+        received = [h async for h in sub_all.iter()]
+        assert_equal(sorted(received), sorted(hashes[0] + hashes[1]))
+        self.log.info("Pass -- 1")
+
+        # -------- 2. receive filtered transactions --------
+        received = [h async for h in sub_to.iter()]
+        assert_equal(sorted(received), sorted(hashes[1]))
+        self.log.info("Pass -- 2")
+
+        await sub_all.unsubscribe()
+        await sub_to.unsubscribe()
+
+        # syncing subscriptions take no parameters
+        sub_syncing = await self.pubsub.subscribe("syncing")
+        await sub_syncing.unsubscribe()
+
+    def run_test(self):
+        asyncio.get_event_loop().run_until_complete(self.run_async())
+
+if __name__ == "__main__":
+    PubSubTest().main()
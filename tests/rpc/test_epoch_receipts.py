@@ -0,0 +1,38 @@
+import sys
+sys.path.append("..")
+
+from conflux.rpc import RpcClient
+from test_framework.util import assert_equal
+
+class TestEpochReceipts(RpcClient):
+    def test_block_not_found(self):
+        assert_equal(self.get_block_receipts(self.rand_hash()), None)
+
+    def test_block_receipts(self):
+        tx_hash = self.send_tx(self.new_tx(), True)
+        receipt = self.get_transaction_receipt(tx_hash)
+        block_hash = receipt["blockHash"]
+
+        receipts = self.get_block_receipts(block_hash)
+        assert_equal(len(receipts), len(self.block_by_hash(block_hash)["transactions"]))
+        assert_equal(receipts[receipt["index"]], receipt)
+        assert_equal(receipt["outcomeStatus"], 0)
+        assert_equal(receipt["storageCollateralized"], [])
+
+    def test_epoch_receipts(self):
+        tx_hash = self.send_tx(self.new_tx(), True)
+        receipt = self.get_transaction_receipt(tx_hash)
+        epoch = hex(receipt["epochNumber"])
+
+        blocks = self.block_hashes_by_epoch(epoch)
+        receipts = self.get_epoch_receipts(epoch)
+        assert_equal(len(receipts), len(blocks))
+
+        # receipts are grouped per block in execution order
+        for (block_hash, block_receipts) in zip(blocks, receipts):
+            assert_equal(block_receipts, self.get_block_receipts(block_hash))
+        assert_equal(receipt in sum(receipts, []), True)
+
+    def test_epoch_not_executed(self):
+        self.generate_block()
+        assert_equal(self.get_epoch_receipts("latest_mined"), None)
@@ -0,0 +1,55 @@
+import sys
+sys.path.append("..")
+
+from conflux.filter import Filter
+from conflux.rpc import RpcClient
+from test_framework.util import assert_equal, assert_raises_rpc_error
+
+NULL_H256 = "0x0000000000000000000000000000000000000000000000000000000000000000"
+
+class TestFilters(RpcClient):
+    def test_block_filter(self):
+        filter_id = self.new_block_filter()
+        assert_equal(self.get_filter_changes(filter_id), [])
+
+        # new epochs may also include blocks generated by other tests
+        blocks = self.generate_blocks(3)
+        changes = self.get_filter_changes(filter_id)
+        for block_hash in blocks:
+            assert_equal(block_hash in changes, True)
+        assert_equal(self.get_filter_changes(filter_id), [])
+
+        # block filters have no logs
+        assert_equal(self.get_filter_logs(filter_id), [])
+        assert_equal(self.uninstall_filter(filter_id), True)
+
+    def test_pending_transaction_filter(self):
+        filter_id = self.new_pending_transaction_filter()
+        assert_equal(self.get_filter_changes(filter_id), [])
+
+        tx_hash = self.send_tx(self.new_tx())
+        assert_equal(self.get_filter_changes(filter_id), [tx_hash])
+        assert_equal(self.get_filter_changes(filter_id), [])
+
+        assert_equal(self.uninstall_filter(filter_id), True)
+
+    def test_log_filter(self):
+        filter_id = self.new_filter(Filter(topics=[NULL_H256]))
+        self.generate_blocks_to_state()
+
+        # no contract emits the topic
+        assert_equal(self.get_filter_changes(filter_id), [])
+        assert_equal(self.get_filter_logs(filter_id), [])
+
+        # filters on block hashes are not supported
+        filter = Filter(block_hashes=[self.best_block_hash()])
+        assert_raises_rpc_error(None, None, self.new_filter, filter)
+
+        assert_equal(self.uninstall_filter(filter_id), True)
+
+    def test_uninstalled_filter(self):
+        filter_id = self.new_block_filter()
+        assert_equal(self.uninstall_filter(filter_id), True)
+        assert_equal(self.uninstall_filter(filter_id), False)
+        assert_raises_rpc_error(None, None, self.get_filter_changes, filter_id)
+        assert_raises_rpc_error(None, None, self.get_filter_logs, filter_id)
@@ -2,7 +2,7 @@ import sys
 sys.path.append("..")
 
 from conflux.rpc import RpcClient
-from test_framework.util import assert_equal, assert_greater_than
+from test_framework.util import assert_equal, assert_greater_than, assert_raises_rpc_error
 
 class TestGasPrice(RpcClient):
     # FIXME remove the "_" prefix to enable this test case
@@ -43,3 +43,21 @@ class TestGasPrice(RpcClient):
         # median of prices
         assert_equal(self.gas_price(), 5)
 
+    def test_suggestion(self):
+        suggestion = self.gas_price_suggestion()
+        assert_equal(suggestion["standard"], self.gas_price())
+        assert_greater_than(suggestion["standard"] + 1, suggestion["slow"])
+        assert_greater_than(suggestion["fast"] + 1, suggestion["standard"])
+
+    def test_fee_history(self):
+        tx = self.send_tx(self.new_tx(gas_price=4), True)
+        epoch = self.get_transaction_receipt(tx)["epochNumber"]
+
+        history = self.fee_history(3, hex(epoch), [0, 50, 100])
+        assert_equal(int(history["oldestEpoch"], 0), epoch - 2)
+        assert_equal(len(history["gasUsedRatio"]), 3)
+        assert_greater_than(history["gasUsedRatio"][2], 0)
+        assert_equal([int(p, 0) for p in history["reward"][2]], [4, 4, 4])
+
+        assert_raises_rpc_error(-32602, "Invalid parameters", self.fee_history, 0, "latest_state", [])
+        assert_raises_rpc_error(-32602, "Invalid parameters", self.fee_history, 1, "latest_state", [50, 10])
@@ -0,0 +1,42 @@
+import sys
+sys.path.append("..")
+
+from conflux.rpc import RpcClient
+from test_framework.util import assert_equal, assert_greater_than, assert_raises_rpc_error
+
+DEFERRED_STATE_EPOCH_COUNT = 5
+MAX_PROOF_STORAGE_KEYS = 1024
+
+class TestGetProof(RpcClient):
+    def test_account_proof(self):
+        proof = self.get_proof(self.GENESIS_ADDR, [])
+        assert_equal(proof["address"], self.GENESIS_ADDR)
+        assert_equal(proof["account"] is None, False)
+        assert_greater_than(len(proof["accountProof"]), 2)
+        assert_equal(proof["storageProof"], [])
+
+    def test_account_not_found(self):
+        proof = self.get_proof(self.rand_addr(), [])
+        assert_equal(proof["account"], None)
+        # the proof of non-existence is still returned
+        assert_greater_than(len(proof["accountProof"]), 2)
+
+    def test_storage_proof(self):
+        keys = [self.rand_hash(), self.rand_hash()]
+        proof = self.get_proof(self.GENESIS_ADDR, keys)
+        assert_equal([p["key"] for p in proof["storageProof"]], keys)
+        for storage_proof in proof["storageProof"]:
+            assert_equal(storage_proof["value"], None)
+
+    def test_state_root(self):
+        self.generate_blocks_to_state()
+        epoch = self.epoch_number("latest_state")
+        proof = self.get_proof(self.GENESIS_ADDR, [], self.EPOCH_NUM(epoch))
+
+        # the state root is committed in the header of a later pivot block
+        block = self.block_by_epoch(self.EPOCH_NUM(epoch + DEFERRED_STATE_EPOCH_COUNT))
+        assert_equal(proof["stateRoot"]["auxInfo"]["stateRootHash"], block["deferredStateRoot"])
+
+    def test_too_many_storage_keys(self):
+        keys = [self.rand_hash() for _ in range(MAX_PROOF_STORAGE_KEYS + 1)]
+        assert_raises_rpc_error(-32602, "storage_keys", self.get_proof, self.GENESIS_ADDR, keys)
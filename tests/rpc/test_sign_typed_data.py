@@ -0,0 +1,94 @@
+import sys
+sys.path.append("..")
+
+import eth_utils
+
+from conflux.rpc import RpcClient
+from conflux.utils import ecrecover_to_pub, sha3
+from test_framework.util import assert_equal, assert_raises_rpc_error
+
+def mail(chain_id: int) -> dict:
+    return {
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+            ],
+            "Person": [
+                {"name": "name", "type": "string"},
+                {"name": "wallet", "type": "address"},
+            ],
+            "Mail": [
+                {"name": "from", "type": "Person"},
+                {"name": "to", "type": "Person"},
+                {"name": "contents", "type": "string"},
+            ],
+        },
+        "primaryType": "Mail",
+        "domain": {"name": "Conflux Mail", "version": "1", "chainId": chain_id},
+        "message": {
+            "from": {"name": "Cow", "wallet": "0x1D2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},
+            "to": {"name": "Bob", "wallet": "0x1bBbBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},
+            "contents": "Hello, Bob!",
+        },
+    }
+
+def mail_hash(data: dict) -> bytes:
+    def type_hash(encoded):
+        return sha3(encoded.encode())
+
+    def person(p):
+        return sha3(type_hash("Person(string name,address wallet)")
+                    + sha3(p["name"].encode())
+                    + b"\x00" * 12 + eth_utils.decode_hex(p["wallet"]))
+
+    domain = data["domain"]
+    domain_separator = sha3(type_hash("EIP712Domain(string name,string version,uint256 chainId)")
+                            + sha3(domain["name"].encode())
+                            + sha3(domain["version"].encode())
+                            + domain["chainId"].to_bytes(32, "big"))
+    message = data["message"]
+    message_hash = sha3(type_hash("Mail(Person from,Person to,string contents)Person(string name,address wallet)")
+                        + person(message["from"])
+                        + person(message["to"])
+                        + sha3(message["contents"].encode()))
+    return sha3(b"\x19\x01" + domain_separator + message_hash)
+
+class TestSignTypedData(RpcClient):
+    def test_sign(self):
+        address = self.new_account("123456")
+        chain_id = int(self.node.cfx_getStatus()["chainId"], 0)
+        data = mail(chain_id)
+
+        signature = eth_utils.decode_hex(self.sign_typed_data(address, data, "123456"))
+        assert_equal(len(signature), 65)
+        r = int.from_bytes(signature[0:32], "big")
+        s = int.from_bytes(signature[32:64], "big")
+        pub, _, _ = ecrecover_to_pub(mail_hash(data), signature[64] + 27, r, s)
+        signer = bytearray(sha3(pub)[12:])
+        signer[0] = (signer[0] & 0x0f) | 0x10
+        assert_equal(eth_utils.encode_hex(bytes(signer)), address)
+
+    def test_invalid(self):
+        address = self.new_account("123456")
+        chain_id = int(self.node.cfx_getStatus()["chainId"], 0)
+
+        # signing for another chain
+        assert_raises_rpc_error(-32602, None, self.sign_typed_data, address, mail(chain_id + 1), "123456")
+
+        # signing for any chain
+        data = mail(chain_id)
+        data["types"]["EIP712Domain"].pop()
+        data["domain"].pop("chainId")
+        assert_raises_rpc_error(-32602, "chainId of the domain is required", self.sign_typed_data, address, data, "123456")
+
+        # chainId which is not signed
+        data = mail(chain_id)
+        data["types"]["EIP712Domain"].pop()
+        assert_raises_rpc_error(-32602, "chainId of the domain is required", self.sign_typed_data, address, data, "123456")
+
+        # undefined type
+        data = mail(chain_id)
+        data["types"].pop("Person")
+        assert_raises_rpc_error(-32602, None, self.sign_typed_data, address, data, "123456")
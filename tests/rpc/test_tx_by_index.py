@@ -0,0 +1,45 @@
+import sys
+sys.path.append("..")
+
+from conflux.rpc import RpcClient
+from test_framework.util import assert_equal
+
+class TestGetTxByIndex(RpcClient):
+    def test_block_not_found(self):
+        block_hash = self.rand_hash()
+        assert_equal(self.block_tx_count_by_hash(block_hash), None)
+        assert_equal(self.get_tx_by_block_hash_and_index(block_hash, 0), None)
+
+    def test_empty_block(self):
+        block_hash = self.generate_block()
+        assert_equal(self.block_tx_count_by_hash(block_hash), 0)
+        assert_equal(self.get_tx_by_block_hash_and_index(block_hash, 0), None)
+
+    def test_tx_by_block_hash_and_index(self):
+        tx_hash = self.send_tx(self.new_tx(), True)
+        block_hash = self.get_tx(tx_hash)["blockHash"]
+        assert_equal(self.block_tx_count_by_hash(block_hash), 1)
+
+        tx = self.get_tx_by_block_hash_and_index(block_hash, 0)
+        assert_equal(tx["hash"], tx_hash)
+        assert_equal(tx["blockHash"], block_hash)
+        assert_equal(tx["transactionIndex"], hex(0))
+
+        # out of range
+        assert_equal(self.get_tx_by_block_hash_and_index(block_hash, 1), None)
+
+    def test_tx_by_epoch_and_index(self):
+        tx_hash = self.send_tx(self.new_tx(), True)
+        block_hash = self.get_tx(tx_hash)["blockHash"]
+        block = self.block_by_hash(block_hash)
+        epoch = block["epochNumber"]
+
+        # only the pivot block of the epoch is considered
+        pivot = self.block_by_epoch(epoch)
+        count = self.block_tx_count_by_epoch(epoch)
+        assert_equal(count, len(pivot["transactions"]))
+        if pivot["hash"] == block_hash:
+            tx = self.get_tx_by_epoch_and_index(epoch, 0)
+            assert_equal(tx["hash"], tx_hash)
+
+        assert_equal(self.get_tx_by_epoch_and_index(epoch, count), None)
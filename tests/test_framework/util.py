@@ -11,6 +11,7 @@ import logging
 import os
 import random
 import re
+import subprocess
 from subprocess import CalledProcessError, check_call
 import time
 import socket
@@ -460,6 +461,13 @@ def wait_for_block_count(node, count, timeout=10):
     wait_until(lambda: node.getblockcount() >= count, timeout=timeout)
 
 
+def run_node_command(node, args):
+    """Run a command of the node binary on the data directory of a stopped node."""
+    return subprocess.run(
+        [node.binary, "--config", os.path.join(node.datadir, "conflux.conf")] + args,
+        cwd=node.datadir, stdout=subprocess.PIPE, stderr=subprocess.PIPE, universal_newlines=True)
+
+
 class WaitHandler:
     def __init__(self, node, msgid, func=None):
         self.keep_wait = True
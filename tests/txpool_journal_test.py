@@ -0,0 +1,45 @@
+#!/usr/bin/env python3
+from conflux.rpc import RpcClient
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import assert_equal, wait_until
+
+
+class TxPoolJournalTest(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 1
+
+    def setup_network(self):
+        self.setup_nodes()
+
+    def run_test(self):
+        client = RpcClient(self.nodes[0])
+        client.generate_empty_blocks(5)
+
+        # transactions submitted but not packed yet
+        txs = [client.new_tx(nonce=nonce) for nonce in range(3)]
+        tx_hashes = [client.send_tx(tx) for tx in txs]
+        assert_equal(client.txpool_status()[0], 3)
+
+        self.stop_node(0)
+        self.start_node(0)
+        client = RpcClient(self.nodes[0])
+
+        # the journal is replayed once the consensus graph is recovered
+        wait_until(lambda: client.txpool_status()[0] == 3)
+        for tx_hash in tx_hashes:
+            assert client.get_tx(tx_hash) is not None
+        self.log.info("Transactions are replayed from the journal")
+
+        for tx_hash in tx_hashes:
+            client.wait_for_receipt(tx_hash)
+        assert_equal(client.get_nonce(client.GENESIS_ADDR), 3)
+
+        # packed transactions are not replayed again
+        self.stop_node(0)
+        self.start_node(0)
+        client = RpcClient(self.nodes[0])
+        assert_equal(client.txpool_status()[0], 0)
+
+
+if __name__ == "__main__":
+    TxPoolJournalTest().main()
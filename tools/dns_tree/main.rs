@@ -0,0 +1,276 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Builds and signs the node lists resolved by DNS discovery.
+
+extern crate cfxkey;
+extern crate network;
+extern crate serde;
+extern crate serde_derive;
+extern crate serde_json;
+
+use cfxkey::{Error as EthkeyError, KeyPair, Secret};
+use docopt::Docopt;
+use network::{
+    dns_discovery::{LinkEntry, Tree},
+    node_table::{Node, NodeEntry},
+    Error as NetworkError,
+};
+use serde::Deserialize;
+use std::{
+    env, fmt,
+    fs::{self, File},
+    io, process,
+    str::FromStr,
+};
+
+const USAGE: &str = r#"
+Usage:
+    cfx-dns-tree build <nodes> <domain> --secret=<file> [--seq=<seq>] [--link=<url>...]
+    cfx-dns-tree url <domain> --secret=<file>
+
+Options:
+    -h, --help          Display this message and exit.
+    --secret=<file>     File of the hex encoded secret key to sign the tree,
+                        e.g. the `key` file in the network config directory.
+    --seq=<seq>         Sequence number of the tree, which must be increased
+                        on every update [default: 1].
+    --link=<url>        URL of another tree to link to.
+
+Commands:
+    build               Build and sign the tree of the nodes in a node table
+                        file (e.g. trusted_nodes.json), and print the TXT
+                        records to publish under <domain> in JSON.
+    url                 Print the URL of the tree published under <domain>.
+"#;
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    cmd_build: bool,
+    cmd_url: bool,
+    arg_nodes: String,
+    arg_domain: String,
+    flag_secret: String,
+    flag_seq: u64,
+    flag_link: Vec<String>,
+}
+
+#[derive(Debug)]
+enum Error {
+    Ethkey(EthkeyError),
+    Network(NetworkError),
+    Docopt(docopt::Error),
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl From<EthkeyError> for Error {
+    fn from(err: EthkeyError) -> Self { Error::Ethkey(err) }
+}
+
+impl From<NetworkError> for Error {
+    fn from(err: NetworkError) -> Self { Error::Network(err) }
+}
+
+impl From<docopt::Error> for Error {
+    fn from(err: docopt::Error) -> Self { Error::Docopt(err) }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self { Error::Io(err) }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self { Error::Json(err) }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::Ethkey(ref e) => write!(f, "{}", e),
+            Error::Network(ref e) => write!(f, "{}", e),
+            Error::Docopt(ref e) => write!(f, "{}", e),
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::Json(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// The node table file saved by the network service.
+#[derive(Deserialize)]
+struct NodeTable {
+    nodes: Vec<NodeTableEntry>,
+}
+
+#[derive(Deserialize)]
+struct NodeTableEntry {
+    url: String,
+}
+
+fn main() {
+    match execute(env::args()) {
+        Ok(ok) => println!("{}", ok),
+        Err(Error::Docopt(ref e)) => e.exit(),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+fn load_key_pair(path: &str) -> Result<KeyPair, Error> {
+    let secret = Secret::from_str(fs::read_to_string(path)?.trim())?;
+    Ok(KeyPair::from_secret(secret)?)
+}
+
+fn load_nodes(path: &str) -> Result<Vec<NodeEntry>, Error> {
+    let table: NodeTable = serde_json::from_reader(File::open(path)?)?;
+    let mut nodes = Vec::with_capacity(table.nodes.len());
+    for entry in table.nodes {
+        let node = Node::from_str(&entry.url)?;
+        nodes.push(NodeEntry {
+            id: node.id,
+            endpoint: node.endpoint,
+        });
+    }
+    Ok(nodes)
+}
+
+fn execute<S, I>(command: I) -> Result<String, Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let args: Args =
+        Docopt::new(USAGE).and_then(|d| d.argv(command).deserialize())?;
+    let key_pair = load_key_pair(&args.flag_secret)?;
+
+    if args.cmd_build {
+        let nodes = load_nodes(&args.arg_nodes)?;
+        let links = args
+            .flag_link
+            .iter()
+            .map(|url| LinkEntry::from_str(url))
+            .collect::<Result<Vec<_>, _>>()?;
+        let tree =
+            Tree::sign(&nodes, &links, args.flag_seq, key_pair.secret())?;
+        Ok(serde_json::to_string_pretty(
+            &tree.txt_records(&args.arg_domain),
+        )?)
+    } else if args.cmd_url {
+        let link = LinkEntry {
+            public: *key_pair.public(),
+            domain: args.arg_domain,
+        };
+        Ok(link.to_string())
+    } else {
+        Ok(USAGE.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::execute;
+    use network::{
+        dns_discovery::{resolve_nodes, StaticResolver, TreeSeqs},
+        node_table::NodeId,
+    };
+    use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+    const SECRET: &str =
+        "17d08f5fe8c77af811caa0c9a187e668ce3b74a99acc3f6d976f075fa8e0be55";
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!(
+            "dns_tree_{}_{:x}",
+            name,
+            NodeId::random()
+        ));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn run(args: &[&str]) -> Result<String, String> {
+        execute(args.iter().cloned()).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn build() {
+        let secret = temp_file("secret", SECRET);
+        let secret = secret.to_str().unwrap();
+        let ids: Vec<NodeId> = (0..20).map(|_| NodeId::random()).collect();
+        let urls: Vec<String> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| {
+                format!(r#"{{"url": "cfxnode://{:x}@10.0.0.{}:32323"}}"#, id, i)
+            })
+            .collect();
+        let nodes = temp_file(
+            "nodes",
+            &format!(r#"{{"nodes": [{}]}}"#, urls.join(",")),
+        );
+        let nodes = nodes.to_str().unwrap();
+
+        let records = run(&[
+            "cfx-dns-tree",
+            "build",
+            nodes,
+            "nodes.example.org",
+            "--secret",
+            secret,
+            "--seq",
+            "3",
+        ])
+        .unwrap();
+        let records: BTreeMap<String, String> =
+            serde_json::from_str(&records).unwrap();
+        assert!(records["nodes.example.org"].contains(" seq=3 "));
+        let mut resolver = StaticResolver::new();
+        for (name, txt) in records {
+            resolver.insert(name, txt);
+        }
+
+        let url = run(&[
+            "cfx-dns-tree",
+            "url",
+            "nodes.example.org",
+            "--secret",
+            secret,
+        ])
+        .unwrap();
+        assert!(url.starts_with("enrtree://"));
+        let mut seqs = TreeSeqs::new();
+        let resolved = resolve_nodes(&resolver, &url, &mut seqs).unwrap();
+        assert_eq!(resolved.len(), ids.len());
+        assert!(resolved.iter().all(|node| ids.contains(&node.id)));
+        assert_eq!(seqs["nodes.example.org"], 3);
+
+        // links must be valid tree URLs
+        assert!(run(&[
+            "cfx-dns-tree",
+            "build",
+            nodes,
+            "nodes.example.org",
+            "--secret",
+            secret,
+            "--link",
+            "nodes.example.org",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn invalid_secret() {
+        let secret = temp_file("secret", "1234");
+        assert!(run(&[
+            "cfx-dns-tree",
+            "url",
+            "nodes.example.org",
+            "--secret",
+            secret.to_str().unwrap(),
+        ])
+        .is_err());
+    }
+}